@@ -121,4 +121,36 @@ mod tests {
             i = (i + 7) % CAPACITY;
         });
     }
+
+    // The writes cycle through more keys than the caches hold, so that they keep evicting,
+    // or flipping every CAPACITY insertions for fliplru.
+    #[bench]
+    fn bench_write_usize_lru(b: &mut Bencher) {
+        let mut cache = LruCache::new(NonZeroUsize::new(CAPACITY).unwrap());
+        let mut i: usize = 0;
+        b.iter(|| {
+            cache.push(i, i);
+            i = (i + 7) % (CAPACITY * 4);
+        });
+    }
+
+    #[bench]
+    fn bench_write_usize_fliplru(b: &mut Bencher) {
+        let mut cache = fliplru::LruCache::new(NonZeroUsize::new(CAPACITY).unwrap());
+        let mut i: usize = 0;
+        b.iter(|| {
+            cache.put(i, i);
+            i = (i + 7) % (CAPACITY * 4);
+        });
+    }
+
+    #[bench]
+    fn bench_write_usize_fliplru_put_many(b: &mut Bencher) {
+        let mut cache = fliplru::LruCache::new(NonZeroUsize::new(CAPACITY).unwrap());
+        let mut i: usize = 0;
+        b.iter(|| {
+            cache.put_many((i..i + 100).map(|k| (k, k)));
+            i = (i + 700) % (CAPACITY * 4);
+        });
+    }
 }
@@ -0,0 +1,250 @@
+use crate::LruCache;
+use alloc::boxed::Box;
+use core::any::{Any, TypeId};
+use core::borrow::Borrow;
+use core::hash::{Hash, Hasher};
+use core::num::NonZeroUsize;
+
+#[derive(Hash, PartialEq, Eq)]
+struct TypedKey<K> {
+    ty: TypeId,
+    key: K,
+}
+
+// Looks up a TypedKey from a borrowed key, which hashes and compares the same way
+trait TypedQuery<K> {
+    fn ty(&self) -> TypeId;
+    fn key(&self) -> &K;
+}
+
+impl<K> TypedQuery<K> for TypedKey<K> {
+    fn ty(&self) -> TypeId {
+        self.ty
+    }
+
+    fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K> TypedQuery<K> for (TypeId, &K) {
+    fn ty(&self) -> TypeId {
+        self.0
+    }
+
+    fn key(&self) -> &K {
+        self.1
+    }
+}
+
+impl<'a, K: 'a> Borrow<dyn TypedQuery<K> + 'a> for TypedKey<K> {
+    fn borrow(&self) -> &(dyn TypedQuery<K> + 'a) {
+        self
+    }
+}
+
+impl<K: Hash> Hash for dyn TypedQuery<K> + '_ {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ty().hash(state);
+        self.key().hash(state);
+    }
+}
+
+impl<K: PartialEq> PartialEq for dyn TypedQuery<K> + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        self.ty() == other.ty() && self.key() == other.key()
+    }
+}
+
+impl<K: Eq> Eq for dyn TypedQuery<K> + '_ {}
+
+/// An LRU Cache of values of different types, where the same key holds one value of each
+/// type and all the types share the capacity and the flips of one [`LruCache`].
+///
+/// # Example
+///
+/// ```
+/// use fliplru::AnyCache;
+/// use std::num::NonZeroUsize;
+/// let mut cache = AnyCache::new(NonZeroUsize::new(1024).unwrap());
+///
+/// cache.put("user:1", String::from("alice"));
+/// cache.put("user:1", 42u32);
+/// assert_eq!(cache.get::<String>(&"user:1").map(|s| s.as_str()), Some("alice"));
+/// assert_eq!(cache.get::<u32>(&"user:1"), Some(&42));
+/// assert_eq!(cache.get::<u64>(&"user:1"), None);
+/// ```
+pub struct AnyCache<K> {
+    cache: LruCache<TypedKey<K>, Box<dyn Any + Send>>,
+}
+
+impl<K: Hash + Eq> AnyCache<K> {
+    /// Creates a new cache that holds at least `cap` values of any type.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::AnyCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: AnyCache<u64> = AnyCache::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> AnyCache<K> {
+        AnyCache {
+            cache: LruCache::new(cap),
+        }
+    }
+
+    /// Returns a reference to the value of type `T` of the key in the cache or `None` if
+    /// it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::AnyCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = AnyCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get::<&str>(&1), Some(&"a"));
+    /// assert_eq!(cache.get::<&str>(&2), None);
+    /// ```
+    pub fn get<T: Any + Send>(&mut self, k: &K) -> Option<&T> {
+        self.get_mut(k).map(|v| &*v)
+    }
+
+    /// Returns a mutable reference to the value of type `T` of the key in the cache or
+    /// `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::AnyCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = AnyCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put("apple", 8);
+    /// *cache.get_mut::<i32>(&"apple").unwrap() += 1;
+    /// assert_eq!(cache.get::<i32>(&"apple"), Some(&9));
+    /// ```
+    pub fn get_mut<T: Any + Send>(&mut self, k: &K) -> Option<&mut T> {
+        let query = (TypeId::of::<T>(), k);
+        let value = self.cache.get_mut(&query as &dyn TypedQuery<K>)?;
+        value.downcast_mut()
+    }
+
+    /// Puts a value of type `T` into cache under the key. If the key already holds a
+    /// value of this type, then it updates the value and returns the old value. Otherwise,
+    /// `None` is returned. The values of the other types are kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::AnyCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = AnyCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(None, cache.put(1, 'a'));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put<T: Any + Send>(&mut self, k: K, v: T) -> Option<T> {
+        let key = TypedKey {
+            ty: TypeId::of::<T>(),
+            key: k,
+        };
+        let old = self.cache.put(key, Box::new(v))?;
+        old.downcast().ok().map(|old| *old)
+    }
+
+    /// Removes the value of type `T` of the key from the cache and returns it, or `None`
+    /// if it was not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::AnyCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = AnyCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop::<&str>(&1), Some("a"));
+    /// assert_eq!(cache.pop::<&str>(&1), None);
+    /// ```
+    pub fn pop<T: Any + Send>(&mut self, k: &K) -> Option<T> {
+        let query = (TypeId::of::<T>(), k);
+        let value = self.cache.pop(&query as &dyn TypedQuery<K>)?;
+        value.downcast().ok().map(|v| *v)
+    }
+
+    /// Returns the number of values that are guaranteed to be in the cache, like
+    /// [`LruCache::len`], counting every type of value of a key.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::AnyCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = AnyCache::new(NonZeroUsize::new(2).unwrap());
+    /// cache.put(1, "a");
+    /// cache.put(1, 'a');
+    /// assert_eq!(cache.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::AnyCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = AnyCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Returns metric on the number of times the cache became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::AnyCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = AnyCache::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.cache.get_flips()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnyCache;
+    use alloc::string::String;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_shared_capacity() {
+        let mut cache = AnyCache::new(NonZeroUsize::new(2).unwrap());
+        cache.put(1, String::from("a"));
+        cache.put(1, 1u8);
+        // The u8 values are in the same budget, so the flip moves both values of key 1 into
+        // the backup cache
+        cache.put(2, 2u8);
+        assert_eq!(cache.get_flips(), 1);
+        assert_eq!(cache.get::<u8>(&1), Some(&1));
+        assert_eq!(cache.pop::<String>(&1).as_deref(), Some("a"));
+        assert_eq!(cache.get::<String>(&1), None);
+        assert_eq!(cache.len(), 2);
+    }
+}
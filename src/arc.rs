@@ -0,0 +1,234 @@
+use crate::{Cache, LruCache};
+use alloc::sync::Arc;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::num::NonZeroUsize;
+
+/// An LRU Cache that stores its values in [`Arc`]s and returns clones of them, so that a
+/// value outlives the borrow of the cache, e.g., across an `await` or after the cache
+/// evicted it.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::ArcCache;
+/// use std::num::NonZeroUsize;
+/// let mut cache = ArcCache::new(NonZeroUsize::new(1).unwrap());
+///
+/// cache.put("config", String::from("v1"));
+/// let config = cache.get("config").unwrap();
+/// // The cache can be updated while the value is in use
+/// cache.put("config", String::from("v2"));
+/// assert_eq!(*config, "v1");
+/// ```
+pub struct ArcCache<K, V> {
+    cache: LruCache<K, Arc<V>>,
+}
+
+impl<K: Hash + Eq, V> ArcCache<K, V> {
+    /// Creates a new LRU Cache that holds at least `cap` items.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ArcCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: ArcCache<u64, String> = ArcCache::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> ArcCache<K, V> {
+        ArcCache {
+            cache: LruCache::new(cap),
+        }
+    }
+
+    /// Returns a clone of the `Arc` of the value of the key in the cache or `None` if it is
+    /// not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ArcCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::Arc;
+    /// let mut cache = ArcCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some(Arc::new("a")));
+    /// assert_eq!(cache.get(&2), None);
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<Arc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.get(k).cloned()
+    }
+
+    /// Returns a clone of the `Arc` of the value of the key without updating the LRU list,
+    /// or `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ArcCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::Arc;
+    /// let mut cache = ArcCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.peek(&1), Some(Arc::new("a")));
+    /// ```
+    pub fn peek<Q>(&self, k: &Q) -> Option<Arc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.peek(k).cloned()
+    }
+
+    /// Puts a key-value pair into cache, wrapping the value in an `Arc`. If the key already
+    /// exists in the cache, then it updates the key's value and returns the old value.
+    /// Otherwise, `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ArcCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::Arc;
+    /// let mut cache = ArcCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some(Arc::new("a")), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Option<Arc<V>> {
+        self.cache.put(k, Arc::new(v))
+    }
+
+    /// Puts a value that is already shared into cache, like [`put`](ArcCache::put).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ArcCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::Arc;
+    /// let mut cache = ArcCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// let a = Arc::new("a");
+    /// cache.put_arc(1, a.clone());
+    /// assert!(Arc::ptr_eq(&cache.get(&1).unwrap(), &a));
+    /// ```
+    pub fn put_arc(&mut self, k: K, v: Arc<V>) -> Option<Arc<V>> {
+        self.cache.put(k, v)
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it was not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ArcCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::Arc;
+    /// let mut cache = ArcCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop(&1), Some(Arc::new("a")));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<Arc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.pop(k)
+    }
+
+    /// Returns the number of key-value pairs that are guaranteed to be in the cache, like
+    /// [`LruCache::len`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ArcCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = ArcCache::new(NonZeroUsize::new(2).unwrap());
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// assert_eq!(cache.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ArcCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = ArcCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Returns metric on the number of times the cache became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ArcCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = ArcCache::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.cache.get_flips()
+    }
+}
+
+impl<K: Hash + Eq, V> Cache<K, Arc<V>> for ArcCache<K, V> {
+    fn get(&mut self, k: &K) -> Option<Arc<V>> {
+        ArcCache::get(self, k)
+    }
+
+    fn put(&mut self, k: K, v: Arc<V>) -> Option<Arc<V>> {
+        self.put_arc(k, v)
+    }
+
+    fn pop(&mut self, k: &K) -> Option<Arc<V>> {
+        ArcCache::pop(self, k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ArcCache;
+    use alloc::sync::Arc;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_values_outlive_eviction() {
+        let mut cache = ArcCache::new(NonZeroUsize::new(1).unwrap());
+        cache.put(1, [1u8; 16]);
+        let first = cache.get(&1).unwrap();
+        assert_eq!(Arc::strong_count(&first), 2);
+
+        // Two flips drop the cache's reference but not the caller's
+        cache.put(2, [2; 16]);
+        cache.put(3, [3; 16]);
+        assert_eq!(cache.peek(&1), None);
+        assert_eq!(Arc::strong_count(&first), 1);
+        assert_eq!(first[0], 1);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{Entry, LruCache, Priority};
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::Hash;
+use core::num::{NonZeroU64, NonZeroUsize};
+use hashbrown::HashMap;
+use rkyv::de::Pool;
+use rkyv::rancor::{Error, Source, Strategy};
+use rkyv::rend::NonZeroU64_le;
+use rkyv::tuple::ArchivedTuple2;
+use rkyv::{Archive, Deserialize, Serialize};
+
+/// The items of an [`LruCache`] in a form that rkyv can archive, returned by
+/// [`LruCache::to_archive`]. The archived form, [`ArchivedCacheArchive`], can be read
+/// straight from the bytes, e.g., of a memory mapped file, and [`LruCache::from_archived`]
+/// rebuilds the cache from it without deserializing the whole archive first.
+///
+/// Like the serde support, the capacities, the flip metric and the tier of each item are
+/// kept, and the configuration is not.
+#[derive(Archive, Serialize, Deserialize)]
+pub struct CacheArchive<K, V> {
+    /// The capacity of the main cache.
+    pub hot_cap: NonZeroU64,
+    /// The capacity of the backup cache.
+    pub cold_cap: NonZeroU64,
+    /// The flip metric.
+    pub flips: u64,
+    /// The items of the main cache.
+    pub hot: Vec<(K, V)>,
+    /// The items of the backup cache.
+    pub cold: Vec<(K, V)>,
+}
+
+impl<K: Hash + Eq, V> LruCache<K, V> {
+    /// Returns a copy of the items and the metrics to archive with rkyv.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{ArchivedCacheArchive, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, 10);
+    /// let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&cache.to_archive()).unwrap();
+    /// let archived = rkyv::access::<ArchivedCacheArchive<i32, i32>, rkyv::rancor::Error>(&bytes).unwrap();
+    /// assert_eq!(archived.hot.len(), 1);
+    /// ```
+    pub fn to_archive(&self) -> CacheArchive<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let pairs = |map: &HashMap<K, Entry<V>>| {
+            map.iter()
+                .map(|(k, e)| (k.clone(), e.value.clone()))
+                .collect()
+        };
+        CacheArchive {
+            hot_cap: to_u64(self.hot_cap),
+            cold_cap: to_u64(self.cold_cap),
+            flips: self.flips as u64,
+            hot: pairs(&self.l1_map),
+            cold: pairs(&self.l2_map),
+        }
+    }
+
+    /// Rebuilds a cache from an archive, deserializing one item at a time into the maps,
+    /// which are only allocated for the items.
+    ///
+    /// Fails like the other loaders if a capacity or the flip metric does not fit in a
+    /// `usize` or if a key appears more than once, also when it is in both tiers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{ArchivedCacheArchive, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, 10);
+    /// cache.put(2, 20);
+    /// cache.put(3, 30);
+    /// let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&cache.to_archive()).unwrap();
+    /// let archived = rkyv::access::<ArchivedCacheArchive<i32, i32>, rkyv::rancor::Error>(&bytes).unwrap();
+    /// let mut cache = LruCache::from_archived(archived).unwrap();
+    /// assert_eq!(cache.get(&1), Some(&10));
+    /// assert_eq!(cache.get_flips(), 1);
+    /// ```
+    pub fn from_archived(archive: &ArchivedCacheArchive<K, V>) -> Result<LruCache<K, V>, Error>
+    where
+        K: Archive,
+        V: Archive,
+        K::Archived: Deserialize<K, Strategy<Pool, Error>>,
+        V::Archived: Deserialize<V, Strategy<Pool, Error>>,
+    {
+        let cap = |n: &NonZeroU64_le| {
+            let cap = usize::try_from(n.get()).map_err(|_| invalid("a capacity is too large"))?;
+            Ok::<_, Error>(NonZeroUsize::new(cap).unwrap())
+        };
+        let mut cache = LruCache::restoring(cap(&archive.hot_cap)?, cap(&archive.cold_cap)?);
+        cache.flips = usize::try_from(archive.flips.to_native())
+            .map_err(|_| invalid("the flips overflow"))?;
+        fill(&mut cache.l1_map, archive.hot.iter())?;
+        fill(&mut cache.l2_map, archive.cold.iter())?;
+        if cache.l2_map.keys().any(|k| cache.l1_map.contains_key(k)) {
+            return Err(invalid("a key is in both tiers"));
+        }
+        Ok(cache)
+    }
+}
+
+fn fill<'a, K, V>(
+    map: &mut HashMap<K, Entry<V>>,
+    pairs: impl ExactSizeIterator<Item = &'a ArchivedTuple2<K::Archived, V::Archived>>,
+) -> Result<(), Error>
+where
+    K: Hash + Eq + Archive + 'a,
+    V: Archive + 'a,
+    K::Archived: Deserialize<K, Strategy<Pool, Error>>,
+    V::Archived: Deserialize<V, Strategy<Pool, Error>>,
+{
+    map.reserve(pairs.len());
+    for pair in pairs {
+        let (k, v) = (rkyv::deserialize(&pair.0)?, rkyv::deserialize(&pair.1)?);
+        if map.insert(k, Entry::new(v, Priority::Normal)).is_some() {
+            return Err(invalid("a key appears more than once"));
+        }
+    }
+    Ok(())
+}
+
+// The reason why an archive is rejected, as an rkyv error wraps an error type.
+#[derive(Debug)]
+struct Invalid(&'static str);
+
+impl fmt::Display for Invalid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid cache: {}", self.0)
+    }
+}
+
+impl core::error::Error for Invalid {}
+
+fn invalid(msg: &'static str) -> Error {
+    Error::new(Invalid(msg))
+}
+
+fn to_u64(n: usize) -> NonZeroU64 {
+    NonZeroU64::new(n as u64).unwrap_or(NonZeroU64::MIN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArchivedCacheArchive, CacheArchive};
+    use crate::LruCache;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::num::{NonZeroU64, NonZeroUsize};
+    use rkyv::rancor::Error;
+
+    #[test]
+    fn test_archive() {
+        let mut cache =
+            LruCache::with_caps(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(4).unwrap());
+        for i in 0..5u32 {
+            cache.put(i, i * 10);
+        }
+        let bytes = rkyv::to_bytes::<Error>(&cache.to_archive()).unwrap();
+        let archived = rkyv::access::<ArchivedCacheArchive<u32, u32>, Error>(&bytes).unwrap();
+        assert_eq!(archived.cold.len(), 4);
+
+        let copy = LruCache::from_archived(archived).unwrap();
+        assert_eq!(copy.cold_cap(), 4);
+        assert_eq!(copy.get_flips(), 2);
+        for i in 0..5 {
+            assert_eq!(copy.peek_entry(&i), cache.peek_entry(&i));
+        }
+    }
+
+    #[test]
+    fn test_rejected_archives() {
+        let archive = |hot: Vec<(u32, u32)>, cold: Vec<(u32, u32)>| CacheArchive {
+            hot_cap: NonZeroU64::new(1 << 40).unwrap(),
+            cold_cap: NonZeroU64::new(2).unwrap(),
+            flips: 0,
+            hot,
+            cold,
+        };
+        let load = |archive: &CacheArchive<u32, u32>| {
+            let bytes = rkyv::to_bytes::<Error>(archive).unwrap();
+            let archived = rkyv::access::<ArchivedCacheArchive<u32, u32>, Error>(&bytes).unwrap();
+            LruCache::from_archived(archived).map(|cache| cache.l1_map.capacity())
+        };
+        // The maps are not allocated for the capacities
+        assert!(load(&archive(vec![(1, 1)], vec![])).unwrap() < 1 << 10);
+        // A key in both tiers or twice in a tier is rejected, like by the other loaders
+        assert!(load(&archive(vec![(1, 1)], vec![(1, 2)])).is_err());
+        assert!(load(&archive(vec![], vec![(1, 1), (1, 2)])).is_err());
+    }
+}
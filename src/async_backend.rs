@@ -0,0 +1,573 @@
+use crate::{AsyncLruCache, LruCache};
+use async_lock::{Semaphore, SemaphoreGuard};
+use core::fmt;
+use core::future::Future;
+use core::hash::Hash;
+use core::num::NonZeroUsize;
+
+/// An async store behind an [`AsyncBackedCache`], e.g., a redis or S3 client.
+///
+/// The futures are `Send`, so that the cache can be used from multi-threaded runtimes.
+/// The methods can be implemented with `async fn`.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::AsyncCacheBackend;
+/// use std::collections::HashMap;
+/// use std::sync::Mutex;
+///
+/// #[derive(Default)]
+/// struct Store(Mutex<HashMap<i32, i32>>);
+///
+/// impl AsyncCacheBackend<i32, i32> for Store {
+///     type Error = ();
+///
+///     async fn load(&self, k: &i32) -> Result<Option<i32>, ()> {
+///         Ok(self.0.lock().unwrap().get(k).copied())
+///     }
+///
+///     async fn store(&self, &k: &i32, &v: &i32) -> Result<(), ()> {
+///         self.0.lock().unwrap().insert(k, v);
+///         Ok(())
+///     }
+///
+///     async fn remove(&self, k: &i32) -> Result<(), ()> {
+///         self.0.lock().unwrap().remove(k);
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait AsyncCacheBackend<K, V> {
+    /// The error of a failed backend call.
+    type Error;
+
+    /// Loads the value of the key, or returns `None` if the backend does not have it.
+    fn load(&self, k: &K) -> impl Future<Output = Result<Option<V>, Self::Error>> + Send;
+
+    /// Stores the value of the key.
+    fn store(&self, k: &K, v: &V) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Removes the key.
+    fn remove(&self, k: &K) -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
+
+/// The error of [`AsyncBackedCache::try_get`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LoadError<E> {
+    /// The backend failed to load the value.
+    Backend(E),
+    /// The load limit was reached, so the value was not loaded.
+    Overloaded,
+}
+
+impl<E: fmt::Display> fmt::Display for LoadError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Backend(e) => write!(f, "backend error: {e}"),
+            LoadError::Overloaded => f.write_str("too many loads in flight"),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for LoadError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Backend(e) => Some(e),
+            LoadError::Overloaded => None,
+        }
+    }
+}
+
+/// An [`AsyncLruCache`] in front of an async backend: a lookup of a key that is not
+/// present in the cache loads it from the backend, and with write-through the puts and
+/// removals are applied to the backend before the cache.
+///
+/// A cached value is never reloaded from the backend, as there is no counterpart of the
+/// reload queue of the synchronous [`ReadThroughCache::with_reload_queue`].
+///
+/// [`ReadThroughCache::with_reload_queue`]: crate::ReadThroughCache::with_reload_queue
+pub struct AsyncBackedCache<K, V, B> {
+    cache: AsyncLruCache<K, V>,
+    backend: B,
+    write_through: bool,
+    // Permits for the loads from the backend, if they are limited
+    loads: Option<Semaphore>,
+}
+
+impl<K: Hash + Eq, V, B: AsyncCacheBackend<K, V>> AsyncBackedCache<K, V, B> {
+    /// Creates a read-through layer of the cache in front of the backend. The puts only
+    /// change the cache unless write-through is enabled, see [`with_write_through`].
+    ///
+    /// [`with_write_through`]: AsyncBackedCache::with_write_through
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncBackedCache, AsyncCacheBackend, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::collections::HashMap;
+    /// use std::sync::Mutex;
+    ///
+    /// #[derive(Default)]
+    /// struct Store(Mutex<HashMap<i32, i32>>);
+    ///
+    /// impl AsyncCacheBackend<i32, i32> for Store {
+    ///     type Error = ();
+    ///
+    ///     async fn load(&self, k: &i32) -> Result<Option<i32>, ()> {
+    ///         Ok(self.0.lock().unwrap().get(k).copied())
+    ///     }
+    ///
+    ///     async fn store(&self, &k: &i32, &v: &i32) -> Result<(), ()> {
+    ///         self.0.lock().unwrap().insert(k, v);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     async fn remove(&self, k: &i32) -> Result<(), ()> {
+    ///         self.0.lock().unwrap().remove(k);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let store = Store::default();
+    /// store.0.lock().unwrap().insert(1, 10);
+    /// let cache = AsyncBackedCache::new(cache, store);
+    ///
+    /// futures_executor::block_on(async {
+    ///     assert_eq!(cache.get(&1).await, Ok(Some(10)));
+    ///     assert_eq!(cache.get(&2).await, Ok(None));
+    /// });
+    /// ```
+    pub fn new(cache: LruCache<K, V>, backend: B) -> AsyncBackedCache<K, V, B> {
+        AsyncBackedCache {
+            cache: AsyncLruCache::new(cache),
+            backend,
+            write_through: false,
+            loads: None,
+        }
+    }
+
+    /// Enables or disables write-through: the puts are stored in the backend and the
+    /// removals are removed from the backend before the cache is changed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncBackedCache, AsyncCacheBackend, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::collections::HashMap;
+    /// use std::sync::Mutex;
+    ///
+    /// #[derive(Default)]
+    /// struct Store(Mutex<HashMap<i32, i32>>);
+    ///
+    /// impl AsyncCacheBackend<i32, i32> for Store {
+    ///     type Error = ();
+    ///
+    ///     async fn load(&self, k: &i32) -> Result<Option<i32>, ()> {
+    ///         Ok(self.0.lock().unwrap().get(k).copied())
+    ///     }
+    ///
+    ///     async fn store(&self, &k: &i32, &v: &i32) -> Result<(), ()> {
+    ///         self.0.lock().unwrap().insert(k, v);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     async fn remove(&self, k: &i32) -> Result<(), ()> {
+    ///         self.0.lock().unwrap().remove(k);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let cache = AsyncBackedCache::new(cache, Store::default()).with_write_through(true);
+    ///
+    /// futures_executor::block_on(async {
+    ///     cache.put(1, 10).await.unwrap();
+    ///     assert_eq!(cache.backend().0.lock().unwrap().get(&1), Some(&10));
+    /// });
+    /// ```
+    pub fn with_write_through(mut self, enabled: bool) -> AsyncBackedCache<K, V, B> {
+        self.write_through = enabled;
+        self
+    }
+
+    /// Limits the number of loads from the backend that run at the same time, so that a
+    /// burst of misses on distinct keys does not overwhelm it. Over the limit, [`get`]
+    /// waits for a running load to finish and [`try_get`] fails with
+    /// [`LoadError::Overloaded`].
+    ///
+    /// [`get`]: AsyncBackedCache::get
+    /// [`try_get`]: AsyncBackedCache::try_get
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncBackedCache, AsyncCacheBackend, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::collections::HashMap;
+    /// use std::sync::Mutex;
+    ///
+    /// #[derive(Default)]
+    /// struct Store(Mutex<HashMap<i32, i32>>);
+    ///
+    /// impl AsyncCacheBackend<i32, i32> for Store {
+    ///     type Error = ();
+    ///
+    ///     async fn load(&self, k: &i32) -> Result<Option<i32>, ()> {
+    ///         Ok(self.0.lock().unwrap().get(k).copied())
+    ///     }
+    ///
+    ///     async fn store(&self, &k: &i32, &v: &i32) -> Result<(), ()> {
+    ///         self.0.lock().unwrap().insert(k, v);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     async fn remove(&self, k: &i32) -> Result<(), ()> {
+    ///         self.0.lock().unwrap().remove(k);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let store = Store::default();
+    /// store.0.lock().unwrap().insert(1, 10);
+    /// let cache = AsyncBackedCache::new(cache, store).with_load_limit(NonZeroUsize::new(8).unwrap());
+    ///
+    /// futures_executor::block_on(async {
+    ///     assert_eq!(cache.get(&1).await, Ok(Some(10)));
+    /// });
+    /// ```
+    pub fn with_load_limit(mut self, max: NonZeroUsize) -> AsyncBackedCache<K, V, B> {
+        self.loads = Some(Semaphore::new(max.get()));
+        self
+    }
+
+    /// Returns a clone of the value of the key. If the key is not present in the cache,
+    /// its value is loaded from the backend and put into the cache. `None` is returned if
+    /// the backend does not have it either. The cache is not locked while loading.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncBackedCache, AsyncCacheBackend, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::collections::HashMap;
+    /// use std::sync::Mutex;
+    ///
+    /// #[derive(Default)]
+    /// struct Store(Mutex<HashMap<i32, i32>>);
+    ///
+    /// impl AsyncCacheBackend<i32, i32> for Store {
+    ///     type Error = ();
+    ///
+    ///     async fn load(&self, k: &i32) -> Result<Option<i32>, ()> {
+    ///         Ok(self.0.lock().unwrap().get(k).copied())
+    ///     }
+    ///
+    ///     async fn store(&self, &k: &i32, &v: &i32) -> Result<(), ()> {
+    ///         self.0.lock().unwrap().insert(k, v);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     async fn remove(&self, k: &i32) -> Result<(), ()> {
+    ///         self.0.lock().unwrap().remove(k);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let store = Store::default();
+    /// store.0.lock().unwrap().insert(1, 10);
+    /// let cache = AsyncBackedCache::new(cache, store);
+    ///
+    /// futures_executor::block_on(async {
+    ///     assert_eq!(cache.get(&1).await, Ok(Some(10)));
+    ///     // Served from the cache
+    ///     cache.backend().0.lock().unwrap().clear();
+    ///     assert_eq!(cache.get(&1).await, Ok(Some(10)));
+    /// });
+    /// ```
+    pub async fn get(&self, k: &K) -> Result<Option<V>, B::Error>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        if let Some(v) = self.cache.get(k).await {
+            return Ok(Some(v));
+        }
+        let permit = match &self.loads {
+            Some(loads) => Some(loads.acquire().await),
+            None => None,
+        };
+        self.load(k, permit).await
+    }
+
+    /// Like [`get`](AsyncBackedCache::get), but if the key has to be loaded while the
+    /// [load limit](AsyncBackedCache::with_load_limit) is reached, it fails with
+    /// [`LoadError::Overloaded`] instead of waiting.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncBackedCache, AsyncCacheBackend, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::collections::HashMap;
+    /// use std::sync::Mutex;
+    ///
+    /// #[derive(Default)]
+    /// struct Store(Mutex<HashMap<i32, i32>>);
+    ///
+    /// impl AsyncCacheBackend<i32, i32> for Store {
+    ///     type Error = ();
+    ///
+    ///     async fn load(&self, k: &i32) -> Result<Option<i32>, ()> {
+    ///         Ok(self.0.lock().unwrap().get(k).copied())
+    ///     }
+    ///
+    ///     async fn store(&self, &k: &i32, &v: &i32) -> Result<(), ()> {
+    ///         self.0.lock().unwrap().insert(k, v);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     async fn remove(&self, k: &i32) -> Result<(), ()> {
+    ///         self.0.lock().unwrap().remove(k);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let store = Store::default();
+    /// store.0.lock().unwrap().insert(1, 10);
+    /// let cache = AsyncBackedCache::new(cache, store).with_load_limit(NonZeroUsize::new(1).unwrap());
+    ///
+    /// futures_executor::block_on(async {
+    ///     assert_eq!(cache.try_get(&1).await, Ok(Some(10)));
+    ///     assert_eq!(cache.try_get(&2).await, Ok(None));
+    /// });
+    /// ```
+    pub async fn try_get(&self, k: &K) -> Result<Option<V>, LoadError<B::Error>>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        if let Some(v) = self.cache.get(k).await {
+            return Ok(Some(v));
+        }
+        let permit = match &self.loads {
+            Some(loads) => Some(loads.try_acquire().ok_or(LoadError::Overloaded)?),
+            None => None,
+        };
+        self.load(k, permit).await.map_err(LoadError::Backend)
+    }
+
+    // Loads the key while holding the permit and caches the value.
+    async fn load(&self, k: &K, _permit: Option<SemaphoreGuard<'_>>) -> Result<Option<V>, B::Error>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let v = self.backend.load(k).await?;
+        if let Some(v) = &v {
+            self.cache.put(k.clone(), v.clone()).await;
+        }
+        Ok(v)
+    }
+
+    /// Puts a key-value pair into the cache, see [`LruCache::put`]. With write-through it
+    /// is stored in the backend first, and if the backend fails, the error is returned and
+    /// the cache is left unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncBackedCache, AsyncCacheBackend, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::collections::HashMap;
+    /// use std::sync::Mutex;
+    ///
+    /// #[derive(Default)]
+    /// struct Store(Mutex<HashMap<i32, i32>>);
+    ///
+    /// impl AsyncCacheBackend<i32, i32> for Store {
+    ///     type Error = ();
+    ///
+    ///     async fn load(&self, k: &i32) -> Result<Option<i32>, ()> {
+    ///         Ok(self.0.lock().unwrap().get(k).copied())
+    ///     }
+    ///
+    ///     async fn store(&self, &k: &i32, &v: &i32) -> Result<(), ()> {
+    ///         self.0.lock().unwrap().insert(k, v);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     async fn remove(&self, k: &i32) -> Result<(), ()> {
+    ///         self.0.lock().unwrap().remove(k);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let cache = AsyncBackedCache::new(cache, Store::default());
+    ///
+    /// futures_executor::block_on(async {
+    ///     assert_eq!(cache.put(1, 10).await, Ok(None));
+    ///     assert_eq!(cache.put(1, 11).await, Ok(Some(10)));
+    ///     assert!(cache.backend().0.lock().unwrap().is_empty());
+    /// });
+    /// ```
+    pub async fn put(&self, k: K, v: V) -> Result<Option<V>, B::Error> {
+        if self.write_through {
+            self.backend.store(&k, &v).await?;
+        }
+        Ok(self.cache.put(k, v).await)
+    }
+
+    /// Removes the key from the cache and returns its value, see [`LruCache::pop`]. With
+    /// write-through it is removed from the backend first, and if the backend fails, the
+    /// error is returned and the cache is left unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncBackedCache, AsyncCacheBackend, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::collections::HashMap;
+    /// use std::sync::Mutex;
+    ///
+    /// #[derive(Default)]
+    /// struct Store(Mutex<HashMap<i32, i32>>);
+    ///
+    /// impl AsyncCacheBackend<i32, i32> for Store {
+    ///     type Error = ();
+    ///
+    ///     async fn load(&self, k: &i32) -> Result<Option<i32>, ()> {
+    ///         Ok(self.0.lock().unwrap().get(k).copied())
+    ///     }
+    ///
+    ///     async fn store(&self, &k: &i32, &v: &i32) -> Result<(), ()> {
+    ///         self.0.lock().unwrap().insert(k, v);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     async fn remove(&self, k: &i32) -> Result<(), ()> {
+    ///         self.0.lock().unwrap().remove(k);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let cache = AsyncBackedCache::new(cache, Store::default()).with_write_through(true);
+    ///
+    /// futures_executor::block_on(async {
+    ///     cache.put(1, 10).await.unwrap();
+    ///     assert_eq!(cache.remove(&1).await, Ok(Some(10)));
+    ///     assert_eq!(cache.get(&1).await, Ok(None));
+    /// });
+    /// ```
+    pub async fn remove(&self, k: &K) -> Result<Option<V>, B::Error> {
+        if self.write_through {
+            self.backend.remove(k).await?;
+        }
+        Ok(self.cache.pop(k).await)
+    }
+
+    /// Returns the cache.
+    pub fn cache(&self) -> &AsyncLruCache<K, V> {
+        &self.cache
+    }
+
+    /// Returns the backend.
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Returns the cache and the backend.
+    pub fn into_parts(self) -> (AsyncLruCache<K, V>, B) {
+        (self.cache, self.backend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AsyncBackedCache, AsyncCacheBackend, LoadError};
+    use crate::LruCache;
+    use core::num::NonZeroUsize;
+    use futures_executor::block_on;
+    use hashbrown::HashMap;
+    use std::sync::Mutex;
+
+    // Fails the stores of odd values and counts the loads
+    #[derive(Default)]
+    struct Even {
+        items: Mutex<HashMap<u32, u32>>,
+        loads: Mutex<usize>,
+    }
+
+    impl AsyncCacheBackend<u32, u32> for Even {
+        type Error = u32;
+
+        async fn load(&self, k: &u32) -> Result<Option<u32>, u32> {
+            *self.loads.lock().unwrap() += 1;
+            Ok(self.items.lock().unwrap().get(k).copied())
+        }
+
+        async fn store(&self, &k: &u32, &v: &u32) -> Result<(), u32> {
+            if v % 2 == 1 {
+                return Err(v);
+            }
+            self.items.lock().unwrap().insert(k, v);
+            Ok(())
+        }
+
+        async fn remove(&self, k: &u32) -> Result<(), u32> {
+            self.items.lock().unwrap().remove(k);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_async_backend() {
+        let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        let cache = AsyncBackedCache::new(cache, Even::default()).with_write_through(true);
+        block_on(async {
+            assert_eq!(cache.put(1, 2).await, Ok(None));
+            // A failed store keeps the cached value
+            assert_eq!(cache.put(1, 3).await, Err(3));
+            assert_eq!(cache.get(&1).await, Ok(Some(2)));
+            assert_eq!(*cache.backend().loads.lock().unwrap(), 0);
+
+            // A miss is loaded once and then cached
+            cache.cache().pop(&1).await;
+            assert_eq!(cache.get(&1).await, Ok(Some(2)));
+            assert_eq!(cache.get(&1).await, Ok(Some(2)));
+            assert_eq!(*cache.backend().loads.lock().unwrap(), 1);
+
+            assert_eq!(cache.remove(&1).await, Ok(Some(2)));
+            assert_eq!(cache.get(&1).await, Ok(None));
+        });
+    }
+
+    #[test]
+    fn test_load_limit() {
+        let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        let cache = AsyncBackedCache::new(cache, Even::default())
+            .with_load_limit(NonZeroUsize::new(1).unwrap());
+        cache.backend().items.lock().unwrap().insert(1, 2);
+        block_on(async {
+            // With the only permit taken, a miss is rejected but a hit is served
+            let permit = cache.loads.as_ref().unwrap().acquire().await;
+            assert_eq!(cache.try_get(&1).await, Err(LoadError::Overloaded));
+            cache.cache().put(2, 4).await;
+            assert_eq!(cache.try_get(&2).await, Ok(Some(4)));
+            drop(permit);
+
+            assert_eq!(cache.try_get(&1).await, Ok(Some(2)));
+            assert_eq!(cache.get(&3).await, Ok(None));
+            assert_eq!(*cache.backend().loads.lock().unwrap(), 2);
+        });
+    }
+}
@@ -0,0 +1,428 @@
+use crate::stats::{CacheStats, Counters};
+use crate::LruCache;
+use alloc::sync::Arc;
+use async_lock::{Mutex, MutexGuard, OnceCell};
+use core::borrow::Borrow;
+use core::future::Future;
+use core::hash::Hash;
+use hashbrown::HashMap;
+use std::sync::{self, PoisonError};
+
+/// An [`LruCache`] behind an async mutex, whose methods are `async` and take `&self`. The
+/// lock can be held across `.await` points without blocking the executor, and it works
+/// with any async runtime.
+pub struct AsyncLruCache<K, V> {
+    cache: Mutex<LruCache<K, V>>,
+    counters: Counters,
+    // The loads in progress, only changed with the cache locked
+    loads: sync::Mutex<HashMap<K, Arc<OnceCell<V>>>>,
+}
+
+impl<K: Hash + Eq, V> AsyncLruCache<K, V> {
+    /// Wraps the cache, keeping its configuration.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncLruCache, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache: AsyncLruCache<isize, &str> = AsyncLruCache::new(LruCache::new(NonZeroUsize::new(10).unwrap()));
+    /// ```
+    pub fn new(cache: LruCache<K, V>) -> AsyncLruCache<K, V> {
+        AsyncLruCache {
+            cache: Mutex::new(cache),
+            counters: Counters::default(),
+            loads: sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Locks the cache for direct access to its whole API. The guard may be held across
+    /// `.await` points.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncLruCache, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = AsyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// futures_executor::block_on(async {
+    ///     cache.lock().await.put(1, "a");
+    ///     assert_eq!(cache.lock().await.get(&1), Some(&"a"));
+    /// });
+    /// ```
+    pub async fn lock(&self) -> MutexGuard<'_, LruCache<K, V>> {
+        self.cache.lock().await
+    }
+
+    /// Calls the function with the value of the key, see [`LruCache::get`], and returns
+    /// its result, or `None` if the key is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncLruCache, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = AsyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// futures_executor::block_on(async {
+    ///     cache.put("apple", String::from("red")).await;
+    ///     assert_eq!(cache.get_with(&"apple", |v| v.len()).await, Some(3));
+    ///     assert_eq!(cache.get_with(&"banana", |v| v.len()).await, None);
+    /// });
+    /// ```
+    pub async fn get_with<Q, R, F>(&self, k: &Q, f: F) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&V) -> R,
+    {
+        self.counters.record(self.lock().await.get(k).map(f))
+    }
+
+    /// Calls the function with the mutable value of the key, see [`LruCache::get_mut`],
+    /// and returns its result, or `None` if the key is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncLruCache, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = AsyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// futures_executor::block_on(async {
+    ///     cache.put("apple", 8).await;
+    ///     cache.get_mut_with(&"apple", |v| *v += 1).await;
+    ///     assert_eq!(cache.get(&"apple").await, Some(9));
+    /// });
+    /// ```
+    pub async fn get_mut_with<Q, R, F>(&self, k: &Q, f: F) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&mut V) -> R,
+    {
+        self.counters.record(self.lock().await.get_mut(k).map(f))
+    }
+
+    /// Returns a clone of the value of the key or `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncLruCache, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = AsyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// futures_executor::block_on(async {
+    ///     cache.put(1, "a").await;
+    ///     assert_eq!(cache.get(&1).await, Some("a"));
+    /// });
+    /// ```
+    pub async fn get<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.get_with(k, V::clone).await
+    }
+
+    /// Returns a clone of the value of the key, or awaits the future to load the value and
+    /// puts it into the cache if the key is not present in either tier. Concurrent calls
+    /// for the same key are coalesced: only the future of one of them is awaited while the
+    /// others wait for its value. The lock is not held while loading. If that call is
+    /// cancelled or its future panics, the future of one of the waiting calls is awaited
+    /// instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncLruCache, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = AsyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// futures_executor::block_on(async {
+    ///     assert_eq!(cache.get_or_insert_with(1, async { "a" }).await, "a");
+    ///     assert_eq!(cache.get_or_insert_with(1, async { unreachable!() }).await, "a");
+    /// });
+    /// ```
+    pub async fn get_or_insert_with<F>(&self, k: K, init: F) -> V
+    where
+        K: Clone,
+        V: Clone,
+        F: Future<Output = V>,
+    {
+        let load = {
+            let mut cache = self.lock().await;
+            if let Some(v) = self.counters.record(cache.get(&k)) {
+                return v.clone();
+            }
+            let mut loads = self.loads.lock().unwrap_or_else(PoisonError::into_inner);
+            loads.entry(k.clone()).or_default().clone()
+        };
+        let v = load.get_or_init(|| async {
+            let v = init.await;
+            // The value is in the cache before the load ends, so later calls find it
+            let mut cache = self.lock().await;
+            let mut loads = self.loads.lock().unwrap_or_else(PoisonError::into_inner);
+            loads.remove(&k);
+            cache.put(k.clone(), v.clone());
+            v
+        });
+        v.await.clone()
+    }
+
+    /// Puts a key-value pair into the cache, see [`LruCache::put`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncLruCache, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = AsyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// futures_executor::block_on(async {
+    ///     assert_eq!(None, cache.put(1, "a").await);
+    ///     assert_eq!(Some("a"), cache.put(1, "alpha").await);
+    /// });
+    /// ```
+    pub async fn put(&self, k: K, v: V) -> Option<V> {
+        self.lock().await.put(k, v)
+    }
+
+    /// Removes the key from the cache and returns its value, see [`LruCache::pop`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncLruCache, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = AsyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// futures_executor::block_on(async {
+    ///     cache.put(1, "a").await;
+    ///     assert_eq!(cache.pop(&1).await, Some("a"));
+    ///     assert_eq!(cache.pop(&1).await, None);
+    /// });
+    /// ```
+    pub async fn pop<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lock().await.pop(k)
+    }
+
+    /// Removes all the items from the cache, see [`LruCache::clear`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncLruCache, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = AsyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// futures_executor::block_on(async {
+    ///     cache.put(1, "a").await;
+    ///     cache.clear().await;
+    ///     assert!(cache.is_empty().await);
+    /// });
+    /// ```
+    pub async fn clear(&self) {
+        self.lock().await.clear()
+    }
+
+    /// Returns the number of key-value pairs that are guaranteed to be in the cache, see
+    /// [`LruCache::len`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncLruCache, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = AsyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// futures_executor::block_on(async {
+    ///     cache.put(1, "a").await;
+    ///     assert_eq!(cache.len().await, 1);
+    /// });
+    /// ```
+    pub async fn len(&self) -> usize {
+        self.lock().await.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncLruCache, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = AsyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// futures_executor::block_on(async {
+    ///     assert!(cache.is_empty().await);
+    ///     cache.put(1, "a").await;
+    ///     assert!(!cache.is_empty().await);
+    /// });
+    /// ```
+    pub async fn is_empty(&self) -> bool {
+        self.lock().await.is_empty()
+    }
+
+    /// Returns the hit, miss and flip counts of the cache. Only the lookups through this
+    /// wrapper are counted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncLruCache, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = AsyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// futures_executor::block_on(async {
+    ///     cache.put(1, "a").await;
+    ///     cache.get(&1).await;
+    ///     cache.get(&2).await;
+    ///     let stats = cache.stats().await;
+    ///     assert_eq!((stats.hits, stats.misses), (1, 1));
+    /// });
+    /// ```
+    pub async fn stats(&self) -> CacheStats {
+        self.counters.stats(self.lock().await.get_flips())
+    }
+
+    /// Returns metric on the number of times the cache flipped, see
+    /// [`LruCache::get_flips`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncLruCache, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = AsyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// futures_executor::block_on(async {
+    ///     for i in 0..5 {
+    ///         cache.put(i, i).await;
+    ///     }
+    ///     assert_eq!(cache.get_flips().await, 2);
+    /// });
+    /// ```
+    pub async fn get_flips(&self) -> usize {
+        self.lock().await.get_flips()
+    }
+
+    /// Returns the wrapped cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{AsyncLruCache, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = AsyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    /// futures_executor::block_on(cache.put(1, "a"));
+    /// let mut cache = cache.into_inner();
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// ```
+    pub fn into_inner(self) -> LruCache<K, V> {
+        self.cache.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AsyncLruCache;
+    use crate::LruCache;
+    use core::num::NonZeroUsize;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use core::time::Duration;
+    use futures_executor::block_on;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_async_cache_between_threads() {
+        let cache = Arc::new(AsyncLruCache::new(LruCache::new(
+            NonZeroUsize::new(100).unwrap(),
+        )));
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let cache = cache.clone();
+                thread::spawn(move || {
+                    block_on(async {
+                        for i in 0..50 {
+                            cache.put(t * 50 + i, i).await;
+                        }
+                    })
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        // The last 100 puts are guaranteed to be in the cache
+        block_on(async {
+            assert_eq!(cache.len().await, 100);
+            assert_eq!(cache.get_flips().await, 1);
+        });
+    }
+
+    #[test]
+    fn test_guard_across_await() {
+        let cache = AsyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+        block_on(async {
+            let mut guard = cache.lock().await;
+            guard.put(1, "a");
+            async {}.await;
+            assert_eq!(guard.get(&1), Some(&"a"));
+            drop(guard);
+
+            assert_eq!(cache.get(&1).await, Some("a"));
+            assert_eq!(cache.get(&2).await, None);
+            let stats = cache.stats().await;
+            assert_eq!((stats.hits, stats.misses), (1, 1));
+        });
+    }
+
+    #[test]
+    fn test_coalesced_loads() {
+        let cache = Arc::new(AsyncLruCache::new(LruCache::new(
+            NonZeroUsize::new(2).unwrap(),
+        )));
+        let loads = Arc::new(AtomicUsize::new(0));
+        let barrier = Arc::new(Barrier::new(8));
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let (cache, loads, barrier) = (cache.clone(), loads.clone(), barrier.clone());
+                thread::spawn(move || {
+                    barrier.wait();
+                    block_on(cache.get_or_insert_with(1, async {
+                        loads.fetch_add(1, Ordering::Relaxed);
+                        thread::sleep(Duration::from_millis(50));
+                        10
+                    }))
+                })
+            })
+            .collect();
+        for t in threads {
+            assert_eq!(t.join().unwrap(), 10);
+        }
+        assert_eq!(loads.load(Ordering::Relaxed), 1);
+
+        // A panicking load hands the load to the next caller
+        let c = cache.clone();
+        assert!(
+            thread::spawn(move || block_on(c.get_or_insert_with(2, async { panic!() })))
+                .join()
+                .is_err()
+        );
+        block_on(async {
+            assert_eq!(cache.get_or_insert_with(2, async { 20 }).await, 20);
+            assert_eq!(cache.get(&2).await, Some(20));
+        });
+    }
+}
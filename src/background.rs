@@ -0,0 +1,140 @@
+use crate::LruCache;
+use alloc::boxed::Box;
+use allocator_api2::alloc::Allocator;
+use core::hash::{BuildHasher, Hash};
+use std::sync::mpsc;
+use std::thread;
+
+impl<K, V, S, A> LruCache<K, V, S, A>
+where
+    K: Hash + Eq + Send + 'static,
+    V: Send + 'static,
+    S: BuildHasher + Clone + Send + 'static,
+    A: Allocator + Clone + Send + 'static,
+{
+    /// Drops the discarded generation on a dedicated thread instead of in the put that
+    /// flips, which would otherwise drop up to `cap` values inline. The thread exits when
+    /// the cache is dropped.
+    ///
+    /// The discarded map is sent along with its items, so the next generation allocates a
+    /// new one. The items are still dropped at the flip when they are handed to an eviction
+    /// listener, a spill sink, an event hook or a flush, or when they are wiped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread cannot be spawned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_drop_thread();
+    ///
+    /// for i in 0..5 {
+    ///     cache.put(i, vec![i; 1024]);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// assert_eq!(cache.get(&4), Some(&vec![4; 1024]));
+    /// ```
+    pub fn with_drop_thread(mut self) -> LruCache<K, V, S, A> {
+        let (tx, rx) = mpsc::channel();
+        thread::Builder::new()
+            .name("fliplru-drop".into())
+            .spawn(move || rx.into_iter().for_each(drop))
+            .expect("failed to spawn the drop thread");
+        self.offload = Some(Box::new(move |map| {
+            // The thread only stops once the sender is dropped
+            let _ = tx.send(map);
+        }));
+        self
+    }
+
+    /// Like [`with_drop_thread`](LruCache::with_drop_thread), but hands the drop of the
+    /// discarded generation as a job to the executor, e.g., a thread pool.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::thread;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap())
+    ///     .with_drop_executor(|job| drop(thread::spawn(job)));
+    ///
+    /// for i in 0..5 {
+    ///     cache.put(i, vec![i; 1024]);
+    /// }
+    /// assert_eq!(cache.get(&4), Some(&vec![4; 1024]));
+    /// ```
+    pub fn with_drop_executor<F>(mut self, executor: F) -> LruCache<K, V, S, A>
+    where
+        F: Fn(Box<dyn FnOnce() + Send>) + Send + Sync + 'static,
+    {
+        self.offload = Some(Box::new(move |map| executor(Box::new(move || drop(map)))));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::LruCache;
+    use alloc::boxed::Box;
+    use alloc::string::String;
+    use alloc::sync::Arc;
+    use core::num::NonZeroUsize;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use core::time::Duration;
+    use std::sync::mpsc;
+    use std::thread;
+
+    struct Tracked(mpsc::Sender<Option<String>>);
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            let name = thread::current().name().map(String::from);
+            let _ = self.0.send(name);
+        }
+    }
+
+    #[test]
+    fn test_drop_thread() {
+        let (tx, rx) = mpsc::channel();
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_drop_thread();
+        for i in 0..5 {
+            cache.put(i, Tracked(tx.clone()));
+        }
+        let timeout = Duration::from_secs(5);
+        for _ in 0..2 {
+            let name = rx.recv_timeout(timeout).unwrap();
+            assert_eq!(name.as_deref(), Some("fliplru-drop"));
+        }
+
+        // An eviction listener receives the items at the flip
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap())
+            .with_drop_thread()
+            .with_eviction_listener(|_, _, _| {});
+        for i in 0..5 {
+            cache.put(i, Tracked(tx.clone()));
+        }
+        let name = rx.recv_timeout(timeout).unwrap();
+        assert_ne!(name.as_deref(), Some("fliplru-drop"));
+    }
+
+    #[test]
+    fn test_drop_executor() {
+        let jobs = Arc::new(AtomicUsize::new(0));
+        let counted = Arc::clone(&jobs);
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_drop_executor(
+            move |job: Box<dyn FnOnce() + Send>| {
+                counted.fetch_add(1, Ordering::Relaxed);
+                job();
+            },
+        );
+        for i in 0..9 {
+            cache.put(i, i);
+        }
+        assert_eq!(cache.get_flips(), 4);
+        assert_eq!(jobs.load(Ordering::Relaxed), 3);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{Entry, LruCache, Priority};
+use borsh::io::{Error, ErrorKind, Read, Result, Write};
+use borsh::{BorshDeserialize, BorshSerialize};
+use core::hash::Hash;
+use core::num::NonZeroUsize;
+use core::{cmp, mem};
+use hashbrown::HashMap;
+
+/// Like the serde support, the capacities, the flip metric and the items of both tiers
+/// are written, as `u64`s followed by two sequences of key-value pairs. The items are
+/// written straight from the maps.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::LruCache;
+/// use std::num::NonZeroUsize;
+/// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+///
+/// cache.put(1u32, 10u32);
+/// cache.put(2, 20);
+/// cache.put(3, 30);
+/// let bytes = borsh::to_vec(&cache).unwrap();
+/// let mut cache: LruCache<u32, u32> = borsh::from_slice(&bytes).unwrap();
+/// assert_eq!(cache.get(&1), Some(&10));
+/// assert_eq!(cache.get_flips(), 1);
+/// ```
+impl<K: Hash + Eq + BorshSerialize, V: BorshSerialize> BorshSerialize for LruCache<K, V> {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        (self.hot_cap as u64).serialize(writer)?;
+        (self.cold_cap as u64).serialize(writer)?;
+        (self.flips as u64).serialize(writer)?;
+        write_items(&self.l1_map, writer)?;
+        write_items(&self.l2_map, writer)
+    }
+}
+
+/// Deserialization fails if a capacity is zero or larger than a map could hold, or if a key
+/// appears more than once. A tier may hold more items than its capacity, as the tiers of a
+/// cache that flips manually do. The maps are only allocated for the items read, like the
+/// ones of [`LruCache::new_growable`], whatever the capacities.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::LruCache;
+///
+/// // A zero capacity
+/// let bytes = borsh::to_vec(&(0u64, 1u64, 0u64, Vec::<(u32, u32)>::new(), Vec::<(u32, u32)>::new())).unwrap();
+/// assert!(borsh::from_slice::<LruCache<u32, u32>>(&bytes).is_err());
+/// ```
+impl<K: Hash + Eq + BorshDeserialize, V: BorshDeserialize> BorshDeserialize for LruCache<K, V> {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let hot_cap = read_cap::<K, V, R>(reader)?;
+        let cold_cap = read_cap::<K, V, R>(reader)?;
+        let flips = u64::deserialize_reader(reader)?;
+        let mut cache = LruCache::restoring(hot_cap, cold_cap);
+        cache.flips = usize::try_from(flips).map_err(|_| invalid("the flips overflow"))?;
+        read_items(&mut cache.l1_map, reader)?;
+        read_items(&mut cache.l2_map, reader)?;
+        if cache.l2_map.keys().any(|k| cache.l1_map.contains_key(k)) {
+            return Err(invalid("a key is in both tiers"));
+        }
+        Ok(cache)
+    }
+}
+
+// Writes the items of a map like a `Vec` of key-value pairs.
+fn write_items<K: BorshSerialize, V: BorshSerialize, W: Write>(
+    map: &HashMap<K, Entry<V>>,
+    writer: &mut W,
+) -> Result<()> {
+    let len = u32::try_from(map.len()).map_err(|_| invalid("too many items"))?;
+    len.serialize(writer)?;
+    for (k, e) in map {
+        k.serialize(writer)?;
+        e.value.serialize(writer)?;
+    }
+    Ok(())
+}
+
+fn read_items<K: Hash + Eq + BorshDeserialize, V: BorshDeserialize, R: Read>(
+    map: &mut HashMap<K, Entry<V>>,
+    reader: &mut R,
+) -> Result<()> {
+    let len = u32::deserialize_reader(reader)? as usize;
+    for _ in 0..len {
+        let k = K::deserialize_reader(reader)?;
+        let v = V::deserialize_reader(reader)?;
+        if map.insert(k, Entry::new(v, Priority::Normal)).is_some() {
+            return Err(invalid("a key appears more than once"));
+        }
+    }
+    Ok(())
+}
+
+// Reads a capacity, which is at most the number of items that a map could ever allocate.
+fn read_cap<K, V, R: Read>(reader: &mut R) -> Result<NonZeroUsize> {
+    let cap = u64::deserialize_reader(reader)?;
+    let max = isize::MAX as usize / cmp::max(mem::size_of::<(K, Entry<V>)>(), 1);
+    usize::try_from(cap)
+        .ok()
+        .filter(|&cap| cap <= max)
+        .and_then(NonZeroUsize::new)
+        .ok_or_else(|| invalid("a capacity is zero or too large"))
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FlipMode, LruCache};
+    use alloc::vec;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_round_trip() {
+        let mut cache =
+            LruCache::with_caps(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(4).unwrap());
+        for i in 0..5u32 {
+            cache.put(i, i * 10);
+        }
+        let bytes = borsh::to_vec(&cache).unwrap();
+        let copy: LruCache<u32, u32> = borsh::from_slice(&bytes).unwrap();
+
+        assert_eq!(copy.cold_cap(), 4);
+        assert_eq!(copy.get_flips(), 2);
+        for i in 0..5 {
+            assert_eq!(copy.peek_entry(&i), cache.peek_entry(&i));
+        }
+
+        // The same key in both tiers is rejected
+        let dup =
+            borsh::to_vec(&(2u64, 2u64, 0u64, vec![(1u32, 1u32)], vec![(1u32, 2u32)])).unwrap();
+        assert!(borsh::from_slice::<LruCache<u32, u32>>(&dup).is_err());
+    }
+
+    #[test]
+    fn test_untrusted_caps() {
+        // The maps are not allocated for the capacities
+        let mut cache = LruCache::new_growable(NonZeroUsize::new(1 << 40).unwrap());
+        cache.put(1u32, 10u32);
+        let bytes = borsh::to_vec(&cache).unwrap();
+        let copy: LruCache<u32, u32> = borsh::from_slice(&bytes).unwrap();
+        assert_eq!(copy.cap().get(), 1 << 40);
+        assert!(copy.l1_map.capacity() < 1 << 10);
+
+        let none = vec![(0u32, 0u32); 0];
+        let huge = borsh::to_vec(&(u64::MAX, 2u64, 0u64, &none, &none)).unwrap();
+        let err = borsh::from_slice::<LruCache<u32, u32>>(&huge)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), borsh::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_round_trip_over_capacity() {
+        let mut cache =
+            LruCache::new(NonZeroUsize::new(2).unwrap()).with_flip_mode(FlipMode::Manual);
+        for i in 0..5u32 {
+            cache.put(i, i * 10);
+        }
+        let bytes = borsh::to_vec(&cache).unwrap();
+        let copy: LruCache<u32, u32> = borsh::from_slice(&bytes).unwrap();
+        for i in 0..5 {
+            assert_eq!(copy.peek(&i), Some(&(i * 10)));
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use crate::Cache;
+use bytes::Bytes;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::mem;
+use core::num::NonZeroUsize;
+use hashbrown::HashMap;
+
+/// A cache of [`Bytes`] with the flip semantics of [`LruCache`](crate::LruCache) whose
+/// capacity is in bytes instead of items, e.g., for HTTP bodies or objects of a proxy. The
+/// main cache flips when the next value does not fit into its `cap` bytes, so the cache
+/// holds at most `2*cap` bytes of values.
+///
+/// Lookups return clones of the buffers, which share the memory. A value of more than
+/// `cap` bytes is not cached.
+///
+/// # Example
+///
+/// ```
+/// use bytes::Bytes;
+/// use fliplru::BytesCache;
+/// use std::num::NonZeroUsize;
+/// let mut bodies = BytesCache::new(NonZeroUsize::new(64 * 1024).unwrap());
+///
+/// bodies.put("/index.html", Bytes::from_static(b"<html></html>"));
+/// assert_eq!(bodies.get("/index.html").unwrap().len(), 13);
+/// assert_eq!(bodies.size(), 13);
+/// ```
+pub struct BytesCache<K> {
+    l1_map: HashMap<K, Bytes>,
+    l2_map: HashMap<K, Bytes>,
+    l1_size: usize,
+    l2_size: usize,
+    cap: NonZeroUsize,
+    flips: usize,
+}
+
+impl<K: Hash + Eq> BytesCache<K> {
+    /// Creates a new cache whose main cache holds `cap` bytes of values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::BytesCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: BytesCache<String> = BytesCache::new(NonZeroUsize::new(1 << 20).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> BytesCache<K> {
+        BytesCache {
+            l1_map: HashMap::new(),
+            l2_map: HashMap::new(),
+            l1_size: 0,
+            l2_size: 0,
+            cap,
+            flips: 0,
+        }
+    }
+
+    /// Returns a clone of the value of the key in the cache or `None` if it is not present
+    /// in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use bytes::Bytes;
+    /// use fliplru::BytesCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = BytesCache::new(NonZeroUsize::new(4).unwrap());
+    ///
+    /// cache.put(1, Bytes::from_static(b"ab"));
+    /// cache.put(2, Bytes::from_static(b"cd"));
+    /// cache.put(3, Bytes::from_static(b"ef"));
+    /// assert_eq!(cache.get(&1), Some(Bytes::from_static(b"ab")));
+    /// assert_eq!(cache.get(&4), None);
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<Bytes>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if let Some(v) = self.l1_map.get(k) {
+            return Some(v.clone());
+        }
+        // A hit in the backup cache moves the value back into the main cache
+        let (rk, rv) = self.l2_map.remove_entry(k)?;
+        self.l2_size -= rv.len();
+        self.insert(rk, rv.clone());
+        Some(rv)
+    }
+
+    /// Returns a clone of the value of the key without updating the LRU list, or `None` if
+    /// it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use bytes::Bytes;
+    /// use fliplru::BytesCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = BytesCache::new(NonZeroUsize::new(4).unwrap());
+    ///
+    /// cache.put(1, Bytes::from_static(b"ab"));
+    /// assert_eq!(cache.peek(&1), Some(Bytes::from_static(b"ab")));
+    /// ```
+    pub fn peek<Q>(&self, k: &Q) -> Option<Bytes>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.l1_map.get(k).or_else(|| self.l2_map.get(k)).cloned()
+    }
+
+    /// Puts a key-value pair into cache. If the key already exists in the cache, then it updates
+    /// the key's value and returns the old value. Otherwise, `None` is returned. A value of
+    /// more than `cap` bytes is not cached, but it still removes the old value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use bytes::Bytes;
+    /// use fliplru::BytesCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = BytesCache::new(NonZeroUsize::new(4).unwrap());
+    ///
+    /// assert_eq!(None, cache.put(1, Bytes::from_static(b"a")));
+    /// assert_eq!(Some(Bytes::from_static(b"a")), cache.put(1, Bytes::from_static(b"alpha")));
+    /// assert_eq!(cache.get(&1), None);
+    /// ```
+    pub fn put(&mut self, k: K, v: Bytes) -> Option<Bytes> {
+        let old = self.pop(&k);
+        if v.len() <= self.cap.get() {
+            self.insert(k, v);
+        }
+        old
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it was not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use bytes::Bytes;
+    /// use fliplru::BytesCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = BytesCache::new(NonZeroUsize::new(4).unwrap());
+    ///
+    /// cache.put(1, Bytes::from_static(b"ab"));
+    /// assert_eq!(cache.pop(&1), Some(Bytes::from_static(b"ab")));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<Bytes>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if let Some(v) = self.l1_map.remove(k) {
+            self.l1_size -= v.len();
+            return Some(v);
+        }
+        let v = self.l2_map.remove(k)?;
+        self.l2_size -= v.len();
+        Some(v)
+    }
+
+    /// Returns the number of bytes of the values in the main and the backup cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use bytes::Bytes;
+    /// use fliplru::BytesCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = BytesCache::new(NonZeroUsize::new(4).unwrap());
+    /// cache.put(1, Bytes::from_static(b"abc"));
+    /// cache.put(2, Bytes::from_static(b"de"));
+    /// assert_eq!(cache.size(), 5);
+    /// ```
+    pub fn size(&self) -> usize {
+        self.l1_size + self.l2_size
+    }
+
+    /// Returns the capacity of the main cache in bytes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::BytesCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: BytesCache<u64> = BytesCache::new(NonZeroUsize::new(4).unwrap());
+    /// assert_eq!(cache.cap().get(), 4);
+    /// ```
+    pub fn cap(&self) -> NonZeroUsize {
+        self.cap
+    }
+
+    /// Returns the number of key-value pairs in the main and the backup cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use bytes::Bytes;
+    /// use fliplru::BytesCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = BytesCache::new(NonZeroUsize::new(4).unwrap());
+    /// cache.put(1, Bytes::from_static(b"abc"));
+    /// cache.put(2, Bytes::from_static(b"de"));
+    /// assert_eq!(cache.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.l1_map.len() + self.l2_map.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use bytes::Bytes;
+    /// use fliplru::BytesCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = BytesCache::new(NonZeroUsize::new(4).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put(1, Bytes::from_static(b"a"));
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.l1_map.is_empty() && self.l2_map.is_empty()
+    }
+
+    /// Returns metric on the number of times the main cache became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use bytes::Bytes;
+    /// use fliplru::BytesCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = BytesCache::new(NonZeroUsize::new(4).unwrap());
+    /// for i in 0..5 {
+    ///     cache.put(i, Bytes::from_static(b"ab"));
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.flips
+    }
+
+    // Inserts a key that is in neither map, flipping first if the value does not fit into
+    // the main cache.
+    fn insert(&mut self, k: K, v: Bytes) {
+        if self.l1_size + v.len() > self.cap.get() {
+            self.flips += 1;
+            // The emptied backup map is reused for the next generation
+            mem::swap(&mut self.l1_map, &mut self.l2_map);
+            self.l1_map.clear();
+            self.l2_size = mem::take(&mut self.l1_size);
+        }
+        self.l1_size += v.len();
+        self.l1_map.insert(k, v);
+    }
+}
+
+impl<K: Hash + Eq> Cache<K, Bytes> for BytesCache<K> {
+    fn get(&mut self, k: &K) -> Option<Bytes> {
+        BytesCache::get(self, k)
+    }
+
+    fn put(&mut self, k: K, v: Bytes) -> Option<Bytes> {
+        BytesCache::put(self, k, v)
+    }
+
+    fn pop(&mut self, k: &K) -> Option<Bytes> {
+        BytesCache::pop(self, k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BytesCache;
+    use alloc::vec;
+    use bytes::Bytes;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_byte_capacity() {
+        let mut cache = BytesCache::new(NonZeroUsize::new(10).unwrap());
+        cache.put("a", Bytes::from(vec![0u8; 6]));
+        cache.put("b", Bytes::from(vec![1u8; 4]));
+        assert_eq!(cache.get_flips(), 0);
+
+        // The main cache is full, so the next byte moves a and b into the backup cache
+        cache.put("c", Bytes::from_static(b"c"));
+        assert_eq!(cache.get_flips(), 1);
+        assert_eq!(cache.size(), 11);
+
+        // A hit on a moves it back into the main cache, so the next flip discards b
+        assert_eq!(cache.get("a").map(|v| v.len()), Some(6));
+        cache.put("d", Bytes::from_static(b"ddd"));
+        cache.put("e", Bytes::from_static(b"e"));
+        assert_eq!(cache.get_flips(), 2);
+        assert_eq!(cache.peek("b"), None);
+        assert_eq!(cache.size(), 11);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::LruCache;
+use core::hash::Hash;
+
+/// The cache API shared by the caches of this crate, so that they can be composed, e.g.,
+/// with [`Layered`](crate::Layered). Lookups return clones of the values, as the shared
+/// caches cannot hand out references.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::{Cache, LruCache};
+/// use std::num::NonZeroUsize;
+///
+/// fn warm<C: Cache<u32, u32>>(cache: &mut C) {
+///     for i in 0..4 {
+///         cache.put(i, i * 10);
+///     }
+/// }
+///
+/// let mut cache = LruCache::new(NonZeroUsize::new(4).unwrap());
+/// warm(&mut cache);
+/// assert_eq!(Cache::get(&mut cache, &2), Some(20));
+/// ```
+pub trait Cache<K, V> {
+    /// Returns a clone of the value of the key or `None` if it is not present in the cache.
+    fn get(&mut self, k: &K) -> Option<V>;
+
+    /// Puts a key-value pair into the cache and returns the old value of the key, if any.
+    fn put(&mut self, k: K, v: V) -> Option<V>;
+
+    /// Removes the key from the cache and returns its value, if any.
+    fn pop(&mut self, k: &K) -> Option<V>;
+}
+
+impl<K: Hash + Eq, V: Clone> Cache<K, V> for LruCache<K, V> {
+    fn get(&mut self, k: &K) -> Option<V> {
+        LruCache::get(self, k).cloned()
+    }
+
+    fn put(&mut self, k: K, v: V) -> Option<V> {
+        LruCache::put(self, k, v)
+    }
+
+    fn pop(&mut self, k: &K) -> Option<V> {
+        LruCache::pop(self, k)
+    }
+}
+
+impl<K, V, C: Cache<K, V> + ?Sized> Cache<K, V> for &mut C {
+    fn get(&mut self, k: &K) -> Option<V> {
+        (**self).get(k)
+    }
+
+    fn put(&mut self, k: K, v: V) -> Option<V> {
+        (**self).put(k, v)
+    }
+
+    fn pop(&mut self, k: &K) -> Option<V> {
+        (**self).pop(k)
+    }
+}
@@ -0,0 +1,61 @@
+use crate::LruCache;
+use core::fmt;
+use core::hash::Hash;
+use core::num::NonZeroUsize;
+
+/// The error of [`LruCache::new_usize`] for a capacity of 0.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::{LruCache, ZeroCapacityError};
+///
+/// let err = LruCache::<u32, u32>::new_usize(0).err();
+/// assert_eq!(err, Some(ZeroCapacityError));
+/// assert_eq!(err.unwrap().to_string(), "the capacity of a cache must not be 0");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ZeroCapacityError;
+
+impl fmt::Display for ZeroCapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the capacity of a cache must not be 0")
+    }
+}
+
+impl std::error::Error for ZeroCapacityError {}
+
+impl<K: Hash + Eq, V> LruCache<K, V> {
+    /// Creates a new LRU Cache like [`new`](LruCache::new) from a plain `usize`, e.g., a
+    /// configuration value, or returns an error if it is 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// let mut cache = LruCache::new_usize(2).unwrap();
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert!(LruCache::<u32, &str>::new_usize(0).is_err());
+    /// ```
+    pub fn new_usize(cap: usize) -> Result<LruCache<K, V>, ZeroCapacityError> {
+        NonZeroUsize::new(cap)
+            .map(LruCache::new)
+            .ok_or(ZeroCapacityError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ZeroCapacityError;
+    use crate::LruCache;
+
+    #[test]
+    fn test_new_usize() {
+        let cache: Result<LruCache<u32, u32>, _> = LruCache::new_usize(0);
+        assert_eq!(cache.err(), Some(ZeroCapacityError));
+        let cache: LruCache<u32, u32> = LruCache::new_usize(3).unwrap();
+        assert_eq!(cache.cap().get(), 3);
+    }
+}
@@ -0,0 +1,97 @@
+use core::borrow::Borrow;
+use core::hash::{Hash, Hasher};
+
+/// A key adapter whose `Hash` and `Eq` ignore the ASCII case of the string, e.g., for
+/// HTTP header names or hostnames. An `AsciiCaseless<String>` key is looked up by any
+/// `&str` wrapped with [`AsciiCaseless::new`], without an allocation.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::{AsciiCaseless, LruCache};
+/// use std::num::NonZeroUsize;
+/// let mut headers = LruCache::new(NonZeroUsize::new(64).unwrap());
+///
+/// headers.put(AsciiCaseless(String::from("content-type")), "text/html");
+/// assert_eq!(headers.get(AsciiCaseless::new("Content-Type")), Some(&"text/html"));
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(transparent)]
+pub struct AsciiCaseless<K: ?Sized>(pub K);
+
+impl AsciiCaseless<str> {
+    /// Wraps a borrowed string to look up an `AsciiCaseless` key.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::AsciiCaseless;
+    /// assert_eq!(AsciiCaseless::new("Host"), AsciiCaseless::new("HOST"));
+    /// ```
+    pub fn new(s: &str) -> &AsciiCaseless<str> {
+        // SAFETY: AsciiCaseless is a transparent wrapper, so it has the layout of a str
+        unsafe { &*(s as *const str as *const AsciiCaseless<str>) }
+    }
+}
+
+impl<K: AsRef<str> + ?Sized> Hash for AsciiCaseless<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Lowercased in chunks, so that the hasher gets few writes
+        let mut buf = [0u8; 64];
+        for chunk in self.0.as_ref().as_bytes().chunks(buf.len()) {
+            let lower = &mut buf[..chunk.len()];
+            lower.copy_from_slice(chunk);
+            lower.make_ascii_lowercase();
+            state.write(lower);
+        }
+        // The same terminator as str, so that consecutive keys do not run together
+        state.write_u8(0xff);
+    }
+}
+
+impl<K: AsRef<str> + ?Sized, L: AsRef<str> + ?Sized> PartialEq<AsciiCaseless<L>>
+    for AsciiCaseless<K>
+{
+    fn eq(&self, other: &AsciiCaseless<L>) -> bool {
+        self.0.as_ref().eq_ignore_ascii_case(other.0.as_ref())
+    }
+}
+
+impl<K: AsRef<str> + ?Sized> Eq for AsciiCaseless<K> {}
+
+impl<K: AsRef<str>> Borrow<AsciiCaseless<str>> for AsciiCaseless<K> {
+    fn borrow(&self) -> &AsciiCaseless<str> {
+        AsciiCaseless::new(self.0.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AsciiCaseless;
+    use crate::LruCache;
+    use alloc::string::String;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_caseless_lookup() {
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        cache.put(AsciiCaseless(String::from("Example.COM")), 1);
+        // A put under another case replaces the value of the same key
+        assert_eq!(
+            cache.put(AsciiCaseless(String::from("example.com")), 2),
+            Some(1)
+        );
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(AsciiCaseless::new("EXAMPLE.com")), Some(&2));
+        assert_eq!(cache.get(AsciiCaseless::new("example.org")), None);
+
+        // The long keys are hashed and compared past the first chunk
+        let long = "x".repeat(100);
+        cache.put(AsciiCaseless(long.clone() + "A"), 3);
+        assert_eq!(
+            cache.pop(AsciiCaseless::new(&(long.clone() + "a"))),
+            Some(3)
+        );
+        assert_eq!(cache.pop(AsciiCaseless::new(&(long + "b"))), None);
+    }
+}
@@ -0,0 +1,74 @@
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+/// A monotonic clock used by the time based features of the caches.
+///
+/// It is implemented for closures returning a [`Duration`], which makes it easy to plug
+/// in the timer of a platform without `std`.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::Clock;
+/// use std::time::Duration;
+///
+/// let clock = || Duration::from_secs(42);
+/// assert_eq!(clock.now(), Duration::from_secs(42));
+/// ```
+pub trait Clock {
+    /// Returns the time elapsed since an arbitrary but fixed point in time.
+    fn now(&self) -> Duration;
+}
+
+impl<F: Fn() -> Duration> Clock for F {
+    fn now(&self) -> Duration {
+        self()
+    }
+}
+
+/// A [`Clock`] that measures the time elapsed since its creation with an [`Instant`].
+///
+/// # Example
+///
+/// ```
+/// use fliplru::{Clock, InstantClock, LruCache};
+/// use std::num::NonZeroUsize;
+/// use std::time::Duration;
+/// let clock = InstantClock::new();
+/// assert!(clock.now() < Duration::from_secs(60));
+///
+/// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap())
+///     .with_flip_interval(Duration::from_secs(60), clock);
+/// cache.put(1, "a");
+/// assert_eq!(cache.get(&1), Some(&"a"));
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug)]
+pub struct InstantClock {
+    start: Instant,
+}
+
+#[cfg(feature = "std")]
+impl InstantClock {
+    /// Creates a clock that starts at the current instant.
+    pub fn new() -> InstantClock {
+        InstantClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for InstantClock {
+    fn default() -> InstantClock {
+        InstantClock::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for InstantClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
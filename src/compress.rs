@@ -0,0 +1,399 @@
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::mem;
+use core::num::NonZeroUsize;
+use hashbrown::HashMap;
+use lz4_flex::block::{compress_prepend_size, decompress_size_prepended};
+
+// Values of fewer bytes are not worth the time of compressing them
+const DEFAULT_THRESHOLD: usize = 1024;
+
+// A value as it is held by the cache, with the length of the raw value.
+struct Stored {
+    data: Vec<u8>,
+    raw_len: usize,
+}
+
+impl Stored {
+    fn is_compressed(&self) -> bool {
+        self.data.len() != self.raw_len
+    }
+
+    fn decompress(&self) -> Cow<'_, [u8]> {
+        if self.is_compressed() {
+            Cow::Owned(self.raw())
+        } else {
+            Cow::Borrowed(&self.data)
+        }
+    }
+
+    fn into_raw(self) -> Vec<u8> {
+        if self.is_compressed() {
+            self.raw()
+        } else {
+            self.data
+        }
+    }
+
+    fn raw(&self) -> Vec<u8> {
+        decompress_size_prepended(&self.data).expect("the value was compressed by the cache")
+    }
+}
+
+/// The memory use of the values of a [`CompressedCache`].
+///
+/// # Example
+///
+/// ```
+/// use fliplru::CompressionStats;
+///
+/// let stats = CompressionStats { raw_bytes: 4096, stored_bytes: 1024, compressed: 1 };
+/// assert_eq!(stats.ratio(), 4.0);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CompressionStats {
+    /// The number of bytes of the values before compression.
+    pub raw_bytes: usize,
+    /// The number of bytes that the cache holds for the values.
+    pub stored_bytes: usize,
+    /// The number of values that are held compressed.
+    pub compressed: usize,
+}
+
+impl CompressionStats {
+    /// Returns how many times smaller the values are in the cache, or 1 if it is empty.
+    pub fn ratio(&self) -> f64 {
+        match self.stored_bytes {
+            0 => 1.0,
+            stored => self.raw_bytes as f64 / stored as f64,
+        }
+    }
+
+    fn add(&mut self, v: &Stored) {
+        self.raw_bytes += v.raw_len;
+        self.stored_bytes += v.data.len();
+        self.compressed += usize::from(v.is_compressed());
+    }
+
+    fn remove(&mut self, v: &Stored) {
+        self.raw_bytes -= v.raw_len;
+        self.stored_bytes -= v.data.len();
+        self.compressed -= usize::from(v.is_compressed());
+    }
+}
+
+/// A cache of byte values with the flip semantics of [`LruCache`](crate::LruCache) that
+/// compresses the large values with LZ4 when they are put and decompresses them when they
+/// are accessed, e.g., for JSON blobs when memory is the constraint.
+///
+/// Values of at least 1 KiB are compressed by default, see
+/// [`with_threshold`](CompressedCache::with_threshold), and kept as they are if they do
+/// not compress. [`memory`](CompressedCache::memory) reports the raw and the stored bytes.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::CompressedCache;
+/// use std::num::NonZeroUsize;
+/// let mut cache = CompressedCache::new(NonZeroUsize::new(1024).unwrap());
+///
+/// let blob = br#"{"id":1,"tags":["a","b"]}"#.repeat(100);
+/// cache.put("user:1", blob.clone());
+/// assert_eq!(cache.get("user:1").as_deref(), Some(&blob[..]));
+/// assert!(cache.memory().stored_bytes < blob.len() / 4);
+/// ```
+pub struct CompressedCache<K> {
+    l1_map: HashMap<K, Stored>,
+    l2_map: HashMap<K, Stored>,
+    l1_memory: CompressionStats,
+    l2_memory: CompressionStats,
+    cap: NonZeroUsize,
+    threshold: usize,
+    flips: usize,
+}
+
+impl<K: Hash + Eq> CompressedCache<K> {
+    /// Creates a new LRU Cache that holds at least `cap` values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CompressedCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: CompressedCache<u64> = CompressedCache::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> CompressedCache<K> {
+        CompressedCache {
+            l1_map: HashMap::with_capacity(cap.get()),
+            l2_map: HashMap::new(),
+            l1_memory: CompressionStats::default(),
+            l2_memory: CompressionStats::default(),
+            cap,
+            threshold: DEFAULT_THRESHOLD,
+            flips: 0,
+        }
+    }
+
+    /// Sets the number of bytes from which the values are compressed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CompressedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = CompressedCache::new(NonZeroUsize::new(2).unwrap()).with_threshold(64);
+    ///
+    /// cache.put(1, vec![0; 100]);
+    /// assert_eq!(cache.memory().compressed, 1);
+    /// ```
+    pub fn with_threshold(mut self, bytes: usize) -> CompressedCache<K> {
+        self.threshold = bytes;
+        self
+    }
+
+    /// Returns the value of the key in the cache, decompressed if needed, or `None` if it
+    /// is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CompressedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = CompressedCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, b"a".to_vec());
+    /// cache.put(2, b"b".to_vec());
+    /// cache.put(3, b"c".to_vec());
+    /// assert_eq!(cache.get(&1).as_deref(), Some(&b"a"[..]));
+    /// assert_eq!(cache.get(&4), None);
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<Cow<'_, [u8]>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if !self.l1_map.contains_key(k) {
+            // A hit in the backup cache moves the value back into the main cache
+            let (rk, rv) = self.l2_map.remove_entry(k)?;
+            self.l2_memory.remove(&rv);
+            self.insert(rk, rv);
+        }
+        self.l1_map.get(k).map(Stored::decompress)
+    }
+
+    /// Returns the value of the key without updating the LRU list, or `None` if it is not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CompressedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = CompressedCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, b"a".to_vec());
+    /// assert_eq!(cache.peek(&1).as_deref(), Some(&b"a"[..]));
+    /// ```
+    pub fn peek<Q>(&self, k: &Q) -> Option<Cow<'_, [u8]>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let stored = self.l1_map.get(k).or_else(|| self.l2_map.get(k))?;
+        Some(stored.decompress())
+    }
+
+    /// Puts a key-value pair into cache, compressing the value if it is large. If the key
+    /// already exists in the cache, then it updates the key's value and returns the old
+    /// value. Otherwise, `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CompressedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = CompressedCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert_eq!(None, cache.put(1, b"a".to_vec()));
+    /// assert_eq!(Some(b"a".to_vec()), cache.put(1, b"alpha".to_vec()));
+    /// ```
+    pub fn put(&mut self, k: K, v: Vec<u8>) -> Option<Vec<u8>> {
+        let stored = self.compress(v);
+        if let Some(old) = self.l1_map.get_mut(&k) {
+            self.l1_memory.remove(old);
+            self.l1_memory.add(&stored);
+            return Some(mem::replace(old, stored).into_raw());
+        }
+        let old = self.l2_map.remove(&k);
+        if let Some(old) = &old {
+            self.l2_memory.remove(old);
+        }
+        self.insert(k, stored);
+        old.map(Stored::into_raw)
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it was not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CompressedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = CompressedCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, b"a".to_vec());
+    /// assert_eq!(cache.pop(&1), Some(b"a".to_vec()));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<Vec<u8>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let stored = match self.l1_map.remove(k) {
+            Some(v) => {
+                self.l1_memory.remove(&v);
+                v
+            }
+            None => {
+                let v = self.l2_map.remove(k)?;
+                self.l2_memory.remove(&v);
+                v
+            }
+        };
+        Some(stored.into_raw())
+    }
+
+    /// Returns the raw and the stored bytes of the values in the main and the backup
+    /// cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CompressedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = CompressedCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, b"small".to_vec());
+    /// cache.put(2, vec![0; 4096]);
+    /// let memory = cache.memory();
+    /// assert_eq!(memory.raw_bytes, 4101);
+    /// assert_eq!(memory.compressed, 1);
+    /// assert!(memory.ratio() > 10.0);
+    /// ```
+    pub fn memory(&self) -> CompressionStats {
+        CompressionStats {
+            raw_bytes: self.l1_memory.raw_bytes + self.l2_memory.raw_bytes,
+            stored_bytes: self.l1_memory.stored_bytes + self.l2_memory.stored_bytes,
+            compressed: self.l1_memory.compressed + self.l2_memory.compressed,
+        }
+    }
+
+    /// Returns the number of key-value pairs in the main and the backup cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CompressedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = CompressedCache::new(NonZeroUsize::new(2).unwrap());
+    /// cache.put(1, b"a".to_vec());
+    /// cache.put(2, b"b".to_vec());
+    /// cache.put(3, b"c".to_vec());
+    /// assert_eq!(cache.len(), 3);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.l1_map.len() + self.l2_map.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CompressedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = CompressedCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put(1, b"a".to_vec());
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.l1_map.is_empty() && self.l2_map.is_empty()
+    }
+
+    /// Returns metric on the number of times the main cache became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CompressedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = CompressedCache::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     cache.put(i, vec![i]);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.flips
+    }
+
+    fn compress(&self, v: Vec<u8>) -> Stored {
+        let raw_len = v.len();
+        if raw_len >= self.threshold {
+            let data = compress_prepend_size(&v);
+            // A value that does not compress is kept as it is, which tells them apart
+            if data.len() < raw_len {
+                return Stored { data, raw_len };
+            }
+        }
+        Stored { data: v, raw_len }
+    }
+
+    // Inserts a key that is in neither map, flipping first if the main cache is full.
+    fn insert(&mut self, k: K, v: Stored) {
+        if self.l1_map.len() >= self.cap.get() {
+            self.flips += 1;
+            // The emptied backup map is reused for the next generation
+            mem::swap(&mut self.l1_map, &mut self.l2_map);
+            self.l1_map.clear();
+            self.l2_memory = mem::take(&mut self.l1_memory);
+        }
+        self.l1_memory.add(&v);
+        self.l1_map.insert(k, v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CompressedCache;
+    use alloc::vec;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_memory_accounting() {
+        let mut cache = CompressedCache::new(NonZeroUsize::new(2).unwrap()).with_threshold(16);
+        cache.put(1, vec![7u8; 1000]);
+        cache.put(2, vec![1, 2, 3]);
+        let memory = cache.memory();
+        assert_eq!(memory.raw_bytes, 1003);
+        assert_eq!(memory.compressed, 1);
+        assert!(memory.stored_bytes < 100);
+
+        // The flips carry the accounting along with the items and then discard it
+        cache.put(1, vec![8u8; 2000]);
+        cache.put(3, vec![9]);
+        cache.put(4, vec![10]);
+        cache.put(5, vec![11]);
+        assert_eq!(cache.get_flips(), 2);
+        assert_eq!(cache.memory().raw_bytes, 3);
+        assert_eq!(cache.memory().compressed, 0);
+        assert_eq!(cache.pop(&5), Some(vec![11]));
+        assert_eq!(cache.memory().raw_bytes, 2);
+    }
+}
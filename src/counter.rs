@@ -0,0 +1,92 @@
+use crate::LruCache;
+use allocator_api2::alloc::Allocator;
+use core::hash::{BuildHasher, Hash};
+
+/// A value that counts, e.g., the requests per client of a rate limiter, for
+/// [`LruCache::increment`]. It is implemented for the unsigned integers.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::CounterValue;
+///
+/// #[derive(Default)]
+/// struct Hits(u64);
+///
+/// impl CounterValue for Hits {
+///     fn add_saturating(&mut self, by: u64) -> u64 {
+///         self.0 = self.0.saturating_add(by);
+///         self.0
+///     }
+/// }
+/// ```
+pub trait CounterValue: Default {
+    /// Adds to the count, saturating at its maximum, and returns the new count.
+    fn add_saturating(&mut self, by: u64) -> u64;
+}
+
+macro_rules! counter_value {
+    ($($t:ty),*) => {
+        $(
+            impl CounterValue for $t {
+                fn add_saturating(&mut self, by: u64) -> u64 {
+                    *self = self.saturating_add(<$t>::try_from(by).unwrap_or(<$t>::MAX));
+                    *self as u64
+                }
+            }
+        )*
+    };
+}
+
+counter_value!(u8, u16, u32, u64, usize);
+
+impl<K: Hash + Eq, V: CounterValue, S: BuildHasher + Clone, A: Allocator + Clone>
+    LruCache<K, V, S, A>
+{
+    /// Adds to the count of the key, starting from zero if it is not present in the cache,
+    /// and returns the new count. The count saturates at the maximum of `V`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut requests: LruCache<&str, u8> = LruCache::new(NonZeroUsize::new(1024).unwrap());
+    ///
+    /// assert_eq!(requests.increment("10.0.0.1", 1), 1);
+    /// assert_eq!(requests.increment("10.0.0.1", 2), 3);
+    /// assert_eq!(requests.increment("10.0.0.1", 1000), 255);
+    /// ```
+    pub fn increment(&mut self, k: K, by: u64) -> u64 {
+        if let Some(count) = self.get_mut(&k) {
+            return count.add_saturating(by);
+        }
+        let mut count = V::default();
+        let n = count.add_saturating(by);
+        self.put(k, count);
+        n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::LruCache;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_increment() {
+        let mut cache: LruCache<u32, u16> = LruCache::new(NonZeroUsize::new(2).unwrap());
+        assert_eq!(cache.increment(1, 5), 5);
+        cache.put(2, 0);
+        cache.put(3, 0);
+        // The count survives the flip and saturates
+        assert_eq!(cache.increment(1, u64::MAX), u64::from(u16::MAX));
+        assert_eq!(cache.get(&1), Some(&u16::MAX));
+
+        // A discarded count starts over
+        cache.put(4, 0);
+        cache.put(5, 0);
+        cache.put(6, 0);
+        assert_eq!(cache.increment(2, 1), 1);
+    }
+}
@@ -0,0 +1,462 @@
+use alloc::boxed::Box;
+use core::borrow::Borrow;
+use core::cmp;
+use core::hash::{BuildHasher, Hash};
+use core::num::NonZeroUsize;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use crossbeam_epoch::{self as epoch, Atomic, Guard, Owned};
+use hashbrown::hash_map::DefaultHashBuilder;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// An experimental concurrent LRU Cache whose lookups in the hot tier are lock-free.
+///
+/// The hot tier is an insert-only table published with epoch-based reclamation, so a hit
+/// in it only pins the epoch and never takes a lock. Puts, pops, promotions from the cold
+/// tier and flips take the writer lock. The replaced values and the tables discarded by a
+/// flip are freed once no lookup can still see them.
+///
+/// Lookups clone the values, so `K` and `V` are `Clone`. Unlike [`LruCache`], there are
+/// no hooks or configuration.
+///
+/// [`LruCache`]: crate::LruCache
+pub struct EpochLruCache<K, V> {
+    // Read without the lock
+    hot: Atomic<Table<K, V>>,
+    // Only accessed with the lock
+    cold: Atomic<Table<K, V>>,
+    writer: Mutex<Writer>,
+    hasher: DefaultHashBuilder,
+    cap: NonZeroUsize,
+}
+
+struct Writer {
+    // Slots taken in the hot table, including the removed items
+    hot_used: usize,
+    hot_len: usize,
+    cold_len: usize,
+    flips: usize,
+}
+
+// An open addressing table with twice the slots of the capacity, so that the probes stop
+// at an empty slot. Slots are only taken, a removed item leaves its key behind.
+struct Table<K, V> {
+    slots: Box<[Atomic<Slot<K, V>>]>,
+}
+
+struct Slot<K, V> {
+    key: K,
+    // None once removed
+    value: Option<V>,
+}
+
+impl<K: 'static, V: 'static> Table<K, V> {
+    fn new(cap: NonZeroUsize) -> Table<K, V> {
+        let len = (cap.get() * 2).next_power_of_two();
+        Table {
+            slots: (0..len).map(|_| Atomic::null()).collect(),
+        }
+    }
+
+    // Returns the index of the slot of the key, or of the empty slot where it goes.
+    fn find<'g, Q>(&self, hash: u64, k: &Q, guard: &'g Guard) -> (usize, Option<&'g Slot<K, V>>)
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let mask = self.slots.len() - 1;
+        let mut i = hash as usize & mask;
+        loop {
+            // SAFETY: the slots are only freed after the epochs that could see them
+            match unsafe { self.slots[i].load(Acquire, guard).as_ref() } {
+                Some(slot) if slot.key.borrow() != k => i = (i + 1) & mask,
+                slot => return (i, slot),
+            }
+        }
+    }
+
+    fn get<'g, Q>(&self, hash: u64, k: &Q, guard: &'g Guard) -> Option<&'g V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.find(hash, k, guard).1?.value.as_ref()
+    }
+
+    // Replaces the slot and frees the old one once no lookup can see it.
+    fn store(&self, i: usize, slot: Slot<K, V>, guard: &Guard) {
+        let old = self.slots[i].swap(Owned::new(slot), Release, guard);
+        if !old.is_null() {
+            // SAFETY: the old slot is no longer reachable from the table
+            unsafe { guard.defer_destroy(old) };
+        }
+    }
+}
+
+impl<K, V> Drop for Table<K, V> {
+    fn drop(&mut self) {
+        for slot in self.slots.iter() {
+            // SAFETY: a table is dropped once no lookup can see it
+            unsafe {
+                let slot = slot.load(Relaxed, epoch::unprotected());
+                if !slot.is_null() {
+                    drop(slot.into_owned());
+                }
+            }
+        }
+    }
+}
+
+impl<K, V> EpochLruCache<K, V>
+where
+    K: Hash + Eq + Clone + Send + 'static,
+    V: Clone + Send + 'static,
+{
+    /// Creates a new cache that holds at least `cap` items.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::EpochLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: EpochLruCache<isize, &str> = EpochLruCache::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> EpochLruCache<K, V> {
+        EpochLruCache {
+            hot: Atomic::new(Table::new(cap)),
+            cold: Atomic::new(Table::new(cap)),
+            writer: Mutex::new(Writer {
+                hot_used: 0,
+                hot_len: 0,
+                cold_len: 0,
+                flips: 0,
+            }),
+            hasher: DefaultHashBuilder::default(),
+            cap,
+        }
+    }
+
+    /// Calls the function with the value of the key and returns its result, or `None` if
+    /// it is not present in the cache. A hit in the hot tier is lock-free, a hit in the
+    /// cold tier takes the writer lock to promote the item.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::EpochLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = EpochLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put("apple", String::from("red"));
+    /// assert_eq!(cache.get_with(&"apple", |v| v.len()), Some(3));
+    /// assert_eq!(cache.get_with(&"banana", |v| v.len()), None);
+    /// ```
+    pub fn get_with<Q, R, F>(&self, k: &Q, f: F) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&V) -> R,
+    {
+        let hash = self.hasher.hash_one(k);
+        let guard = &epoch::pin();
+        if let Some(v) = self.table(&self.hot, guard).get(hash, k, guard) {
+            return Some(f(v));
+        }
+
+        let mut writer = self.lock();
+        // The item may have been promoted or put while waiting for the lock
+        if let Some(v) = self.table(&self.hot, guard).get(hash, k, guard) {
+            return Some(f(v));
+        }
+        let cold = self.table(&self.cold, guard);
+        let (i, slot) = cold.find(hash, k, guard);
+        let (rk, v) = match slot {
+            Some(Slot {
+                key,
+                value: Some(v),
+            }) => (key.clone(), v.clone()),
+            _ => return None,
+        };
+        let r = f(&v);
+        cold.store(
+            i,
+            Slot {
+                key: rk.clone(),
+                value: None,
+            },
+            guard,
+        );
+        writer.cold_len -= 1;
+        self.insert(&mut writer, hash, rk, v, guard);
+        Some(r)
+    }
+
+    /// Returns a clone of the value of the key or `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::EpochLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = EpochLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some("a"));
+    /// ```
+    pub fn get<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_with(k, V::clone)
+    }
+
+    /// Puts a key-value pair into the hot tier. If the key already exists in the cache,
+    /// then it updates the key's value and returns the old value. Otherwise, `None` is
+    /// returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::EpochLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = EpochLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&self, k: K, v: V) -> Option<V> {
+        let hash = self.hasher.hash_one(&k);
+        let guard = &epoch::pin();
+        let mut writer = self.lock();
+        let hot = self.table(&self.hot, guard);
+        if let (i, Some(slot)) = hot.find(hash, &k, guard) {
+            let old = slot.value.clone();
+            if old.is_none() {
+                writer.hot_len += 1;
+            }
+            hot.store(
+                i,
+                Slot {
+                    key: k,
+                    value: Some(v),
+                },
+                guard,
+            );
+            return old;
+        }
+        let old = self.remove_cold(&mut writer, hash, &k, guard);
+        self.insert(&mut writer, hash, k, v, guard);
+        old
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it is not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::EpochLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = EpochLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hasher.hash_one(k);
+        let guard = &epoch::pin();
+        let mut writer = self.lock();
+        let hot = self.table(&self.hot, guard);
+        if let (i, Some(slot)) = hot.find(hash, k, guard) {
+            if let Some(v) = &slot.value {
+                let v = v.clone();
+                let key = slot.key.clone();
+                hot.store(i, Slot { key, value: None }, guard);
+                writer.hot_len -= 1;
+                return Some(v);
+            }
+        }
+        self.remove_cold(&mut writer, hash, k, guard)
+    }
+
+    fn remove_cold<Q>(&self, writer: &mut Writer, hash: u64, k: &Q, guard: &Guard) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let cold = self.table(&self.cold, guard);
+        let (i, slot) = cold.find(hash, k, guard);
+        let slot = slot?;
+        let v = slot.value.clone()?;
+        let key = slot.key.clone();
+        cold.store(i, Slot { key, value: None }, guard);
+        writer.cold_len -= 1;
+        Some(v)
+    }
+
+    // Inserts an item that is not in the hot table.
+    fn insert(&self, writer: &mut Writer, hash: u64, k: K, v: V, guard: &Guard) {
+        if writer.hot_used == self.cap.get() {
+            self.flip(writer, guard);
+        }
+        let hot = self.table(&self.hot, guard);
+        let (i, _) = hot.find(hash, &k, guard);
+        hot.store(
+            i,
+            Slot {
+                key: k,
+                value: Some(v),
+            },
+            guard,
+        );
+        writer.hot_used += 1;
+        writer.hot_len += 1;
+    }
+
+    // Publishes an empty hot table and makes the old one the cold table.
+    fn flip(&self, writer: &mut Writer, guard: &Guard) {
+        let hot = self
+            .hot
+            .swap(Owned::new(Table::new(self.cap)), Release, guard);
+        let cold = self.cold.swap(hot, Release, guard);
+        // SAFETY: the old cold table is no longer reachable
+        unsafe { guard.defer_destroy(cold) };
+        writer.cold_len = writer.hot_len;
+        writer.hot_len = 0;
+        writer.hot_used = 0;
+        writer.flips += 1;
+    }
+
+    fn table<'g>(&self, table: &Atomic<Table<K, V>>, guard: &'g Guard) -> &'g Table<K, V> {
+        // SAFETY: the tables are never null and only freed after the epochs that could
+        // see them
+        unsafe { table.load(Acquire, guard).deref() }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Writer> {
+        self.writer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the number of key-value pairs that are guaranteed to be in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::EpochLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = EpochLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.len(), 1);
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        let writer = self.lock();
+        cmp::min(writer.hot_len + writer.cold_len, self.cap.get())
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::EpochLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = EpochLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns metric on the number of times the hot tier became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::EpochLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = EpochLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.lock().flips
+    }
+}
+
+impl<K, V> Drop for EpochLruCache<K, V> {
+    fn drop(&mut self) {
+        // SAFETY: no lookup can run while the cache is dropped
+        unsafe {
+            let guard = epoch::unprotected();
+            drop(self.hot.load(Relaxed, guard).into_owned());
+            drop(self.cold.load(Relaxed, guard).into_owned());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EpochLruCache;
+    use core::num::NonZeroUsize;
+    use std::sync::Arc;
+    use std::thread;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_tiers() {
+        let cache = EpochLruCache::new(NonZeroUsize::new(2).unwrap());
+        for i in 0..3 {
+            assert_eq!(cache.put(i, i), None);
+        }
+        assert_eq!(cache.get_flips(), 1);
+
+        // A hit in the cold tier is promoted
+        assert_eq!(cache.get(&0), Some(0));
+        assert_eq!(cache.put(4, 4), None);
+        assert_eq!(cache.get_flips(), 2);
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&0), Some(0));
+
+        // Put and pop reach both tiers
+        assert_eq!(cache.put(2, 20), Some(2));
+        assert_eq!(cache.pop(&4), Some(4));
+        assert_eq!(cache.pop(&4), None);
+        assert_eq!(cache.put(4, 40), None);
+        assert_eq!(cache.get(&4), Some(40));
+    }
+
+    #[test]
+    fn test_concurrent_lookups() {
+        let cache = Arc::new(EpochLruCache::new(NonZeroUsize::new(100).unwrap()));
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let cache = cache.clone();
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        if t == 0 {
+                            cache.put(i % 150, i);
+                        } else if let Some(v) = cache.get(&(i % 150)) {
+                            assert_eq!(v % 150, i % 150);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(cache.len(), 100);
+    }
+}
@@ -0,0 +1,30 @@
+use crate::EvictionReason;
+
+/// The tier of the cache an item was found in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Tier {
+    /// The main cache.
+    Hot,
+    /// The backup cache.
+    Cold,
+}
+
+/// A cache event, see [`LruCache::with_event_channel`](crate::LruCache::with_event_channel).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CacheEvent {
+    /// A put stored a value.
+    Insert,
+    /// A lookup found the key in the given tier.
+    Hit(Tier),
+    /// A lookup did not find the key.
+    Miss,
+    /// The cache flipped and discarded the given number of items.
+    Flip {
+        /// The number of items of the discarded generation.
+        discarded: usize,
+    },
+    /// An item was discarded for the given reason.
+    Eviction(EvictionReason),
+}
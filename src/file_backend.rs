@@ -0,0 +1,178 @@
+use crate::Backend;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+use std::vec::Vec;
+
+/// A [`Backend`] that stores each item in a file of a directory, named after the hash of
+/// its key, e.g., to spill a [`TwoTierCache`](crate::TwoTierCache) to disk.
+///
+/// The keys and values are stored as bytes. The hash is stable across runs, so the files
+/// can be read again by a new process. The keys whose hashes collide are stored in
+/// separate files.
+pub struct FileBackend {
+    dir: PathBuf,
+}
+
+impl FileBackend {
+    /// Creates a backend that stores the items in the directory, creating it if needed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{Backend, FileBackend};
+    ///
+    /// let dir = std::env::temp_dir().join("fliplru-doc-new");
+    /// let mut disk = FileBackend::new(&dir).unwrap();
+    /// disk.store(&"apple", &b"red".to_vec()).unwrap();
+    /// let mut disk = FileBackend::new(&dir).unwrap();
+    /// assert_eq!(disk.load(&"apple").unwrap(), Some(b"red".to_vec()));
+    /// std::fs::remove_dir_all(dir).unwrap();
+    /// ```
+    pub fn new<P: Into<PathBuf>>(dir: P) -> io::Result<FileBackend> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(FileBackend { dir })
+    }
+
+    // The FNV-1a hash of the key, which names its file.
+    fn hash(k: &[u8]) -> u64 {
+        k.iter().fold(0xcbf29ce484222325u64, |h, &b| {
+            (h ^ b as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    // The files of the keys of a hash: the first one is named after the hash and the keys
+    // that collide with it are probed in the next ones.
+    fn slot(&self, hash: u64, i: usize) -> PathBuf {
+        match i {
+            0 => self.dir.join(std::format!("{hash:016x}")),
+            _ => self.dir.join(std::format!("{hash:016x}-{i}")),
+        }
+    }
+
+    // Returns the slot of the key and the bytes of its file, or the first free slot.
+    fn find(&self, hash: u64, k: &[u8]) -> io::Result<(usize, Option<Vec<u8>>)> {
+        let mut i = 0;
+        loop {
+            let bytes = match fs::read(self.slot(hash, i)) {
+                Ok(bytes) => bytes,
+                Err(e) if e.kind() == ErrorKind::NotFound => return Ok((i, None)),
+                Err(e) => return Err(e),
+            };
+            let len = bytes
+                .get(..8)
+                .map(|len| u64::from_le_bytes(len.try_into().unwrap()) as usize)
+                .filter(|&len| bytes.len() >= 8 + len)
+                .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "truncated item file"))?;
+            if bytes[8..8 + len] == *k {
+                return Ok((i, Some(bytes)));
+            }
+            i += 1;
+        }
+    }
+
+    fn load_at(&self, hash: u64, k: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let (_, bytes) = self.find(hash, k)?;
+        Ok(bytes.map(|mut bytes| bytes.split_off(8 + k.len())))
+    }
+
+    fn store_at(&self, hash: u64, k: &[u8], v: &[u8]) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(8 + k.len() + v.len());
+        bytes.extend_from_slice(&(k.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(k);
+        bytes.extend_from_slice(v);
+        // Write a temporary file and rename it so that a crash does not leave half an item
+        let path = self.slot(hash, self.find(hash, k)?.0);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, bytes)?;
+        fs::rename(tmp, path)
+    }
+
+    fn remove_at(&self, hash: u64, k: &[u8]) -> io::Result<()> {
+        let (i, Some(_)) = self.find(hash, k)? else {
+            return Ok(());
+        };
+        // The last file of the probe sequence fills the hole, so that the probing of the
+        // keys after it still finds them
+        let mut last = i;
+        while self.slot(hash, last + 1).try_exists()? {
+            last += 1;
+        }
+        if last == i {
+            fs::remove_file(self.slot(hash, i))
+        } else {
+            fs::rename(self.slot(hash, last), self.slot(hash, i))
+        }
+    }
+}
+
+// A file holds the length of the key, the key and the value.
+impl<K: AsRef<[u8]>, V: AsRef<[u8]> + From<Vec<u8>>> Backend<K, V> for FileBackend {
+    type Error = io::Error;
+
+    fn load(&mut self, k: &K) -> io::Result<Option<V>> {
+        let k = k.as_ref();
+        Ok(self.load_at(Self::hash(k), k)?.map(V::from))
+    }
+
+    fn store(&mut self, k: &K, v: &V) -> io::Result<()> {
+        let k = k.as_ref();
+        self.store_at(Self::hash(k), k, v.as_ref())
+    }
+
+    fn remove(&mut self, k: &K) -> io::Result<()> {
+        let k = k.as_ref();
+        self.remove_at(Self::hash(k), k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileBackend;
+    use crate::{Backend, LruCache, TwoTierCache};
+    use core::num::NonZeroUsize;
+    use std::fs;
+    use std::string::String;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_file_backend() {
+        let dir = std::env::temp_dir().join(std::format!("fliplru-test-{}", std::process::id()));
+        let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        let mut cache = TwoTierCache::new(cache, FileBackend::new(&dir).unwrap());
+        for i in 0..10 {
+            let v: Vec<u8> = std::vec![i; i as usize];
+            cache.put(std::format!("key{i}"), v).unwrap();
+        }
+
+        // The discarded items are read back from the files
+        assert_eq!(
+            cache.get(&String::from("key3")).unwrap(),
+            Some(&std::vec![3; 3])
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 10);
+        cache.remove(&String::from("key3")).unwrap();
+        assert_eq!(cache.get(&String::from("key3")).unwrap(), None);
+        // Removing a missing key is not an error
+        cache.remove(&String::from("key3")).unwrap();
+
+        let mut disk = cache.into_parts().1;
+        let missing: Option<Vec<u8>> = disk.load(&"key10").unwrap();
+        assert_eq!(missing, None);
+
+        // The colliding keys are probed in the next files, and a removal keeps the others
+        disk.store_at(7, b"a", b"1").unwrap();
+        disk.store_at(7, b"b", b"2").unwrap();
+        disk.store_at(7, b"c", b"3").unwrap();
+        disk.store_at(7, b"b", b"22").unwrap();
+        assert_eq!(disk.load_at(7, b"b").unwrap(), Some(b"22".to_vec()));
+        disk.remove_at(7, b"a").unwrap();
+        disk.remove_at(7, b"d").unwrap();
+        assert_eq!(disk.load_at(7, b"a").unwrap(), None);
+        assert_eq!(disk.load_at(7, b"b").unwrap(), Some(b"22".to_vec()));
+        assert_eq!(disk.load_at(7, b"c").unwrap(), Some(b"3".to_vec()));
+        assert!(!disk.slot(7, 2).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
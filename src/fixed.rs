@@ -0,0 +1,387 @@
+use crate::FnvHasher;
+use core::borrow::Borrow;
+use core::hash::{Hash, Hasher};
+use core::{cmp, mem};
+
+/// An LRU Cache with the flip semantics of [`LruCache`](crate::LruCache) whose storage is
+/// two fixed-size tables of `CAP` slots, so that it never allocates, e.g., on targets
+/// without an allocator. It is the only cache left without the default `alloc` feature.
+///
+/// Each tier holds up to `CAP` items. The tables are stored inline, so a large cache
+/// belongs in a `static` rather than on the stack. The keys are hashed with FNV-1a, which
+/// is fast for small keys but not resistant to collision attacks.
+pub struct FixedLruCache<K, V, const CAP: usize> {
+    hot: Table<K, V, CAP>,
+    cold: Table<K, V, CAP>,
+    // The items of a tier, which is CAP unless it is the inline storage of a SmallLruCache
+    limit: usize,
+    flips: usize,
+}
+
+impl<K: Hash + Eq, V, const CAP: usize> FixedLruCache<K, V, CAP> {
+    /// Creates a new cache whose tiers each hold `CAP` items. `CAP` must be at least 1,
+    /// otherwise this fails to compile. It is `const`, so the cache can initialize a
+    /// `static`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FixedLruCache;
+    /// let mut cache: FixedLruCache<isize, &str, 10> = FixedLruCache::new();
+    /// ```
+    pub const fn new() -> FixedLruCache<K, V, CAP> {
+        const { assert!(CAP >= 1, "a FixedLruCache needs at least one slot") };
+        FixedLruCache::with_limit(CAP)
+    }
+
+    // Creates a cache whose tiers each hold `limit` items, which is at most CAP.
+    pub(crate) const fn with_limit(limit: usize) -> FixedLruCache<K, V, CAP> {
+        assert!(limit >= 1 && limit <= CAP);
+        FixedLruCache {
+            hot: Table::new(),
+            cold: Table::new(),
+            limit,
+            flips: 0,
+        }
+    }
+
+    /// Returns a reference to the value of the key in the cache or `None` if it is not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FixedLruCache;
+    /// let mut cache: FixedLruCache<_, _, 2> = FixedLruCache::new();
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert_eq!(cache.get(&4), None);
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_mut(k).map(|v| &*v)
+    }
+
+    /// Returns a mutable reference to the value of the key in the cache or `None` if it
+    /// is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FixedLruCache;
+    /// let mut cache: FixedLruCache<_, _, 2> = FixedLruCache::new();
+    ///
+    /// cache.put("apple", 8);
+    /// *cache.get_mut("apple").unwrap() += 1;
+    /// assert_eq!(cache.get("apple"), Some(&9));
+    /// ```
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if let Some(i) = self.hot.find(k) {
+            return self.hot.value_mut(i);
+        }
+        let (rk, rv) = self.cold.remove(k)?;
+        let i = self.insert(rk, rv);
+        self.hot.value_mut(i)
+    }
+
+    /// Returns a reference to the value of the key without promoting it, or `None` if it
+    /// is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FixedLruCache;
+    /// let mut cache: FixedLruCache<_, _, 2> = FixedLruCache::new();
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.peek(&1), Some(&"a"));
+    /// ```
+    pub fn peek<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.hot.get(k).or_else(|| self.cold.get(k))
+    }
+
+    /// Puts a key-value pair into cache. If the key already exists in the cache, then it updates
+    /// the key's value and returns the old value. Otherwise, `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FixedLruCache;
+    /// let mut cache: FixedLruCache<_, _, 2> = FixedLruCache::new();
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Option<V> {
+        // invalidate any existing entry in the cold tier
+        let ov = self.cold.remove(&k).map(|(_, v)| v);
+        if let Some(i) = self.hot.find(&k) {
+            return self.hot.value_mut(i).map(|old| mem::replace(old, v));
+        }
+        self.insert(k, v);
+        ov
+    }
+
+    // Inserts a key that is not in the hot tier, flipping first if it is full, and returns
+    // its slot.
+    fn insert(&mut self, k: K, v: V) -> usize {
+        if self.hot.len == self.limit {
+            mem::swap(&mut self.hot, &mut self.cold);
+            self.hot.clear();
+            self.flips += 1;
+        }
+        self.hot.insert(k, v)
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it was not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FixedLruCache;
+    /// let mut cache: FixedLruCache<_, _, 2> = FixedLruCache::new();
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (_, v) = self.hot.remove(k).or_else(|| self.cold.remove(k))?;
+        Some(v)
+    }
+
+    /// Returns the number of key-value pairs that are guaranteed to be in the cache, like
+    /// [`LruCache::len`](crate::LruCache::len).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FixedLruCache;
+    /// let mut cache: FixedLruCache<_, _, 2> = FixedLruCache::new();
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.len(), 1);
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        cmp::min(self.hot.len + self.cold.len, self.limit)
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FixedLruCache;
+    /// let mut cache: FixedLruCache<_, _, 2> = FixedLruCache::new();
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.hot.len == 0 && self.cold.len == 0
+    }
+
+    /// Returns metric on the number of times the hot tier became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FixedLruCache;
+    /// let mut cache: FixedLruCache<_, _, 2> = FixedLruCache::new();
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.flips
+    }
+
+    /// Reset the flip metric.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FixedLruCache;
+    /// let mut cache: FixedLruCache<_, _, 2> = FixedLruCache::new();
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// cache.reset();
+    /// assert_eq!(cache.get_flips(), 0);
+    /// ```
+    pub fn reset(&mut self) {
+        self.flips = 0;
+    }
+}
+
+impl<K: Hash + Eq, V, const CAP: usize> Default for FixedLruCache<K, V, CAP> {
+    fn default() -> Self {
+        FixedLruCache::new()
+    }
+}
+
+// An open-addressing table with linear probing. Removals shift the rest of the cluster
+// back instead of leaving tombstones.
+struct Table<K, V, const N: usize> {
+    slots: [Option<(K, V)>; N],
+    len: usize,
+    // The longest distance of an item from its home slot, which bounds the probes of a miss
+    max_probe: usize,
+}
+
+impl<K: Hash + Eq, V, const N: usize> Table<K, V, N> {
+    const fn new() -> Table<K, V, N> {
+        Table {
+            slots: [const { None }; N],
+            len: 0,
+            max_probe: 0,
+        }
+    }
+
+    fn home<Q: Hash + ?Sized>(k: &Q) -> usize {
+        let mut hasher = FnvHasher::default();
+        k.hash(&mut hasher);
+        (hasher.finish() % N as u64) as usize
+    }
+
+    fn find<Q>(&self, k: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let home = Self::home(k);
+        for d in 0..=self.max_probe {
+            let i = (home + d) % N;
+            match &self.slots[i] {
+                None => return None,
+                Some((sk, _)) if sk.borrow() == k => return Some(i),
+                Some(_) => {}
+            }
+        }
+        None
+    }
+
+    fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let i = self.find(k)?;
+        self.slots[i].as_ref().map(|(_, v)| v)
+    }
+
+    fn value_mut(&mut self, i: usize) -> Option<&mut V> {
+        self.slots[i].as_mut().map(|(_, v)| v)
+    }
+
+    // Inserts a key that is not in the table, which must not be full.
+    fn insert(&mut self, k: K, v: V) -> usize {
+        let home = Self::home(&k);
+        let d = (0..N)
+            .find(|d| self.slots[(home + d) % N].is_none())
+            .expect("the table is full");
+        let i = (home + d) % N;
+        self.slots[i] = Some((k, v));
+        self.len += 1;
+        self.max_probe = cmp::max(self.max_probe, d);
+        i
+    }
+
+    fn remove<Q>(&mut self, k: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut hole = self.find(k)?;
+        let item = self.slots[hole].take();
+        self.len -= 1;
+        let mut i = hole;
+        loop {
+            i = (i + 1) % N;
+            let Some((sk, _)) = &self.slots[i] else {
+                break;
+            };
+            // The item can fill the hole if the hole is between its home slot and its slot
+            let home = Self::home(sk);
+            if (i + N - home) % N >= (i + N - hole) % N {
+                self.slots[hole] = self.slots[i].take();
+                hole = i;
+            }
+        }
+        item
+    }
+
+    fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|s| *s = None);
+        self.len = 0;
+        self.max_probe = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FixedLruCache;
+
+    #[test]
+    fn test_flips() {
+        let mut cache: FixedLruCache<_, _, 2> = FixedLruCache::new();
+        for i in 0..4 {
+            assert_eq!(cache.put(i, i), None);
+        }
+        assert_eq!(cache.get_flips(), 1);
+
+        // A hit in the cold tier moves it back into the hot tier, flipping it first
+        assert_eq!(cache.get(&1), Some(&1));
+        assert_eq!(cache.get_flips(), 2);
+        assert_eq!(cache.get(&0), None);
+        assert_eq!(cache.put(3, 30), Some(3));
+        assert_eq!(cache.peek(&3), Some(&30));
+        assert_eq!(cache.pop(&1), Some(1));
+        assert_eq!(cache.peek(&2), Some(&2));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_full_tables() {
+        // Fill both tables up, leaving no empty slot to stop the probes, and remove items
+        // in between
+        let mut cache: FixedLruCache<u32, u32, 16> = FixedLruCache::new();
+        for i in 0..32 {
+            cache.put(i, i);
+        }
+        for i in (0..32).step_by(3) {
+            assert_eq!(cache.pop(&i), Some(i));
+        }
+        for i in (16..32).filter(|i| i % 3 == 0) {
+            cache.put(i, i);
+        }
+        for i in 0..32 {
+            let expected = (i >= 16 || i % 3 != 0).then_some(i);
+            assert_eq!(cache.peek(&i).copied(), expected);
+        }
+        assert_eq!(cache.peek(&32), None);
+        assert_eq!(cache.get_flips(), 1);
+    }
+}
@@ -0,0 +1,101 @@
+use alloc::sync::Arc;
+use core::hash::Hash;
+use hashbrown::HashMap;
+use std::sync::{Condvar, Mutex, PoisonError};
+
+// The loads in progress, so that concurrent loads of a key run only once.
+pub(crate) struct Flights<K, V> {
+    pending: Mutex<HashMap<K, Arc<Flight<V>>>>,
+}
+
+// A load in progress that the other callers wait for.
+pub(crate) struct Flight<V> {
+    // None while loading, then the value or None if the load was abandoned
+    result: Mutex<Option<Option<V>>>,
+    landed: Condvar,
+}
+
+impl<V: Clone> Flight<V> {
+    // Waits for the load and returns its value, or None if it was abandoned.
+    pub(crate) fn wait(&self) -> Option<V> {
+        let result = self.result.lock().unwrap_or_else(PoisonError::into_inner);
+        let result = self
+            .landed
+            .wait_while(result, |r| r.is_none())
+            .unwrap_or_else(PoisonError::into_inner);
+        result.clone().flatten()
+    }
+}
+
+impl<K, V> Flights<K, V> {
+    pub(crate) fn new() -> Flights<K, V> {
+        Flights {
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Flights<K, V> {
+    // Joins the load of the key, or returns None if there is none and the caller leads a
+    // new one. The leader must land it.
+    pub(crate) fn join(&self, k: &K) -> Option<Arc<Flight<V>>> {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(flight) = pending.get(k) {
+            return Some(flight.clone());
+        }
+        let flight = Flight {
+            result: Mutex::new(None),
+            landed: Condvar::new(),
+        };
+        pending.insert(k.clone(), Arc::new(flight));
+        None
+    }
+
+    // Ends the load of the key, handing the value, or None if it was abandoned, to the
+    // callers waiting for it.
+    pub(crate) fn land(&self, k: &K, v: Option<V>) {
+        let flight = self
+            .pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(k);
+        if let Some(flight) = flight {
+            *flight.result.lock().unwrap_or_else(PoisonError::into_inner) = Some(v);
+            flight.landed.notify_all();
+        }
+    }
+}
+
+// Abandons the load of the leader unless it landed, e.g., when the loader panics.
+pub(crate) struct Leader<'a, K: Hash + Eq + Clone, V: Clone> {
+    flights: &'a Flights<K, V>,
+    key: Option<K>,
+}
+
+impl<'a, K: Hash + Eq + Clone, V: Clone> Leader<'a, K, V> {
+    pub(crate) fn new(flights: &'a Flights<K, V>, k: K) -> Leader<'a, K, V> {
+        Leader {
+            flights,
+            key: Some(k),
+        }
+    }
+
+    pub(crate) fn key(&self) -> &K {
+        self.key.as_ref().unwrap()
+    }
+
+    // Lands the load with the value and returns the key.
+    pub(crate) fn land(mut self, v: V) -> K {
+        let k = self.key.take().unwrap();
+        self.flights.land(&k, Some(v));
+        k
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Drop for Leader<'_, K, V> {
+    fn drop(&mut self) {
+        if let Some(k) = &self.key {
+            self.flights.land(k, None);
+        }
+    }
+}
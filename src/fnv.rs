@@ -0,0 +1,38 @@
+use core::hash::{BuildHasherDefault, Hasher};
+
+/// The 64-bit FNV-1a hash. It is fast for small keys and needs no random seed, but it is
+/// not resistant to collision attacks, so it does not suit keys from untrusted input.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::FnvHasher;
+/// use std::hash::Hasher;
+/// let mut hasher = FnvHasher::default();
+/// hasher.write(b"apple");
+/// assert_eq!(hasher.finish(), 0xf74a62a458befdbf);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> FnvHasher {
+        FnvHasher(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+/// A builder of [`FnvHasher`]s, which can be created in a `const` context, see
+/// [`LruCache::new_const`](crate::LruCache::new_const).
+pub type FnvBuildHasher = BuildHasherDefault<FnvHasher>;
@@ -0,0 +1,375 @@
+use crate::{Entry, LruCache, Priority, Tier};
+use alloc::vec::Vec;
+use allocator_api2::alloc::Allocator;
+use core::borrow::Borrow;
+use core::hash::{BuildHasher, Hash};
+use core::num::NonZeroUsize;
+use hashbrown::HashMap;
+
+/// An immutable copy of the items of an [`LruCache`], returned by
+/// [`LruCache::to_frozen_copy`]. It owns its items, so it can be sent to another thread
+/// while the cache keeps changing, and lookups do not promote the items.
+/// [`LruCache::from_frozen_copy`] turns it back into a cache.
+pub struct FrozenCacheView<K, V> {
+    hot: HashMap<K, V>,
+    cold: HashMap<K, V>,
+    hot_cap: NonZeroUsize,
+    cold_cap: NonZeroUsize,
+    flips: usize,
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Clone, A: Allocator + Clone> LruCache<K, V, S, A> {
+    /// Returns an immutable copy of the items of both tiers. This is a full copy, not a
+    /// view of the cache: every key and value is cloned into new maps, which takes time
+    /// and memory in the number of items. For large values store them in an `Arc` to make
+    /// the clones cheap.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// let view = cache.to_frozen_copy();
+    /// cache.put(1, "alpha");
+    /// assert_eq!(view.get(&1), Some(&"a"));
+    /// ```
+    pub fn to_frozen_copy(&self) -> FrozenCacheView<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let copy = |map: &HashMap<K, Entry<V>, S, A>| {
+            let mut copy = HashMap::with_capacity(map.len());
+            copy.extend(map.iter().map(|(k, e)| (k.clone(), e.value.clone())));
+            copy
+        };
+        FrozenCacheView {
+            hot: copy(&self.l1_map),
+            cold: copy(&self.l2_map),
+            hot_cap: NonZeroUsize::new(self.hot_cap).unwrap_or(NonZeroUsize::MIN),
+            cold_cap: NonZeroUsize::new(self.cold_cap).unwrap_or(NonZeroUsize::MIN),
+            flips: self.get_flips(),
+        }
+    }
+
+    /// Returns a copy of the items as key-value pairs, the hot tier first, in arbitrary
+    /// order within a tier. Pass them to [`restore`] to warm a new cache. The pairs do not
+    /// record the tiers or the flip metric, use [`to_frozen_copy`] for that.
+    ///
+    /// [`restore`]: LruCache::restore
+    /// [`to_frozen_copy`]: LruCache::to_frozen_copy
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// let pairs = cache.snapshot_pairs();
+    /// assert_eq!(pairs[0], (3, "c"));
+    /// assert_eq!(pairs.len(), 3);
+    /// ```
+    pub fn snapshot_pairs(&self) -> Vec<(K, V)>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let mut pairs = Vec::with_capacity(self.l1_map.len() + self.l2_map.len());
+        for map in [&self.l1_map, &self.l2_map] {
+            pairs.extend(map.iter().map(|(k, e)| (k.clone(), e.value.clone())));
+        }
+        pairs
+    }
+}
+
+impl<K: Hash + Eq, V> LruCache<K, V> {
+    /// Creates a cache from a frozen copy, with the capacities of the copied cache. The
+    /// items go back into the tier they were in and the flip metric is kept, so the next
+    /// flip happens when it would have in the original cache. The configuration, e.g., the
+    /// flip mode and the hooks, starts with the defaults of [`with_caps`].
+    ///
+    /// [`with_caps`]: LruCache::with_caps
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// let mut copy = LruCache::from_frozen_copy(cache.to_frozen_copy());
+    /// assert_eq!(copy.get_flips(), 1);
+    /// // 3 is still in the main cache, so a put does not flip
+    /// copy.put(4, "d");
+    /// assert_eq!(copy.get_flips(), 1);
+    /// ```
+    pub fn from_frozen_copy(view: FrozenCacheView<K, V>) -> LruCache<K, V> {
+        let mut cache = LruCache::with_caps(view.hot_cap, view.cold_cap);
+        let entries = |map: HashMap<K, V>| {
+            map.into_iter()
+                .map(|(k, v)| (k, Entry::new(v, Priority::Normal)))
+        };
+        cache.l1_map.extend(entries(view.hot));
+        cache.l2_map.extend(entries(view.cold));
+        cache.flips = view.flips;
+        cache
+    }
+
+    /// Creates a new cache that holds `cap` items, see [`new`], from the pairs in the
+    /// order of [`snapshot_pairs`]: the first `cap` distinct keys go into the main cache,
+    /// the next `cap` into the backup cache, and the rest is discarded. When a key appears
+    /// more than once, its first value is kept. No flips are counted.
+    ///
+    /// [`new`]: LruCache::new
+    /// [`snapshot_pairs`]: LruCache::snapshot_pairs
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cap = NonZeroUsize::new(2).unwrap();
+    /// let mut cache = LruCache::new(cap);
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// let mut cache = LruCache::restore(cap, cache.snapshot_pairs());
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert_eq!(cache.get_flips(), 0);
+    /// ```
+    pub fn restore<I>(cap: NonZeroUsize, pairs: I) -> LruCache<K, V>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut cache = LruCache::new(cap);
+        for (k, v) in pairs {
+            if cache.l2_map.len() == cap.get() {
+                break;
+            }
+            if cache.l1_map.contains_key(&k) || cache.l2_map.contains_key(&k) {
+                continue;
+            }
+            let map = if cache.l1_map.len() < cap.get() {
+                &mut cache.l1_map
+            } else {
+                &mut cache.l2_map
+            };
+            map.insert(k, Entry::new(v, Priority::Normal));
+        }
+        cache
+    }
+}
+
+impl<K: Hash + Eq, V> FrozenCacheView<K, V> {
+    /// Returns a reference to the value of the key or `None` if it was not present in the
+    /// cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// let view = cache.to_frozen_copy();
+    /// assert_eq!(view.get(&1), Some(&"a"));
+    /// assert_eq!(view.get(&2), None);
+    /// ```
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.hot.get(k).or_else(|| self.cold.get(k))
+    }
+
+    /// Returns the tier that held the key or `None` if it was not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, Tier};
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// let view = cache.to_frozen_copy();
+    /// assert_eq!(view.tier(&3), Some(Tier::Hot));
+    /// assert_eq!(view.tier(&1), Some(Tier::Cold));
+    /// ```
+    pub fn tier<Q>(&self, k: &Q) -> Option<Tier>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.hot.contains_key(k) {
+            Some(Tier::Hot)
+        } else if self.cold.contains_key(k) {
+            Some(Tier::Cold)
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over the items, the hot tier first, in arbitrary order within
+    /// a tier.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, 10);
+    /// cache.put(2, 20);
+    /// cache.put(3, 30);
+    /// assert_eq!(cache.to_frozen_copy().iter().map(|(_, v)| v).sum::<i32>(), 60);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.hot.iter().chain(self.cold.iter())
+    }
+
+    /// Returns the number of items in the copy, including the ones that the cache does
+    /// not guarantee to keep, unlike [`LruCache::len`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.to_frozen_copy().len(), 3);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.hot.len() + self.cold.len()
+    }
+
+    /// Returns a bool indicating whether the copy is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert!(cache.to_frozen_copy().is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.to_frozen_copy().is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.hot.is_empty() && self.cold.is_empty()
+    }
+
+    /// Returns the flip metric of the cache when the copy was taken.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.to_frozen_copy().get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.flips
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FnvBuildHasher, LruCache, Tier};
+    use allocator_api2::alloc::Global;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_frozen_copy() {
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        cache.put(1, 10);
+        cache.put(2, 20);
+        cache.put(3, 30);
+        let view = cache.to_frozen_copy();
+
+        // Lookups in the view do not promote, and the cache changes do not show
+        assert_eq!(view.get(&1), Some(&10));
+        cache.put(4, 40);
+        cache.put(5, 50);
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(view.get(&1), Some(&10));
+        assert_eq!(view.tier(&1), Some(Tier::Cold));
+        assert_eq!(view.get(&5), None);
+        assert_eq!(view.len(), 3);
+        assert_eq!(view.get_flips(), 1);
+
+        // Any hasher and allocator can be copied
+        let cap = NonZeroUsize::new(2).unwrap();
+        let mut cache = LruCache::with_hasher_in(cap, cap, FnvBuildHasher::new(), Global);
+        cache.put(1, 10);
+        assert_eq!(cache.to_frozen_copy().get(&1), Some(&10));
+    }
+
+    #[test]
+    fn test_restore() {
+        let cap = NonZeroUsize::new(2).unwrap();
+        let pairs = [(1, 10), (2, 20), (1, 11), (3, 30), (4, 40), (5, 50)];
+        let mut cache = LruCache::restore(cap, pairs);
+
+        // The duplicate is skipped and the pairs past both tiers are discarded
+        let view = cache.to_frozen_copy();
+        assert_eq!(view.tier(&1), Some(Tier::Hot));
+        assert_eq!(view.tier(&2), Some(Tier::Hot));
+        assert_eq!(view.tier(&4), Some(Tier::Cold));
+        assert_eq!(view.get(&5), None);
+        assert_eq!(cache.get(&1), Some(&10));
+
+        // The hot tier comes back hot after a round trip
+        let copy = LruCache::restore(cap, cache.snapshot_pairs());
+        assert_eq!(copy.to_frozen_copy().tier(&1), Some(Tier::Hot));
+        assert_eq!(copy.len(), 2);
+    }
+
+    #[test]
+    fn test_from_frozen_copy() {
+        let mut cache =
+            LruCache::with_caps(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(4).unwrap());
+        for i in 0..5 {
+            cache.put(i, i);
+        }
+        let mut copy = LruCache::from_frozen_copy(cache.to_frozen_copy());
+
+        assert_eq!(copy.cold_cap(), 4);
+        assert_eq!(copy.get_flips(), 2);
+        for i in 0..5 {
+            assert_eq!(copy.peek_entry(&i), cache.peek_entry(&i));
+        }
+        // Both flip at the same put
+        copy.put(5, 5);
+        cache.put(5, 5);
+        copy.put(6, 6);
+        cache.put(6, 6);
+        assert_eq!(copy.get_flips(), cache.get_flips());
+    }
+}
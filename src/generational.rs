@@ -0,0 +1,278 @@
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::num::NonZeroUsize;
+use core::{array, cmp, mem};
+use hashbrown::HashMap;
+
+/// An LRU Cache that keeps `N` generations instead of two.
+///
+/// Lookups walk the generations from the newest to the oldest and move a hit into the
+/// newest generation. When the newest generation is full, a flip rotates the ring so that
+/// only the oldest generation is discarded. With more generations eviction is smoother:
+/// the last `cap` items are guaranteed to be in the cache and up to the last `N*cap`
+/// items can be fetched.
+pub struct GenLruCache<K, V, const N: usize> {
+    gens: [HashMap<K, V>; N],
+    // Index of the newest generation
+    head: usize,
+    cap: NonZeroUsize,
+    flips: usize,
+}
+
+impl<K: Hash + Eq, V, const N: usize> GenLruCache<K, V, N> {
+    /// Creates a new cache of `N` generations that each hold `cap` items. `N` must be at
+    /// least 2, otherwise this fails to compile.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::GenLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: GenLruCache<isize, &str, 3> = GenLruCache::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> GenLruCache<K, V, N> {
+        const { assert!(N >= 2, "a GenLruCache needs at least two generations") };
+        GenLruCache {
+            gens: array::from_fn(|i| {
+                if i == 0 {
+                    HashMap::with_capacity(cap.into())
+                } else {
+                    HashMap::new()
+                }
+            }),
+            head: 0,
+            cap,
+            flips: 0,
+        }
+    }
+
+    /// Returns a reference to the value of the key in the cache or `None` if it is not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::GenLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: GenLruCache<_, _, 3> = GenLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// for i in 0..6 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get(&0), Some(&0));
+    /// ```
+    pub fn get<'a, Q>(&'a mut self, k: &Q) -> Option<&'a V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_mut(k).map(|v| &*v)
+    }
+
+    /// Returns a mutable reference to the value of the key in the cache or `None` if it
+    /// is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::GenLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: GenLruCache<_, _, 3> = GenLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put("apple", 8);
+    /// *cache.get_mut(&"apple").unwrap() += 1;
+    /// assert_eq!(cache.get(&"apple"), Some(&9));
+    /// ```
+    pub fn get_mut<'a, Q>(&'a mut self, k: &Q) -> Option<&'a mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if !self.gens[self.head].contains_key(k) {
+            let (rk, rv) = Self::older(self.head).find_map(|i| self.gens[i].remove_entry(k))?;
+            self.insert(rk, rv);
+        }
+        self.gens[self.head].get_mut(k)
+    }
+
+    /// Puts a key-value pair into cache. If the key already exists in the cache, then it updates
+    /// the key's value and returns the old value. Otherwise, `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::GenLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: GenLruCache<_, _, 3> = GenLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Option<V> {
+        // An update of the newest generation must not flip it, which would leave a copy
+        if let Some(old) = self.gens[self.head].get_mut(&k) {
+            return Some(mem::replace(old, v));
+        }
+        // invalidate any existing entry in the older generations
+        let ov = Self::older(self.head).find_map(|i| self.gens[i].remove(&k));
+        self.insert(k, v).or(ov)
+    }
+
+    fn insert(&mut self, k: K, v: V) -> Option<V> {
+        if self.gens[self.head].len() == self.cap.get() {
+            self.flip();
+        }
+        self.gens[self.head].insert(k, v)
+    }
+
+    // Makes the oldest generation the newest one after discarding its items.
+    fn flip(&mut self) {
+        self.head = (self.head + 1) % N;
+        let newest = &mut self.gens[self.head];
+        newest.clear();
+        newest.reserve(self.cap.get());
+        self.flips += 1;
+    }
+
+    // Indices of the generations older than the newest one, from the newest to the oldest.
+    fn older(head: usize) -> impl Iterator<Item = usize> {
+        (1..N).map(move |age| (head + N - age) % N)
+    }
+
+    /// Returns the number of items a generation holds.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::GenLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: GenLruCache<isize, &str, 4> = GenLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert_eq!(cache.cap().get(), 2);
+    /// ```
+    pub fn cap(&self) -> NonZeroUsize {
+        self.cap
+    }
+
+    /// Returns the number of key-value pairs that are guaranteed to be in the cache, like
+    /// [`LruCache::len`](crate::LruCache::len).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::GenLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: GenLruCache<_, _, 3> = GenLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.len(), 1);
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        let len = self.gens.iter().map(HashMap::len).sum();
+        cmp::min(len, self.cap.get())
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::GenLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: GenLruCache<_, _, 3> = GenLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.gens.iter().all(HashMap::is_empty)
+    }
+
+    /// Returns metric on the number of times the newest generation became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::GenLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: GenLruCache<_, _, 3> = GenLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.flips
+    }
+
+    /// Reset the flip metric.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::GenLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: GenLruCache<_, _, 3> = GenLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// cache.reset();
+    /// assert_eq!(cache.get_flips(), 0);
+    /// ```
+    pub fn reset(&mut self) {
+        self.flips = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GenLruCache;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_generations() {
+        let mut cache: GenLruCache<_, _, 3> = GenLruCache::new(NonZeroUsize::new(2).unwrap());
+        for i in 0..6 {
+            assert_eq!(cache.put(i, i), None);
+        }
+        assert_eq!(cache.get_flips(), 2);
+
+        // The oldest generation is still reachable and a hit moves it to the newest one
+        assert_eq!(cache.get(&1), Some(&1));
+        assert_eq!(cache.get_flips(), 3);
+
+        // That flip discarded 0 and the generation of 2 and 3 is now the oldest
+        assert_eq!(cache.get(&0), None);
+        assert_eq!(cache.put(3, 30), Some(3));
+        assert_eq!(cache.get(&3), Some(&30));
+        assert_eq!(cache.get(&4), Some(&4));
+    }
+
+    #[test]
+    fn test_cache_over_capacity() {
+        let mut cache: GenLruCache<_, _, 4> = GenLruCache::new(NonZeroUsize::new(5).unwrap());
+        for i in 0..5 {
+            cache.put(i, i);
+        }
+        for i in 0..20 {
+            cache.get(&(i % 5));
+        }
+        assert_eq!(cache.get_flips(), 0);
+    }
+
+    #[test]
+    fn test_update_in_full_head() {
+        let mut cache: GenLruCache<_, _, 3> = GenLruCache::new(NonZeroUsize::new(2).unwrap());
+        cache.put(1, 1);
+        cache.put(2, 2);
+        assert_eq!(cache.put(2, 20), Some(2));
+        assert_eq!(cache.get_flips(), 0);
+        cache.put(3, 3);
+        cache.put(4, 4);
+        cache.put(5, 5);
+        // The flips move 2 out without leaving a stale copy behind
+        assert_eq!(cache.get(&2), Some(&20));
+        assert_eq!(cache.put(2, 21), Some(20));
+    }
+}
@@ -0,0 +1,515 @@
+use alloc::sync::Arc;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::mem;
+use core::num::NonZeroUsize;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use hashbrown::HashMap;
+
+// The budget of a group and how much of it the main caches of the current generation use.
+struct Shared {
+    budget: usize,
+    used: AtomicUsize,
+    generation: AtomicUsize,
+}
+
+impl Shared {
+    // Charges the weight to the current generation, starting a new generation, i.e., a flip
+    // of the whole group, if the budget is exceeded.
+    fn charge(&self, weight: usize) {
+        // A single update, so that the concurrent charges are not lost by the reset
+        let mut flipped = false;
+        let _ = self
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                flipped = used > 0 && used.saturating_add(weight) > self.budget;
+                Some(if flipped { weight } else { used + weight })
+            });
+        if flipped {
+            self.generation.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn uncharge(&self, weight: usize) {
+        let _ = self
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                Some(used.saturating_sub(weight))
+            });
+    }
+}
+
+/// A budget that is shared by caches of different key and value types, e.g., all the
+/// caches of a process, in number of items or in the weight of their items, e.g., bytes.
+///
+/// The budget bounds what the main caches of all the members charge to the current
+/// generation, like the capacity of an [`LruCache`](crate::LruCache) bounds its main cache.
+/// When it is exceeded the whole group flips: every member moves its main cache to its
+/// backup cache and discards its previous backup cache. The group can be shared by threads,
+/// where the budget is approximate.
+///
+/// The budget is not a bound of the memory of the members, as a member flips at its next
+/// access or [`catch_up`](GroupedCache::catch_up): one that is not used keeps the items of
+/// the generations that the group discarded until then, although they are not counted by
+/// its [`len`](GroupedCache::len) anymore.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::CacheGroup;
+/// use std::num::NonZeroUsize;
+/// let group = CacheGroup::new(NonZeroUsize::new(1_000_000).unwrap());
+///
+/// let mut users = group.cache::<u64, String>();
+/// let mut pages = group.cache_with_weigher(|_: &String, page: &Vec<u8>| page.len());
+/// users.put(1, String::from("alice"));
+/// pages.put(String::from("/index.html"), vec![0; 4096]);
+/// assert_eq!(group.used(), 4097);
+/// ```
+#[derive(Clone)]
+pub struct CacheGroup {
+    shared: Arc<Shared>,
+}
+
+impl CacheGroup {
+    /// Creates a new group whose members hold at least `budget` items, or weight, in their
+    /// main caches together.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CacheGroup;
+    /// use std::num::NonZeroUsize;
+    /// let group = CacheGroup::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(budget: NonZeroUsize) -> CacheGroup {
+        CacheGroup {
+            shared: Arc::new(Shared {
+                budget: budget.get(),
+                used: AtomicUsize::new(0),
+                generation: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Creates a new member of the group whose items each weigh one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CacheGroup;
+    /// use std::num::NonZeroUsize;
+    /// let group = CacheGroup::new(NonZeroUsize::new(10).unwrap());
+    ///
+    /// let mut cache = group.cache();
+    /// cache.put(1, "a");
+    /// assert_eq!(group.used(), 1);
+    /// ```
+    pub fn cache<K, V>(&self) -> GroupedCache<K, V> {
+        self.cache_with_weigher(|_, _| 1)
+    }
+
+    /// Creates a new member of the group whose items weigh what the weigher returns, e.g.,
+    /// their size in bytes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CacheGroup;
+    /// use std::num::NonZeroUsize;
+    /// let group = CacheGroup::new(NonZeroUsize::new(1024).unwrap());
+    ///
+    /// let mut cache = group.cache_with_weigher(|k: &&str, v: &&str| k.len() + v.len());
+    /// cache.put("key", "value");
+    /// assert_eq!(group.used(), 8);
+    /// ```
+    pub fn cache_with_weigher<K, V>(&self, weigher: fn(&K, &V) -> usize) -> GroupedCache<K, V> {
+        GroupedCache {
+            l1_map: HashMap::new(),
+            l2_map: HashMap::new(),
+            l1_weight: 0,
+            generation: self.shared.generation.load(Ordering::Relaxed),
+            flips: 0,
+            weigher,
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Returns the budget of the group.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CacheGroup;
+    /// use std::num::NonZeroUsize;
+    /// let group = CacheGroup::new(NonZeroUsize::new(10).unwrap());
+    /// assert_eq!(group.budget(), 10);
+    /// ```
+    pub fn budget(&self) -> usize {
+        self.shared.budget
+    }
+
+    /// Returns how much of the budget the main caches of the members use.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CacheGroup;
+    /// use std::num::NonZeroUsize;
+    /// let group = CacheGroup::new(NonZeroUsize::new(10).unwrap());
+    ///
+    /// let mut cache = group.cache();
+    /// cache.put(1, "a");
+    /// cache.pop(&1);
+    /// assert_eq!(group.used(), 0);
+    /// ```
+    pub fn used(&self) -> usize {
+        self.shared.used.load(Ordering::Relaxed)
+    }
+
+    /// Returns metric on the number of times the group exceeded its budget.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CacheGroup;
+    /// use std::num::NonZeroUsize;
+    /// let group = CacheGroup::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// let mut a = group.cache();
+    /// let mut b = group.cache();
+    /// for i in 0..5 {
+    ///     a.put(i, i);
+    ///     b.put(i, i);
+    /// }
+    /// assert_eq!(group.get_flips(), 4);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.shared.generation.load(Ordering::Relaxed)
+    }
+}
+
+/// A cache that is a member of a [`CacheGroup`] and flips with it.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::CacheGroup;
+/// use std::num::NonZeroUsize;
+/// let group = CacheGroup::new(NonZeroUsize::new(2).unwrap());
+///
+/// let mut a = group.cache();
+/// let mut b = group.cache();
+/// a.put(1, "a");
+/// b.put("b", 2);
+/// // The group is full, so this put flips it
+/// b.put("c", 3);
+/// assert_eq!(a.get(&1), Some(&"a"));
+/// ```
+pub struct GroupedCache<K, V> {
+    l1_map: HashMap<K, V>,
+    l2_map: HashMap<K, V>,
+    l1_weight: usize,
+    generation: usize,
+    flips: usize,
+    weigher: fn(&K, &V) -> usize,
+    shared: Arc<Shared>,
+}
+
+impl<K: Hash + Eq, V> GroupedCache<K, V> {
+    /// Returns a reference to the value of the key in the cache or `None` if it is not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CacheGroup;
+    /// use std::num::NonZeroUsize;
+    /// let group = CacheGroup::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = group.cache();
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert_eq!(cache.get(&2), None);
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.sync();
+        if !self.l1_map.contains_key(k) {
+            let (rk, rv) = self.l2_map.remove_entry(k)?;
+            self.insert(rk, rv);
+        }
+        self.l1_map.get(k)
+    }
+
+    /// Returns a reference to the value of the key without updating the LRU list, or
+    /// `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CacheGroup;
+    /// use std::num::NonZeroUsize;
+    /// let group = CacheGroup::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = group.cache();
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.peek(&1), Some(&"a"));
+    /// ```
+    pub fn peek<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        // Skips the maps that the pending flips discard
+        match self.lag() {
+            0 => self.l1_map.get(k).or_else(|| self.l2_map.get(k)),
+            1 => self.l1_map.get(k),
+            _ => None,
+        }
+    }
+
+    /// Puts a key-value pair into cache, charging its weight to the group. If the key
+    /// already exists in the cache, then it updates the key's value and returns the old
+    /// value. Otherwise, `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CacheGroup;
+    /// use std::num::NonZeroUsize;
+    /// let group = CacheGroup::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = group.cache();
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Option<V> {
+        let old = self.pop(&k);
+        self.insert(k, v);
+        old
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it was not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CacheGroup;
+    /// use std::num::NonZeroUsize;
+    /// let group = CacheGroup::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = group.cache();
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.sync();
+        if let Some((rk, rv)) = self.l1_map.remove_entry(k) {
+            let weight = (self.weigher)(&rk, &rv);
+            self.l1_weight -= weight;
+            self.shared.uncharge(weight);
+            return Some(rv);
+        }
+        self.l2_map.remove(k)
+    }
+
+    /// Applies the flips of the group that the cache missed since its last access, which
+    /// frees the items of the generations that they discarded, e.g., from a timer for a
+    /// cache that is rarely used.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CacheGroup;
+    /// use std::num::NonZeroUsize;
+    /// let group = CacheGroup::new(NonZeroUsize::new(2).unwrap());
+    /// let mut idle = group.cache();
+    /// let mut busy = group.cache();
+    ///
+    /// idle.put(1, "a");
+    /// for i in 0..4 {
+    ///     busy.put(i, i);
+    /// }
+    /// idle.catch_up();
+    /// assert_eq!(idle.get_flips(), 1);
+    /// ```
+    pub fn catch_up(&mut self) {
+        self.sync();
+    }
+
+    /// Returns the number of key-value pairs that are guaranteed to be in the cache, like
+    /// [`LruCache::len`](crate::LruCache::len).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CacheGroup;
+    /// use std::num::NonZeroUsize;
+    /// let group = CacheGroup::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = group.cache();
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// assert_eq!(cache.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        match self.lag() {
+            0 => self.l1_map.len() + self.l2_map.len(),
+            1 => self.l1_map.len(),
+            _ => 0,
+        }
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CacheGroup;
+    /// use std::num::NonZeroUsize;
+    /// let group = CacheGroup::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = group.cache();
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns metric on the number of times the cache flipped with its group.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::CacheGroup;
+    /// use std::num::NonZeroUsize;
+    /// let group = CacheGroup::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = group.cache();
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.flips
+    }
+
+    // Inserts a key that is in neither map into the main cache.
+    fn insert(&mut self, k: K, v: V) {
+        let weight = (self.weigher)(&k, &v);
+        self.shared.charge(weight);
+        self.sync();
+        self.l1_map.insert(k, v);
+        self.l1_weight += weight;
+    }
+
+    // Applies the flips of the group since the last access.
+    fn sync(&mut self) {
+        let lag = self.lag();
+        if lag == 0 {
+            return;
+        }
+        self.flips += 1;
+        // The emptied backup map is reused for the next generation
+        mem::swap(&mut self.l1_map, &mut self.l2_map);
+        self.l1_map.clear();
+        if lag > 1 {
+            // The main cache is older than the previous generation of the group too
+            self.l2_map.clear();
+        }
+        // The weight was charged to a generation that is over
+        self.l1_weight = 0;
+        self.generation = self.shared.generation.load(Ordering::Relaxed);
+    }
+
+    fn lag(&self) -> usize {
+        self.shared
+            .generation
+            .load(Ordering::Relaxed)
+            .wrapping_sub(self.generation)
+    }
+}
+
+impl<K, V> Drop for GroupedCache<K, V> {
+    fn drop(&mut self) {
+        if self.shared.generation.load(Ordering::Relaxed) == self.generation {
+            self.shared.uncharge(self.l1_weight);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheGroup, GroupedCache};
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_shared_budget() {
+        let group = CacheGroup::new(NonZeroUsize::new(4).unwrap());
+        let mut numbers = group.cache::<u32, u32>();
+        let mut names = group.cache::<String, &str>();
+        for i in 0..3 {
+            numbers.put(i, i);
+        }
+        names.put(String::from("a"), "a");
+        assert_eq!(group.used(), 4);
+
+        // The put of one member flips the other one too
+        names.put(String::from("b"), "b");
+        assert_eq!(group.get_flips(), 1);
+        assert_eq!(numbers.len(), 3);
+        assert_eq!(numbers.get(&0), Some(&0));
+        assert_eq!(numbers.get_flips(), 1);
+        assert_eq!(group.used(), 2);
+
+        // A member that missed two flips of the group discards all its items
+        let mut idle = group.cache::<u32, u32>();
+        idle.put(9, 9);
+        for i in 0..8 {
+            names.put(i.to_string(), "n");
+        }
+        assert!(group.get_flips() >= 3);
+        assert_eq!(idle.peek(&9), None);
+        assert!(idle.is_empty());
+        drop(names);
+        drop(numbers);
+        assert_eq!(idle.pop(&9), None);
+    }
+
+    #[test]
+    fn test_catch_up_frees_idle_members() {
+        let group = CacheGroup::new(NonZeroUsize::new(10).unwrap());
+        let mut members: Vec<_> = (0..6).map(|_| group.cache::<u32, u32>()).collect();
+        for member in &mut members {
+            for i in 0..10 {
+                member.put(i, i);
+            }
+        }
+        let held = |members: &[GroupedCache<u32, u32>]| -> usize {
+            members
+                .iter()
+                .map(|m| m.l1_map.len() + m.l2_map.len())
+                .sum()
+        };
+        // The idle members keep the items of the discarded generations until they catch up
+        assert_eq!(held(&members), 60);
+        members.iter_mut().for_each(GroupedCache::catch_up);
+        assert!(held(&members) <= 2 * group.budget());
+        assert_eq!(
+            held(&members),
+            members.iter().map(|m| m.len()).sum::<usize>()
+        );
+    }
+}
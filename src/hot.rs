@@ -0,0 +1,379 @@
+use crate::LruCache;
+use alloc::borrow::ToOwned;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp::Reverse;
+use core::hash::Hash;
+use core::num::NonZeroUsize;
+use hashbrown::HashMap;
+
+/// An LRU Cache that also profiles its traffic: it counts the lookups of the hottest keys
+/// with the space-saving algorithm, so that [`hottest_keys`](HotKeysCache::hottest_keys)
+/// shows the keys that dominate the gets, whether they hit or miss.
+///
+/// Only `tracked` keys are counted at a time. A lookup of an untracked key replaces the
+/// key with the lowest count and inherits that count, so the counts are upper bounds, and
+/// every key that makes up more than `1 / tracked` of the lookups is tracked. The counts are
+/// kept in a min-heap, so counting a lookup takes `O(log tracked)`.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::HotKeysCache;
+/// use std::num::NonZeroUsize;
+/// let mut cache: HotKeysCache<u64, &str> = HotKeysCache::new(
+///     NonZeroUsize::new(1024).unwrap(),
+///     NonZeroUsize::new(16).unwrap(),
+/// );
+///
+/// for i in 0..100 {
+///     cache.get(&7);
+///     cache.get(&(i % 10));
+/// }
+/// assert_eq!(cache.hottest_keys(1), vec![(7, 110)]);
+/// ```
+pub struct HotKeysCache<K, V> {
+    cache: LruCache<K, V>,
+    // A min-heap of the counts of the tracked keys, with the coldest key at the root
+    counts: Vec<(u64, K)>,
+    // The position of each tracked key in the heap
+    slots: HashMap<K, usize>,
+    tracked: usize,
+}
+
+impl<K: Hash + Eq + Clone, V> HotKeysCache<K, V> {
+    /// Creates a new LRU Cache that holds at least `cap` items and counts the lookups of
+    /// `tracked` keys.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::HotKeysCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: HotKeysCache<u64, &str> = HotKeysCache::new(
+    ///     NonZeroUsize::new(10).unwrap(),
+    ///     NonZeroUsize::new(4).unwrap(),
+    /// );
+    /// ```
+    pub fn new(cap: NonZeroUsize, tracked: NonZeroUsize) -> HotKeysCache<K, V> {
+        HotKeysCache {
+            cache: LruCache::new(cap),
+            counts: Vec::with_capacity(tracked.get()),
+            slots: HashMap::with_capacity(tracked.get()),
+            tracked: tracked.get(),
+        }
+    }
+
+    /// Returns a reference to the value of the key in the cache or `None` if it is not
+    /// present in the cache, counting the lookup.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::HotKeysCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = HotKeysCache::new(
+    ///     NonZeroUsize::new(2).unwrap(),
+    ///     NonZeroUsize::new(2).unwrap(),
+    /// );
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert_eq!(cache.get(&2), None);
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        self.record(k);
+        self.cache.get(k)
+    }
+
+    /// Returns a mutable reference to the value of the key in the cache or `None` if it
+    /// is not present in the cache, counting the lookup.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::HotKeysCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = HotKeysCache::new(
+    ///     NonZeroUsize::new(2).unwrap(),
+    ///     NonZeroUsize::new(2).unwrap(),
+    /// );
+    ///
+    /// cache.put("apple", 8);
+    /// *cache.get_mut(&"apple").unwrap() += 1;
+    /// assert_eq!(cache.get(&"apple"), Some(&9));
+    /// ```
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        self.record(k);
+        self.cache.get_mut(k)
+    }
+
+    /// Returns a reference to the value of the key without updating the LRU list or
+    /// counting the lookup, or `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::HotKeysCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = HotKeysCache::new(
+    ///     NonZeroUsize::new(2).unwrap(),
+    ///     NonZeroUsize::new(2).unwrap(),
+    /// );
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.peek(&1), Some(&"a"));
+    /// assert!(cache.hottest_keys(1).is_empty());
+    /// ```
+    pub fn peek<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.peek(k)
+    }
+
+    /// Puts a key-value pair into cache. If the key already exists in the cache, then it
+    /// updates the key's value and returns the old value. Otherwise, `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::HotKeysCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = HotKeysCache::new(
+    ///     NonZeroUsize::new(2).unwrap(),
+    ///     NonZeroUsize::new(2).unwrap(),
+    /// );
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Option<V> {
+        self.cache.put(k, v)
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it was not
+    /// present in the cache. The key stays tracked.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::HotKeysCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = HotKeysCache::new(
+    ///     NonZeroUsize::new(2).unwrap(),
+    ///     NonZeroUsize::new(2).unwrap(),
+    /// );
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.pop(k)
+    }
+
+    /// Returns up to `n` of the tracked keys with the most lookups, along with their
+    /// counts, from the hottest.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::HotKeysCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: HotKeysCache<&str, u64> = HotKeysCache::new(
+    ///     NonZeroUsize::new(2).unwrap(),
+    ///     NonZeroUsize::new(4).unwrap(),
+    /// );
+    ///
+    /// for key in ["a", "b", "a", "c", "a", "b"] {
+    ///     cache.get(&key);
+    /// }
+    /// assert_eq!(cache.hottest_keys(2), vec![("a", 3), ("b", 2)]);
+    /// ```
+    pub fn hottest_keys(&self, n: usize) -> Vec<(K, u64)> {
+        let mut hottest: Vec<_> = self.counts.iter().map(|(c, k)| (k.clone(), *c)).collect();
+        hottest.sort_unstable_by_key(|&(_, c)| Reverse(c));
+        hottest.truncate(n);
+        hottest
+    }
+
+    /// Returns the number of key-value pairs that are guaranteed to be in the cache, like
+    /// [`LruCache::len`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::HotKeysCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = HotKeysCache::new(
+    ///     NonZeroUsize::new(2).unwrap(),
+    ///     NonZeroUsize::new(2).unwrap(),
+    /// );
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// assert_eq!(cache.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::HotKeysCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = HotKeysCache::new(
+    ///     NonZeroUsize::new(2).unwrap(),
+    ///     NonZeroUsize::new(2).unwrap(),
+    /// );
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Returns metric on the number of times the cache became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::HotKeysCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = HotKeysCache::new(
+    ///     NonZeroUsize::new(2).unwrap(),
+    ///     NonZeroUsize::new(2).unwrap(),
+    /// );
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.cache.get_flips()
+    }
+
+    fn record<Q>(&mut self, k: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        let i = match self.slots.get(k) {
+            Some(&i) => {
+                self.counts[i].0 += 1;
+                i
+            }
+            None if self.counts.len() < self.tracked => {
+                let k = k.to_owned();
+                self.slots.insert(k.clone(), self.counts.len());
+                self.counts.push((1, k));
+                self.sift_up(self.counts.len() - 1);
+                return;
+            }
+            None => {
+                // The new key replaces the coldest one and inherits its count
+                let k = k.to_owned();
+                let (count, coldest) = &mut self.counts[0];
+                *count += 1;
+                self.slots.remove(&*coldest);
+                self.slots.insert(k.clone(), 0);
+                *coldest = k;
+                0
+            }
+        };
+        self.sift_down(i);
+    }
+
+    // Moves a key whose count decreased or that was pushed towards the root.
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 && self.counts[(i - 1) / 2].0 > self.counts[i].0 {
+            self.swap(i, (i - 1) / 2);
+            i = (i - 1) / 2;
+        }
+    }
+
+    // Moves a key whose count increased towards the leaves.
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let mut min = i;
+            for child in [2 * i + 1, 2 * i + 2] {
+                if child < self.counts.len() && self.counts[child].0 < self.counts[min].0 {
+                    min = child;
+                }
+            }
+            if min == i {
+                return;
+            }
+            self.swap(i, min);
+            i = min;
+        }
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        self.counts.swap(i, j);
+        *self.slots.get_mut(&self.counts[i].1).unwrap() = i;
+        *self.slots.get_mut(&self.counts[j].1).unwrap() = j;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HotKeysCache;
+    use alloc::vec::Vec;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_heavy_hitter_stays_tracked() {
+        let mut cache: HotKeysCache<u32, u32> =
+            HotKeysCache::new(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(2).unwrap());
+        // The cold keys keep replacing each other with inherited counts, while the hot key
+        // is never replaced
+        for i in 0..50 {
+            cache.get(&0);
+            cache.get(&0);
+            cache.get(&(i + 1));
+        }
+        assert_eq!(cache.hottest_keys(2), [(0, 100), (50, 50)]);
+        assert!(cache.hottest_keys(0).is_empty());
+    }
+
+    #[test]
+    fn test_heap_of_counts() {
+        let mut cache: HotKeysCache<u32, u32> =
+            HotKeysCache::new(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        for i in 0..1000u32 {
+            cache.get(&(i % 3));
+            cache.get(&(i.wrapping_mul(2654435761) % 97));
+        }
+        // The heap is ordered and every tracked key knows its position
+        for (i, (count, k)) in cache.counts.iter().enumerate() {
+            assert_eq!(cache.slots[k], i);
+            if i > 0 {
+                assert!(cache.counts[(i - 1) / 2].0 <= *count);
+            }
+        }
+        assert_eq!(cache.slots.len(), 8);
+        let hottest = cache.hottest_keys(3);
+        let mut keys: Vec<_> = hottest.iter().map(|&(k, _)| k).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, [0, 1, 2]);
+        assert!(hottest.iter().all(|&(_, c)| c >= 333));
+    }
+}
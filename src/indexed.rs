@@ -0,0 +1,315 @@
+use core::borrow::Borrow;
+use core::hash::{BuildHasher, Hash};
+use core::mem;
+use core::num::NonZeroUsize;
+use hashbrown::hash_map::DefaultHashBuilder;
+use indexmap::IndexMap;
+
+/// An LRU Cache with the flip semantics of [`LruCache`](crate::LruCache) whose two maps
+/// keep the insertion order, so that [`iter`](IndexedLruCache::iter) is deterministic,
+/// e.g., for test assertions and golden files. The items are iterated from the backup
+/// cache to the main cache, each in the order the items were put into it.
+///
+/// A hit in the backup cache shifts the items that follow it, so it is `O(n)` instead of
+/// `O(1)`.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::IndexedLruCache;
+/// use std::num::NonZeroUsize;
+/// let mut cache = IndexedLruCache::new(NonZeroUsize::new(2).unwrap());
+///
+/// cache.put("c", 3);
+/// cache.put("a", 1);
+/// cache.put("b", 2);
+/// let keys: Vec<_> = cache.iter().map(|(k, _)| *k).collect();
+/// assert_eq!(keys, ["c", "a", "b"]);
+/// ```
+pub struct IndexedLruCache<K, V, S = DefaultHashBuilder> {
+    l1_map: IndexMap<K, V, S>,
+    l2_map: IndexMap<K, V, S>,
+    cap: NonZeroUsize,
+    flips: usize,
+}
+
+impl<K: Hash + Eq, V> IndexedLruCache<K, V> {
+    /// Creates a new LRU Cache that holds at least `cap` items.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::IndexedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: IndexedLruCache<u64, &str> = IndexedLruCache::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> IndexedLruCache<K, V> {
+        IndexedLruCache::with_hasher(cap, DefaultHashBuilder::default())
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Clone> IndexedLruCache<K, V, S> {
+    /// Creates a new LRU Cache that holds at least `cap` items and hashes the keys with
+    /// the hasher.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{FnvBuildHasher, IndexedLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cap = NonZeroUsize::new(10).unwrap();
+    /// let cache: IndexedLruCache<u64, &str, _> =
+    ///     IndexedLruCache::with_hasher(cap, FnvBuildHasher::new());
+    /// ```
+    pub fn with_hasher(cap: NonZeroUsize, hasher: S) -> IndexedLruCache<K, V, S> {
+        IndexedLruCache {
+            l1_map: IndexMap::with_capacity_and_hasher(cap.get(), hasher.clone()),
+            l2_map: IndexMap::with_hasher(hasher),
+            cap,
+            flips: 0,
+        }
+    }
+
+    /// Returns a reference to the value of the key in the cache or `None` if it is not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::IndexedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = IndexedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert_eq!(cache.get(&4), None);
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_mut(k).map(|v| &*v)
+    }
+
+    /// Returns a mutable reference to the value of the key in the cache or `None` if it
+    /// is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::IndexedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = IndexedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put("apple", 8);
+    /// *cache.get_mut("apple").unwrap() += 1;
+    /// assert_eq!(cache.get("apple"), Some(&9));
+    /// ```
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if !self.l1_map.contains_key(k) {
+            // A hit in the backup cache moves the item to the end of the main cache
+            let (rk, rv) = self.l2_map.shift_remove_entry(k)?;
+            self.insert(rk, rv);
+        }
+        self.l1_map.get_mut(k)
+    }
+
+    /// Returns a reference to the value of the key without updating the LRU list, or
+    /// `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::IndexedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = IndexedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.peek(&1), Some(&"a"));
+    /// ```
+    pub fn peek<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.l1_map.get(k).or_else(|| self.l2_map.get(k))
+    }
+
+    /// Puts a key-value pair into cache. If the key already exists in the cache, then it updates
+    /// the key's value and returns the old value. Otherwise, `None` is returned. An update
+    /// in the main cache keeps the position of the key.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::IndexedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = IndexedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Option<V> {
+        if let Some(old) = self.l1_map.get_mut(&k) {
+            return Some(mem::replace(old, v));
+        }
+        let old = self.l2_map.shift_remove(&k);
+        self.insert(k, v);
+        old
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it was not
+    /// present in the cache. The order of the other items is kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::IndexedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = IndexedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.l1_map
+            .shift_remove(k)
+            .or_else(|| self.l2_map.shift_remove(k))
+    }
+
+    /// Removes all the items from the cache. The flip metric is kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::IndexedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = IndexedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// cache.put(1, "a");
+    /// cache.clear();
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.l1_map.clear();
+        self.l2_map.clear();
+    }
+}
+
+impl<K, V, S> IndexedLruCache<K, V, S> {
+    /// Returns an iterator over the items of the backup cache and then of the main cache,
+    /// each in insertion order, without updating the LRU list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::IndexedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = IndexedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// cache.get(&1);
+    /// let items: Vec<_> = cache.iter().collect();
+    /// assert_eq!(items, [(&2, &"b"), (&3, &"c"), (&1, &"a")]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.l2_map.iter().chain(self.l1_map.iter())
+    }
+
+    /// Returns the number of key-value pairs in the main and the backup cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::IndexedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = IndexedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.len(), 3);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.l1_map.len() + self.l2_map.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::IndexedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = IndexedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.l1_map.is_empty() && self.l2_map.is_empty()
+    }
+
+    /// Returns metric on the number of times the main cache became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::IndexedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = IndexedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.flips
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> IndexedLruCache<K, V, S> {
+    // Inserts a key that is in neither map, flipping first if the main cache is full.
+    fn insert(&mut self, k: K, v: V) {
+        if self.l1_map.len() >= self.cap.get() {
+            self.flips += 1;
+            // The emptied backup map is reused for the next generation
+            mem::swap(&mut self.l1_map, &mut self.l2_map);
+            self.l1_map.clear();
+        }
+        self.l1_map.insert(k, v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IndexedLruCache;
+    use alloc::vec::Vec;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_insertion_order() {
+        let mut cache = IndexedLruCache::new(NonZeroUsize::new(3).unwrap());
+        for k in [5, 3, 9, 1, 7] {
+            cache.put(k, k);
+        }
+        // The update keeps the position and the hit in the backup cache moves 3 last
+        cache.put(1, 10);
+        cache.get(&3);
+        cache.pop(&9);
+        let items: Vec<_> = cache.iter().map(|(&k, &v)| (k, v)).collect();
+        assert_eq!(items, [(5, 5), (1, 10), (7, 7), (3, 3)]);
+    }
+}
@@ -0,0 +1,303 @@
+use crate::LruCache;
+use alloc::sync::Arc;
+use core::borrow::Borrow;
+use core::hash::{Hash, Hasher};
+use core::num::NonZeroUsize;
+use hashbrown::HashSet;
+
+// A key that shares its string with the interner, so that two interned keys compare by
+// their pointers first.
+struct Interned(Arc<str>);
+
+impl Borrow<str> for Interned {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Hash for Interned {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // The same hash as the str, which the lookups borrow
+        self.0.hash(state);
+    }
+}
+
+impl PartialEq for Interned {
+    fn eq(&self, other: &Interned) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl Eq for Interned {}
+
+/// An LRU Cache of string keys, e.g., long URLs, that stores every key once as an
+/// `Arc<str>`: the cache and other structures share the string returned by
+/// [`intern`](InternedLruCache::intern), and the keys of the cache are looked up by
+/// `&str`.
+///
+/// The strings that are neither in the cache nor held elsewhere are released at the
+/// flips and when their key is popped.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::InternedLruCache;
+/// use std::num::NonZeroUsize;
+/// use std::sync::Arc;
+/// let mut cache = InternedLruCache::new(NonZeroUsize::new(1024).unwrap());
+///
+/// let url = cache.intern("https://example.com/a/very/long/path?with=query");
+/// cache.put(&url, 200);
+/// // The key of the cache and the string held by the caller are the same allocation
+/// assert!(Arc::ptr_eq(&url, &cache.intern(&url)));
+/// assert_eq!(cache.get("https://example.com/a/very/long/path?with=query"), Some(&200));
+/// ```
+pub struct InternedLruCache<V> {
+    cache: LruCache<Interned, V>,
+    strings: HashSet<Arc<str>>,
+}
+
+impl<V> InternedLruCache<V> {
+    /// Creates a new LRU Cache that holds at least `cap` items.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::InternedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: InternedLruCache<u64> = InternedLruCache::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> InternedLruCache<V> {
+        InternedLruCache {
+            cache: LruCache::new(cap),
+            strings: HashSet::new(),
+        }
+    }
+
+    /// Returns the shared string that is equal to the key, allocating it if it is not
+    /// interned yet.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::InternedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::Arc;
+    /// let mut cache: InternedLruCache<u64> = InternedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// let a = cache.intern("a");
+    /// assert!(Arc::ptr_eq(&a, &cache.intern("a")));
+    /// ```
+    pub fn intern(&mut self, key: &str) -> Arc<str> {
+        if let Some(s) = self.strings.get(key) {
+            return s.clone();
+        }
+        let s: Arc<str> = Arc::from(key);
+        self.strings.insert(s.clone());
+        s
+    }
+
+    /// Returns a reference to the value of the key in the cache or `None` if it is not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::InternedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = InternedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put("a", 1);
+    /// assert_eq!(cache.get("a"), Some(&1));
+    /// assert_eq!(cache.get("b"), None);
+    /// ```
+    pub fn get(&mut self, key: &str) -> Option<&V> {
+        self.cache.get(key)
+    }
+
+    /// Returns a mutable reference to the value of the key in the cache or `None` if it
+    /// is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::InternedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = InternedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put("apple", 8);
+    /// *cache.get_mut("apple").unwrap() += 1;
+    /// assert_eq!(cache.get("apple"), Some(&9));
+    /// ```
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        self.cache.get_mut(key)
+    }
+
+    /// Returns a reference to the value of the key without updating the LRU list, or
+    /// `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::InternedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = InternedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put("a", 1);
+    /// assert_eq!(cache.peek("a"), Some(&1));
+    /// ```
+    pub fn peek(&self, key: &str) -> Option<&V> {
+        self.cache.peek(key)
+    }
+
+    /// Puts a key-value pair into cache, interning the key. If the key already exists in
+    /// the cache, then it updates the key's value and returns the old value. Otherwise,
+    /// `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::InternedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = InternedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert_eq!(None, cache.put("a", 1));
+    /// assert_eq!(Some(1), cache.put("a", 2));
+    /// ```
+    pub fn put(&mut self, key: &str, v: V) -> Option<V> {
+        let key = Interned(self.intern(key));
+        let flips = self.cache.get_flips();
+        let old = self.cache.put(key, v);
+        if self.cache.get_flips() != flips {
+            self.release_unused();
+        }
+        old
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it was not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::InternedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = InternedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put("a", 1);
+    /// assert_eq!(cache.pop("a"), Some(1));
+    /// assert_eq!(cache.pop("a"), None);
+    /// ```
+    pub fn pop(&mut self, key: &str) -> Option<V> {
+        let v = self.cache.pop(key)?;
+        if self
+            .strings
+            .get(key)
+            .is_some_and(|s| Arc::strong_count(s) == 1)
+        {
+            self.strings.remove(key);
+        }
+        Some(v)
+    }
+
+    /// Returns the number of strings that are interned, which includes the keys of the
+    /// cache and the strings held elsewhere.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::InternedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = InternedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// let held = cache.intern("held");
+    /// cache.put("a", 1);
+    /// assert_eq!(cache.interned(), 2);
+    /// ```
+    pub fn interned(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Returns the number of key-value pairs that are guaranteed to be in the cache, like
+    /// [`LruCache::len`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::InternedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = InternedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// cache.put("a", 1);
+    /// cache.put("b", 2);
+    /// assert_eq!(cache.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::InternedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = InternedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put("a", 1);
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Returns metric on the number of times the cache became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::InternedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = InternedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// for key in ["a", "b", "c", "d", "e"] {
+    ///     cache.put(key, 0);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.cache.get_flips()
+    }
+
+    // Releases the strings that only the interner holds, e.g., the keys a flip discarded.
+    fn release_unused(&mut self) {
+        self.strings.retain(|s| Arc::strong_count(s) > 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InternedLruCache;
+    use alloc::sync::Arc;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_release_discarded_keys() {
+        let mut cache = InternedLruCache::new(NonZeroUsize::new(1).unwrap());
+        let held = cache.intern("held");
+        cache.put("a", 1);
+        cache.put("b", 2);
+        cache.put(&held, 3);
+        // The second flip discarded a, while held is still shared with the caller
+        assert_eq!(cache.interned(), 2);
+        assert_eq!(Arc::strong_count(&held), 3);
+
+        assert_eq!(cache.pop("held"), Some(3));
+        assert_eq!(cache.pop("b"), Some(2));
+        assert_eq!(cache.interned(), 1);
+        // Once the caller drops it, the next flip releases it
+        drop(held);
+        cache.put("c", 4);
+        cache.put("d", 5);
+        assert_eq!(cache.interned(), 2);
+    }
+}
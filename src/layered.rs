@@ -0,0 +1,115 @@
+use crate::Cache;
+
+/// Two caches stacked on top of each other, e.g., a small [`LruCache`] per thread in front
+/// of a big shared [`ShardedLruCache`]. Lookups check the upper cache, then the lower one,
+/// and copy a hit in the lower cache into the upper one. Puts and pops apply to both.
+///
+/// It implements [`Cache`] itself, so more levels can be stacked.
+///
+/// [`LruCache`]: crate::LruCache
+/// [`ShardedLruCache`]: crate::ShardedLruCache
+pub struct Layered<A, B> {
+    upper: A,
+    lower: B,
+}
+
+impl<A, B> Layered<A, B> {
+    /// Stacks the upper cache on top of the lower one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{Cache, Layered, LruCache};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// let upper = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let mut lower = LruCache::new(NonZeroUsize::new(100).unwrap());
+    /// lower.put(1, "a");
+    /// let mut cache = Layered::new(upper, lower);
+    /// assert_eq!(cache.get(&1), Some("a"));
+    /// assert_eq!(cache.upper_mut().peek(&1), Some(&"a"));
+    /// ```
+    pub fn new(upper: A, lower: B) -> Layered<A, B> {
+        Layered { upper, lower }
+    }
+
+    /// Returns the upper cache.
+    pub fn upper(&self) -> &A {
+        &self.upper
+    }
+
+    /// Returns the upper cache mutably.
+    pub fn upper_mut(&mut self) -> &mut A {
+        &mut self.upper
+    }
+
+    /// Returns the lower cache.
+    pub fn lower(&self) -> &B {
+        &self.lower
+    }
+
+    /// Returns the lower cache mutably.
+    pub fn lower_mut(&mut self) -> &mut B {
+        &mut self.lower
+    }
+
+    /// Returns the upper and the lower cache.
+    pub fn into_parts(self) -> (A, B) {
+        (self.upper, self.lower)
+    }
+}
+
+impl<K: Clone, V: Clone, A: Cache<K, V>, B: Cache<K, V>> Cache<K, V> for Layered<A, B> {
+    fn get(&mut self, k: &K) -> Option<V> {
+        if let Some(v) = self.upper.get(k) {
+            return Some(v);
+        }
+        let v = self.lower.get(k)?;
+        self.upper.put(k.clone(), v.clone());
+        Some(v)
+    }
+
+    // The old value of the lower cache, or of the upper one if the lower one lost it
+    fn put(&mut self, k: K, v: V) -> Option<V> {
+        let upper = self.upper.put(k.clone(), v.clone());
+        self.lower.put(k, v).or(upper)
+    }
+
+    fn pop(&mut self, k: &K) -> Option<V> {
+        let upper = self.upper.pop(k);
+        self.lower.pop(k).or(upper)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Layered;
+    use crate::{Cache, LruCache};
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_layers() {
+        let upper = LruCache::new(NonZeroUsize::new(2).unwrap());
+        let lower = LruCache::new(NonZeroUsize::new(100).unwrap());
+        let mut cache = Layered::new(upper, lower);
+        for i in 0..10 {
+            assert_eq!(cache.put(i, i), None);
+        }
+        assert_eq!(cache.upper_mut().peek(&0), None);
+
+        // A hit in the lower cache is copied up
+        assert_eq!(cache.get(&0), Some(0));
+        assert_eq!(cache.upper_mut().peek(&0), Some(&0));
+        assert_eq!(cache.put(0, 1), Some(0));
+        assert_eq!(cache.lower_mut().peek(&0), Some(&1));
+
+        // Three levels
+        let top = LruCache::new(NonZeroUsize::new(1).unwrap());
+        let mut cache = Layered::new(top, cache);
+        assert_eq!(cache.get(&5), Some(5));
+        assert_eq!(cache.pop(&5), Some(5));
+        assert_eq!(cache.get(&5), None);
+        let (_, middle) = cache.into_parts();
+        assert_eq!(middle.lower().len(), 9);
+    }
+}
@@ -0,0 +1,331 @@
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::mem;
+use core::num::NonZeroUsize;
+use hashbrown::HashMap;
+
+// A value of the main cache with its hits, which decay at every flip.
+struct Counted<V> {
+    value: V,
+    hits: u32,
+}
+
+/// A cache with the flip model of [`LruCache`](crate::LruCache) where survival is decided
+/// by frequency: when the main cache is full, the items with the most hits are carried
+/// over into the next generation and the others are moved into the backup cache, replacing
+/// the previous backup. This gives LFU-like behavior with the map operations of an LRU
+/// Cache and a flip that is linear in `cap`.
+///
+/// `cap / 4` items are carried over by default, see
+/// [`with_carry_over`](FlipLfuCache::with_carry_over). Items without hits are never
+/// carried over, and the carried over items keep half of their hits, so that a popular
+/// item survives a few generations without hits but not forever.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::FlipLfuCache;
+/// use std::num::NonZeroUsize;
+/// let mut cache = FlipLfuCache::new(NonZeroUsize::new(4).unwrap());
+///
+/// cache.put(0, "config");
+/// cache.get(&0);
+/// cache.get(&0);
+/// // A scan of more than twice the capacity does not discard the popular item
+/// for i in 1..=10 {
+///     cache.put(i, "scan");
+/// }
+/// assert_eq!(cache.get(&0), Some(&"config"));
+/// ```
+pub struct FlipLfuCache<K, V> {
+    hot: HashMap<K, Counted<V>>,
+    cold: HashMap<K, V>,
+    cap: NonZeroUsize,
+    carry: usize,
+    flips: usize,
+}
+
+impl<K: Hash + Eq, V> FlipLfuCache<K, V> {
+    /// Creates a new cache whose main cache holds `cap` items.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FlipLfuCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: FlipLfuCache<u64, &str> = FlipLfuCache::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> FlipLfuCache<K, V> {
+        FlipLfuCache {
+            hot: HashMap::with_capacity(cap.get()),
+            cold: HashMap::new(),
+            cap,
+            carry: cap.get() / 4,
+            flips: 0,
+        }
+    }
+
+    /// Sets how many of the most frequently hit items a flip carries over into the next
+    /// generation. It is at most `cap - 1`, so that a flip always makes room.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FlipLfuCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = FlipLfuCache::new(NonZeroUsize::new(2).unwrap()).with_carry_over(1);
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.get(&2);
+    /// // The flip carries 2 over and moves 1 into the backup cache
+    /// cache.put(3, "c");
+    /// cache.put(4, "d");
+    /// assert_eq!(cache.get(&1), None);
+    /// assert_eq!(cache.get(&2), Some(&"b"));
+    /// ```
+    pub fn with_carry_over(mut self, n: usize) -> FlipLfuCache<K, V> {
+        self.carry = n.min(self.cap.get() - 1);
+        self
+    }
+
+    /// Returns a reference to the value of the key in the cache or `None` if it is not
+    /// present in the cache. A hit counts towards the survival of the item.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FlipLfuCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = FlipLfuCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert_eq!(cache.get(&4), None);
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_mut(k).map(|v| &*v)
+    }
+
+    /// Returns a mutable reference to the value of the key in the cache or `None` if it
+    /// is not present in the cache. A hit counts towards the survival of the item.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FlipLfuCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = FlipLfuCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put("apple", 8);
+    /// *cache.get_mut("apple").unwrap() += 1;
+    /// assert_eq!(cache.get("apple"), Some(&9));
+    /// ```
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if !self.hot.contains_key(k) {
+            // A hit in the backup cache moves the item back into the main cache
+            let (rk, rv) = self.cold.remove_entry(k)?;
+            self.insert(rk, rv);
+        }
+        let counted = self.hot.get_mut(k)?;
+        counted.hits = counted.hits.saturating_add(1);
+        Some(&mut counted.value)
+    }
+
+    /// Returns a reference to the value of the key without counting a hit, or `None` if it
+    /// is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FlipLfuCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = FlipLfuCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.peek(&1), Some(&"a"));
+    /// ```
+    pub fn peek<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.hot.get(k) {
+            Some(counted) => Some(&counted.value),
+            None => self.cold.get(k),
+        }
+    }
+
+    /// Puts a key-value pair into cache. If the key already exists in the cache, then it updates
+    /// the key's value and returns the old value. Otherwise, `None` is returned. The hits of
+    /// the key are kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FlipLfuCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = FlipLfuCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Option<V> {
+        if let Some(counted) = self.hot.get_mut(&k) {
+            return Some(mem::replace(&mut counted.value, v));
+        }
+        let old = self.cold.remove(&k);
+        self.insert(k, v);
+        old
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it was not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FlipLfuCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = FlipLfuCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.hot.remove(k) {
+            Some(counted) => Some(counted.value),
+            None => self.cold.remove(k),
+        }
+    }
+
+    /// Returns the number of key-value pairs in the main and the backup cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FlipLfuCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = FlipLfuCache::new(NonZeroUsize::new(2).unwrap());
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.len(), 3);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.hot.len() + self.cold.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FlipLfuCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = FlipLfuCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.hot.is_empty() && self.cold.is_empty()
+    }
+
+    /// Returns metric on the number of times the main cache became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::FlipLfuCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = FlipLfuCache::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.flips
+    }
+
+    // Inserts a key that is in neither map, flipping first if the main cache is full.
+    fn insert(&mut self, k: K, v: V) {
+        if self.hot.len() >= self.cap.get() {
+            self.flip();
+        }
+        self.hot.insert(k, Counted { value: v, hits: 0 });
+    }
+
+    fn flip(&mut self) {
+        self.flips += 1;
+        // The fewest hits of the carried over items, of which only some may be carried over
+        let (threshold, mut ties) = match self.carry {
+            0 => (u32::MAX, 0),
+            n => {
+                let mut hits: Vec<u32> = self.hot.values().map(|c| c.hits).collect();
+                let (_, &mut t, _) = hits.select_nth_unstable_by(n - 1, |a, b| b.cmp(a));
+                let t = t.max(1);
+                let above = hits.iter().filter(|&&h| h > t).count();
+                (t, n - above)
+            }
+        };
+        self.cold.clear();
+        let demoted = self.hot.extract_if(|_, c| {
+            if c.hits > threshold || (c.hits == threshold && ties > 0) {
+                ties -= usize::from(c.hits == threshold);
+                c.hits /= 2;
+                return false;
+            }
+            true
+        });
+        self.cold.extend(demoted.map(|(k, c)| (k, c.value)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlipLfuCache;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_frequent_items_survive() {
+        let mut cache = FlipLfuCache::new(NonZeroUsize::new(4).unwrap()).with_carry_over(2);
+        for i in 0..4 {
+            cache.put(i, i);
+        }
+        // 1 and 3 are hit the most
+        for k in [1, 1, 3, 3, 3, 2] {
+            cache.get(&k);
+        }
+        cache.put(10, 10);
+        assert_eq!(cache.get_flips(), 1);
+        assert_eq!(cache.hot.len(), 3);
+        assert!(cache.hot.contains_key(&1) && cache.hot.contains_key(&3));
+        assert_eq!(cache.peek(&0), Some(&0));
+
+        // The carried over items keep half of their hits, enough for another flip
+        cache.put(11, 11);
+        cache.put(12, 12);
+        assert_eq!(cache.get_flips(), 2);
+        assert!(cache.hot.contains_key(&1) && cache.hot.contains_key(&3));
+        assert_eq!(cache.peek(&0), None);
+        assert_eq!(cache.peek(&10), Some(&10));
+    }
+}
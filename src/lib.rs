@@ -1,20 +1,633 @@
 #![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+mod any;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+mod arc;
+#[cfg(feature = "rkyv")]
+mod archive;
+#[cfg(feature = "async-lock")]
+mod async_backend;
+#[cfg(feature = "async-lock")]
+mod async_lru;
+#[cfg(feature = "std")]
+mod background;
+#[cfg(feature = "borsh")]
+mod borsh_impl;
+#[cfg(feature = "bytes")]
+mod byte_cache;
+#[cfg(feature = "alloc")]
+mod cache;
+#[cfg(feature = "std")]
+mod capacity;
+#[cfg(feature = "alloc")]
+mod caseless;
+mod clock;
+#[cfg(feature = "compress")]
+mod compress;
+#[cfg(feature = "alloc")]
+mod counter;
+#[cfg(feature = "crossbeam-epoch")]
+mod epoch;
+mod event;
+#[cfg(feature = "std")]
+mod file_backend;
+mod fixed;
+#[cfg(feature = "std")]
+mod flight;
+mod fnv;
+#[cfg(feature = "alloc")]
+mod frozen;
+#[cfg(feature = "alloc")]
+mod generational;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+mod group;
+#[cfg(feature = "alloc")]
+mod hot;
+#[cfg(feature = "indexmap")]
+mod indexed;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+mod intern;
+#[cfg(feature = "alloc")]
+mod layered;
+#[cfg(feature = "alloc")]
+mod lfu;
+#[cfg(feature = "lock_api")]
+mod locked;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "alloc")]
+mod multimap;
+#[cfg(feature = "alloc")]
+mod namespaced;
+#[cfg(feature = "alloc")]
+mod observer;
+#[cfg(feature = "alloc")]
+mod ordered;
+#[cfg(feature = "rayon")]
+mod par;
+#[cfg(feature = "std")]
+mod persist;
+#[cfg(feature = "alloc")]
+mod prefix;
+mod random;
+#[cfg(feature = "alloc")]
+mod read_through;
+#[cfg(feature = "std")]
+mod rw;
+#[cfg(feature = "alloc")]
+mod sampled;
+#[cfg(feature = "alloc")]
+mod seeded;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "alloc")]
+mod set;
+#[cfg(feature = "std")]
+mod sharded;
+#[cfg(feature = "alloc")]
+mod small;
+#[cfg(feature = "alloc")]
+mod spill;
+#[cfg(feature = "critical-section")]
+mod static_cache;
+mod stats;
+#[cfg(feature = "std")]
+mod sync;
+#[cfg(feature = "alloc")]
+mod tagged;
+#[cfg(feature = "alloc")]
+mod ttl;
+#[cfg(feature = "alloc")]
+mod two_tier;
+#[cfg(feature = "alloc")]
+mod versioned;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+mod weak;
+#[cfg(feature = "zeroize")]
+mod wipe;
+#[cfg(feature = "alloc")]
+mod write_through;
+
+#[cfg(feature = "alloc")]
+pub use any::AnyCache;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use arc::ArcCache;
+#[cfg(feature = "rkyv")]
+pub use archive::{ArchivedCacheArchive, CacheArchive};
+#[cfg(feature = "async-lock")]
+pub use async_backend::{AsyncBackedCache, AsyncCacheBackend, LoadError};
+#[cfg(feature = "async-lock")]
+pub use async_lru::AsyncLruCache;
+#[cfg(feature = "bytes")]
+pub use byte_cache::BytesCache;
+#[cfg(feature = "alloc")]
+pub use cache::Cache;
+#[cfg(feature = "std")]
+pub use capacity::ZeroCapacityError;
+#[cfg(feature = "alloc")]
+pub use caseless::AsciiCaseless;
+pub use clock::Clock;
+#[cfg(feature = "std")]
+pub use clock::InstantClock;
+#[cfg(feature = "compress")]
+pub use compress::{CompressedCache, CompressionStats};
+#[cfg(feature = "alloc")]
+pub use counter::CounterValue;
+#[cfg(feature = "crossbeam-epoch")]
+pub use epoch::EpochLruCache;
+pub use event::{CacheEvent, Tier};
+#[cfg(feature = "std")]
+pub use file_backend::FileBackend;
+pub use fixed::FixedLruCache;
+pub use fnv::{FnvBuildHasher, FnvHasher};
+#[cfg(feature = "alloc")]
+pub use frozen::FrozenCacheView;
+#[cfg(feature = "alloc")]
+pub use generational::GenLruCache;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use group::{CacheGroup, GroupedCache};
+#[cfg(feature = "alloc")]
+pub use hot::HotKeysCache;
+#[cfg(feature = "indexmap")]
+pub use indexed::IndexedLruCache;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use intern::InternedLruCache;
+#[cfg(feature = "alloc")]
+pub use layered::Layered;
+#[cfg(feature = "alloc")]
+pub use lfu::FlipLfuCache;
+#[cfg(feature = "lock_api")]
+pub use locked::LockedLruCache;
+#[cfg(feature = "spin")]
+pub use locked::SpinLruCache;
+#[cfg(feature = "mmap")]
+pub use mmap::FromBytes;
+#[cfg(feature = "alloc")]
+pub use multimap::LruMultiMap;
+#[cfg(feature = "alloc")]
+pub use namespaced::NamespacedCache;
+#[cfg(feature = "alloc")]
+pub use observer::CacheObserver;
+#[cfg(feature = "alloc")]
+pub use ordered::OrderedLruCache;
+#[cfg(feature = "std")]
+pub use persist::SnapshotError;
+pub use random::{Lcg, RandomSource};
+#[cfg(feature = "alloc")]
+pub use read_through::{CacheLoader, Lookup, ReadThroughCache};
+#[cfg(feature = "std")]
+pub use rw::RwLruCache;
+#[cfg(feature = "alloc")]
+pub use sampled::SampledLruCache;
+#[cfg(feature = "alloc")]
+pub use seeded::SeededState;
+#[cfg(feature = "alloc")]
+pub use set::LruSet;
+#[cfg(feature = "std")]
+pub use sharded::ShardedLruCache;
+#[cfg(feature = "alloc")]
+pub use small::SmallLruCache;
+#[cfg(feature = "alloc")]
+pub use spill::SpillSink;
+#[cfg(feature = "critical-section")]
+pub use static_cache::StaticLruCache;
+pub use stats::CacheStats;
+#[cfg(feature = "std")]
+pub use sync::{InsertGuard, SharedLruCache, SyncLruCache, ValueGuard};
+#[cfg(feature = "alloc")]
+pub use tagged::TaggedLruCache;
+#[cfg(feature = "alloc")]
+pub use ttl::TtlCache;
+#[cfg(feature = "alloc")]
+pub use two_tier::{Backend, TwoTierCache};
+#[cfg(feature = "alloc")]
+pub use versioned::VersionedLruCache;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use weak::WeakLruCache;
+#[cfg(feature = "alloc")]
+pub use write_through::{WriteBackend, WriteThroughCache};
+
+#[cfg(feature = "alloc")]
+pub use allocator_api2;
+#[cfg(feature = "alloc")]
+pub use hashbrown::TryReserveError;
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::collections::BTreeMap;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use allocator_api2::alloc::{Allocator, Global};
+#[cfg(feature = "alloc")]
 use core::borrow::Borrow;
-use core::hash::Hash;
-use core::num::NonZeroUsize;
+#[cfg(feature = "alloc")]
+use core::hash::{BuildHasher, Hash};
+#[cfg(feature = "alloc")]
+use core::num::{NonZeroU32, NonZeroUsize};
+#[cfg(feature = "alloc")]
+use core::ops::{Deref, DerefMut};
+#[cfg(feature = "alloc")]
+use core::time::Duration;
+#[cfg(feature = "alloc")]
 use core::{cmp, mem};
+#[cfg(feature = "alloc")]
+use hashbrown::hash_map::{self, DefaultHashBuilder, RawEntryMut};
+#[cfg(feature = "alloc")]
 use hashbrown::HashMap;
-use polonius_the_crab::{polonius, polonius_return};
+
+/// The priority class of an item, see [`LruCache::put_with_priority`].
+#[cfg(feature = "alloc")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Priority {
+    /// Items that are discarded along with their generation.
+    #[default]
+    Normal,
+    /// Items that are carried over to the new main cache at a flip, up to the
+    /// configured reserve.
+    High,
+}
+
+/// A cached value together with the bookkeeping the cache keeps for it.
+#[cfg(feature = "alloc")]
+struct Entry<V> {
+    value: V,
+    // Number of hits received while in the l2_map.
+    hits: u32,
+    priority: Priority,
+    // Position in the insertion order, or the recency order in the strict LRU mode.
+    tick: u64,
+    // Whether the value was modified in the cache and not flushed since.
+    dirty: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl<V> Entry<V> {
+    fn new(value: V, priority: Priority) -> Entry<V> {
+        Entry {
+            value,
+            hits: 0,
+            priority,
+            tick: 0,
+            dirty: false,
+        }
+    }
+
+    // The entry as it starts over in a new generation.
+    fn renewed(self) -> Entry<V> {
+        Entry {
+            hits: 0,
+            tick: 0,
+            ..self
+        }
+    }
+}
+
+// The random source used for probabilistic promotion.
+#[cfg(feature = "alloc")]
+enum Rng {
+    Builtin(Lcg),
+    Custom(Box<dyn RandomSource + Send + Sync>),
+}
+
+#[cfg(feature = "alloc")]
+impl RandomSource for Rng {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            Rng::Builtin(rng) => rng.next_u32(),
+            Rng::Custom(rng) => rng.next_u32(),
+        }
+    }
+}
+
+// A promotion chance at or above this value always promotes.
+#[cfg(feature = "alloc")]
+const ALWAYS_PROMOTE: u64 = 1 << 32;
+
+/// Controls when the cache flips, see [`LruCache::with_flip_mode`].
+#[cfg(feature = "alloc")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FlipMode {
+    /// Flip when the main cache is full.
+    #[default]
+    Auto,
+    /// Only flip when [`LruCache::flip`] is called. The main cache grows beyond its
+    /// capacity until then.
+    Manual,
+    /// Flip after every given number of insertions into the main cache, counting puts and
+    /// promotions from the backup cache. The main cache grows beyond its capacity if
+    /// needed, which makes the flip frequency independent of the capacity.
+    EveryInsertions(NonZeroUsize),
+    /// Never flip. Instead the main cache keeps the exact recency order of its items and
+    /// evicts precisely the least recently used one when it is full, like a classic LRU
+    /// cache. This costs an ordered index update on every access. The high priority reserve
+    /// does not apply in this mode.
+    StrictLru,
+}
+
+// Flips the cache at a fixed interval.
+#[cfg(feature = "alloc")]
+struct FlipTimer {
+    clock: Box<dyn Clock + Send + Sync>,
+    interval: Duration,
+    // Start of the current interval
+    start: Duration,
+}
+
+// Bounds for the capacity autotuning.
+#[cfg(feature = "alloc")]
+struct Autotune {
+    min: NonZeroUsize,
+    max: NonZeroUsize,
+}
+
+// Grow the capacity when more than this share of a generation was recovered.
+#[cfg(feature = "alloc")]
+const AUTOTUNE_GROW_SURVIVAL: f64 = 0.25;
+// Shrink the capacity when the cache sees this many times its size in accesses
+// without flipping.
+#[cfg(feature = "alloc")]
+const AUTOTUNE_SHRINK_ACCESSES: usize = 8;
+
+// Items that each access drops after invalidate_all without the incremental drop.
+#[cfg(feature = "alloc")]
+const INVALIDATED_DROP_BATCH: usize = 64;
+
+/// Why an item was discarded, see [`LruCache::with_eviction_listener`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EvictionReason {
+    /// The item was discarded to make room: along with its generation at a flip, when the
+    /// backup cache overflowed or as the least recently used item in
+    /// [`FlipMode::StrictLru`].
+    Flipped,
+    /// The value was replaced by a new value for its key, see the [`Extend`] implementation.
+    Replaced,
+    /// The item was removed by [`LruCache::retain`] or [`LruCache::remove`].
+    Removed,
+    /// The item was discarded along with its generation by a time based flip, see
+    /// [`LruCache::with_flip_interval`].
+    Expired,
+    /// The item was removed by [`LruCache::clear`] or [`LruCache::invalidate_all`].
+    Cleared,
+}
+
+// Receives the items that the cache discards.
+#[cfg(feature = "alloc")]
+type EvictionListener<K, V> = Box<dyn FnMut(K, V, EvictionReason) + Send + Sync>;
+#[cfg(feature = "alloc")]
+type Observer<K, V> = Box<dyn CacheObserver<K, V> + Send + Sync>;
+#[cfg(feature = "alloc")]
+type Sink<K, V> = Box<dyn SpillSink<K, V> + Send + Sync>;
+#[cfg(feature = "alloc")]
+type EventHook = Box<dyn FnMut(CacheEvent) + Send + Sync>;
+#[cfg(feature = "alloc")]
+type Flush<K, V> = Box<dyn FnMut(&K, &V) + Send + Sync>;
+
+// Overwrites the memory of the values, and of the keys if set, before they are dropped.
+#[cfg(feature = "alloc")]
+struct Wipe<K, V> {
+    value: fn(&mut V),
+    key: Option<fn(&mut K)>,
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V> Clone for Wipe<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V> Copy for Wipe<K, V> {}
+
+#[cfg(feature = "alloc")]
+impl<K, V> Wipe<K, V> {
+    fn item(self, k: &mut K, v: &mut V) {
+        if let Some(key) = self.key {
+            key(k);
+        }
+        (self.value)(v);
+    }
+
+    // Wipes the items where the map stores them and clears it, as draining the map would
+    // only wipe the copies that are moved out.
+    fn clear<S, A: Allocator + Clone>(self, map: &mut HashMap<K, Entry<V>, S, A>) {
+        // SAFETY: the buckets are those of the table, which is not resized while they are
+        // borrowed, and the wiped keys are not hashed again as the table is cleared next.
+        unsafe {
+            for bucket in map.raw_table_mut().iter() {
+                let (k, e) = bucket.as_mut();
+                self.item(k, &mut e.value);
+            }
+        }
+        map.clear();
+    }
+
+    // Removes the entry of the key after wiping it where the map stores it.
+    fn remove<Q, S: BuildHasher, A: Allocator + Clone>(
+        self,
+        map: &mut HashMap<K, Entry<V>, S, A>,
+        k: &Q,
+    ) -> Option<Entry<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = map.hasher().hash_one(k);
+        let table = map.raw_table_mut();
+        let bucket = table.find(hash, |(q, _)| q.borrow() == k)?;
+        // SAFETY: the bucket was just found in the table, and it is removed right after its
+        // key is wiped, without a lookup in between.
+        unsafe {
+            let (rk, e) = bucket.as_mut();
+            self.item(rk, &mut e.value);
+            Some(table.remove(bucket).0 .1)
+        }
+    }
+}
+
+// A map of the cache, which wipes its items where it stores them when it is dropped if
+// the cache wipes the items. LruCache itself does not implement Drop, so that the features
+// do not change how it can be dropped or taken apart.
+#[cfg(feature = "alloc")]
+struct Generation<K, V, S, A: Allocator + Clone> {
+    items: HashMap<K, Entry<V>, S, A>,
+    wipe: Option<Wipe<K, V>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S, A: Allocator + Clone> Deref for Generation<K, V, S, A> {
+    type Target = HashMap<K, Entry<V>, S, A>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.items
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S, A: Allocator + Clone> DerefMut for Generation<K, V, S, A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.items
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S, A: Allocator + Clone> Drop for Generation<K, V, S, A> {
+    fn drop(&mut self) {
+        if let Some(wipe) = self.wipe {
+            wipe.clear(&mut self.items);
+        }
+    }
+}
+
+// Discarded items that are being dropped, and why they were discarded.
+#[cfg(feature = "alloc")]
+type Parked<K, V, A> = (hash_map::IntoIter<K, Entry<V>, A>, EvictionReason);
+#[cfg(feature = "alloc")]
+type Offload<K, V, S, A> = Box<dyn FnMut(HashMap<K, Entry<V>, S, A>) + Send + Sync>;
+
+// Where the discarded items and the cache events go.
+#[cfg(feature = "alloc")]
+struct Hooks<K, V> {
+    listener: Option<EvictionListener<K, V>>,
+    sink: Option<Sink<K, V>>,
+    events: Option<EventHook>,
+    flush: Option<Flush<K, V>>,
+    wipe: Option<Wipe<K, V>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V> Hooks<K, V> {
+    // Whether the discarded items need more than being dropped.
+    fn observes(&self) -> bool {
+        self.listener.is_some()
+            || self.sink.is_some()
+            || self.events.is_some()
+            || self.flush.is_some()
+            || self.wipe.is_some()
+    }
+
+    fn emit(&mut self, event: CacheEvent) {
+        if let Some(events) = &mut self.events {
+            events(event);
+        }
+    }
+
+    // Flushes the dirty items and hands the discarded items to the spill sink if they were
+    // discarded for capacity or age, to the eviction listener otherwise, or wipes and drops
+    // them.
+    fn evicted<I>(&mut self, items: I, reason: EvictionReason)
+    where
+        I: IntoIterator<Item = (K, Entry<V>)>,
+    {
+        let (events, flush) = (&mut self.events, &mut self.flush);
+        let mut items = items.into_iter().map(|(k, e)| {
+            if let (true, Some(flush)) = (e.dirty, flush.as_mut()) {
+                flush(&k, &e.value);
+            }
+            if let Some(events) = events {
+                events(CacheEvent::Eviction(reason));
+            }
+            (k, e.value)
+        });
+        let spilled = matches!(reason, EvictionReason::Flipped | EvictionReason::Expired);
+        match (&mut self.sink, &mut self.listener) {
+            (Some(sink), _) if spilled => sink.spill(&mut items),
+            (_, Some(listener)) => items.by_ref().for_each(|(k, v)| listener(k, v, reason)),
+            _ => {}
+        }
+        match self.wipe {
+            Some(wipe) => items.for_each(|(mut k, mut v)| wipe.item(&mut k, &mut v)),
+            None => items.for_each(drop),
+        }
+    }
+
+    // Discards all the items of the map like evicted, but wipes the items that are not
+    // handed over where the map stores them.
+    fn discard<S, A: Allocator + Clone>(
+        &mut self,
+        map: &mut HashMap<K, Entry<V>, S, A>,
+        reason: EvictionReason,
+    ) {
+        let spilled = matches!(reason, EvictionReason::Flipped | EvictionReason::Expired);
+        let handed = self.listener.is_some() || (self.sink.is_some() && spilled);
+        let Some(wipe) = self.wipe.filter(|_| !handed) else {
+            return self.evicted(map.drain(), reason);
+        };
+        for (k, e) in map.iter() {
+            if let (true, Some(flush)) = (e.dirty, self.flush.as_mut()) {
+                flush(k, &e.value);
+            }
+            self.emit(CacheEvent::Eviction(reason));
+        }
+        wipe.clear(map);
+    }
+}
+
+// Where the hits of the current generation landed.
+#[cfg(feature = "alloc")]
+#[derive(Default)]
+struct GenHits {
+    l1: u64,
+    l2: u64,
+}
 
 /// An LRU Cache
-pub struct LruCache<K, V> {
-    l1_map: HashMap<K, V>,
-    l2_map: HashMap<K, V>,
+///
+/// The maps of both tiers use the hasher `S` and allocate from `A`, see
+/// [`with_hasher_in`](LruCache::with_hasher_in).
+#[cfg(feature = "alloc")]
+pub struct LruCache<K, V, S = DefaultHashBuilder, A: Allocator + Clone = Global> {
+    l1_map: Generation<K, V, S, A>,
+    l2_map: Generation<K, V, S, A>,
     cap: NonZeroUsize,
+    // Size at which the l1_map flips
+    hot_cap: usize,
+    // Maximum size of the l2_map
+    cold_cap: usize,
+    adaptive: bool,
+    flip_mode: FlipMode,
+    flip_timer: Option<FlipTimer>,
+    autotune: Option<Autotune>,
+    gen_hits: GenHits,
+    // Insertions into the l1_map since the last flip
+    gen_inserts: usize,
     flips: usize,
+    // Insertion order of the l1_map, or recency order in the strict LRU mode: tick -> key hash
+    order: BTreeMap<u64, u64>,
+    l2_order: BTreeMap<u64, u64>,
+    track_order: bool,
+    tick: u64,
+    promote_after: NonZeroU32,
+    promote_chance: u64,
+    rng: Rng,
+    priority_reserve: f32,
+    scan_resistant: bool,
+    // The maps of the new generations grow with use instead of being preallocated
+    growable: bool,
+    hooks: Hooks<K, V>,
+    observer: Option<Observer<K, V>>,
+    // A preallocated map for the next generation, see try_put
+    spare: Option<HashMap<K, Entry<V>, S, A>>,
+    // Entries of the discarded generation that each access drops, or 0 to drop them at the flip
+    drop_batch: usize,
+    // Whether the flips keep the emptied maps for reuse
+    pooling: bool,
+    // The discarded items that the following accesses drop, the latest last
+    parked: Vec<Parked<K, V, A>>,
+    // Where the discarded generations are dropped instead of at the flip
+    offload: Option<Offload<K, V, S, A>>,
 }
 
+#[cfg(feature = "alloc")]
 impl<K: Hash + Eq, V> LruCache<K, V> {
     /// Creates a new LRU Cache that holds `cap` items.
     /// It can fetch upto the last `cap*2` items, but only
@@ -25,6 +638,9 @@ impl<K: Hash + Eq, V> LruCache<K, V> {
     /// Then as cache misses occur, the cache gets populated internally from the backup
     /// cache if the item is found there or a miss is reported to the user.
     ///
+    /// Only the main cache is allocated upfront. The backup cache takes over its
+    /// allocation at the first flip, so a cache that never fills uses half the memory.
+    ///
     /// # Example
     ///
     /// ```
@@ -33,335 +649,3304 @@ impl<K: Hash + Eq, V> LruCache<K, V> {
     /// let mut cache: LruCache<isize, &str> = LruCache::new(NonZeroUsize::new(10).unwrap());
     /// ```
     pub fn new(cap: NonZeroUsize) -> LruCache<K, V> {
-        LruCache {
-            l1_map: HashMap::with_capacity(cap.into()),
-            l2_map: HashMap::with_capacity(cap.into()),
-            cap,
-            flips: 0,
-        }
+        LruCache::with_caps(cap, cap)
     }
 
-    /// Returns a reference to the value of the key in the cache or `None` if it is not
-    /// present in the cache.
+    /// Like [`new`](LruCache::new), but returns an error instead of aborting if the maps
+    /// cannot be allocated. Use [`try_put`](LruCache::try_put) to handle the allocation
+    /// failures of the flips too.
     ///
     /// # Example
     ///
     /// ```
     /// use fliplru::LruCache;
     /// use std::num::NonZeroUsize;
-    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let cache: Result<LruCache<u64, u64>, _> = LruCache::try_new(NonZeroUsize::new(10).unwrap());
+    /// assert!(cache.is_ok());
+    /// let cache: Result<LruCache<u64, u64>, _> = LruCache::try_new(NonZeroUsize::MAX);
+    /// assert!(cache.is_err());
+    /// ```
+    pub fn try_new(cap: NonZeroUsize) -> Result<LruCache<K, V>, TryReserveError> {
+        let mut cache = LruCache::empty(cap, cap, DefaultHashBuilder::default(), Global);
+        cache.l1_map.try_reserve(cap.get())?;
+        Ok(cache)
+    }
+
+    /// Creates a new LRU Cache like [`new`], but whose main cache starts empty and grows
+    /// as items are put into it, instead of being allocated for `cap` items upfront.
     ///
-    /// cache.put(1, "a");
-    /// cache.put(2, "b");
-    /// cache.put(2, "c");
-    /// cache.put(3, "d");
+    /// This keeps the memory proportional to the number of items for a large `cap` that
+    /// may never be reached, at the cost of rehashing while the main cache grows. The new
+    /// generations that replace it at the flips are sized like the one that flipped.
+    ///
+    /// [`new`]: LruCache::new
+    ///
+    /// # Example
     ///
-    /// assert_eq!(cache.get(&2), Some(&"c"));
-    /// assert_eq!(cache.get(&3), Some(&"d"));
     /// ```
-    pub fn get<'a, Q>(&'a mut self, k: &Q) -> Option<&'a V>
-    where
-        K: Borrow<Q>,
-        Q: Hash + Eq + ?Sized,
-    {
-        let mut this = self;
-        polonius!(|this| -> Option<&'polonius V> {
-            if let Some(v) = this.l1_map.get(k) {
-                polonius_return!(Some(v));
-            }
-        });
-
-        match this.l2_map.remove_entry(k) {
-            Some((rk, rv)) => {
-                this.put(rk, rv);
-                this.l1_map.get(k)
-            }
-            None => None,
-        }
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new_growable(NonZeroUsize::new(1_000_000).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert_eq!(cache.cap().get(), 1_000_000);
+    /// ```
+    pub fn new_growable(cap: NonZeroUsize) -> LruCache<K, V> {
+        let mut cache = LruCache::empty(cap, cap, DefaultHashBuilder::default(), Global);
+        cache.growable = true;
+        cache
     }
 
-    /// Returns a mutable reference to the value of the key in the cache or `None` if it
-    /// is not present in the cache. Moves the key to the l1_map if it exists in the l2_map.
+    /// Creates a new LRU Cache whose main cache holds `hot_cap` items and whose backup
+    /// cache holds up to `cold_cap` items. [`new`] uses `cap` for both.
+    ///
+    /// At a flip the full main cache moves into the backup cache. When the backup cache is
+    /// larger, it keeps the previous generations for as long as they fit along with the new
+    /// one. When it is smaller, the items of the flipped generation that do not fit are
+    /// discarded in no particular order.
+    ///
+    /// [`new`]: LruCache::new
     ///
     /// # Example
     ///
     /// ```
     /// use fliplru::LruCache;
     /// use std::num::NonZeroUsize;
-    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
-    ///
-    /// cache.put("apple", 8);
-    /// cache.put("banana", 4);
-    /// cache.put("banana", 6);
-    /// cache.put("pear", 2);
+    /// let mut cache = LruCache::with_caps(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(4).unwrap());
+    /// assert_eq!(cache.hot_cap(), 2);
+    /// assert_eq!(cache.cold_cap(), 4);
     ///
-    /// assert_eq!(cache.get_mut(&"apple"), Some(&mut 8));
-    /// assert_eq!(cache.get_mut(&"banana"), Some(&mut 6));
-    /// assert_eq!(cache.get_mut(&"pear"), Some(&mut 2));
+    /// for i in 0..6 {
+    ///     cache.put(i, i);
+    /// }
+    /// // The backup cache holds the last two generations.
+    /// assert_eq!(cache.get_flips(), 2);
+    /// assert_eq!(cache.get(&0), Some(&0));
     /// ```
-    pub fn get_mut<'a, Q>(&'a mut self, k: &Q) -> Option<&'a mut V>
-    where
-        K: Borrow<Q>,
-        Q: Hash + Eq + ?Sized,
-    {
-        let mut this = self;
-        polonius!(|this| -> Option<&'polonius mut V> {
-            if let Some(v) = this.l1_map.get_mut(k) {
-                polonius_return!(Some(v));
-            }
-        });
+    pub fn with_caps(hot_cap: NonZeroUsize, cold_cap: NonZeroUsize) -> LruCache<K, V> {
+        LruCache::with_hasher_in(hot_cap, cold_cap, DefaultHashBuilder::default(), Global)
+    }
 
-        match this.l2_map.remove_entry(k) {
-            Some((rk, rv)) => {
-                this.put(rk, rv);
-                this.l1_map.get_mut(k)
-            }
-            None => None,
-        }
+    // A cache of the capacities read from its serialized form, which may not be trusted, so
+    // its maps grow as the items are read and put into them, like for new_growable.
+    #[cfg(any(
+        feature = "serde",
+        feature = "borsh",
+        feature = "rkyv",
+        feature = "std"
+    ))]
+    pub(crate) fn restoring(hot_cap: NonZeroUsize, cold_cap: NonZeroUsize) -> LruCache<K, V> {
+        let mut cache = LruCache::empty(hot_cap, cold_cap, DefaultHashBuilder::default(), Global);
+        cache.growable = true;
+        cache
     }
+}
 
-    /// Puts a key-value pair into cache. If the key already exists in the cache, then it updates
-    /// the key's value and returns the old value. Otherwise, `None` is returned.
+#[cfg(feature = "alloc")]
+impl<K: Hash + Eq, V> LruCache<K, V, FnvBuildHasher> {
+    /// Creates a new LRU Cache like [`new`] in a `const` context, e.g., for a `static`
+    /// without lazy initialization. None of its maps is allocated until the first put,
+    /// and the main cache grows as items are put into it until the first flip.
+    ///
+    /// The keys are hashed with [`FnvHasher`], as the default hasher needs a random seed
+    /// at runtime. Use [`with_hasher_in`] for keys from untrusted input.
+    ///
+    /// [`new`]: LruCache::new
+    /// [`with_hasher_in`]: LruCache::with_hasher_in
     ///
     /// # Example
     ///
     /// ```
-    /// use fliplru::LruCache;
+    /// use fliplru::{FnvBuildHasher, LruCache};
     /// use std::num::NonZeroUsize;
-    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
-    ///
-    /// assert_eq!(None, cache.put(1, "a"));
-    /// assert_eq!(None, cache.put(2, "b"));
-    /// assert_eq!(Some("b"), cache.put(2, "beta"));
+    /// use std::sync::Mutex;
+    /// static CACHE: Mutex<LruCache<u32, &str, FnvBuildHasher>> =
+    ///     Mutex::new(LruCache::new_const(NonZeroUsize::new(16).unwrap()));
     ///
-    /// assert_eq!(cache.get(&1), Some(&"a"));
-    /// assert_eq!(cache.get(&2), Some(&"beta"));
+    /// CACHE.lock().unwrap().put(1, "a");
+    /// assert_eq!(CACHE.lock().unwrap().get(&1), Some(&"a"));
     /// ```
-    pub fn put(&mut self, k: K, v: V) -> Option<V> {
-        if self.l1_map.len() == self.cap.into() {
-            mem::swap(&mut self.l2_map, &mut self.l1_map);
-            let _ = mem::replace(&mut self.l1_map, HashMap::with_capacity(self.cap.into()));
-            self.flips += 1;
-        }
-        // invalidate any existing entry in L2 cache
-        let ov = self.l2_map.remove(&k);
-        match self.l1_map.insert(k, v) {
-            Some(l1_v) => Some(l1_v),
-            None => ov,
-        }
+    pub const fn new_const(cap: NonZeroUsize) -> LruCache<K, V, FnvBuildHasher> {
+        LruCache::from_maps(
+            HashMap::with_hasher_in(FnvBuildHasher::new(), Global),
+            HashMap::with_hasher_in(FnvBuildHasher::new(), Global),
+            cap,
+            cap,
+        )
     }
+}
 
-    /// Returns the maximum number of key-value pairs the cache can hold.
+#[cfg(feature = "alloc")]
+impl<K: Hash + Eq, V, S: BuildHasher + Clone, A: Allocator + Clone> LruCache<K, V, S, A> {
+    /// Creates a new LRU Cache like [`with_caps`] whose maps hash the keys with `hasher`
+    /// and allocate from `alloc`, e.g., an arena. The maps created at the flips use them
+    /// too. The allocator trait is the one of the re-exported [`allocator_api2`] crate,
+    /// which is `core::alloc::Allocator` on nightly once its `nightly` feature is enabled.
+    ///
+    /// [`with_caps`]: LruCache::with_caps
     ///
     /// # Example
     ///
     /// ```
+    /// use fliplru::allocator_api2::alloc::Global;
     /// use fliplru::LruCache;
+    /// use std::collections::hash_map::RandomState;
     /// use std::num::NonZeroUsize;
-    /// let mut cache: LruCache<isize, &str> = LruCache::new(NonZeroUsize::new(2).unwrap());
-    /// assert_eq!(cache.cap().get(), 2);
+    /// let cap = NonZeroUsize::new(2).unwrap();
+    /// let mut cache = LruCache::with_hasher_in(cap, cap, RandomState::new(), Global);
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
     /// ```
-    pub fn cap(&self) -> NonZeroUsize {
-        self.cap
+    pub fn with_hasher_in(
+        hot_cap: NonZeroUsize,
+        cold_cap: NonZeroUsize,
+        hasher: S,
+        alloc: A,
+    ) -> LruCache<K, V, S, A> {
+        let mut cache = LruCache::empty(hot_cap, cold_cap, hasher, alloc);
+        // The backup cache is allocated at the first flip, when the main cache moves in
+        cache.l1_map.reserve(hot_cap.get());
+        cache
     }
 
-    /// Returns the number of key-value pairs that are currently in the the cache.
+    // A cache whose maps are not allocated yet.
+    fn empty(
+        hot_cap: NonZeroUsize,
+        cold_cap: NonZeroUsize,
+        hasher: S,
+        alloc: A,
+    ) -> LruCache<K, V, S, A> {
+        // Both maps share the hasher, so that key hashes stay valid across flips
+        LruCache::from_maps(
+            HashMap::with_hasher_in(hasher.clone(), alloc.clone()),
+            HashMap::with_hasher_in(hasher, alloc),
+            hot_cap,
+            cold_cap,
+        )
+    }
+
+    // A cache of the given empty maps, which is usable in a const context.
+    const fn from_maps(
+        l1_map: HashMap<K, Entry<V>, S, A>,
+        l2_map: HashMap<K, Entry<V>, S, A>,
+        hot_cap: NonZeroUsize,
+        cold_cap: NonZeroUsize,
+    ) -> LruCache<K, V, S, A> {
+        LruCache {
+            l1_map: Generation {
+                items: l1_map,
+                wipe: None,
+            },
+            l2_map: Generation {
+                items: l2_map,
+                wipe: None,
+            },
+            cap: hot_cap,
+            hot_cap: hot_cap.get(),
+            cold_cap: cold_cap.get(),
+            adaptive: false,
+            flip_mode: FlipMode::Auto,
+            flip_timer: None,
+            autotune: None,
+            gen_hits: GenHits { l1: 0, l2: 0 },
+            gen_inserts: 0,
+            flips: 0,
+            order: BTreeMap::new(),
+            l2_order: BTreeMap::new(),
+            track_order: false,
+            tick: 0,
+            promote_after: NonZeroU32::MIN,
+            promote_chance: ALWAYS_PROMOTE,
+            rng: Rng::Builtin(Lcg::new(random::DEFAULT_SEED)),
+            priority_reserve: 0.0,
+            scan_resistant: false,
+            growable: false,
+            hooks: Hooks {
+                listener: None,
+                sink: None,
+                events: None,
+                flush: None,
+                wipe: None,
+            },
+            observer: None,
+            spare: None,
+            drop_batch: 0,
+            pooling: true,
+            parked: Vec::new(),
+            offload: None,
+        }
+    }
+
+    /// Sets the number of hits an item needs while in the backup cache before it is
+    /// moved back into the main cache. The default is 1, i.e., a single access rescues an item.
+    ///
+    /// Items that are accessed less often than the threshold are still returned, but they
+    /// are not carried over to the next flip, which keeps rarely used items from surviving
+    /// at the expense of the hot ones.
     ///
     /// # Example
     ///
     /// ```
     /// use fliplru::LruCache;
-    /// use std::num::NonZeroUsize;
-    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
-    /// assert_eq!(cache.len(), 0);
+    /// use std::num::{NonZeroU32, NonZeroUsize};
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap())
+    ///     .with_promotion_threshold(NonZeroU32::new(2).unwrap());
     ///
     /// cache.put(1, "a");
-    /// assert_eq!(cache.len(), 1);
-    ///
     /// cache.put(2, "b");
-    /// assert_eq!(cache.len(), 2);
-    ///
     /// cache.put(3, "c");
-    /// assert_eq!(cache.len(), 2);
+    ///
+    /// // 1 is in the backup cache and needs two hits to be moved back.
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// cache.put(4, "d");
+    /// cache.put(5, "e");
+    /// assert_eq!(cache.get(&1), None);
     /// ```
-    pub fn len(&self) -> usize {
-        cmp::min(self.l1_map.len() + self.l2_map.len(), self.cap().into())
+    pub fn with_promotion_threshold(mut self, hits: NonZeroU32) -> LruCache<K, V, S, A> {
+        self.promote_after = hits;
+        self
     }
 
-    /// Returns a bool indicating whether the cache is empty or not.
+    /// Sets the probability `p` with which a hit in the backup cache moves the item back
+    /// into the main cache. The default is 1.0, i.e., every hit promotes.
+    ///
+    /// A low probability gives cheap scan resistance: items touched only once by a large
+    /// scan are unlikely to be carried over, while frequently accessed items get promoted
+    /// after a few hits. It is applied on top of the promotion threshold.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` is not within `0.0..=1.0`.
     ///
     /// # Example
     ///
     /// ```
     /// use fliplru::LruCache;
     /// use std::num::NonZeroUsize;
-    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
-    /// assert!(cache.is_empty());
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_promotion_probability(0.0);
     ///
     /// cache.put(1, "a");
-    /// assert!(!cache.is_empty());
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    ///
+    /// // Served from the backup cache, but never moved back.
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// cache.put(4, "d");
+    /// cache.put(5, "e");
+    /// assert_eq!(cache.get(&1), None);
     /// ```
-    pub fn is_empty(&self) -> bool {
-        self.l1_map.len() == 0 && self.l2_map.len() == 0
+    pub fn with_promotion_probability(mut self, p: f32) -> LruCache<K, V, S, A> {
+        assert!(
+            (0.0..=1.0).contains(&p),
+            "promotion probability must be within 0.0..=1.0"
+        );
+        self.promote_chance = (f64::from(p) * ALWAYS_PROMOTE as f64) as u64;
+        self
     }
 
-    /// Returns metric on the number of times the cache became full.
+    /// Sets the random source used for probabilistic promotion. By default a [`Lcg`]
+    /// with a fixed seed is used.
     ///
     /// # Example
     ///
     /// ```
-    /// use fliplru::LruCache;
+    /// use fliplru::{Lcg, LruCache};
     /// use std::num::NonZeroUsize;
-    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let cache: LruCache<isize, &str> = LruCache::new(NonZeroUsize::new(2).unwrap())
+    ///     .with_promotion_probability(0.25)
+    ///     .with_random_source(Lcg::new(7));
+    /// ```
+    pub fn with_random_source<R>(mut self, rng: R) -> LruCache<K, V, S, A>
+    where
+        R: RandomSource + Send + Sync + 'static,
+    {
+        self.rng = Rng::Custom(Box::new(rng));
+        self
+    }
+
+    /// Sets the fraction of `cap` that high priority items from the discarded backup cache
+    /// may take up in the new main cache at a flip. The default is 0.0, i.e., high priority
+    /// items are discarded like any other item.
     ///
-    /// for i in 0..5 {
-    ///     cache.put(i, i);
-    /// }
-    /// for i in 0..20 {
-    ///     cache.get(&(i % 5));
+    /// # Panics
+    ///
+    /// Panics if `fraction` is not within `0.0..=1.0`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, Priority};
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(4).unwrap()).with_priority_reserve(0.25);
+    ///
+    /// cache.put_with_priority("config", 1, Priority::High);
+    /// for i in 0..12 {
+    ///     cache.put("data", i);
+    ///     cache.put("more", i);
+    ///     cache.put("other", i);
+    ///     cache.put("misc", i);
     /// }
-    /// assert_eq!(cache.get_flips(), 8);
+    /// assert_eq!(cache.get(&"config"), Some(&1));
     /// ```
-
-    pub fn get_flips(&self) -> usize {
-        self.flips
+    pub fn with_priority_reserve(mut self, fraction: f32) -> LruCache<K, V, S, A> {
+        assert!(
+            (0.0..=1.0).contains(&fraction),
+            "priority reserve must be within 0.0..=1.0"
+        );
+        self.priority_reserve = fraction;
+        self
     }
 
-    /// Reset the flip metric.
+    /// Enables or disables the scan-resistant mode. In this mode [`put`] inserts keys that
+    /// are not in the cache into the backup cache, as [`put_cold`] does, and they only move
+    /// into the main cache on their next hit. This keeps insert-heavy scans from flushing the
+    /// main cache.
+    ///
+    /// [`put`]: LruCache::put
+    /// [`put_cold`]: LruCache::put_cold
     ///
     /// # Example
     ///
     /// ```
     /// use fliplru::LruCache;
     /// use std::num::NonZeroUsize;
-    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_scan_resistance(true);
     ///
-    /// for i in 0..5 {
-    ///     cache.put(i, i);
-    /// }
-    /// for i in 0..20 {
-    ///     cache.get(&(i % 5));
+    /// cache.put("apple", 1);
+    /// cache.get(&"apple");
+    /// for i in 0..10 {
+    ///     cache.put("scan", i);
     /// }
-    /// assert_eq!(cache.get_flips(), 8);
-    /// cache.reset();
     /// assert_eq!(cache.get_flips(), 0);
+    /// assert_eq!(cache.get(&"apple"), Some(&1));
     /// ```
-    pub fn reset(&mut self) {
-        self.flips = 0;
+    pub fn with_scan_resistance(mut self, enabled: bool) -> LruCache<K, V, S, A> {
+        self.scan_resistant = enabled;
+        self
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::LruCache;
-    use core::{fmt::Debug, num::NonZeroUsize};
 
-    fn assert_opt_eq<V: PartialEq + Debug>(opt: Option<&V>, v: V) {
-        assert!(opt.is_some());
-        assert_eq!(opt.unwrap(), &v);
+    /// Enables or disables the adaptive split between the main and the backup cache.
+    ///
+    /// The main and the backup cache normally have fixed sizes, see [`with_caps`]. In adaptive
+    /// mode their combined size is kept, but the split is adjusted at every flip based on
+    /// where the hits of the last generation landed:
+    /// hits in the main cache grow the main cache, so that flips are rarer, while hits in
+    /// the backup cache grow the backup cache, so that it can keep more than one generation
+    /// of recently flipped items. Each cache keeps at least a quarter of the budget.
+    ///
+    /// As the main cache can shrink, only the last `cap/2` items are guaranteed to be in
+    /// the cache in this mode.
+    ///
+    /// [`with_caps`]: LruCache::with_caps
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(4).unwrap()).with_adaptive_split(true);
+    ///
+    /// for i in 0..20 {
+    ///     cache.put(i % 6, i);
+    ///     cache.get(&(i % 6));
+    /// }
+    /// assert_eq!(cache.get(&3), Some(&15));
+    /// ```
+    pub fn with_adaptive_split(mut self, enabled: bool) -> LruCache<K, V, S, A> {
+        self.adaptive = enabled;
+        self
+    }
+
+    /// Sets when the cache flips. In [`FlipMode::Manual`] the cache only flips when
+    /// [`flip`] is called, so that generations can follow the epochs of the application,
+    /// e.g., one generation per frame or batch. [`FlipMode::EveryInsertions`] flips at a
+    /// fixed cadence, which makes the flip rate predictable. In both modes the whole
+    /// flipped generation is kept in the backup cache.
+    ///
+    /// The items already in the cache when switching to [`FlipMode::StrictLru`] are
+    /// ordered arbitrarily, as if they were used in some order before the newer ones.
+    ///
+    /// [`flip`]: LruCache::flip
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{FlipMode, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_flip_mode(FlipMode::Manual);
+    ///
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 0);
+    ///
+    /// cache.flip();
+    /// cache.flip();
+    /// assert_eq!(cache.get(&0), None);
+    /// ```
+    pub fn with_flip_mode(mut self, mode: FlipMode) -> LruCache<K, V, S, A> {
+        let ordered = self.is_ordered();
+        self.flip_mode = mode;
+        if !ordered {
+            self.reorder();
+        }
+        self
+    }
+
+    /// Makes the cache also flip every `interval`, as measured by `clock`, regardless of
+    /// how full it is. The backup cache then holds the items used in the last interval,
+    /// and items that were not used in the last two intervals are discarded.
+    ///
+    /// The clock is checked on every access, so the flips happen lazily on the first access
+    /// after an interval has passed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let secs = Arc::new(AtomicU64::new(0));
+    /// let clock = {
+    ///     let secs = secs.clone();
+    ///     move || Duration::from_secs(secs.load(Ordering::Relaxed))
+    /// };
+    /// let mut cache = LruCache::new(NonZeroUsize::new(10).unwrap())
+    ///     .with_flip_interval(Duration::from_secs(30), clock);
+    ///
+    /// cache.put("apple", 1);
+    /// secs.store(30, Ordering::Relaxed);
+    /// cache.put("banana", 2);
+    /// assert_eq!(cache.get_flips(), 1);
+    ///
+    /// secs.store(60, Ordering::Relaxed);
+    /// assert_eq!(cache.get(&"apple"), None);
+    /// assert_eq!(cache.get(&"banana"), Some(&2));
+    /// ```
+    pub fn with_flip_interval<C>(mut self, interval: Duration, clock: C) -> LruCache<K, V, S, A>
+    where
+        C: Clock + Send + Sync + 'static,
+    {
+        assert!(!interval.is_zero(), "flip interval must not be zero");
+        self.flip_timer = Some(FlipTimer {
+            start: clock.now(),
+            clock: Box::new(clock),
+            interval,
+        });
+        self
+    }
+
+    /// Enables autotuning of the capacity within `min..=max`.
+    ///
+    /// The cache grows its capacity by a quarter at a flip when more than a quarter of the
+    /// flipped generation was recovered from the backup cache, as the working set does not
+    /// fit. It shrinks its capacity by a quarter when it sees eight times its size in
+    /// accesses without filling up the main cache. The sizes of the main and the backup
+    /// cache are scaled along with the capacity.
+    ///
+    /// Working sets larger than `2*cap` are not recovered at all, so the capacity only
+    /// grows for working sets that partly fit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is greater than `max`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(4).unwrap())
+    ///     .with_autotune(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(64).unwrap());
+    ///
+    /// for i in 0..200 {
+    ///     if cache.get(&(i % 7)).is_none() {
+    ///         cache.put(i % 7, i);
+    ///     }
+    /// }
+    /// assert!(cache.cap().get() >= 7);
+    /// ```
+    pub fn with_autotune(mut self, min: NonZeroUsize, max: NonZeroUsize) -> LruCache<K, V, S, A> {
+        assert!(min <= max, "autotune bounds must satisfy min <= max");
+        self.autotune = Some(Autotune { min, max });
+        self
+    }
+
+    /// Enables or disables tracking the insertion order of the items within each generation,
+    /// which is needed by [`peek_oldest`] and [`pop_oldest`]. It costs an ordered index
+    /// update on every insertion. The items already in the cache are tracked in an arbitrary
+    /// order within their generation.
+    ///
+    /// [`peek_oldest`]: LruCache::peek_oldest
+    /// [`pop_oldest`]: LruCache::pop_oldest
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_insertion_order(true);
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// assert_eq!(cache.pop_oldest(), Some((1, "a")));
+    /// ```
+    pub fn with_insertion_order(mut self, enabled: bool) -> LruCache<K, V, S, A> {
+        let ordered = self.is_ordered();
+        self.track_order = enabled;
+        if !ordered {
+            self.reorder();
+        }
+        self
+    }
+
+    /// Sets a listener that receives every item the cache discards along with the
+    /// [`EvictionReason`]: the generation dropped at a flip, the items evicted to make room
+    /// and the items removed by [`retain`], [`remove`] or [`clear`]. This allows releasing
+    /// the resources held by the values as soon as they leave the cache.
+    ///
+    /// Items that are handed back to the caller, like the old value returned by [`put`],
+    /// are not reported, as the listener cannot take the value that the caller gets. A key
+    /// removed with [`remove`] is reported, while the value returned by [`pop`] is not.
+    /// Neither are the items that go to the spill sink, see [`with_spill_sink`].
+    ///
+    /// [`retain`]: LruCache::retain
+    /// [`remove`]: LruCache::remove
+    /// [`clear`]: LruCache::clear
+    /// [`put`]: LruCache::put
+    /// [`pop`]: LruCache::pop
+    /// [`with_spill_sink`]: LruCache::with_spill_sink
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{EvictionReason, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::{Arc, Mutex};
+    /// let evicted = Arc::new(Mutex::new(Vec::new()));
+    /// let sink = evicted.clone();
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap())
+    ///     .with_eviction_listener(move |k, v, reason| sink.lock().unwrap().push((k, v, reason)));
+    ///
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// // The second flip discarded the first generation
+    /// let mut evicted = evicted.lock().unwrap();
+    /// evicted.sort_by_key(|&(k, _, _)| k);
+    /// assert_eq!(
+    ///     *evicted,
+    ///     [(0, 0, EvictionReason::Flipped), (1, 1, EvictionReason::Flipped)]
+    /// );
+    /// ```
+    pub fn with_eviction_listener<F>(mut self, listener: F) -> LruCache<K, V, S, A>
+    where
+        F: FnMut(K, V, EvictionReason) + Send + Sync + 'static,
+    {
+        self.hooks.listener = Some(Box::new(listener));
+        self
+    }
+
+    /// Pushes every discarded item into the channel, whose [`Receiver`] is a bounded
+    /// stream of the evicted items, e.g., to write them behind to a database. This sets
+    /// the eviction listener, see [`with_eviction_listener`]. The items are dropped while
+    /// the channel is full or after the receiver was dropped.
+    ///
+    /// [`Receiver`]: futures_channel::mpsc::Receiver
+    /// [`with_eviction_listener`]: LruCache::with_eviction_listener
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{EvictionReason, LruCache};
+    /// use futures_channel::mpsc;
+    /// use std::num::NonZeroUsize;
+    ///
+    /// let (tx, mut rx) = mpsc::channel(16);
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_eviction_stream(tx);
+    /// cache.put(1, "a");
+    /// cache.pop(&1);
+    /// cache.put(2, "b");
+    /// cache.clear();
+    /// assert_eq!(rx.try_recv().ok(), Some((2, "b", EvictionReason::Cleared)));
+    /// ```
+    #[cfg(feature = "futures")]
+    pub fn with_eviction_stream(
+        self,
+        mut tx: futures_channel::mpsc::Sender<(K, V, EvictionReason)>,
+    ) -> LruCache<K, V, S, A>
+    where
+        K: Send + 'static,
+        V: Send + 'static,
+    {
+        self.with_eviction_listener(move |k, v, reason| {
+            let _ = tx.try_send((k, v, reason));
+        })
+    }
+
+    /// Sets a sink that receives the items discarded for capacity or age, i.e., for
+    /// [`EvictionReason::Flipped`] and [`EvictionReason::Expired`], instead of the eviction
+    /// listener. At a flip the whole discarded generation is spilled at once.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SpillSink};
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// struct Tier(Arc<Mutex<Vec<(i32, i32)>>>);
+    ///
+    /// impl SpillSink<i32, i32> for Tier {
+    ///     fn spill(&mut self, items: &mut dyn Iterator<Item = (i32, i32)>) {
+    ///         self.0.lock().unwrap().extend(items);
+    ///     }
+    /// }
+    ///
+    /// let tier = Arc::new(Mutex::new(Vec::new()));
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_spill_sink(Tier(tier.clone()));
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// let mut spilled = tier.lock().unwrap();
+    /// spilled.sort();
+    /// assert_eq!(*spilled, [(0, 0), (1, 1)]);
+    /// ```
+    pub fn with_spill_sink<T>(mut self, sink: T) -> LruCache<K, V, S, A>
+    where
+        T: SpillSink<K, V> + Send + Sync + 'static,
+    {
+        self.hooks.sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Sends a [`CacheEvent`] to the channel for every insertion, lookup, flip and
+    /// eviction, e.g., to process cache telemetry off the hot path. Sending fails silently
+    /// once the receiver is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheEvent, LruCache, Tier};
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::mpsc;
+    ///
+    /// let (tx, rx) = mpsc::channel();
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_event_channel(tx);
+    /// cache.put(1, "a");
+    /// cache.get(&1);
+    /// cache.get(&2);
+    /// let events: Vec<_> = rx.try_iter().collect();
+    /// assert_eq!(events, [CacheEvent::Insert, CacheEvent::Hit(Tier::Hot), CacheEvent::Miss]);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn with_event_channel(
+        mut self,
+        tx: std::sync::mpsc::Sender<CacheEvent>,
+    ) -> LruCache<K, V, S, A> {
+        self.hooks.events = Some(Box::new(move |event| {
+            let _ = tx.send(event);
+        }));
+        self
+    }
+
+    /// Sets an observer whose hooks are invoked whenever a put inserts a key or replaces
+    /// its value, see [`CacheObserver`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheObserver, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// struct Log(Arc<Mutex<Vec<String>>>);
+    ///
+    /// impl CacheObserver<i32, &str> for Log {
+    ///     fn on_insert(&mut self, k: &i32, v: &&str) {
+    ///         self.0.lock().unwrap().push(format!("{k} = {v}"));
+    ///     }
+    ///     fn on_update(&mut self, k: &i32, old: &&str, new: &&str) {
+    ///         self.0.lock().unwrap().push(format!("{k}: {old} -> {new}"));
+    ///     }
+    /// }
+    ///
+    /// let log = Arc::new(Mutex::new(Vec::new()));
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_observer(Log(log.clone()));
+    /// cache.put(1, "a");
+    /// cache.put(1, "b");
+    /// assert_eq!(*log.lock().unwrap(), ["1 = a", "1: a -> b"]);
+    /// ```
+    pub fn with_observer<O>(mut self, observer: O) -> LruCache<K, V, S, A>
+    where
+        O: CacheObserver<K, V> + Send + Sync + 'static,
+    {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    /// Returns a reference to the value of the key in the cache or `None` if it is not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(2, "c");
+    /// cache.put(3, "d");
+    ///
+    /// assert_eq!(cache.get(&2), Some(&"c"));
+    /// assert_eq!(cache.get(&3), Some(&"d"));
+    /// ```
+    pub fn get<'a, Q>(&'a mut self, k: &Q) -> Option<&'a V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_entry(k).map(|e| &e.value)
+    }
+
+    // Looks up the key in the cache of the owner like get, and returns the value if keep
+    // accepts it, or else the owner, which can still change the cache. The borrow checker
+    // would keep the cache borrowed past a miss if the value of get were returned, so a
+    // wrapper would otherwise find a hit twice, with a get and then a peek.
+    pub(crate) fn get_in<'a, T: ?Sized, Q>(
+        owner: &'a mut T,
+        cache: fn(&mut T) -> &mut Self,
+        k: &Q,
+        keep: impl FnOnce(&V) -> bool,
+    ) -> Result<&'a V, &'a mut T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hit = cache(owner)
+            .get(k)
+            .filter(|v| keep(v))
+            .map(|v| v as *const V);
+        match hit {
+            // SAFETY: the value is in the cache, which stays borrowed for 'a from the owner
+            // and is not changed before the reference is returned
+            Some(v) => Ok(unsafe { &*v }),
+            None => Err(owner),
+        }
+    }
+
+    /// Returns a mutable reference to the value of the key in the cache or `None` if it
+    /// is not present in the cache. Moves the key to the l1_map if it exists in the l2_map.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put("apple", 8);
+    /// cache.put("banana", 4);
+    /// cache.put("banana", 6);
+    /// cache.put("pear", 2);
+    ///
+    /// assert_eq!(cache.get_mut(&"apple"), Some(&mut 8));
+    /// assert_eq!(cache.get_mut(&"banana"), Some(&mut 6));
+    /// assert_eq!(cache.get_mut(&"pear"), Some(&mut 2));
+    /// ```
+    pub fn get_mut<'a, Q>(&'a mut self, k: &Q) -> Option<&'a mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_entry(k).map(|e| {
+            e.dirty = true;
+            &mut e.value
+        })
+    }
+
+    /// Marks the value of the key as modified, as [`get_mut`] does, without promoting it.
+    /// Returns `false` if the key is not present in the cache.
+    ///
+    /// [`get_mut`]: LruCache::get_mut
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert!(cache.mark_dirty(&1));
+    /// assert!(!cache.mark_dirty(&2));
+    /// ```
+    pub fn mark_dirty<Q>(&mut self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.l1_map.get_mut(k).or_else(|| self.l2_map.get_mut(k)) {
+            Some(e) => {
+                e.dirty = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Writes back the modified values, see [`get_mut`] and [`mark_dirty`]. The function
+    /// is called for every dirty item, which is clean afterwards unless the function fails.
+    /// The first error stops the flush and is returned.
+    ///
+    /// [`get_mut`]: LruCache::get_mut
+    /// [`mark_dirty`]: LruCache::mark_dirty
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, 1);
+    /// cache.put(2, 2);
+    /// *cache.get_mut(&2).unwrap() += 1;
+    ///
+    /// let mut stored = Vec::new();
+    /// cache.flush_dirty(|&k, &v| -> Result<(), ()> {
+    ///     stored.push((k, v));
+    ///     Ok(())
+    /// }).unwrap();
+    /// assert_eq!(stored, [(2, 3)]);
+    ///
+    /// // Nothing is dirty anymore
+    /// assert_eq!(cache.flush_dirty(|_, _| Err("no store")), Ok(()));
+    /// ```
+    pub fn flush_dirty<E, F>(&mut self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&K, &V) -> Result<(), E>,
+    {
+        for (k, e) in self.l1_map.iter_mut().chain(self.l2_map.iter_mut()) {
+            if e.dirty {
+                f(k, &e.value)?;
+                e.dirty = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets a function that writes back a dirty item right before the cache discards it,
+    /// for whatever reason, so that modified values are never lost. It is called ahead of
+    /// the eviction listener and the spill sink. Values that are handed back to the caller,
+    /// e.g., by [`pop`], are not flushed.
+    ///
+    /// [`pop`]: LruCache::pop
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::{Arc, Mutex};
+    /// let stored = Arc::new(Mutex::new(Vec::new()));
+    /// let store = stored.clone();
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap())
+    ///     .with_dirty_flush(move |&k, &v| store.lock().unwrap().push((k, v)));
+    ///
+    /// cache.put(1, 1);
+    /// *cache.get_mut(&1).unwrap() = 10;
+    /// cache.put(2, 2);
+    /// for i in 3..6 {
+    ///     cache.put(i, i);
+    /// }
+    /// // Only the modified value was written back when its generation was discarded
+    /// assert_eq!(*stored.lock().unwrap(), [(1, 10)]);
+    /// ```
+    pub fn with_dirty_flush<F>(mut self, flush: F) -> LruCache<K, V, S, A>
+    where
+        F: FnMut(&K, &V) + Send + Sync + 'static,
+    {
+        self.hooks.flush = Some(Box::new(flush));
+        self
+    }
+
+    /// Drops the items of a discarded generation `batch` at a time on the following gets
+    /// and puts, instead of all at once at the flip, which keeps the flip from stalling a
+    /// put when the values are expensive to drop, e.g., large strings. The items still go
+    /// to the eviction listener or the spill sink, as they are dropped. Any items left at
+    /// the next flip are dropped then.
+    ///
+    /// The map of the discarded generation is freed afterwards, so each flip allocates a
+    /// new one instead of reusing it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// let evicted = Arc::new(AtomicUsize::new(0));
+    /// let count = evicted.clone();
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap())
+    ///     .with_incremental_drop(NonZeroUsize::new(1).unwrap())
+    ///     .with_eviction_listener(move |_, _, _| {
+    ///         count.fetch_add(1, Ordering::Relaxed);
+    ///     });
+    ///
+    /// for i in 0..5 {
+    ///     cache.put(i, i.to_string());
+    /// }
+    /// // The flip at the last put discarded 0 and 1, and no access dropped them yet
+    /// assert_eq!(evicted.load(Ordering::Relaxed), 0);
+    /// cache.get(&4);
+    /// assert_eq!(evicted.load(Ordering::Relaxed), 1);
+    /// ```
+    pub fn with_incremental_drop(mut self, batch: NonZeroUsize) -> LruCache<K, V, S, A> {
+        self.drop_batch = batch.get();
+        self
+    }
+
+    /// Sets whether the flips reuse the maps they empty. This is enabled by default: the new
+    /// generation takes over the map of the discarded generation, or that of the flipped
+    /// one when the backup cache takes it in, and a map that is not needed at that point,
+    /// e.g., the one preallocated by [`try_put`], is kept for the next flip. The flips then
+    /// do not allocate, at the cost of keeping the memory of the largest generation.
+    ///
+    /// When disabled, each flip frees the emptied map and allocates one for the new
+    /// generation, which keeps the resident memory minimal. The maps of the generations
+    /// that are dropped by [`with_incremental_drop`] are always freed.
+    ///
+    /// [`try_put`]: LruCache::try_put
+    /// [`with_incremental_drop`]: LruCache::with_incremental_drop
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_pooling(false);
+    ///
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// assert_eq!(cache.get(&2), Some(&2));
+    /// ```
+    pub fn with_pooling(mut self, enabled: bool) -> LruCache<K, V, S, A> {
+        self.pooling = enabled;
+        if !enabled {
+            self.spare = None;
+        }
+        self
+    }
+
+    /// Returns a reference to the value of the key without promoting it or counting the
+    /// access, or `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.peek(&1), Some(&"a"));
+    ///
+    /// // 1 was not promoted and is discarded by the next flip
+    /// cache.put(4, "d");
+    /// cache.put(5, "e");
+    /// assert_eq!(cache.peek(&1), None);
+    /// ```
+    pub fn peek<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.peek_entry(k).map(|(_, v, _)| v)
+    }
+
+    // Looks up the key without promoting it and returns the tier it was found in.
+    pub(crate) fn peek_entry<Q>(&self, k: &Q) -> Option<(&K, &V, Tier)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if let Some((rk, e)) = self.l1_map.get_key_value(k) {
+            return Some((rk, &e.value, Tier::Hot));
+        }
+        let (rk, e) = self.l2_map.get_key_value(k)?;
+        Some((rk, &e.value, Tier::Cold))
+    }
+
+    /// Puts a key-value pair into cache. If the key already exists in the cache, then it updates
+    /// the key's value and returns the old value. Otherwise, `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(None, cache.put(2, "b"));
+    /// assert_eq!(Some("b"), cache.put(2, "beta"));
+    ///
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert_eq!(cache.get(&2), Some(&"beta"));
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Option<V> {
+        let old = self.put_entry(k, Entry::new(v, Priority::Normal));
+        self.replaced(old)
+    }
+
+    /// Puts all the key-value pairs into the cache like [`put`](LruCache::put), but checks
+    /// for a flip once per batch of items that fits into the main cache instead of once per
+    /// item, and skips looking up the backup cache while it is empty. The cache ends up as
+    /// if the items were put one at a time, and the values that are replaced are handed to
+    /// the eviction listener with [`EvictionReason::Replaced`].
+    ///
+    /// The batches need the default [`FlipMode::Auto`] without insertion order, scan
+    /// resistance or an observer, otherwise the items are put one at a time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(4).unwrap());
+    ///
+    /// cache.put_many((0..10).map(|i| (i, i * 10)));
+    /// assert_eq!(cache.get_flips(), 2);
+    /// assert_eq!(cache.get(&9), Some(&90));
+    /// assert_eq!(cache.get(&3), None);
+    /// ```
+    pub fn put_many<I: IntoIterator<Item = (K, V)>>(&mut self, items: I) {
+        let items = items.into_iter();
+        let size_hint = items.size_hint().0;
+        let batched = self.flip_mode == FlipMode::Auto
+            && !self.scan_resistant
+            && !self.is_ordered()
+            && self.observer.is_none();
+        if !batched {
+            for (k, v) in items {
+                let old = self.put_entry(k, Entry::new(v, Priority::Normal));
+                self.hooks.evicted(old, EvictionReason::Replaced);
+            }
+            return;
+        }
+
+        // The insertions left before the main cache is full, which is checked again then, or
+        // when an access flipped or resized the cache
+        let mut room = 0;
+        let mut cold = !self.l2_map.is_empty();
+        for (k, v) in items {
+            let before = (self.flips, self.hot_cap);
+            self.on_access();
+            self.hooks.emit(CacheEvent::Insert);
+            if room == 0 || before != (self.flips, self.hot_cap) {
+                // Like a put, an update of a full main cache flips it too
+                if self.l1_map.len() >= self.hot_cap {
+                    self.flip();
+                }
+                room = self.hot_cap.saturating_sub(self.l1_map.len());
+                self.l1_map.reserve(cmp::min(size_hint, room));
+                cold = !self.l2_map.is_empty();
+            }
+            self.gen_inserts += 1;
+            let e = Entry::new(v, Priority::Normal);
+            // Both maps share the hasher
+            let hash = self.l1_map.hasher().hash_one(&k);
+            let old = match self
+                .l1_map
+                .raw_entry_mut()
+                .from_key_hashed_nocheck(hash, &k)
+            {
+                RawEntryMut::Occupied(mut o) => Some((k, o.insert(e))),
+                RawEntryMut::Vacant(v) => {
+                    let old = match cold {
+                        true => Self::remove_hashed(&mut self.l2_map, hash, &k),
+                        false => None,
+                    };
+                    v.insert_hashed_nocheck(hash, k, e);
+                    room = room.saturating_sub(1);
+                    old
+                }
+            };
+            self.hooks.evicted(old, EvictionReason::Replaced);
+        }
+    }
+
+    /// Like [`put`](LruCache::put), but first allocates what the put may need, i.e., room
+    /// for the item or the map of the next generation if it flips, and returns an error
+    /// instead of aborting if that fails. The cache is unchanged then. The bookkeeping of
+    /// [`with_insertion_order`](LruCache::with_insertion_order) still allocates
+    /// infallibly.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::try_new(NonZeroUsize::new(2).unwrap()).unwrap();
+    ///
+    /// assert_eq!(cache.try_put(1, "a"), Ok(None));
+    /// assert_eq!(cache.try_put(1, "alpha"), Ok(Some("a")));
+    /// ```
+    pub fn try_put(&mut self, k: K, v: V) -> Result<Option<V>, TryReserveError> {
+        let may_flip = self.flip_timer.is_some()
+            || match self.flip_mode {
+                FlipMode::Auto => self.l1_map.len() >= self.hot_cap,
+                FlipMode::EveryInsertions(n) => self.gen_inserts >= n.get(),
+                FlipMode::Manual | FlipMode::StrictLru => false,
+            };
+        if may_flip {
+            // The flip reuses the map of the discarded generation if it is large enough
+            let reusable = self.pooling
+                && self.drop_batch == 0
+                && !self.flip_extends()
+                && self.l2_map.capacity() >= self.next_capacity();
+            if self.spare.is_none() && !reusable {
+                let (hasher, alloc) = (self.l1_map.hasher(), self.l1_map.allocator());
+                let mut next = HashMap::with_hasher_in(hasher.clone(), alloc.clone());
+                next.try_reserve(self.next_capacity())?;
+                self.spare = Some(next);
+            }
+            // The backup cache may take in the flipped generation
+            if self.flip_extends() {
+                self.l2_map.try_reserve(self.l1_map.len())?;
+            }
+        } else {
+            self.l1_map.try_reserve(1)?;
+        }
+        if self.scan_resistant {
+            self.l2_map.try_reserve(1)?;
+        }
+        Ok(self.put(k, v))
+    }
+
+    /// Puts a key-value pair into the backup cache, so that it only moves into the main
+    /// cache when it is hit. If the key is already in the main cache, then its value is
+    /// updated in place. Returns the old value of the key if there was one.
+    ///
+    /// The backup cache holds at most `cap` items; when it is full, it is discarded to make
+    /// room for the new item.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// assert_eq!(None, cache.put_cold(3, "c"));
+    /// assert_eq!(Some("a"), cache.put_cold(1, "alpha"));
+    /// assert_eq!(cache.get_flips(), 0);
+    ///
+    /// assert_eq!(cache.get(&3), Some(&"c"));
+    /// assert_eq!(cache.get_flips(), 1);
+    /// ```
+    pub fn put_cold(&mut self, k: K, v: V) -> Option<V> {
+        let old = self.insert_cold(k, Entry::new(v, Priority::Normal));
+        self.replaced(old)
+    }
+
+    /// Puts a key-value pair into cache with the given priority. It behaves like [`put`],
+    /// except that high priority items from the backup cache are re-inserted into the main
+    /// cache when a flip occurs, up to the reserve set with [`with_priority_reserve`].
+    /// Updating the key with [`put`] resets its priority to [`Priority::Normal`].
+    ///
+    /// [`put`]: LruCache::put
+    /// [`with_priority_reserve`]: LruCache::with_priority_reserve
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, Priority};
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_priority_reserve(0.5);
+    ///
+    /// assert_eq!(None, cache.put_with_priority(1, "a", Priority::High));
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// cache.put(4, "d");
+    /// cache.put(5, "e");
+    ///
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert_eq!(cache.get(&2), None);
+    /// ```
+    pub fn put_with_priority(&mut self, k: K, v: V, priority: Priority) -> Option<V> {
+        let old = self.put_entry(k, Entry::new(v, priority));
+        self.replaced(old)
+    }
+
+    // Looks up the entry of the key, moving it to the l1_map once it has been hit
+    // `promote_after` times in the l2_map and the promotion chance allows it.
+    fn get_entry<'a, Q>(&'a mut self, k: &Q) -> Option<&'a mut Entry<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.on_access();
+
+        // Both maps share the hasher, so the key is hashed once for all the probes
+        let strict = self.flip_mode == FlipMode::StrictLru;
+        let hash = self.l1_map.hasher().hash_one(k);
+        // A bucket does not borrow the map, unlike an occupied entry, which would stay
+        // borrowed past the hit and keep the promotion below from flipping
+        let bucket = self
+            .l1_map
+            .raw_table_mut()
+            .find(hash, |(q, _)| q.borrow() == k);
+        if let Some(bucket) = bucket {
+            self.gen_hits.l1 += 1;
+            self.hooks.emit(CacheEvent::Hit(Tier::Hot));
+            // SAFETY: the bucket was just found in the table, which is not changed until
+            // the entry is returned.
+            let e = unsafe { &mut bucket.as_mut().1 };
+            if strict {
+                self.order.remove(&e.tick);
+                e.tick = Self::track(&mut self.order, &mut self.tick, hash);
+            }
+            return Some(e);
+        }
+
+        let table = self.l2_map.raw_table_mut();
+        let Some(bucket) = table.find(hash, |(q, _)| q.borrow() == k) else {
+            self.hooks.emit(CacheEvent::Miss);
+            return None;
+        };
+        self.gen_hits.l2 += 1;
+        self.hooks.emit(CacheEvent::Hit(Tier::Cold));
+        // SAFETY: the bucket was just found in the table, which is not changed until the
+        // entry is returned or the bucket is removed.
+        let e = unsafe { &mut bucket.as_mut().1 };
+        e.hits = e.hits.saturating_add(1);
+        if e.hits < self.promote_after.get()
+            || (self.promote_chance < ALWAYS_PROMOTE
+                && u64::from(self.rng.next_u32()) >= self.promote_chance)
+        {
+            return Some(e);
+        }
+
+        // SAFETY: as above, the bucket is still in the table.
+        let (rk, re) = unsafe { table.remove(bucket).0 };
+        if self.is_ordered() {
+            self.l2_order.remove(&re.tick);
+        }
+        Some(self.promote(hash, rk, re.renewed()))
+    }
+
+    // Removes the entry of the key whose hash is known.
+    fn remove_hashed<Q>(
+        map: &mut HashMap<K, Entry<V>, S, A>,
+        hash: u64,
+        k: &Q,
+    ) -> Option<(K, Entry<V>)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        match map.raw_entry_mut().from_key_hashed_nocheck(hash, k) {
+            RawEntryMut::Occupied(o) => Some(o.remove_entry()),
+            RawEntryMut::Vacant(_) => None,
+        }
+    }
+
+    // Puts the entry and returns the item that it replaced.
+    fn put_entry(&mut self, k: K, e: Entry<V>) -> Option<(K, Entry<V>)> {
+        self.on_access();
+        self.hooks.emit(CacheEvent::Insert);
+
+        // In scan-resistant mode only keys that are hit in the l2_map move to the l1_map
+        if self.scan_resistant && !self.l2_map.contains_key(&k) {
+            return self.insert_cold(k, e);
+        }
+        self.insert(k, e, true)
+    }
+
+    // The value that a put replaced, which is wiped instead of returned if the cache wipes
+    // the values.
+    fn replaced(&self, old: Option<(K, Entry<V>)>) -> Option<V> {
+        let (_, mut old) = old?;
+        match self.hooks.wipe {
+            Some(wipe) => {
+                (wipe.value)(&mut old.value);
+                None
+            }
+            None => Some(old.value),
+        }
+    }
+
+    fn insert_cold(&mut self, k: K, mut e: Entry<V>) -> Option<(K, Entry<V>)> {
+        if let Some((rk, l1_e)) = self.l1_map.get_key_value_mut(&k) {
+            let tick = l1_e.tick;
+            let old = mem::replace(l1_e, Entry { tick, ..e });
+            if let Some(observer) = &mut self.observer {
+                observer.on_update(rk, &old.value, &l1_e.value);
+            }
+            return Some((k, old));
+        }
+        if self.l2_map.len() >= self.cold_cap && !self.l2_map.contains_key(&k) {
+            self.hooks
+                .discard(&mut self.l2_map, EvictionReason::Flipped);
+            self.l2_order.clear();
+        }
+        let ordered = self.is_ordered();
+        if ordered {
+            let hash = self.l2_map.hasher().hash_one(&k);
+            e.tick = Self::track(&mut self.l2_order, &mut self.tick, hash);
+        }
+        let oe = Self::store(&mut self.l2_map, self.observer.as_mut(), k, e, None);
+        if let (true, Some((_, oe))) = (ordered, &oe) {
+            self.l2_order.remove(&oe.tick);
+        }
+        oe
+    }
+
+    // Inserts into the l1_map, flipping first if needed. Only puts are reported to the
+    // observer.
+    fn insert(&mut self, k: K, mut e: Entry<V>, put: bool) -> Option<(K, Entry<V>)> {
+        self.make_room(|this| !this.l1_map.contains_key(&k));
+        // invalidate any existing entry in L2 cache, which is wiped where it was stored
+        let (ok, oe) = match self.hooks.wipe {
+            Some(wipe) => (None, wipe.remove(&mut self.l2_map, &k)),
+            None => match self.l2_map.remove_entry(&k) {
+                Some((ok, oe)) => (Some(ok), Some(oe)),
+                None => (None, None),
+            },
+        };
+
+        let ordered = self.is_ordered();
+        if ordered {
+            if let Some(oe) = &oe {
+                self.l2_order.remove(&oe.tick);
+            }
+            let hash = self.l1_map.hasher().hash_one(&k);
+            e.tick = Self::track(&mut self.order, &mut self.tick, hash);
+        }
+        let observer = if put { self.observer.as_mut() } else { None };
+        match Self::store(&mut self.l1_map, observer, k, e, oe.as_ref()) {
+            Some(l1_e) => {
+                if ordered {
+                    self.order.remove(&l1_e.1.tick);
+                }
+                Some(l1_e)
+            }
+            // An entry that was wiped in the l2_map is not returned
+            None => ok.zip(oe),
+        }
+    }
+
+    // Flips or evicts the least recently used items as the flip mode requires before an
+    // insertion into the l1_map, and counts the insertion. `absent` tells whether the key
+    // of the insertion is not in the l1_map, in which case it needs room.
+    fn make_room<F: FnOnce(&Self) -> bool>(&mut self, absent: F) {
+        let due = match self.flip_mode {
+            FlipMode::Auto => self.l1_map.len() >= self.hot_cap,
+            FlipMode::Manual => false,
+            FlipMode::EveryInsertions(n) => self.gen_inserts >= n.get(),
+            FlipMode::StrictLru => {
+                if self.l1_map.len() >= self.hot_cap && absent(self) {
+                    while self.l1_map.len() >= self.hot_cap && self.evict_lru() {}
+                }
+                false
+            }
+        };
+        if due {
+            self.flip();
+        }
+        self.gen_inserts += 1;
+    }
+
+    // Moves an entry that was removed from the l2_map into the l1_map and returns it. The
+    // key was not in the l1_map and its hash is reused for the insertion.
+    fn promote(&mut self, hash: u64, k: K, mut e: Entry<V>) -> &mut Entry<V> {
+        self.make_room(|_| true);
+        let ordered = self.is_ordered();
+        if ordered {
+            e.tick = Self::track(&mut self.order, &mut self.tick, hash);
+        }
+        match self
+            .l1_map
+            .raw_entry_mut()
+            .from_key_hashed_nocheck(hash, &k)
+        {
+            RawEntryMut::Vacant(v) => v.insert_hashed_nocheck(hash, k, e).1,
+            // A flip may have rescued the key into the new generation
+            RawEntryMut::Occupied(mut o) => {
+                let old = o.insert(e);
+                if ordered {
+                    self.order.remove(&old.tick);
+                }
+                o.into_mut()
+            }
+        }
+    }
+
+    // Inserts the entry into the map and reports it to the observer, if any. `displaced` is
+    // the entry of the key that was removed from the other map. Returns the replaced item,
+    // whose key is the one passed in, as the map keeps its own.
+    fn store(
+        map: &mut HashMap<K, Entry<V>, S, A>,
+        observer: Option<&mut Observer<K, V>>,
+        k: K,
+        e: Entry<V>,
+        displaced: Option<&Entry<V>>,
+    ) -> Option<(K, Entry<V>)> {
+        match map.raw_entry_mut().from_key(&k) {
+            RawEntryMut::Occupied(mut o) => {
+                let old = o.insert(e);
+                if let Some(observer) = observer {
+                    observer.on_update(o.key(), &old.value, &o.get().value);
+                }
+                Some((k, old))
+            }
+            RawEntryMut::Vacant(v) => {
+                let (k, e) = v.insert(k, e);
+                match (observer, displaced) {
+                    (Some(observer), Some(d)) => observer.on_update(k, &d.value, &e.value),
+                    (Some(observer), None) => observer.on_insert(k, &e.value),
+                    (None, _) => {}
+                }
+                None
+            }
+        }
+    }
+
+    fn is_ordered(&self) -> bool {
+        self.track_order || self.flip_mode == FlipMode::StrictLru
+    }
+
+    // Records the key hash as the newest one in the order and returns its tick.
+    fn track(order: &mut BTreeMap<u64, u64>, tick: &mut u64, hash: u64) -> u64 {
+        *tick += 1;
+        order.insert(*tick, hash);
+        *tick
+    }
+
+    // Records the items that are already in the cache in the order, which starts being
+    // tracked. Their order is arbitrary, as their insertions were not tracked.
+    fn reorder(&mut self) {
+        if !self.is_ordered() {
+            return;
+        }
+        let hasher = self.l1_map.hasher().clone();
+        for (k, e) in self.l2_map.iter_mut() {
+            e.tick = Self::track(&mut self.l2_order, &mut self.tick, hasher.hash_one(k));
+        }
+        for (k, e) in self.l1_map.iter_mut() {
+            e.tick = Self::track(&mut self.order, &mut self.tick, hasher.hash_one(k));
+        }
+    }
+
+    // Evicts the least recently used item of the l1_map and returns whether there was one.
+    fn evict_lru(&mut self) -> bool {
+        let Some((tick, hash)) = self.order.pop_first() else {
+            return false;
+        };
+        let lru = self
+            .l1_map
+            .raw_table_mut()
+            .remove_entry(hash, |(_, e)| e.tick == tick);
+        self.hooks.evicted(lru, EvictionReason::Flipped);
+        true
+    }
+
+    /// Flips the cache: the main cache is backed up and the previous backup is discarded,
+    /// as happens when the main cache is full. This is the only way to flip the cache in
+    /// [`FlipMode::Manual`], but it also forces a turnover in the default mode.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.flip();
+    /// assert_eq!(cache.get_flips(), 1);
+    ///
+    /// // 1 is recovered from the backup cache
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// cache.flip();
+    /// cache.flip();
+    /// assert_eq!(cache.get(&1), None);
+    /// ```
+    pub fn flip(&mut self) {
+        self.flip_for(EvictionReason::Flipped);
+    }
+
+    // Capacity of the map of a new generation.
+    fn next_capacity(&self) -> usize {
+        if self.growable {
+            cmp::min(self.l1_map.len(), self.hot_cap)
+        } else {
+            self.hot_cap
+        }
+    }
+
+    // Flips the cache, reporting the discarded items for the given reason. It is kept out
+    // of the puts, which flip once per `cap` insertions, so that their common path stays
+    // small enough to inline.
+    #[cold]
+    #[inline(never)]
+    fn flip_for(&mut self, reason: EvictionReason) {
+        self.flips += 1;
+        self.gen_inserts = 0;
+        self.grow_if_recovering();
+        self.adapt_split();
+
+        // The backup cache is only bounded when flipping automatically
+        let bounded = self.flip_mode == FlipMode::Auto;
+        let strict = self.flip_mode == FlipMode::StrictLru;
+        let extends = self.flip_extends();
+        let capacity = self.next_capacity();
+        let (hasher, alloc) = (
+            self.l1_map.hasher().clone(),
+            self.l1_map.allocator().clone(),
+        );
+        let mut l1_map = mem::replace(&mut *self.l1_map, HashMap::with_hasher_in(hasher, alloc));
+        let order = mem::take(&mut self.order);
+        let mut dying = if extends {
+            // The backup has room for another generation, and the emptied map is reused
+            self.l2_map.extend(l1_map.drain());
+            self.l2_order.extend(order);
+            l1_map
+        } else {
+            self.l2_order = order;
+            mem::replace(&mut *self.l2_map, l1_map)
+        };
+        if bounded && self.l2_map.len() > self.cold_cap {
+            let excess = self.l2_map.len() - self.cold_cap;
+            let trimmed = self.l2_map.extract_if(|_, _| true).take(excess);
+            dying.extend(trimmed.inspect(|(_, e)| {
+                self.l2_order.remove(&e.tick);
+            }));
+        }
+
+        let reserve = (self.cap.get() as f32 * self.priority_reserve) as usize;
+        let rescued: Vec<_> = if reserve > 0 && !strict {
+            dying
+                .extract_if(|_, e| e.priority == Priority::High)
+                .take(reserve)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        self.hooks.emit(CacheEvent::Flip {
+            discarded: dying.len(),
+        });
+        if !dying.is_empty() {
+            if let (Some(offload), false) = (&mut self.offload, self.hooks.observes()) {
+                // The map goes along with its items, see with_drop_thread
+                let (hasher, alloc) = (dying.hasher().clone(), dying.allocator().clone());
+                offload(mem::replace(
+                    &mut dying,
+                    HashMap::with_hasher_in(hasher, alloc),
+                ));
+            } else if self.drop_batch > 0 && self.hooks.wipe.is_none() {
+                // The following accesses drop the items, see drop_parked
+                self.drop_parked(usize::MAX);
+                let (hasher, alloc) = (dying.hasher().clone(), dying.allocator().clone());
+                let items = mem::replace(&mut dying, HashMap::with_hasher_in(hasher, alloc));
+                self.parked.push((items.into_iter(), reason));
+            } else {
+                self.hooks.discard(&mut dying, reason);
+            }
+        }
+
+        // The new generation goes into the pooled map or the emptied one, so that a flip
+        // does not allocate. The other one is kept in the pool.
+        let mut next = match self.spare.take() {
+            Some(spare) => {
+                if self.pooling && dying.capacity() > 0 {
+                    self.spare = Some(dying);
+                }
+                spare
+            }
+            None if self.pooling => dying,
+            None => HashMap::with_hasher_in(dying.hasher().clone(), dying.allocator().clone()),
+        };
+        next.reserve(capacity);
+        *self.l1_map = next;
+        let ordered = self.is_ordered();
+        for (k, e) in rescued {
+            let mut e = e.renewed();
+            if ordered {
+                let hash = self.l1_map.hasher().hash_one(&k);
+                e.tick = Self::track(&mut self.order, &mut self.tick, hash);
+            }
+            self.l1_map.insert(k, e);
+        }
+    }
+
+    // Drops up to `n` of the parked items, the ones discarded last first.
+    fn drop_parked(&mut self, mut n: usize) {
+        while n > 0 {
+            let Some((items, reason)) = self.parked.last_mut() else {
+                return;
+            };
+            let len = items.len();
+            self.hooks.evicted(items.by_ref().take(n), *reason);
+            n -= len - items.len();
+            if items.len() == 0 {
+                self.parked.pop();
+            }
+        }
+    }
+
+    // Whether the next flip moves the main cache into the backup cache along with the
+    // previous generations, instead of replacing them.
+    fn flip_extends(&self) -> bool {
+        self.flip_mode == FlipMode::Auto
+            && self.cold_cap > self.hot_cap
+            && !self.l2_map.is_empty()
+            && self.l2_map.len() + self.l1_map.len() <= self.cold_cap
+    }
+
+    /// Returns the oldest key-value pair without removing it, or `None` if the cache is
+    /// empty. Items of the backup cache are older than those of the main cache, and within
+    /// each of them the item inserted first is the oldest. In [`FlipMode::StrictLru`] the
+    /// oldest item is the least recently used one.
+    ///
+    /// This needs [`with_insertion_order`] and always returns `None` without it.
+    ///
+    /// [`with_insertion_order`]: LruCache::with_insertion_order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_insertion_order(true);
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.peek_oldest(), Some((&1, &"a")));
+    /// ```
+    pub fn peek_oldest(&self) -> Option<(&K, &V)> {
+        let (order, map) = if self.l2_order.is_empty() {
+            (&self.order, &self.l1_map)
+        } else {
+            (&self.l2_order, &self.l2_map)
+        };
+        let (&tick, &hash) = order.first_key_value()?;
+        map.raw_table()
+            .get(hash, |(_, e)| e.tick == tick)
+            .map(|(k, e)| (k, &e.value))
+    }
+
+    /// Removes and returns the oldest key-value pair, see [`peek_oldest`]. This needs
+    /// [`with_insertion_order`] and always returns `None` without it.
+    ///
+    /// [`peek_oldest`]: LruCache::peek_oldest
+    /// [`with_insertion_order`]: LruCache::with_insertion_order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_insertion_order(true);
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.pop_oldest(), Some((1, "a")));
+    /// assert_eq!(cache.pop_oldest(), Some((2, "b")));
+    /// assert_eq!(cache.pop_oldest(), Some((3, "c")));
+    /// assert_eq!(cache.pop_oldest(), None);
+    /// ```
+    pub fn pop_oldest(&mut self) -> Option<(K, V)> {
+        let (order, map) = if self.l2_order.is_empty() {
+            (&mut self.order, &mut self.l1_map)
+        } else {
+            (&mut self.l2_order, &mut self.l2_map)
+        };
+        let (tick, hash) = order.pop_first()?;
+        map.raw_table_mut()
+            .remove_entry(hash, |(_, e)| e.tick == tick)
+            .map(|(k, e)| (k, e.value))
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it is not present
+    /// in the cache. The value is handed back to the caller, so it is not reported to the
+    /// eviction listener, see [`remove`].
+    ///
+    /// [`remove`]: LruCache::remove
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&3), Some("c"));
+    /// assert_eq!(cache.pop(&3), None);
+    /// assert_eq!(cache.get(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.pop_entry(k).map(|(_, e)| e.value)
+    }
+
+    /// Removes the key from the cache and hands its item to the eviction listener with
+    /// [`EvictionReason::Removed`], see [`with_eviction_listener`], instead of returning its
+    /// value like [`pop`]. Returns whether the key was present in the cache.
+    ///
+    /// [`with_eviction_listener`]: LruCache::with_eviction_listener
+    /// [`pop`]: LruCache::pop
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{EvictionReason, LruCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::{Arc, Mutex};
+    /// let removed = Arc::new(Mutex::new(Vec::new()));
+    /// let sink = removed.clone();
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap())
+    ///     .with_eviction_listener(move |k, v, reason| sink.lock().unwrap().push((k, v, reason)));
+    ///
+    /// cache.put(1, "a");
+    /// assert!(cache.remove(&1));
+    /// assert!(!cache.remove(&1));
+    /// assert_eq!(*removed.lock().unwrap(), [(1, "a", EvictionReason::Removed)]);
+    /// ```
+    pub fn remove<Q>(&mut self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let item = self.pop_entry(k);
+        let found = item.is_some();
+        self.hooks.evicted(item, EvictionReason::Removed);
+        found
+    }
+
+    fn pop_entry<Q>(&mut self, k: &Q) -> Option<(K, Entry<V>)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let ordered = self.is_ordered();
+        let ((rk, e), order) = match self.l1_map.remove_entry(k) {
+            Some(kv) => (kv, &mut self.order),
+            None => (self.l2_map.remove_entry(k)?, &mut self.l2_order),
+        };
+        if ordered {
+            order.remove(&e.tick);
+        }
+        Some((rk, e))
+    }
+
+    /// Keeps only the items for which the predicate returns `true`. The other items are
+    /// handed to the eviction listener, see [`with_eviction_listener`].
+    ///
+    /// [`with_eviction_listener`]: LruCache::with_eviction_listener
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// cache.retain(|&k, _| k != 1);
+    /// assert_eq!(cache.get(&1), None);
+    /// assert_eq!(cache.get(&2), Some(&"b"));
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let ordered = self.is_ordered();
+        for (map, order) in [
+            (&mut self.l1_map, &mut self.order),
+            (&mut self.l2_map, &mut self.l2_order),
+        ] {
+            let removed = map.extract_if(|k, e| !f(k, &mut e.value));
+            let removed = removed.inspect(|(_, e)| {
+                if ordered {
+                    order.remove(&e.tick);
+                }
+            });
+            self.hooks.evicted(removed, EvictionReason::Removed);
+        }
+    }
+
+    /// Removes all the items from the cache and hands them to the eviction listener, see
+    /// [`with_eviction_listener`]. The configuration and the flip metric are kept.
+    ///
+    /// [`with_eviction_listener`]: LruCache::with_eviction_listener
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// cache.clear();
+    /// assert!(cache.is_empty());
+    /// assert_eq!(cache.get(&1), None);
+    /// ```
+    pub fn clear(&mut self) {
+        self.drop_parked(usize::MAX);
+        self.hooks
+            .discard(&mut self.l2_map, EvictionReason::Cleared);
+        self.hooks
+            .discard(&mut self.l1_map, EvictionReason::Cleared);
+        self.l2_order.clear();
+        self.order.clear();
+        self.gen_hits = GenHits::default();
+        self.gen_inserts = 0;
+    }
+
+    /// Removes all the items from the cache like [`clear`] and releases the memory of the
+    /// maps, which otherwise keep their full capacity for the next items. This suits
+    /// services that want the memory back between bursts of work; the main cache then grows
+    /// again as items are put into it.
+    ///
+    /// [`clear`]: LruCache::clear
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// cache.clear_and_shrink();
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// ```
+    pub fn clear_and_shrink(&mut self) {
+        self.clear();
+        self.l1_map.shrink_to_fit();
+        self.l2_map.shrink_to_fit();
+        self.parked.shrink_to_fit();
+        self.spare = None;
+    }
+
+    /// Removes all the items from the cache in constant time, like [`clear`] but without
+    /// dropping them right away. The following gets and puts drop them a batch at a time,
+    /// see [`with_incremental_drop`], and hand them to the eviction listener. This keeps
+    /// invalidating a large cache, e.g., after a configuration change, from stalling.
+    ///
+    /// The emptied main cache grows again as items are put into it.
+    ///
+    /// [`clear`]: LruCache::clear
+    /// [`with_incremental_drop`]: LruCache::with_incremental_drop
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// cache.invalidate_all();
+    /// assert!(cache.is_empty());
+    /// assert_eq!(cache.get(&1), None);
+    /// ```
+    pub fn invalidate_all(&mut self) {
+        // The items to wipe are not parked, as the maps could not wipe them in place then
+        if self.hooks.wipe.is_some() {
+            return self.clear();
+        }
+        let (hasher, alloc) = (self.l1_map.hasher(), self.l1_map.allocator());
+        let empty = HashMap::with_hasher_in(hasher.clone(), alloc.clone());
+        let l2_map = mem::replace(&mut *self.l2_map, empty);
+        let next = match self.spare.take() {
+            Some(spare) => spare,
+            None => HashMap::with_hasher_in(hasher.clone(), alloc.clone()),
+        };
+        let l1_map = mem::replace(&mut *self.l1_map, next);
+        for map in [l2_map, l1_map] {
+            if !map.is_empty() {
+                self.parked.push((map.into_iter(), EvictionReason::Cleared));
+            }
+        }
+        mem::take(&mut self.l2_order);
+        mem::take(&mut self.order);
+        self.gen_hits = GenHits::default();
+        self.gen_inserts = 0;
+    }
+
+    fn on_access(&mut self) {
+        if !self.parked.is_empty() {
+            let batch = match self.drop_batch {
+                0 => INVALIDATED_DROP_BATCH,
+                n => n,
+            };
+            self.drop_parked(batch);
+        }
+        self.flip_if_due();
+        self.shrink_if_idle();
+    }
+
+    // Flips once for every flip interval that has passed, which is at most twice as that
+    // discards all the items.
+    fn flip_if_due(&mut self) {
+        let Some(timer) = &mut self.flip_timer else {
+            return;
+        };
+        let now = timer.clock.now();
+        let elapsed = now.saturating_sub(timer.start);
+        if elapsed < timer.interval {
+            return;
+        }
+        let intervals = elapsed.as_nanos() / timer.interval.as_nanos();
+        timer.start = u32::try_from(intervals)
+            .ok()
+            .and_then(|n| timer.interval.checked_mul(n))
+            .map_or(now, |d| timer.start + d);
+        for _ in 0..cmp::min(intervals, 2) {
+            self.flip_for(EvictionReason::Expired);
+        }
+    }
+
+    fn grow_if_recovering(&mut self) {
+        let Some(autotune) = &self.autotune else {
+            return;
+        };
+        let survival = self.gen_hits.l2 as f64 / self.hot_cap as f64;
+        if survival > AUTOTUNE_GROW_SURVIVAL && self.cap < autotune.max {
+            let step = cmp::max(self.cap.get() / 4, 1);
+            let cap = cmp::min(self.cap.saturating_add(step), autotune.max);
+            self.resize(cap);
+        }
+    }
+
+    fn shrink_if_idle(&mut self) {
+        let Some(autotune) = &self.autotune else {
+            return;
+        };
+        let accesses = self.gen_hits.l1 + self.gen_hits.l2 + self.gen_inserts as u64;
+        let budget = (self.hot_cap + self.cold_cap) as u64;
+        if accesses >= AUTOTUNE_SHRINK_ACCESSES as u64 * budget
+            && self.l1_map.len() < self.hot_cap
+            && self.cap > autotune.min
+        {
+            let step = cmp::max(self.cap.get() / 4, 1);
+            let cap = cmp::max(self.cap.get() - step, autotune.min.get());
+            // The new capacity takes effect at the next flip, which starts a new count
+            self.resize(NonZeroUsize::new(cap).unwrap_or(autotune.min));
+            self.gen_hits = GenHits::default();
+            self.gen_inserts = 0;
+        }
+    }
+
+    // Sets the capacity, scaling the sizes of the l1_map and the l2_map along with it.
+    fn resize(&mut self, cap: NonZeroUsize) {
+        let scale = |n: usize| {
+            // The product of two large capacities of a growable cache overflows a usize
+            let n = n as u128 * cap.get() as u128 / self.cap.get() as u128;
+            cmp::max(n as usize, 1)
+        };
+        self.hot_cap = scale(self.hot_cap);
+        self.cold_cap = scale(self.cold_cap);
+        self.cap = cap;
+    }
+
+    // Moves the split of the budget halfway towards the share of the hits that landed in
+    // the l1_map during the last generation.
+    fn adapt_split(&mut self) {
+        let GenHits { l1, l2 } = mem::take(&mut self.gen_hits);
+        if !self.adaptive || l1 + l2 == 0 {
+            return;
+        }
+        let budget = self.hot_cap + self.cold_cap;
+        let min = cmp::max(budget / 4, 1);
+        let target = (budget as f64 * l1 as f64 / (l1 + l2) as f64) as usize;
+        self.hot_cap = (self.hot_cap + target.clamp(min, budget - min)) / 2;
+        self.cold_cap = budget - self.hot_cap;
+    }
+
+    /// Returns the maximum number of key-value pairs the cache can hold.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: LruCache<isize, &str> = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert_eq!(cache.cap().get(), 2);
+    /// ```
+    pub fn cap(&self) -> NonZeroUsize {
+        self.cap
+    }
+
+    /// Returns the number of items at which the main cache flips. It equals `cap` unless
+    /// the adaptive split is enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: LruCache<isize, &str> = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert_eq!(cache.hot_cap(), 2);
+    /// ```
+    pub fn hot_cap(&self) -> usize {
+        self.hot_cap
+    }
+
+    /// Returns the maximum number of items in the backup cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: LruCache<isize, &str> = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert_eq!(cache.cold_cap(), 2);
+    /// ```
+    pub fn cold_cap(&self) -> usize {
+        self.cold_cap
+    }
+
+    /// Returns the number of key-value pairs that are currently in the the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert_eq!(cache.len(), 0);
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.len(), 1);
+    ///
+    /// cache.put(2, "b");
+    /// assert_eq!(cache.len(), 2);
+    ///
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        cmp::min(self.l1_map.len() + self.l2_map.len(), self.cap().into())
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    ///
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.l1_map.len() == 0 && self.l2_map.len() == 0
+    }
+
+    /// Returns metric on the number of times the cache became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// for i in 0..20 {
+    ///     cache.get(&(i % 5));
+    /// }
+    /// assert_eq!(cache.get_flips(), 8);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.flips
+    }
+
+    /// Reset the flip metric.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// for i in 0..20 {
+    ///     cache.get(&(i % 5));
+    /// }
+    /// assert_eq!(cache.get_flips(), 8);
+    /// cache.reset();
+    /// assert_eq!(cache.get_flips(), 0);
+    /// ```
+    pub fn reset(&mut self) {
+        self.flips = 0;
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K: Hash + Eq, V, S: BuildHasher + Clone, A: Allocator + Clone> Extend<(K, V)>
+    for LruCache<K, V, S, A>
+{
+    /// Puts all the key-value pairs into the cache, see [`put_many`](LruCache::put_many).
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        self.put_many(iter);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::{CacheObserver, EvictionReason, FlipMode, Lcg, LruCache, Priority, SpillSink};
+    use alloc::sync::Arc;
+    use core::{
+        fmt::Debug,
+        num::{NonZeroU32, NonZeroUsize},
+        sync::atomic::{AtomicU64, Ordering},
+        time::Duration,
+    };
+
+    fn assert_opt_eq<V: PartialEq + Debug>(opt: Option<&V>, v: V) {
+        assert!(opt.is_some());
+        assert_eq!(opt.unwrap(), &v);
+    }
+
+    #[test]
+    fn test_put_and_get() {
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        assert!(cache.is_empty());
+        assert_eq!(cache.get_flips(), 0);
+
+        assert_eq!(cache.put("apple", "red"), None);
+        assert_eq!(cache.put("banana", "yellow"), None);
+
+        assert_eq!(cache.cap().get(), 2);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get_flips(), 0);
+        assert!(!cache.is_empty());
+        assert_opt_eq(cache.get(&"apple"), "red");
+        assert_opt_eq(cache.get(&"banana"), "yellow");
+    }
+
+    #[test]
+    fn test_put_update() {
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+
+        assert_eq!(cache.put("apple", "red"), None);
+        assert_eq!(cache.put("apple", "green"), Some("red"));
+
+        assert_eq!(cache.len(), 1);
+        assert_opt_eq(cache.get(&"apple"), "green");
+    }
+
+    #[test]
+    fn test_l2() {
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+
+        assert_eq!(cache.get_flips(), 0);
+        assert_eq!(cache.put("apple", "red"), None);
+        assert_eq!(cache.put("banana", "yellow"), None);
+        assert_eq!(cache.put("pear", "green"), None);
+        assert_eq!(cache.get_flips(), 1);
+
+        // This is retrieved from the overflow (L2 cache)
+        assert_opt_eq(cache.get(&"apple"), "red");
+        assert_opt_eq(cache.get(&"banana"), "yellow");
+        assert_opt_eq(cache.get(&"pear"), "green");
+        assert_eq!(cache.get_flips(), 2);
+
+        // apple is no longer in both the caches
+        assert_eq!(cache.put("apple", "green"), None);
+        assert_eq!(cache.put("tomato", "red"), None);
+        assert_eq!(cache.get_flips(), 3);
+
+        assert_opt_eq(cache.get(&"pear"), "green");
+        assert_opt_eq(cache.get(&"apple"), "green");
+        assert_opt_eq(cache.get(&"tomato"), "red");
+        assert_eq!(cache.get_flips(), 5);
+    }
+
+    #[test]
+    fn test_max_cache_len() {
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+
+        assert_eq!(cache.put("apple", "red"), None);
+        assert_eq!(cache.put("banana", "yellow"), None);
+        assert_eq!(cache.put("pear", "green"), None);
+        assert_eq!(cache.put("tomato", "red"), None);
+        assert_eq!(cache.get_flips(), 1);
+
+        // Could retrieve `cap*2` oldest item, i.e., the 4th oldest item.
+        assert_opt_eq(cache.get(&"apple"), "red");
+        assert_eq!(cache.get_flips(), 2);
+
+        // Could not retrieve `cap+1` oldest item, i.e., the 3rd oldest item, showing that only the
+        // first `cap` items is guaranteed to be in the cache.
+        assert_eq!(cache.get(&"banana"), None);
+        assert_eq!(cache.get_flips(), 2);
+
+        cache.reset();
+        assert_eq!(cache.get_flips(), 0);
+    }
+
+    #[test]
+    fn test_cache_under_capacity() {
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        for i in 0..5 {
+            cache.put(i, i);
+        }
+        for i in 0..20 {
+            cache.get(&(i % 5));
+        }
+
+        assert_eq!(cache.get_flips(), 8);
+    }
+
+    #[test]
+    fn test_cache_over_capacity() {
+        let mut cache = LruCache::new(NonZeroUsize::new(5).unwrap());
+        for i in 0..5 {
+            cache.put(i, i);
+        }
+        for i in 0..20 {
+            cache.get(&(i % 5));
+        }
+
+        assert_eq!(cache.get_flips(), 0);
+    }
+
+    #[test]
+    fn test_promotion_threshold() {
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap())
+            .with_promotion_threshold(NonZeroU32::new(3).unwrap());
+
+        assert_eq!(cache.put("apple", "red"), None);
+        assert_eq!(cache.put("banana", "yellow"), None);
+        assert_eq!(cache.put("pear", "green"), None);
+        assert_eq!(cache.get_flips(), 1);
+
+        // Hits below the threshold are served from the L2 cache without a promotion
+        assert_opt_eq(cache.get(&"apple"), "red");
+        assert_opt_eq(cache.get(&"apple"), "red");
+        assert_eq!(cache.get_flips(), 1);
+
+        // The third hit promotes apple
+        assert_opt_eq(cache.get(&"apple"), "red");
+        assert_eq!(cache.len(), 2);
+
+        // banana was hit only once, so it does not survive the next flip
+        assert_opt_eq(cache.get(&"banana"), "yellow");
+        assert_eq!(cache.put("tomato", "red"), None);
+        assert_eq!(cache.get_flips(), 2);
+        assert_eq!(cache.get(&"banana"), None);
+        assert_opt_eq(cache.get(&"apple"), "red");
+    }
+
+    #[test]
+    fn test_promotion_probability() {
+        let mut cache = LruCache::new(NonZeroUsize::new(100).unwrap())
+            .with_promotion_probability(0.5)
+            .with_random_source(Lcg::new(1));
+        for i in 0..101 {
+            cache.put(i, i);
+        }
+        assert_eq!(cache.get_flips(), 1);
+
+        // Every item is still served, but only about half of them are promoted from L2
+        for i in 0..100 {
+            assert_opt_eq(cache.get(&i), i);
+        }
+        assert_eq!(cache.get_flips(), 1);
+        let promoted = cache.l1_map.len() - 1;
+        assert!(promoted > 30 && promoted < 70);
+    }
+
+    #[test]
+    fn test_priority_reserve() {
+        let mut cache = LruCache::new(NonZeroUsize::new(4).unwrap()).with_priority_reserve(0.5);
+
+        assert_eq!(
+            cache.put_with_priority("apple", "red", Priority::High),
+            None
+        );
+        assert_eq!(
+            cache.put_with_priority("banana", "yellow", Priority::High),
+            None
+        );
+        assert_eq!(
+            cache.put_with_priority("pear", "green", Priority::High),
+            None
+        );
+        for _ in 0..20 {
+            cache.put("tomato", "red");
+            cache.put("grape", "red");
+            cache.put("plum", "red");
+            cache.put("kiwi", "red");
+        }
+        assert!(cache.get_flips() > 2);
+
+        // Only two of the three high priority items fit in the reserve
+        let survivors = ["apple", "banana", "pear"]
+            .iter()
+            .filter(|k| cache.get(*k).is_some())
+            .count();
+        assert_eq!(survivors, 2);
+
+        // A regular put resets the priority
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_priority_reserve(1.0);
+        cache.put_with_priority("apple", "red", Priority::High);
+        cache.put("apple", "green");
+        for _ in 0..4 {
+            cache.put("tomato", "red");
+            cache.put("kiwi", "red");
+        }
+        assert_eq!(cache.get(&"apple"), None);
+    }
+
+    #[test]
+    fn test_put_cold() {
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+
+        assert_eq!(cache.put_cold("apple", "red"), None);
+        assert_eq!(cache.put_cold("banana", "yellow"), None);
+        assert_eq!(cache.put_cold("apple", "green"), Some("red"));
+        assert_eq!(cache.len(), 2);
+
+        // The full L2 cache is discarded for a new cold item
+        assert_eq!(cache.put_cold("pear", "green"), None);
+        assert_eq!(cache.get(&"apple"), None);
+        assert_opt_eq(cache.get(&"pear"), "green");
+        assert_eq!(cache.get_flips(), 0);
+    }
+
+    #[test]
+    fn test_scan_resistance() {
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_scan_resistance(true);
+
+        assert_eq!(cache.put("apple", "red"), None);
+        assert_eq!(cache.put("banana", "yellow"), None);
+        assert_opt_eq(cache.get(&"apple"), "red");
+        assert_opt_eq(cache.get(&"banana"), "yellow");
+
+        // A scan of new keys never flips the L1 cache
+        for key in ["pear", "tomato", "plum", "kiwi", "grape"] {
+            assert_eq!(cache.put(key, "x"), None);
+        }
+        assert_eq!(cache.get_flips(), 0);
+        assert_opt_eq(cache.get(&"apple"), "red");
+        assert_opt_eq(cache.get(&"banana"), "yellow");
+
+        // A hit promotes a scanned key
+        assert_opt_eq(cache.get(&"grape"), "x");
+        assert_eq!(cache.get_flips(), 1);
+        assert_opt_eq(cache.get(&"apple"), "red");
+        assert_eq!(cache.put("grape", "purple"), Some("x"));
+    }
+
+    #[test]
+    fn test_adaptive_split() {
+        let mut cache = LruCache::new(NonZeroUsize::new(8).unwrap()).with_adaptive_split(true);
+
+        // Cycling over 12 keys recovers every item from the L2 cache, growing it
+        for i in 0..96 {
+            if cache.get(&(i % 12)).is_none() {
+                cache.put(i % 12, i);
+            }
+        }
+        assert!(cache.cold_cap > 8);
+        assert_eq!(cache.hot_cap + cache.cold_cap, 16);
+        for i in 0..12 {
+            assert!(cache.get(&i).is_some());
+        }
+
+        // Repeated hits on a small working set grow the L1 cache back
+        for i in 0..200 {
+            cache.put(100 + i % 2, i);
+            cache.get(&(100 + i % 2));
+            cache.put(200 + i, i);
+        }
+        assert!(cache.hot_cap > 8);
+        assert_eq!(cache.hot_cap + cache.cold_cap, 16);
+    }
+
+    #[test]
+    fn test_with_caps() {
+        let hot = NonZeroUsize::new(4).unwrap();
+        let cold = NonZeroUsize::new(2).unwrap();
+        let mut cache = LruCache::with_caps(hot, cold);
+        for i in 0..5 {
+            cache.put(i, i);
+        }
+        assert_eq!(cache.get_flips(), 1);
+        assert_eq!(cache.l2_map.len(), 2);
+        assert_eq!((0..4).filter(|i| cache.get(i).is_some()).count(), 2);
+
+        // The L2 cache keeps two generations
+        let mut cache = LruCache::with_caps(cold, hot);
+        for i in 0..6 {
+            cache.put(i, i);
+        }
+        assert_eq!(cache.get_flips(), 2);
+        assert!((0..4).all(|i| cache.l2_map.contains_key(&i)));
+
+        // Both are replaced when the next generation does not fit
+        cache.put(6, 6);
+        assert_eq!(cache.get_flips(), 3);
+        assert!(cache.l2_map.contains_key(&4) && cache.l2_map.contains_key(&5));
+        assert_eq!(cache.l2_map.len(), 2);
     }
 
     #[test]
-    fn test_put_and_get() {
-        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
-        assert!(cache.is_empty());
+    fn test_manual_flip() {
+        let mut cache =
+            LruCache::new(NonZeroUsize::new(2).unwrap()).with_flip_mode(FlipMode::Manual);
+
+        for i in 0..4 {
+            assert_eq!(cache.put(i, i), None);
+        }
         assert_eq!(cache.get_flips(), 0);
+        assert_eq!(cache.l1_map.len(), 4);
 
-        assert_eq!(cache.put("apple", "red"), None);
-        assert_eq!(cache.put("banana", "yellow"), None);
+        // The whole generation is backed up
+        cache.flip();
+        assert_eq!(cache.get_flips(), 1);
+        assert_eq!(cache.l2_map.len(), 4);
+        assert_opt_eq(cache.get(&3), 3);
 
-        assert_eq!(cache.cap().get(), 2);
-        assert_eq!(cache.len(), 2);
-        assert_eq!(cache.get_flips(), 0);
-        assert!(!cache.is_empty());
-        assert_opt_eq(cache.get(&"apple"), "red");
-        assert_opt_eq(cache.get(&"banana"), "yellow");
+        cache.flip();
+        assert_opt_eq(cache.get(&3), 3);
+        assert_eq!(cache.get(&2), None);
     }
 
     #[test]
-    fn test_put_update() {
-        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    fn test_flip_interval() {
+        let secs = Arc::new(AtomicU64::new(0));
+        let clock = {
+            let secs = secs.clone();
+            move || Duration::from_secs(secs.load(Ordering::Relaxed))
+        };
+        let mut cache = LruCache::new(NonZeroUsize::new(10).unwrap())
+            .with_flip_interval(Duration::from_secs(10), clock);
 
         assert_eq!(cache.put("apple", "red"), None);
-        assert_eq!(cache.put("apple", "green"), Some("red"));
+        secs.store(15, Ordering::Relaxed);
+        assert_eq!(cache.put("banana", "yellow"), None);
+        assert_eq!(cache.get_flips(), 1);
 
-        assert_eq!(cache.len(), 1);
-        assert_opt_eq(cache.get(&"apple"), "green");
+        // The next interval starts at 10 seconds
+        secs.store(20, Ordering::Relaxed);
+        assert_opt_eq(cache.get(&"banana"), "yellow");
+        assert_eq!(cache.get_flips(), 2);
+        assert_eq!(cache.get(&"apple"), None);
+
+        // Several intervals without any access discard everything
+        secs.store(100, Ordering::Relaxed);
+        assert_eq!(cache.get(&"banana"), None);
+        assert_eq!(cache.get_flips(), 4);
+        assert!(cache.is_empty());
     }
 
     #[test]
-    fn test_l2() {
-        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    fn test_flip_every_insertions() {
+        let mode = FlipMode::EveryInsertions(NonZeroUsize::new(3).unwrap());
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_flip_mode(mode);
 
+        for i in 0..3 {
+            assert_eq!(cache.put(i, i), None);
+        }
         assert_eq!(cache.get_flips(), 0);
-        assert_eq!(cache.put("apple", "red"), None);
-        assert_eq!(cache.put("banana", "yellow"), None);
-        assert_eq!(cache.put("pear", "green"), None);
-        assert_eq!(cache.get_flips(), 1);
+        assert_eq!(cache.l1_map.len(), 3);
 
-        // This is retrieved from the overflow (L2 cache)
-        assert_opt_eq(cache.get(&"apple"), "red");
-        assert_opt_eq(cache.get(&"banana"), "yellow");
-        assert_opt_eq(cache.get(&"pear"), "green");
+        // Updates and promotions count as insertions too
+        assert_eq!(cache.put(0, 10), Some(0));
+        assert_eq!(cache.get_flips(), 1);
+        assert_opt_eq(cache.get(&1), 1);
+        assert_opt_eq(cache.get(&2), 2);
+        assert_eq!(cache.get_flips(), 1);
+        assert_eq!(cache.put(3, 3), None);
         assert_eq!(cache.get_flips(), 2);
+        assert_eq!(cache.l2_map.len(), 3);
+    }
 
-        // apple is no longer in both the caches
-        assert_eq!(cache.put("apple", "green"), None);
-        assert_eq!(cache.put("tomato", "red"), None);
-        assert_eq!(cache.get_flips(), 3);
+    #[test]
+    fn test_autotune() {
+        let mut cache = LruCache::new(NonZeroUsize::new(4).unwrap()).with_autotune(
+            NonZeroUsize::new(2).unwrap(),
+            NonZeroUsize::new(64).unwrap(),
+        );
 
-        assert_opt_eq(cache.get(&"pear"), "green");
-        assert_opt_eq(cache.get(&"apple"), "green");
-        assert_opt_eq(cache.get(&"tomato"), "red");
-        assert_eq!(cache.get_flips(), 5);
+        // A working set that does not fit grows the cache until it does
+        for i in 0..300 {
+            if cache.get(&(i % 7)).is_none() {
+                cache.put(i % 7, i);
+            }
+        }
+        let cap = cache.cap().get();
+        assert!(cap >= 7);
+        assert_eq!(cache.hot_cap(), cap);
+        let flips = cache.get_flips();
+        for i in 0..700 {
+            assert!(cache.get(&(i % 7)).is_some());
+        }
+        assert_eq!(cache.get_flips(), flips);
+        assert_eq!(cache.cap().get(), cap);
+
+        // A cache that never fills up shrinks down to its working set
+        let mut cache = LruCache::new(NonZeroUsize::new(16).unwrap()).with_autotune(
+            NonZeroUsize::new(2).unwrap(),
+            NonZeroUsize::new(64).unwrap(),
+        );
+        for i in 0..3 {
+            cache.put(i, i);
+        }
+        for i in 0..10_000 {
+            cache.get(&(i % 3));
+        }
+        assert_eq!(cache.cap().get(), 3);
+        assert_eq!(cache.cold_cap(), 3);
+
+        // The capacities of a large growable cache are scaled without overflowing
+        let mut cache = LruCache::<u32, u32>::new_growable(NonZeroUsize::new(1 << 40).unwrap());
+        cache.resize(NonZeroUsize::new(1 << 41).unwrap());
+        assert_eq!((cache.hot_cap(), cache.cold_cap()), (1 << 41, 1 << 41));
     }
 
     #[test]
-    fn test_max_cache_len() {
+    fn test_order_after_puts() {
+        // The items put before the mode switch are ordered, so they can be evicted
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        cache.put(1, 1);
+        cache.put(2, 2);
+        let mut cache = cache.with_flip_mode(FlipMode::StrictLru);
+        assert_eq!(cache.put(3, 3), None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.order.len(), 2);
+        assert_opt_eq(cache.get(&3), 3);
+
         let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        for i in 0..3 {
+            cache.put(i, i);
+        }
+        // The backup cache is older than the main cache
+        let mut cache = cache.with_insertion_order(true);
+        assert!(cache.pop_oldest().unwrap().0 < 2);
+        assert!(cache.pop_oldest().unwrap().0 < 2);
+        assert_eq!(cache.pop_oldest(), Some((2, 2)));
+        assert_eq!(cache.pop_oldest(), None);
+    }
+
+    #[test]
+    fn test_strict_lru() {
+        let mut cache =
+            LruCache::new(NonZeroUsize::new(3).unwrap()).with_flip_mode(FlipMode::StrictLru);
 
         assert_eq!(cache.put("apple", "red"), None);
         assert_eq!(cache.put("banana", "yellow"), None);
         assert_eq!(cache.put("pear", "green"), None);
+        assert_opt_eq(cache.get(&"apple"), "red");
+        assert_eq!(cache.put("banana", "brown"), Some("yellow"));
+
+        // pear is the least recently used item
         assert_eq!(cache.put("tomato", "red"), None);
+        assert_eq!(cache.get(&"pear"), None);
+        assert_eq!(cache.put("kiwi", "green"), None);
+        assert_eq!(cache.get(&"apple"), None);
+        assert_opt_eq(cache.get(&"banana"), "brown");
+        assert_opt_eq(cache.get(&"tomato"), "red");
+        assert_opt_eq(cache.get(&"kiwi"), "green");
+        assert_eq!(cache.get_flips(), 0);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.order.len(), 3);
+
+        // Matches a reference LRU over a longer access pattern
+        let mut cache =
+            LruCache::new(NonZeroUsize::new(4).unwrap()).with_flip_mode(FlipMode::StrictLru);
+        let mut recency: alloc::vec::Vec<u32> = alloc::vec::Vec::new();
+        for i in 0..500u32 {
+            let k = (i * 7 + i / 3) % 9;
+            let hit = cache.get(&k).is_some();
+            assert_eq!(hit, recency.contains(&k));
+            recency.retain(|r| *r != k);
+            recency.push(k);
+            if !hit {
+                cache.put(k, i);
+                if recency.len() > 4 {
+                    recency.remove(0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_insertion_order() {
+        let mut cache = LruCache::new(NonZeroUsize::new(4).unwrap()).with_insertion_order(true);
+
+        for i in 0..5 {
+            assert_eq!(cache.put(i, i), None);
+        }
+        // 0..4 were flipped to L2, promotions and updates become the newest items of L1
+        assert_opt_eq(cache.get(&0), 0);
+        assert_eq!(cache.put(1, 10), Some(1));
+        assert_eq!(cache.put_cold(5, 5), None);
+        assert_eq!(cache.peek_oldest(), Some((&2, &2)));
+
+        let mut popped = alloc::vec::Vec::new();
+        while let Some((k, v)) = cache.pop_oldest() {
+            popped.push((k, v));
+        }
+        assert_eq!(popped, [(2, 2), (3, 3), (5, 5), (4, 4), (0, 0), (1, 10)]);
+        assert!(cache.is_empty());
+        assert!(cache.order.is_empty() && cache.l2_order.is_empty());
+
+        // Nothing is tracked by default
+        let mut cache = LruCache::new(NonZeroUsize::new(3).unwrap());
+        cache.put(1, 1);
+        assert_eq!(cache.pop_oldest(), None);
+    }
+
+    #[test]
+    fn test_eviction_listener() {
+        let count = Arc::new(AtomicU64::new(0));
+        let sum = Arc::new(AtomicU64::new(0));
+        let (c, s) = (count.clone(), sum.clone());
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap())
+            .with_flip_mode(FlipMode::StrictLru)
+            .with_eviction_listener(move |k: u64, v: u64, _| {
+                c.fetch_add(1, Ordering::Relaxed);
+                s.fetch_add(k * 10 + v, Ordering::Relaxed);
+            });
+
+        cache.put(1, 1);
+        cache.put(2, 2);
+        cache.get(&1);
+        // 2 is the least recently used item
+        cache.put(3, 3);
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+        assert_eq!(sum.load(Ordering::Relaxed), 22);
+
+        // Popped and replaced values are returned instead
+        assert_eq!(cache.pop(&1), Some(1));
+        assert_eq!(cache.put(3, 4), Some(3));
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+
+        cache.put(5, 5);
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(count.load(Ordering::Relaxed), 3);
+        assert_eq!(sum.load(Ordering::Relaxed), 22 + 34 + 55);
+    }
+
+    #[test]
+    fn test_eviction_reason() {
+        // Sums the keys reported for each reason
+        let sums: Arc<[AtomicU64; 5]> = Arc::new(Default::default());
+        let s = sums.clone();
+        let now = Arc::new(AtomicU64::new(0));
+        let clock = now.clone();
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap())
+            .with_flip_interval(Duration::from_secs(10), move || {
+                Duration::from_secs(clock.load(Ordering::Relaxed))
+            })
+            .with_eviction_listener(move |k: u64, _: u64, reason| {
+                s[reason as usize].fetch_add(k, Ordering::Relaxed);
+            });
+        let sum = |reason: EvictionReason| sums[reason as usize].load(Ordering::Relaxed);
+
+        cache.extend([(1, 1), (2, 2)]);
+        cache.extend([(1, 10)]);
+        assert_eq!(sum(EvictionReason::Replaced), 1);
+        // Like a put, the update flipped the full main cache, and the flip at 4 discards 2
+        cache.put(3, 3);
+        cache.put(4, 4);
+        cache.put(5, 5);
+        assert_eq!(sum(EvictionReason::Flipped), 2);
+
+        cache.retain(|&k, _| k != 4);
+        assert_eq!(sum(EvictionReason::Removed), 4);
+
+        // Two intervals flip twice, discarding 1 and 3 and then 5
+        now.store(20, Ordering::Relaxed);
+        cache.put(6, 6);
+        assert_eq!(sum(EvictionReason::Expired), 9);
+
+        cache.clear();
+        assert_eq!(sum(EvictionReason::Cleared), 6);
+
+        // A popped value goes back to the caller, a removed one to the listener
+        cache.put(7, 7);
+        assert_eq!(cache.pop(&7), Some(7));
+        cache.put(8, 8);
+        assert!(cache.remove(&8));
+        assert!(!cache.remove(&8));
+        assert_eq!(sum(EvictionReason::Removed), 12);
+    }
+
+    #[test]
+    fn test_observer() {
+        // Counts inserts and updates and sums the new values
+        struct Counts(Arc<[AtomicU64; 3]>);
+
+        impl CacheObserver<u64, u64> for Counts {
+            fn on_insert(&mut self, _: &u64, v: &u64) {
+                self.0[0].fetch_add(1, Ordering::Relaxed);
+                self.0[2].fetch_add(*v, Ordering::Relaxed);
+            }
+            fn on_update(&mut self, _: &u64, _: &u64, new: &u64) {
+                self.0[1].fetch_add(1, Ordering::Relaxed);
+                self.0[2].fetch_add(*new, Ordering::Relaxed);
+            }
+        }
+
+        let counts: Arc<[AtomicU64; 3]> = Arc::new(Default::default());
+        let mut cache =
+            LruCache::new(NonZeroUsize::new(2).unwrap()).with_observer(Counts(counts.clone()));
+        let load = |i: usize| counts[i].load(Ordering::Relaxed);
+
+        cache.put(1, 1);
+        cache.put(2, 2);
+        cache.put(3, 3);
+        assert_eq!((load(0), load(1), load(2)), (3, 0, 6));
+
+        // Promotions are not reported, but replacing a value in either map is
+        assert_eq!(cache.get(&1), Some(&1));
+        assert_eq!(cache.put(3, 30), Some(3));
+        assert_eq!(cache.put_cold(1, 10), Some(1));
+        assert_eq!((load(0), load(1), load(2)), (3, 2, 46));
+    }
+
+    #[test]
+    fn test_spill_sink() {
+        // Counts the spilled items and the calls per flip
+        struct Counts(Arc<[AtomicU64; 2]>);
+
+        impl SpillSink<u64, u64> for Counts {
+            fn spill(&mut self, items: &mut dyn Iterator<Item = (u64, u64)>) {
+                self.0[0].fetch_add(1, Ordering::Relaxed);
+                self.0[1].fetch_add(items.count() as u64, Ordering::Relaxed);
+            }
+        }
+
+        let counts: Arc<[AtomicU64; 2]> = Arc::new(Default::default());
+        let removed = Arc::new(AtomicU64::new(0));
+        let r = removed.clone();
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap())
+            .with_spill_sink(Counts(counts.clone()))
+            .with_eviction_listener(move |_: u64, _: u64, _| {
+                r.fetch_add(1, Ordering::Relaxed);
+            });
+
+        for i in 0..7 {
+            cache.put(i, i);
+        }
+        // Two generations were discarded by the last two flips
+        assert_eq!(counts[0].load(Ordering::Relaxed), 2);
+        assert_eq!(counts[1].load(Ordering::Relaxed), 4);
+
+        // Intentional removals still go to the listener
+        cache.clear();
+        assert_eq!(counts[1].load(Ordering::Relaxed), 4);
+        assert_eq!(removed.load(Ordering::Relaxed), 3);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_event_channel() {
+        use super::{CacheEvent, Tier};
+        use std::sync::mpsc;
+        use std::vec::Vec;
+
+        let (tx, rx) = mpsc::channel();
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_event_channel(tx);
+        for i in 0..5 {
+            cache.put(i, i);
+        }
+        assert_eq!(cache.get(&3), Some(&3));
+        // Promoting 2 flips the full main cache
+        assert_eq!(cache.get(&2), Some(&2));
+        assert_eq!(cache.get(&0), None);
+
+        let events: Vec<_> = rx.try_iter().filter(|e| *e != CacheEvent::Insert).collect();
+        assert_eq!(
+            events,
+            [
+                CacheEvent::Flip { discarded: 0 },
+                CacheEvent::Flip { discarded: 2 },
+                CacheEvent::Eviction(EvictionReason::Flipped),
+                CacheEvent::Eviction(EvictionReason::Flipped),
+                CacheEvent::Hit(Tier::Cold),
+                CacheEvent::Hit(Tier::Cold),
+                CacheEvent::Flip { discarded: 0 },
+                CacheEvent::Miss,
+            ]
+        );
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn test_eviction_stream() {
+        use futures_channel::mpsc;
+
+        let (tx, mut rx) = mpsc::channel(0);
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_eviction_stream(tx);
+        for i in 0..5 {
+            cache.put(i, i);
+        }
+        // The channel is full after one item, the other one is dropped
+        let (k, v, reason) = rx.try_recv().unwrap();
+        assert!(k < 2 && k == v && reason == EvictionReason::Flipped);
+        assert!(rx.try_recv().is_err());
+
+        cache.retain(|&k, _| k != 4);
+        assert_eq!(rx.try_recv().ok(), Some((4, 4, EvictionReason::Removed)));
+    }
+
+    #[test]
+    fn test_dirty_flush() {
+        let flushed = Arc::new(AtomicU64::new(0));
+        let f = flushed.clone();
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_dirty_flush(
+            move |&k: &u64, &v: &u64| {
+                f.fetch_add(k * 100 + v, Ordering::Relaxed);
+            },
+        );
+
+        cache.put(1, 1);
+        cache.put(2, 2);
+        cache.put(3, 3);
+        // Dirty items stay dirty when promoted from the backup cache
+        assert!(cache.mark_dirty(&2));
+        *cache.get_mut(&2).unwrap() = 20;
+        cache.put(4, 4);
+        cache.put(5, 5);
+        assert_eq!(flushed.load(Ordering::Relaxed), 0);
+
+        // A flushed item is clean, a failed flush keeps it dirty
+        assert_eq!(cache.flush_dirty(|_, _| Err(())), Err(()));
+        cache.flush_dirty(|_, _| Ok::<_, ()>(())).unwrap();
+        cache.put(6, 6);
+        cache.put(7, 7);
+        assert_eq!(flushed.load(Ordering::Relaxed), 0);
+
+        assert!(cache.mark_dirty(&5));
+        cache.clear();
+        assert_eq!(flushed.load(Ordering::Relaxed), 505);
+    }
+
+    #[test]
+    fn test_custom_allocator() {
+        use allocator_api2::alloc::{AllocError, Allocator, Global};
+        use core::alloc::Layout;
+        use core::ptr::NonNull;
+        use hashbrown::hash_map::DefaultHashBuilder;
+
+        // Counts the allocations
+        #[derive(Clone, Default)]
+        struct Counting(Arc<AtomicU64>);
+
+        unsafe impl Allocator for Counting {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                Global.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                unsafe { Global.deallocate(ptr, layout) }
+            }
+        }
+
+        let alloc = Counting::default();
+        let cap = NonZeroUsize::new(2).unwrap();
+        let hasher = DefaultHashBuilder::default();
+        let mut cache = LruCache::with_hasher_in(cap, cap, hasher, alloc.clone());
+        // Only the main cache is allocated upfront
+        let allocs = alloc.0.load(Ordering::Relaxed);
+        assert_eq!(allocs, 1);
+
+        // The map of the new generation comes from the allocator too
+        for i in 0..3 {
+            cache.put(i, i);
+        }
         assert_eq!(cache.get_flips(), 1);
+        assert_eq!(alloc.0.load(Ordering::Relaxed), allocs + 1);
+        assert_eq!(cache.get(&0), Some(&0));
 
-        // Could retrieve `cap*2` oldest item, i.e., the 4th oldest item.
-        assert_opt_eq(cache.get(&"apple"), "red");
-        assert_eq!(cache.get_flips(), 2);
+        // The later flips reuse the map of the discarded generation
+        for i in 3..20 {
+            cache.put(i, i);
+        }
+        assert_eq!(cache.get_flips(), 10);
+        assert_eq!(alloc.0.load(Ordering::Relaxed), allocs + 1);
+    }
 
-        // Could not retrieve `cap+1` oldest item, i.e., the 3rd oldest item, showing that only the
-        // first `cap` items is guaranteed to be in the cache.
-        assert_eq!(cache.get(&"banana"), None);
+    #[test]
+    fn test_try_put() {
+        let mut cache = LruCache::try_new(NonZeroUsize::new(2).unwrap()).unwrap();
+        assert_eq!(cache.try_put(1, 1), Ok(None));
+        assert_eq!(cache.try_put(2, 2), Ok(None));
+        assert!(cache.spare.is_none());
+
+        // The full main cache gets a spare map that the flip then uses
+        assert_eq!(cache.try_put(3, 3), Ok(None));
+        assert!(cache.spare.is_none());
+        assert_eq!(cache.get_flips(), 1);
+        assert!(cache.l1_map.capacity() >= 2);
+        assert_eq!(cache.get(&1), Some(&1));
+
+        let huge: Result<LruCache<u64, u64>, _> = LruCache::try_new(NonZeroUsize::MAX);
+        assert!(huge.is_err());
+    }
+
+    #[test]
+    fn test_lazy_backup() {
+        let mut cache = LruCache::new(NonZeroUsize::new(4).unwrap());
+        for i in 0..4 {
+            cache.put(i, i);
+        }
+        assert_eq!(cache.l2_map.capacity(), 0);
+
+        cache.put(4, 4);
+        assert!(cache.l2_map.capacity() >= 4);
+        assert_eq!(cache.get(&0), Some(&0));
+    }
+
+    #[test]
+    fn test_growable() {
+        let mut cache = LruCache::new_growable(NonZeroUsize::new(1 << 20).unwrap());
+        assert_eq!(cache.l1_map.capacity(), 0);
+        for i in 0..10 {
+            cache.put(i, i);
+        }
+        assert!(cache.l1_map.capacity() < 1 << 10);
+
+        cache.flip();
+        assert_eq!(cache.l2_map.len(), 10);
+        assert!(cache.l1_map.capacity() >= 10 && cache.l1_map.capacity() < 1 << 10);
+        assert_eq!(cache.get(&0), Some(&0));
+    }
+
+    #[test]
+    fn test_new_const() {
+        const CACHE: LruCache<u32, u32, crate::FnvBuildHasher> =
+            LruCache::new_const(NonZeroUsize::new(2).unwrap());
+        let mut cache = CACHE;
+        assert_eq!(cache.l1_map.capacity(), 0);
+        for i in 0..3 {
+            cache.put(i, i);
+        }
+        assert_eq!(cache.get_flips(), 1);
+        assert_eq!(cache.get(&0), Some(&0));
+    }
+
+    #[test]
+    fn test_incremental_drop() {
+        let evicted = Arc::new(AtomicU64::new(0));
+        let count = evicted.clone();
+        let mut cache = LruCache::new(NonZeroUsize::new(4).unwrap())
+            .with_incremental_drop(NonZeroUsize::new(2).unwrap())
+            .with_eviction_listener(move |_, _, _| {
+                count.fetch_add(1, Ordering::Relaxed);
+            });
+        for i in 0..9 {
+            cache.put(i, i);
+        }
         assert_eq!(cache.get_flips(), 2);
+        assert_eq!(evicted.load(Ordering::Relaxed), 0);
 
-        cache.reset();
-        assert_eq!(cache.get_flips(), 0);
+        // Each access drops a batch of the discarded generation
+        assert_eq!(cache.get(&8), Some(&8));
+        assert_eq!(evicted.load(Ordering::Relaxed), 2);
+        assert_eq!(cache.get(&0), None);
+        assert_eq!(evicted.load(Ordering::Relaxed), 4);
+        assert!(cache.parked.is_empty());
+
+        // The next flip drops what is left of the previous one first
+        for i in 9..13 {
+            cache.put(i, i);
+        }
+        assert_eq!(cache.get_flips(), 3);
+        cache.flip();
+        assert_eq!(evicted.load(Ordering::Relaxed), 8);
+        assert_eq!(
+            cache
+                .parked
+                .iter()
+                .map(|(items, _)| items.len())
+                .sum::<usize>(),
+            4
+        );
     }
 
     #[test]
-    fn test_cache_under_capacity() {
-        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
-        for i in 0..5 {
+    fn test_pooling() {
+        let (two, eight) = (NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        let mut pooled = LruCache::with_caps(two, eight);
+        let mut unpooled = LruCache::with_caps(two, eight).with_pooling(false);
+        for i in 0..12 {
+            pooled.put(i, i);
+            unpooled.put(i, i);
+        }
+        // The last flip discarded the full backup cache, whose map takes the new generation
+        assert_eq!(pooled.get_flips(), 5);
+        assert_eq!(pooled.l2_map.len(), 2);
+        assert!(pooled.l1_map.capacity() >= 8);
+        assert!(unpooled.l1_map.capacity() < 8);
+        assert_eq!(unpooled.get(&10), Some(&10));
+    }
+
+    #[test]
+    fn test_invalidate_all() {
+        let evicted = Arc::new(AtomicU64::new(0));
+        let count = evicted.clone();
+        let mut cache = LruCache::new(NonZeroUsize::new(100).unwrap()).with_eviction_listener(
+            move |_, _, reason| {
+                assert_eq!(reason, EvictionReason::Cleared);
+                count.fetch_add(1, Ordering::Relaxed);
+            },
+        );
+        for i in 0..150 {
             cache.put(i, i);
         }
-        for i in 0..20 {
-            cache.get(&(i % 5));
+        cache.invalidate_all();
+        assert!(cache.is_empty());
+        assert_eq!(evicted.load(Ordering::Relaxed), 0);
+
+        // The following accesses drop the items a batch at a time
+        assert_eq!(cache.get(&149), None);
+        assert_eq!(evicted.load(Ordering::Relaxed), 64);
+        cache.put(1, 1);
+        cache.put(2, 2);
+        assert_eq!(evicted.load(Ordering::Relaxed), 150);
+        assert!(cache.parked.is_empty());
+        assert_eq!(cache.get(&1), Some(&1));
+    }
+
+    #[test]
+    fn test_promotion_hashes_once() {
+        use allocator_api2::alloc::Global;
+        use core::hash::BuildHasher;
+
+        // Counts the hashed keys
+        #[derive(Clone, Default)]
+        struct Counting(Arc<AtomicU64>);
+
+        impl BuildHasher for Counting {
+            type Hasher = crate::FnvHasher;
+
+            fn build_hasher(&self) -> crate::FnvHasher {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                crate::FnvHasher::default()
+            }
         }
 
-        assert_eq!(cache.get_flips(), 8);
+        let hasher = Counting::default();
+        let cap = NonZeroUsize::new(2).unwrap();
+        let mut cache = LruCache::with_hasher_in(cap, cap, hasher.clone(), Global);
+        for i in 0..3 {
+            cache.put(i, i);
+        }
+        let hashes = hasher.0.load(Ordering::Relaxed);
+        assert_eq!(cache.get(&0), Some(&0));
+        assert_eq!(hasher.0.load(Ordering::Relaxed), hashes + 1);
+        assert_eq!(
+            cache.peek_entry(&0).map(|(_, _, tier)| tier),
+            Some(crate::Tier::Hot)
+        );
     }
 
     #[test]
-    fn test_cache_over_capacity() {
-        let mut cache = LruCache::new(NonZeroUsize::new(5).unwrap());
-        for i in 0..5 {
+    fn test_lookup_probes_once() {
+        // Counts the key comparisons, one per probe that finds the key
+        static COMPARED: AtomicU64 = AtomicU64::new(0);
+
+        #[derive(Eq)]
+        struct Key(u32);
+
+        impl core::hash::Hash for Key {
+            fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+                self.0.hash(state);
+            }
+        }
+
+        impl PartialEq for Key {
+            fn eq(&self, other: &Key) -> bool {
+                COMPARED.fetch_add(1, Ordering::Relaxed);
+                self.0 == other.0
+            }
+        }
+
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap())
+            .with_promotion_threshold(NonZeroU32::new(2).unwrap());
+        for i in 0..3 {
+            cache.put(Key(i), i);
+        }
+        let compared = || COMPARED.swap(0, Ordering::Relaxed);
+        compared();
+        // A hit in the main cache, in the backup cache and a promotion
+        assert_eq!(cache.get(&Key(2)), Some(&2));
+        assert_eq!(compared(), 1);
+        assert_eq!(cache.get(&Key(0)), Some(&0));
+        assert_eq!(compared(), 1);
+        assert_eq!(cache.get(&Key(0)), Some(&0));
+        assert_eq!(compared(), 1);
+        assert_eq!(
+            cache.peek_entry(&Key(0)).map(|(_, _, tier)| tier),
+            Some(crate::Tier::Hot)
+        );
+    }
+
+    #[test]
+    fn test_clear_and_shrink() {
+        let mut cache = LruCache::new(NonZeroUsize::new(4).unwrap());
+        for i in 0..6 {
             cache.put(i, i);
         }
-        for i in 0..20 {
-            cache.get(&(i % 5));
+        cache.clear();
+        assert!(cache.l1_map.capacity() >= 4);
+
+        for i in 0..6 {
+            cache.put(i, i);
+        }
+        cache.clear_and_shrink();
+        assert_eq!(cache.l1_map.capacity(), 0);
+        assert_eq!(cache.l2_map.capacity(), 0);
+        for i in 0..10 {
+            cache.put(i, i);
         }
+        assert_eq!(cache.get(&9), Some(&9));
+        assert_eq!(cache.get_flips(), 4);
+    }
 
-        assert_eq!(cache.get_flips(), 0);
+    #[test]
+    fn test_put_many() {
+        // The batches end up like one put at a time, replacing keys in both tiers
+        let replaced = Arc::new(AtomicU64::new(0));
+        let r = replaced.clone();
+        let mut batched = LruCache::new(NonZeroUsize::new(4).unwrap()).with_eviction_listener(
+            move |k: u64, _: u64, reason| {
+                if reason == EvictionReason::Replaced {
+                    r.fetch_add(k, Ordering::Relaxed);
+                }
+            },
+        );
+        let mut single = LruCache::new(NonZeroUsize::new(4).unwrap());
+        let items = [0, 1, 2, 1, 3, 4, 5, 2, 6, 7, 8, 9, 9, 3];
+        batched.put_many(items.iter().enumerate().map(|(i, &k)| (k, i as u64)));
+        for (i, &k) in items.iter().enumerate() {
+            single.put(k, i as u64);
+        }
+        assert_eq!(batched.get_flips(), single.get_flips());
+        for k in 0..10 {
+            assert_eq!(batched.peek(&k), single.peek(&k));
+        }
+        assert_eq!(replaced.load(Ordering::Relaxed), 1 + 2 + 9);
+    }
+
+    #[test]
+    fn test_put_many_like_puts() {
+        // Counts the inserts and the updates
+        struct Counts(Arc<[AtomicU64; 2]>);
+
+        impl CacheObserver<u64, u64> for Counts {
+            fn on_insert(&mut self, _: &u64, _: &u64) {
+                self.0[0].fetch_add(1, Ordering::Relaxed);
+            }
+            fn on_update(&mut self, _: &u64, _: &u64, _: &u64) {
+                self.0[1].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        type Mode = fn(LruCache<u64, u64>, Arc<AtomicU64>, Counts) -> LruCache<u64, u64>;
+        let modes: [Mode; 7] = [
+            |cache, _, _| cache,
+            |cache, _, _| cache.with_flip_mode(FlipMode::Manual),
+            |cache, _, _| cache.with_flip_mode(FlipMode::EveryInsertions(NonZeroUsize::MIN)),
+            |cache, _, _| cache.with_flip_mode(FlipMode::StrictLru),
+            |cache, _, _| cache.with_scan_resistance(true),
+            |cache, _, counts| cache.with_observer(counts),
+            |cache, now, _| {
+                cache.with_flip_interval(Duration::from_secs(10), move || {
+                    Duration::from_secs(now.load(Ordering::Relaxed))
+                })
+            },
+        ];
+        let items = [
+            0, 1, 2, 1, 3, 4, 5, 2, 6, 7, 8, 9, 9, 3, 3, 1, 10, 11, 12, 0, 0,
+        ];
+        let now = Arc::new(AtomicU64::new(0));
+        for mode in modes {
+            let counts: [Arc<[AtomicU64; 2]>; 2] = Default::default();
+            let new = |counts: &Arc<[AtomicU64; 2]>| {
+                let cache = LruCache::new(NonZeroUsize::new(4).unwrap());
+                mode(cache, now.clone(), Counts(counts.clone()))
+            };
+            let (mut batched, mut single) = (new(&counts[0]), new(&counts[1]));
+            for (chunk, keys) in items.chunks(5).enumerate() {
+                now.store(chunk as u64 * 7, Ordering::Relaxed);
+                let pairs = keys.iter().map(|&k| (k, k * 100 + chunk as u64));
+                batched.put_many(pairs.clone());
+                // Updates of hot keys stay hot
+                batched.get(&keys[0]);
+                for (k, v) in pairs {
+                    single.put(k, v);
+                }
+                single.get(&keys[0]);
+                assert_eq!(batched.get_flips(), single.get_flips());
+                for k in 0..13 {
+                    assert_eq!(batched.peek_entry(&k), single.peek_entry(&k));
+                }
+            }
+            let load =
+                |counts: &Arc<[AtomicU64; 2]>| counts.each_ref().map(|c| c.load(Ordering::Relaxed));
+            assert_eq!(load(&counts[0]), load(&counts[1]));
+        }
     }
 }
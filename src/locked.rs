@@ -0,0 +1,284 @@
+use crate::stats::{CacheStats, Counters};
+use crate::LruCache;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use lock_api::{Mutex, MutexGuard, RawMutex};
+
+/// An [`LruCache`] behind a mutex of the [`lock_api`] raw mutex `R`, which allows sharing
+/// the cache without `std`, e.g., between an interrupt handler and a task. It has the
+/// same API as [`SyncLruCache`](crate::SyncLruCache), taking `&self` and locking the cache
+/// for the duration of each call.
+pub struct LockedLruCache<R, K, V> {
+    cache: Mutex<R, LruCache<K, V>>,
+    counters: Counters,
+}
+
+/// A [`LockedLruCache`] behind a spinlock.
+#[cfg(feature = "spin")]
+pub type SpinLruCache<K, V> = LockedLruCache<spin::Mutex<()>, K, V>;
+
+impl<R: RawMutex, K: Hash + Eq, V> LockedLruCache<R, K, V> {
+    /// Wraps the cache, keeping its configuration.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SpinLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache: SpinLruCache<isize, &str> = SpinLruCache::new(LruCache::new(NonZeroUsize::new(10).unwrap()));
+    /// ```
+    pub fn new(cache: LruCache<K, V>) -> LockedLruCache<R, K, V> {
+        LockedLruCache {
+            cache: Mutex::new(cache),
+            counters: Counters::default(),
+        }
+    }
+
+    /// Locks the cache for direct access to its whole API.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SpinLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SpinLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.lock().peek_oldest(), None);
+    /// ```
+    pub fn lock(&self) -> MutexGuard<'_, R, LruCache<K, V>> {
+        self.cache.lock()
+    }
+
+    /// Calls the function with the value of the key, see [`LruCache::get`], and returns
+    /// its result, or `None` if the key is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SpinLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SpinLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put("apple", [1, 2, 3]);
+    /// assert_eq!(cache.get_with(&"apple", |v| v.len()), Some(3));
+    /// assert_eq!(cache.get_with(&"banana", |v| v.len()), None);
+    /// ```
+    pub fn get_with<Q, T, F>(&self, k: &Q, f: F) -> Option<T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&V) -> T,
+    {
+        self.counters.record(self.lock().get(k).map(f))
+    }
+
+    /// Calls the function with the mutable value of the key, see [`LruCache::get_mut`],
+    /// and returns its result, or `None` if the key is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SpinLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SpinLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put("apple", 8);
+    /// cache.get_mut_with(&"apple", |v| *v += 1);
+    /// assert_eq!(cache.get(&"apple"), Some(9));
+    /// ```
+    pub fn get_mut_with<Q, T, F>(&self, k: &Q, f: F) -> Option<T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&mut V) -> T,
+    {
+        self.counters.record(self.lock().get_mut(k).map(f))
+    }
+
+    /// Returns a clone of the value of the key or `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SpinLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SpinLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some("a"));
+    /// ```
+    pub fn get<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.get_with(k, V::clone)
+    }
+
+    /// Puts a key-value pair into the cache, see [`LruCache::put`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SpinLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SpinLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&self, k: K, v: V) -> Option<V> {
+        self.lock().put(k, v)
+    }
+
+    /// Removes the key from the cache and returns its value, see [`LruCache::pop`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SpinLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SpinLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lock().pop(k)
+    }
+
+    /// Removes all the items from the cache, see [`LruCache::clear`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SpinLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SpinLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// cache.clear();
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn clear(&self) {
+        self.lock().clear()
+    }
+
+    /// Returns the number of key-value pairs that are guaranteed to be in the cache, see
+    /// [`LruCache::len`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SpinLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SpinLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SpinLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SpinLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Returns the hit, miss and flip counts of the cache. Only the lookups through this
+    /// wrapper are counted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheStats, LruCache, SpinLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SpinLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// cache.get(&1);
+    /// cache.get(&2);
+    /// assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 1, flips: 0 });
+    /// ```
+    pub fn stats(&self) -> CacheStats {
+        self.counters.stats(self.lock().get_flips())
+    }
+
+    /// Returns metric on the number of times the cache flipped, see
+    /// [`LruCache::get_flips`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SpinLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SpinLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.lock().get_flips()
+    }
+
+    /// Returns the wrapped cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SpinLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SpinLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    /// cache.put(1, "a");
+    /// let mut cache = cache.into_inner();
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// ```
+    pub fn into_inner(self) -> LruCache<K, V> {
+        self.cache.into_inner()
+    }
+}
+
+impl<R: RawMutex, K: Hash + Eq, V> From<LruCache<K, V>> for LockedLruCache<R, K, V> {
+    fn from(cache: LruCache<K, V>) -> LockedLruCache<R, K, V> {
+        LockedLruCache::new(cache)
+    }
+}
+
+#[cfg(all(test, feature = "spin"))]
+mod tests {
+    use super::SpinLruCache;
+    use crate::LruCache;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_spin_lru_cache() {
+        let cache = SpinLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+        for i in 0..3 {
+            cache.put(i, i);
+        }
+        // The lock is released between the calls
+        assert_eq!(cache.get_mut_with(&0, |v| *v += 10), Some(()));
+        assert_eq!(cache.lock().get(&0), Some(&10));
+        assert_eq!(cache.get_flips(), 1);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::persist::{invalid, read_cap, read_header, read_u64, rejected, Checksum};
+use crate::SnapshotError;
+use crate::{Entry, LruCache, Priority};
+use core::cmp;
+use core::hash::Hash;
+use hashbrown::HashMap;
+use memmap2::Mmap;
+use std::boxed::Box;
+use std::fs::File;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::string::String;
+use std::vec::Vec;
+
+/// A key or value type that can be rebuilt from the bytes written by
+/// [`LruCache::save_to`], see [`LruCache::load_mmap`].
+pub trait FromBytes: Sized {
+    /// Rebuilds the value from its bytes, or returns `None` if they are not valid.
+    fn from_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+impl FromBytes for Vec<u8> {
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+impl FromBytes for Box<[u8]> {
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.into())
+    }
+}
+
+impl FromBytes for String {
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        core::str::from_utf8(bytes).ok().map(String::from)
+    }
+}
+
+impl<K: Hash + Eq, V> LruCache<K, V> {
+    /// Reads a cache file written by [`save_to`](LruCache::save_to) through a memory map,
+    /// so that the items are rebuilt straight from the pages of the file instead of being
+    /// read into buffers first. The items go back into the tier they were in and the flip
+    /// metric is kept.
+    ///
+    /// Fails like [`load_from`](LruCache::load_from), and with [`ErrorKind::InvalidData`]
+    /// if [`FromBytes`] rejects a key or value. Compressed files are rejected too, as they
+    /// cannot be read in place. The checksum is verified before any item is rebuilt, and
+    /// the maps are only allocated for the items, like the ones of `load_from`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::fs::File;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// let path = std::env::temp_dir().join("fliplru-doc-mmap");
+    /// cache.put("apple", "red");
+    /// cache.save_to(File::create(&path).unwrap()).unwrap();
+    /// let mut cache: LruCache<String, String> = LruCache::load_mmap(&path).unwrap();
+    /// assert_eq!(cache.get("apple").map(String::as_str), Some("red"));
+    /// std::fs::remove_file(path).unwrap();
+    /// ```
+    pub fn load_mmap<P: AsRef<Path>>(path: P) -> io::Result<LruCache<K, V>>
+    where
+        K: FromBytes,
+        V: FromBytes,
+    {
+        let file = File::open(path)?;
+        // SAFETY: the map is only read while it is alive, and a file that is changed
+        // meanwhile at worst yields invalid items, as the bytes are only copied.
+        let map = unsafe { Mmap::map(&file)? };
+        let mut bytes = &map[..];
+        #[cfg(feature = "lz4")]
+        if bytes.starts_with(&crate::persist::LZ4_MAGIC) {
+            return Err(invalid("a compressed file, read it with load_from"));
+        }
+
+        // The checksum is verified before any item is rebuilt
+        read_header(&mut bytes)?;
+        let Some(at) = bytes.len().checked_sub(8) else {
+            return Err(ErrorKind::UnexpectedEof.into());
+        };
+        let (mut bytes, mut sum) = bytes.split_at(at);
+        let mut payload = Checksum::new(());
+        payload.update(bytes);
+        if read_u64(&mut sum)? != payload.hash {
+            return Err(rejected(SnapshotError::ChecksumMismatch));
+        }
+
+        let hot_cap = read_cap(&mut bytes)?;
+        let cold_cap = read_cap(&mut bytes)?;
+        let flips = read_u64(&mut bytes)?;
+        // A file of a large growable cache would not fit in the maps of its capacities
+        let mut cache = LruCache::restoring(hot_cap, cold_cap);
+        cache.flips = usize::try_from(flips).map_err(|_| invalid("the flips overflow"))?;
+        read_items(&mut bytes, &mut cache.l1_map)?;
+        read_items(&mut bytes, &mut cache.l2_map)?;
+        if !bytes.is_empty() {
+            return Err(invalid("trailing bytes after the items"));
+        }
+        if cache.l2_map.keys().any(|k| cache.l1_map.contains_key(k)) {
+            return Err(invalid("a key is in both tiers"));
+        }
+        Ok(cache)
+    }
+}
+
+fn read_items<K: Hash + Eq + FromBytes, V: FromBytes>(
+    bytes: &mut &[u8],
+    map: &mut HashMap<K, Entry<V>>,
+) -> io::Result<()> {
+    let len = read_u64(bytes)?;
+    // A tier may hold more items than its capacity, but not more than the bytes left,
+    // as an item takes at least the two lengths
+    map.reserve(cmp::min(len, bytes.len() as u64 / 16) as usize);
+    for _ in 0..len {
+        let k = K::from_bytes(read_slice(bytes)?).ok_or_else(|| invalid("an invalid key"))?;
+        let v = V::from_bytes(read_slice(bytes)?).ok_or_else(|| invalid("an invalid value"))?;
+        if map.insert(k, Entry::new(v, Priority::Normal)).is_some() {
+            return Err(invalid("a key appears more than once"));
+        }
+    }
+    Ok(())
+}
+
+fn read_slice<'a>(bytes: &mut &'a [u8]) -> io::Result<&'a [u8]> {
+    let len = usize::try_from(read_u64(bytes)?).map_err(|_| invalid("a length overflows"))?;
+    if bytes.len() < len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    let (slice, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FlipMode, LruCache};
+    use core::num::NonZeroUsize;
+    use std::fs::{self, File};
+    use std::io::ErrorKind;
+    use std::string::{String, ToString};
+
+    #[test]
+    fn test_load_mmap() {
+        let path = std::env::temp_dir().join("fliplru-test-mmap");
+        let mut cache =
+            LruCache::with_caps(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(4).unwrap());
+        for i in 0..5 {
+            cache.put(i.to_string(), "v".repeat(i));
+        }
+        cache.save_to(File::create(&path).unwrap()).unwrap();
+        let copy: LruCache<String, String> = LruCache::load_mmap(&path).unwrap();
+
+        assert_eq!(copy.get_flips(), 2);
+        for i in 0..5 {
+            let k = i.to_string();
+            assert_eq!(copy.peek_entry(&k), cache.peek_entry(&k));
+        }
+
+        // Bytes that are not UTF-8 are rejected
+        let mut bad = LruCache::new(NonZeroUsize::new(1).unwrap());
+        bad.put(std::vec![0xff], std::vec![]);
+        bad.save_to(File::create(&path).unwrap()).unwrap();
+        let err = LruCache::<String, String>::load_mmap(&path).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_load_mmap_over_capacity() {
+        let path = std::env::temp_dir().join("fliplru-test-mmap-over");
+        let mut cache =
+            LruCache::new(NonZeroUsize::new(2).unwrap()).with_flip_mode(FlipMode::Manual);
+        for i in 0..5 {
+            cache.put(i.to_string(), "v".repeat(i));
+        }
+        cache.save_to(File::create(&path).unwrap()).unwrap();
+        let copy: LruCache<String, String> = LruCache::load_mmap(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        for i in 0..5 {
+            assert_eq!(copy.peek(&i.to_string()), Some(&"v".repeat(i)));
+        }
+    }
+
+    #[test]
+    fn test_load_mmap_growable() {
+        let path = std::env::temp_dir().join("fliplru-test-mmap-growable");
+        let mut cache = LruCache::new_growable(NonZeroUsize::new(1 << 40).unwrap());
+        cache.put("apple".to_string(), "red".to_string());
+        cache.save_to(File::create(&path).unwrap()).unwrap();
+        let copy: LruCache<String, String> = LruCache::load_mmap(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(copy.cap().get(), 1 << 40);
+        assert!(copy.l1_map.capacity() < 1 << 10);
+        assert_eq!(copy.peek("apple").map(String::as_str), Some("red"));
+    }
+}
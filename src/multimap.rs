@@ -0,0 +1,214 @@
+use crate::LruCache;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::num::NonZeroUsize;
+
+/// A cache of many values per key with the flip semantics of [`LruCache`], e.g., the
+/// recent events of each user. The keys are evicted along with all their values, and
+/// [`with_value_limit`](LruMultiMap::with_value_limit) bounds the values of a key.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::LruMultiMap;
+/// use std::num::NonZeroUsize;
+/// let mut events = LruMultiMap::new(NonZeroUsize::new(1024).unwrap())
+///     .with_value_limit(NonZeroUsize::new(2).unwrap());
+///
+/// events.append("alice", "login");
+/// events.append("alice", "upload");
+/// events.append("alice", "logout");
+/// assert_eq!(events.get_all("alice"), Some(&["upload", "logout"][..]));
+/// ```
+pub struct LruMultiMap<K, V> {
+    cache: LruCache<K, VecDeque<V>>,
+    value_limit: Option<NonZeroUsize>,
+}
+
+impl<K: Hash + Eq, V> LruMultiMap<K, V> {
+    /// Creates a new cache that holds the values of `cap` keys.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruMultiMap;
+    /// use std::num::NonZeroUsize;
+    /// let events: LruMultiMap<u64, &str> = LruMultiMap::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> LruMultiMap<K, V> {
+        LruMultiMap {
+            cache: LruCache::new(cap),
+            value_limit: None,
+        }
+    }
+
+    /// Keeps at most `limit` values per key: appending to a key that has as many drops its
+    /// oldest value. The values are unbounded by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruMultiMap;
+    /// use std::num::NonZeroUsize;
+    /// let mut events = LruMultiMap::new(NonZeroUsize::new(2).unwrap())
+    ///     .with_value_limit(NonZeroUsize::new(1).unwrap());
+    ///
+    /// events.append(1, "a");
+    /// events.append(1, "b");
+    /// assert_eq!(events.get_all(&1), Some(&["b"][..]));
+    /// ```
+    pub fn with_value_limit(mut self, limit: NonZeroUsize) -> LruMultiMap<K, V> {
+        self.value_limit = Some(limit);
+        self
+    }
+
+    /// Appends a value to the values of the key, putting the key into the cache if it is
+    /// not present.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruMultiMap;
+    /// use std::num::NonZeroUsize;
+    /// let mut events = LruMultiMap::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// events.append(1, "a");
+    /// events.append(1, "b");
+    /// assert_eq!(events.get_all(&1), Some(&["a", "b"][..]));
+    /// ```
+    pub fn append(&mut self, k: K, v: V) {
+        let Some(values) = self.cache.get_mut(&k) else {
+            self.cache.put(k, VecDeque::from([v]));
+            return;
+        };
+        if self
+            .value_limit
+            .is_some_and(|limit| values.len() >= limit.get())
+        {
+            values.pop_front();
+        }
+        values.push_back(v);
+    }
+
+    /// Returns the values of the key in cache, from the oldest to the latest, or `None` if
+    /// it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruMultiMap;
+    /// use std::num::NonZeroUsize;
+    /// let mut events = LruMultiMap::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// events.append(1, "a");
+    /// assert_eq!(events.get_all(&1), Some(&["a"][..]));
+    /// assert_eq!(events.get_all(&2), None);
+    /// ```
+    pub fn get_all<Q>(&mut self, k: &Q) -> Option<&[V]>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let values = self.cache.get_mut(k)?;
+        Some(values.make_contiguous())
+    }
+
+    /// Removes the key from the cache and returns its values, from the oldest to the
+    /// latest, or `None` if it was not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruMultiMap;
+    /// use std::num::NonZeroUsize;
+    /// let mut events = LruMultiMap::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// events.append(1, "a");
+    /// events.append(1, "b");
+    /// assert_eq!(events.remove(&1), Some(vec!["a", "b"]));
+    /// assert_eq!(events.remove(&1), None);
+    /// ```
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<Vec<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.pop(k).map(Vec::from)
+    }
+
+    /// Returns the number of keys that are guaranteed to be in the cache, like
+    /// [`LruCache::len`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruMultiMap;
+    /// use std::num::NonZeroUsize;
+    /// let mut events = LruMultiMap::new(NonZeroUsize::new(2).unwrap());
+    /// events.append(1, "a");
+    /// events.append(1, "b");
+    /// assert_eq!(events.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruMultiMap;
+    /// use std::num::NonZeroUsize;
+    /// let mut events = LruMultiMap::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(events.is_empty());
+    /// events.append(1, "a");
+    /// assert!(!events.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Returns metric on the number of times the cache became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruMultiMap;
+    /// use std::num::NonZeroUsize;
+    /// let mut events = LruMultiMap::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     events.append(i, i);
+    /// }
+    /// assert_eq!(events.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.cache.get_flips()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LruMultiMap;
+    use alloc::vec;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_multimap() {
+        let mut events = LruMultiMap::new(NonZeroUsize::new(2).unwrap())
+            .with_value_limit(NonZeroUsize::new(3).unwrap());
+        for i in 0..5 {
+            events.append("alice", i);
+        }
+        events.append("bob", 10);
+        events.append("carol", 20);
+        // Appending keeps alice in use, so the flip at dave discards bob
+        events.append("alice", 5);
+        events.append("dave", 30);
+        assert_eq!(events.get_all("bob"), None);
+        assert_eq!(events.get_all("alice"), Some(&[3, 4, 5][..]));
+        assert_eq!(events.remove("carol"), Some(vec![20]));
+    }
+}
@@ -0,0 +1,366 @@
+use crate::{CacheStats, LruCache};
+use core::borrow::Borrow;
+use core::hash::{Hash, Hasher};
+use core::num::NonZeroUsize;
+use hashbrown::HashMap;
+
+#[derive(Hash, PartialEq, Eq)]
+struct Scoped<N, K> {
+    ns: N,
+    key: K,
+}
+
+// Looks up a Scoped key from a borrowed namespace and key, which hash and compare the same
+// way
+trait ScopedQuery<N, K> {
+    fn ns(&self) -> &N;
+    fn key(&self) -> &K;
+}
+
+impl<N, K> ScopedQuery<N, K> for Scoped<N, K> {
+    fn ns(&self) -> &N {
+        &self.ns
+    }
+
+    fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<N, K> ScopedQuery<N, K> for (&N, &K) {
+    fn ns(&self) -> &N {
+        self.0
+    }
+
+    fn key(&self) -> &K {
+        self.1
+    }
+}
+
+impl<'a, N: 'a, K: 'a> Borrow<dyn ScopedQuery<N, K> + 'a> for Scoped<N, K> {
+    fn borrow(&self) -> &(dyn ScopedQuery<N, K> + 'a) {
+        self
+    }
+}
+
+impl<N: Hash, K: Hash> Hash for dyn ScopedQuery<N, K> + '_ {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ns().hash(state);
+        self.key().hash(state);
+    }
+}
+
+impl<N: PartialEq, K: PartialEq> PartialEq for dyn ScopedQuery<N, K> + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        self.ns() == other.ns() && self.key() == other.key()
+    }
+}
+
+impl<N: Eq, K: Eq> Eq for dyn ScopedQuery<N, K> + '_ {}
+
+// A value with the generation of its namespace when it was put.
+struct Stamped<V> {
+    value: V,
+    generation: u64,
+}
+
+#[derive(Default)]
+struct Namespace {
+    generation: u64,
+    hits: u64,
+    misses: u64,
+}
+
+/// An LRU Cache shared by many namespaces, e.g., the tenants of a service, where all the
+/// namespaces share the capacity and the flips but are looked up, invalidated and counted
+/// separately.
+///
+/// [`invalidate_namespace`](NamespacedCache::invalidate_namespace) is `O(1)`: it starts a
+/// new generation of the namespace, and the items of the previous generations are misses
+/// that are removed when they are looked up or discarded by the flips. The cache keeps the
+/// state of every namespace that was put into, while a lookup in any other namespace is a
+/// plain miss.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::NamespacedCache;
+/// use std::num::NonZeroUsize;
+/// let mut cache = NamespacedCache::new(NonZeroUsize::new(1024).unwrap());
+///
+/// cache.put("acme", "user:1", "alice");
+/// cache.put("zeta", "user:1", "zoe");
+/// cache.invalidate_namespace(&"acme");
+/// assert_eq!(cache.get(&"acme", &"user:1"), None);
+/// assert_eq!(cache.get(&"zeta", &"user:1"), Some(&"zoe"));
+/// assert_eq!(cache.stats(&"zeta").hits, 1);
+/// ```
+pub struct NamespacedCache<N, K, V> {
+    cache: LruCache<Scoped<N, K>, Stamped<V>>,
+    namespaces: HashMap<N, Namespace>,
+}
+
+impl<N: Hash + Eq + Clone, K: Hash + Eq, V> NamespacedCache<N, K, V> {
+    /// Creates a new LRU Cache that holds at least `cap` items of all the namespaces.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::NamespacedCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: NamespacedCache<u32, u64, &str> =
+    ///     NamespacedCache::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> NamespacedCache<N, K, V> {
+        NamespacedCache {
+            cache: LruCache::new(cap),
+            namespaces: HashMap::new(),
+        }
+    }
+
+    /// Returns a reference to the value of the key in the namespace or `None` if it is not
+    /// present in the cache or it was invalidated.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::NamespacedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = NamespacedCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a", 10);
+    /// assert_eq!(cache.get(&1, &"a"), Some(&10));
+    /// assert_eq!(cache.get(&2, &"a"), None);
+    /// ```
+    pub fn get(&mut self, ns: &N, k: &K) -> Option<&V> {
+        // Nothing was put into an unknown namespace, so it holds no items
+        let state = self.namespaces.get_mut(ns)?;
+        let query = (ns, k);
+        let query = &query as &dyn ScopedQuery<N, K>;
+        let mut stale = false;
+        let fresh = |e: &Stamped<V>| {
+            stale = e.generation != state.generation;
+            !stale
+        };
+        match LruCache::get_in(&mut self.cache, |c| c, query, fresh) {
+            Ok(e) => {
+                state.hits += 1;
+                Some(&e.value)
+            }
+            Err(cache) => {
+                if stale {
+                    cache.remove(query);
+                }
+                state.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Puts a key-value pair into the namespace. If the key already exists in the
+    /// namespace, then it updates the key's value and returns the old value. Otherwise,
+    /// `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::NamespacedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = NamespacedCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert_eq!(None, cache.put(1, "a", 10));
+    /// assert_eq!(None, cache.put(2, "a", 20));
+    /// assert_eq!(Some(10), cache.put(1, "a", 11));
+    /// ```
+    pub fn put(&mut self, ns: N, k: K, v: V) -> Option<V> {
+        let generation = self.namespace(&ns).generation;
+        let key = Scoped { ns, key: k };
+        let old = self.cache.put(
+            key,
+            Stamped {
+                value: v,
+                generation,
+            },
+        )?;
+        (old.generation == generation).then_some(old.value)
+    }
+
+    /// Removes the key from the namespace and returns its value, or `None` if it was not
+    /// present in the cache or it was invalidated.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::NamespacedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = NamespacedCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a", 10);
+    /// assert_eq!(cache.pop(&1, &"a"), Some(10));
+    /// assert_eq!(cache.pop(&1, &"a"), None);
+    /// ```
+    pub fn pop(&mut self, ns: &N, k: &K) -> Option<V> {
+        let generation = self.namespaces.get(ns)?.generation;
+        let query = (ns, k);
+        let old = self.cache.pop(&query as &dyn ScopedQuery<N, K>)?;
+        (old.generation == generation).then_some(old.value)
+    }
+
+    /// Invalidates all the items of the namespace in `O(1)`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::NamespacedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = NamespacedCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a", 10);
+    /// cache.invalidate_namespace(&1);
+    /// assert_eq!(cache.get(&1, &"a"), None);
+    /// cache.put(1, "a", 11);
+    /// assert_eq!(cache.get(&1, &"a"), Some(&11));
+    /// ```
+    pub fn invalidate_namespace(&mut self, ns: &N) {
+        if let Some(state) = self.namespaces.get_mut(ns) {
+            state.generation += 1;
+        }
+    }
+
+    /// Returns the hits and the misses of the lookups in the namespace, along with the
+    /// flips of the whole cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheStats, NamespacedCache};
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = NamespacedCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a", 10);
+    /// cache.get(&1, &"a");
+    /// cache.get(&1, &"b");
+    /// cache.get(&2, &"a");
+    /// assert_eq!(cache.stats(&1), CacheStats { hits: 1, misses: 1, flips: 0 });
+    /// ```
+    pub fn stats(&self, ns: &N) -> CacheStats {
+        let (hits, misses) = self
+            .namespaces
+            .get(ns)
+            .map_or((0, 0), |state| (state.hits, state.misses));
+        CacheStats {
+            hits,
+            misses,
+            flips: self.cache.get_flips(),
+        }
+    }
+
+    /// Returns the number of items that are guaranteed to be in the cache, like
+    /// [`LruCache::len`]. The items of an invalidated namespace are counted until they are
+    /// looked up or discarded by the flips, so this is an upper bound of the items that
+    /// can still be hit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::NamespacedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = NamespacedCache::new(NonZeroUsize::new(2).unwrap());
+    /// cache.put(1, "a", 10);
+    /// cache.put(2, "a", 20);
+    /// cache.invalidate_namespace(&1);
+    /// assert_eq!(cache.len(), 2);
+    /// cache.get(&1, &"a");
+    /// assert_eq!(cache.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::NamespacedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = NamespacedCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a", 10);
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Returns metric on the number of times the cache became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::NamespacedCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = NamespacedCache::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     cache.put(i % 2, i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.cache.get_flips()
+    }
+
+    fn namespace(&mut self, ns: &N) -> &mut Namespace {
+        if !self.namespaces.contains_key(ns) {
+            self.namespaces.insert(ns.clone(), Namespace::default());
+        }
+        self.namespaces.get_mut(ns).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NamespacedCache;
+    use crate::CacheStats;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_invalidate_namespace() {
+        let mut cache = NamespacedCache::new(NonZeroUsize::new(8).unwrap());
+        for k in 0..2 {
+            cache.put("acme", k, k);
+            cache.put("zeta", k, k + 10);
+        }
+        cache.invalidate_namespace(&"acme");
+        assert_eq!(cache.put("acme", 0, 100), None);
+
+        assert_eq!(cache.get(&"acme", &0), Some(&100));
+        // The invalidated item is a miss and removed
+        assert_eq!(cache.get(&"acme", &1), None);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.pop(&"zeta", &1), Some(11));
+        assert_eq!(
+            cache.stats(&"acme"),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                flips: 0
+            }
+        );
+        assert_eq!(cache.stats(&"zeta"), CacheStats::default());
+    }
+
+    #[test]
+    fn test_unknown_namespaces() {
+        let mut cache = NamespacedCache::new(NonZeroUsize::new(2).unwrap());
+        cache.put(0, 0, 0);
+        // The lookups in the namespaces that were never put into keep no state
+        for ns in 1..100 {
+            assert_eq!(cache.get(&ns, &0), None);
+            assert_eq!(cache.pop(&ns, &0), None);
+        }
+        assert_eq!(cache.namespaces.len(), 1);
+        assert_eq!(cache.stats(&1), CacheStats::default());
+        assert_eq!(cache.get(&0, &0), Some(&0));
+    }
+}
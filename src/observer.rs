@@ -0,0 +1,31 @@
+/// Hooks that [`LruCache`](crate::LruCache) invokes when a put stores a value, e.g., to keep
+/// a secondary index coherent with the cache. Both methods do nothing by default.
+///
+/// The hooks are not invoked when the cache moves items between its generations or discards
+/// them, see [`LruCache::with_eviction_listener`](crate::LruCache::with_eviction_listener)
+/// for the latter.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::CacheObserver;
+///
+/// struct Inserts(usize);
+///
+/// impl<K, V> CacheObserver<K, V> for Inserts {
+///     fn on_insert(&mut self, _: &K, _: &V) {
+///         self.0 += 1;
+///     }
+/// }
+/// ```
+pub trait CacheObserver<K, V> {
+    /// Called after a value was stored for a key that was not in the cache.
+    fn on_insert(&mut self, k: &K, v: &V) {
+        let _ = (k, v);
+    }
+
+    /// Called after the value of a key in the cache was replaced.
+    fn on_update(&mut self, k: &K, old: &V, new: &V) {
+        let _ = (k, old, new);
+    }
+}
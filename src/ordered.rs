@@ -0,0 +1,308 @@
+use alloc::collections::BTreeMap;
+use core::borrow::Borrow;
+use core::iter;
+use core::mem;
+use core::num::NonZeroUsize;
+use core::ops::RangeBounds;
+
+/// An LRU Cache with the flip semantics of [`LruCache`](crate::LruCache) whose two maps
+/// are `BTreeMap`s, so that the keys are ordered, e.g., `(tenant, timestamp)` tuples. The
+/// items of a range of keys can be iterated with [`range`](OrderedLruCache::range) and
+/// invalidated with [`remove_range`](OrderedLruCache::remove_range) in both caches.
+///
+/// The map operations are `O(log n)` instead of `O(1)`.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::OrderedLruCache;
+/// use std::num::NonZeroUsize;
+/// let mut cache = OrderedLruCache::new(NonZeroUsize::new(1024).unwrap());
+///
+/// cache.put(("acme", 10), "a");
+/// cache.put(("acme", 20), "b");
+/// cache.put(("zeta", 10), "c");
+/// assert_eq!(cache.remove_range(("acme", 0)..("acme", u64::MAX)), 2);
+/// assert_eq!(cache.len(), 1);
+/// ```
+pub struct OrderedLruCache<K, V> {
+    l1_map: BTreeMap<K, V>,
+    l2_map: BTreeMap<K, V>,
+    cap: NonZeroUsize,
+    flips: usize,
+}
+
+impl<K: Ord, V> OrderedLruCache<K, V> {
+    /// Creates a new LRU Cache that holds at least `cap` items.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::OrderedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: OrderedLruCache<u64, &str> = OrderedLruCache::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> OrderedLruCache<K, V> {
+        OrderedLruCache {
+            l1_map: BTreeMap::new(),
+            l2_map: BTreeMap::new(),
+            cap,
+            flips: 0,
+        }
+    }
+
+    /// Returns a reference to the value of the key in the cache or `None` if it is not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::OrderedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = OrderedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert_eq!(cache.get(&4), None);
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get_mut(k).map(|v| &*v)
+    }
+
+    /// Returns a mutable reference to the value of the key in the cache or `None` if it
+    /// is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::OrderedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = OrderedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put("apple", 8);
+    /// *cache.get_mut("apple").unwrap() += 1;
+    /// assert_eq!(cache.get("apple"), Some(&9));
+    /// ```
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        if !self.l1_map.contains_key(k) {
+            // A hit in the backup cache moves the item back into the main cache
+            let (rk, rv) = self.l2_map.remove_entry(k)?;
+            self.insert(rk, rv);
+        }
+        self.l1_map.get_mut(k)
+    }
+
+    /// Returns a reference to the value of the key without updating the LRU list, or
+    /// `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::OrderedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = OrderedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.peek(&1), Some(&"a"));
+    /// ```
+    pub fn peek<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.l1_map.get(k).or_else(|| self.l2_map.get(k))
+    }
+
+    /// Puts a key-value pair into cache. If the key already exists in the cache, then it updates
+    /// the key's value and returns the old value. Otherwise, `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::OrderedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = OrderedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Option<V> {
+        if let Some(old) = self.l1_map.get_mut(&k) {
+            return Some(mem::replace(old, v));
+        }
+        let old = self.l2_map.remove(&k);
+        self.insert(k, v);
+        old
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it was not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::OrderedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = OrderedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.l1_map.remove(k).or_else(|| self.l2_map.remove(k))
+    }
+
+    /// Returns an iterator over the items of the range of keys in both caches, in key
+    /// order, without updating the LRU list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::OrderedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = OrderedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// for i in 0..4 {
+    ///     cache.put(i, i * 10);
+    /// }
+    /// let items: Vec<_> = cache.range(1..3).collect();
+    /// assert_eq!(items, [(&1, &10), (&2, &20)]);
+    /// ```
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> impl Iterator<Item = (&K, &V)> {
+        let bounds = (range.start_bound(), range.end_bound());
+        let mut l1 = self.l1_map.range(bounds).peekable();
+        let mut l2 = self.l2_map.range(bounds).peekable();
+        // The caches have distinct keys, so merging them keeps the key order
+        iter::from_fn(move || match (l1.peek(), l2.peek()) {
+            (Some((a, _)), Some((b, _))) if b < a => l2.next(),
+            (Some(_), _) => l1.next(),
+            (None, _) => l2.next(),
+        })
+    }
+
+    /// Removes the items of the range of keys from both caches and returns how many were
+    /// removed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::OrderedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = OrderedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// for i in 0..4 {
+    ///     cache.put(i, i * 10);
+    /// }
+    /// assert_eq!(cache.remove_range(..2), 2);
+    /// assert_eq!(cache.peek(&1), None);
+    /// assert_eq!(cache.peek(&2), Some(&20));
+    /// ```
+    pub fn remove_range<R: RangeBounds<K>>(&mut self, range: R) -> usize {
+        let bounds = (range.start_bound(), range.end_bound());
+        let l1 = self.l1_map.extract_if(bounds, |_, _| true).count();
+        l1 + self.l2_map.extract_if(bounds, |_, _| true).count()
+    }
+
+    /// Returns the number of key-value pairs in the main and the backup cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::OrderedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = OrderedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.len(), 3);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.l1_map.len() + self.l2_map.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::OrderedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = OrderedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.l1_map.is_empty() && self.l2_map.is_empty()
+    }
+
+    /// Returns metric on the number of times the main cache became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::OrderedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = OrderedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.flips
+    }
+
+    // Inserts a key that is in neither map, flipping first if the main cache is full.
+    fn insert(&mut self, k: K, v: V) {
+        if self.l1_map.len() >= self.cap.get() {
+            self.flips += 1;
+            self.l2_map = mem::take(&mut self.l1_map);
+        }
+        self.l1_map.insert(k, v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OrderedLruCache;
+    use alloc::vec::Vec;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_range_across_caches() {
+        let mut cache = OrderedLruCache::new(NonZeroUsize::new(3).unwrap());
+        for t in [30, 10, 50, 20, 40] {
+            cache.put(("acme", t), t);
+        }
+        cache.put(("zeta", 0), 0);
+        assert_eq!(cache.get_flips(), 1);
+
+        // The range is merged in key order from both caches
+        let keys: Vec<u64> = cache
+            .range(("acme", 0)..("acme", u64::MAX))
+            .map(|(k, _)| k.1)
+            .collect();
+        assert_eq!(keys, [10, 20, 30, 40, 50]);
+
+        assert_eq!(cache.remove_range(("acme", 15)..=("acme", 40)), 3);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get(&("acme", 10)), Some(&10));
+        assert_eq!(cache.get(&("acme", 30)), None);
+    }
+}
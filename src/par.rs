@@ -0,0 +1,88 @@
+use crate::LruCache;
+use core::hash::Hash;
+use rayon::iter::{IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
+
+impl<K: Hash + Eq, V> LruCache<K, V> {
+    /// Returns a parallel iterator over the items of both tiers in arbitrary order. The
+    /// work is split across the buckets of the two maps and the items are not promoted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use rayon::prelude::*;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, 10);
+    /// cache.put(2, 20);
+    /// cache.put(3, 30);
+    /// assert_eq!(cache.par_iter().map(|(_, v)| v).sum::<i32>(), 60);
+    /// ```
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = (&K, &V)> + '_
+    where
+        K: Sync,
+        V: Sync,
+    {
+        self.l1_map
+            .par_iter()
+            .chain(self.l2_map.par_iter())
+            .map(|(k, e)| (k, &e.value))
+    }
+
+    /// Returns a parallel iterator over the items of both tiers with mutable values. Like
+    /// [`get_mut`](LruCache::get_mut), it marks the values as dirty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use rayon::prelude::*;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, 10);
+    /// cache.put(2, 20);
+    /// cache.put(3, 30);
+    /// cache.par_iter_mut().for_each(|(_, v)| *v += 1);
+    /// assert_eq!(cache.get(&1), Some(&11));
+    /// ```
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = (&K, &mut V)> + '_
+    where
+        K: Sync,
+        V: Send,
+    {
+        self.l1_map
+            .par_iter_mut()
+            .chain(self.l2_map.par_iter_mut())
+            .map(|(k, e)| {
+                e.dirty = true;
+                (k, &mut e.value)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::LruCache;
+    use core::num::NonZeroUsize;
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_par_iter() {
+        let mut cache = LruCache::new(NonZeroUsize::new(1000).unwrap());
+        for i in 0..1500u64 {
+            cache.put(i, i);
+        }
+        // Both tiers are visited
+        assert_eq!(cache.par_iter().count(), 1500);
+        assert_eq!(
+            cache.par_iter().map(|(_, v)| v).sum::<u64>(),
+            1499 * 1500 / 2
+        );
+
+        cache.par_iter_mut().for_each(|(k, v)| *v = k * 2);
+        assert_eq!(cache.peek(&10), Some(&20));
+        assert_eq!(cache.peek(&1200), Some(&2400));
+    }
+}
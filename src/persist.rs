@@ -0,0 +1,586 @@
+use crate::{Entry, LruCache, Priority};
+use core::fmt;
+use core::hash::Hash;
+use core::num::NonZeroUsize;
+use hashbrown::HashMap;
+#[cfg(feature = "lz4")]
+use lz4_flex::frame::{FrameDecoder, FrameEncoder};
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind, Read, Write};
+use std::path::Path;
+use std::vec::Vec;
+
+// The magic number that starts a saved cache, followed by the format version.
+const MAGIC: [u8; 4] = *b"FLRU";
+const VERSION: u32 = 1;
+
+// The magic number that starts an LZ4 frame, in the byte order of the stream.
+#[cfg(feature = "lz4")]
+pub(crate) const LZ4_MAGIC: [u8; 4] = 0x184d2204u32.to_le_bytes();
+
+/// The reason a saved cache was rejected by [`LruCache::load_from`], carried by an
+/// [`io::Error`] of kind [`ErrorKind::InvalidData`].
+///
+/// # Example
+///
+/// ```
+/// use fliplru::{LruCache, SnapshotError};
+///
+/// let err = LruCache::<Vec<u8>, Vec<u8>>::load_from(&b"not a cache"[..]).err().unwrap();
+/// let err = err.get_ref().and_then(|e| e.downcast_ref::<SnapshotError>());
+/// assert_eq!(err, Some(&SnapshotError::NotACache));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SnapshotError {
+    /// The stream does not start like a saved cache.
+    NotACache,
+    /// The cache was saved in a format version that this version of the crate cannot read.
+    UnsupportedVersion(u32),
+    /// The checksum does not match the items, so the stream was corrupted.
+    ChecksumMismatch,
+    /// The items are invalid, e.g., a key appears more than once.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::NotACache => f.write_str("not a saved cache"),
+            SnapshotError::UnsupportedVersion(v) => write!(f, "unsupported format version {v}"),
+            SnapshotError::ChecksumMismatch => f.write_str("checksum mismatch"),
+            SnapshotError::Invalid(msg) => write!(f, "invalid cache: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+// The stream starts with the magic number and the format version as a little-endian u32.
+// The payload holds the capacities and the flip metric as little-endian u64s, then for
+// each tier the number of items followed by the items. An item is the length of the key,
+// the key, the length of the value and the value. The stream ends with the FNV-1a hash of
+// the payload as a little-endian u64.
+impl<K: Hash + Eq, V> LruCache<K, V> {
+    /// Writes the items to the writer one at a time along with the capacities and the flip
+    /// metric, so that a large cache is saved without an intermediate copy. The keys and
+    /// values are written as bytes, like in a [`FileBackend`](crate::FileBackend). Wrap
+    /// the writer in a [`BufWriter`](std::io::BufWriter) when it is a file.
+    ///
+    /// The stream starts with a format version and ends with a checksum, so that
+    /// [`load_from`](LruCache::load_from) rejects corrupted or incompatible streams.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put("apple", "red");
+    /// let mut bytes = Vec::new();
+    /// cache.save_to(&mut bytes).unwrap();
+    /// assert!(!bytes.is_empty());
+    /// ```
+    pub fn save_to<W: Write>(&self, mut writer: W) -> io::Result<()>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.write_to(&mut writer, false)?;
+        writer.flush()
+    }
+
+    /// Like [`save_to`](LruCache::save_to), but writes only the items of the main cache,
+    /// e.g., when most of the backup cache would age out soon anyway. The stream is
+    /// loaded like any other: the saved items go back into the main cache, the backup
+    /// cache starts empty and the flip metric is kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(b"apple".to_vec(), b"red".to_vec());
+    /// cache.put(b"banana".to_vec(), b"yellow".to_vec());
+    /// cache.put(b"cherry".to_vec(), b"red".to_vec());
+    /// let mut bytes = Vec::new();
+    /// cache.save_hot_to(&mut bytes).unwrap();
+    /// let mut cache: LruCache<Vec<u8>, Vec<u8>> = LruCache::load_from(&bytes[..]).unwrap();
+    /// assert_eq!(cache.get(&b"cherry".to_vec()), Some(&b"red".to_vec()));
+    /// assert_eq!(cache.get(&b"apple".to_vec()), None);
+    /// ```
+    pub fn save_hot_to<W: Write>(&self, mut writer: W) -> io::Result<()>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.write_to(&mut writer, true)?;
+        writer.flush()
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W, hot_only: bool) -> io::Result<()>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        let mut payload = Checksum::new(&mut *writer);
+        for n in [self.hot_cap, self.cold_cap, self.flips] {
+            write_u64(&mut payload, n as u64)?;
+        }
+        let empty = HashMap::new();
+        let cold = if hot_only { &empty } else { &self.l2_map };
+        for map in [&self.l1_map, cold] {
+            write_u64(&mut payload, map.len() as u64)?;
+            for (k, e) in map {
+                write_bytes(&mut payload, k.as_ref())?;
+                write_bytes(&mut payload, e.value.as_ref())?;
+            }
+        }
+        let sum = payload.hash;
+        write_u64(writer, sum)
+    }
+
+    /// Like [`save_to`](LruCache::save_to), but writes an LZ4 frame, which
+    /// [`load_from`](LruCache::load_from) detects and decompresses. Caches of text
+    /// compress well.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(b"apple".to_vec(), b"red ".repeat(100));
+    /// let mut bytes = Vec::new();
+    /// cache.save_compressed_to(&mut bytes).unwrap();
+    /// assert!(bytes.len() < 100);
+    /// let mut cache: LruCache<Vec<u8>, Vec<u8>> = LruCache::load_from(&bytes[..]).unwrap();
+    /// assert_eq!(cache.get(&b"apple".to_vec()).unwrap().len(), 400);
+    /// ```
+    #[cfg(feature = "lz4")]
+    pub fn save_compressed_to<W: Write>(&self, writer: W) -> io::Result<()>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let mut encoder = FrameEncoder::new(writer);
+        self.save_to(&mut encoder)?;
+        encoder.finish()?.flush()
+    }
+
+    /// Reads a cache written by [`save_to`](LruCache::save_to) one item at a time. The
+    /// items go back into the tier they were in and the flip metric is kept. The
+    /// configuration starts with the defaults of [`with_caps`](LruCache::with_caps), but the
+    /// maps are only allocated for the items read, like the ones of
+    /// [`new_growable`](LruCache::new_growable), as the capacities are not trusted.
+    ///
+    /// Fails with [`ErrorKind::InvalidData`] carrying a [`SnapshotError`] if the stream was
+    /// not written by `save_to`, has another format version or a wrong checksum, or if a
+    /// capacity is zero or a key appears more than once. Fails with
+    /// [`ErrorKind::UnexpectedEof`] if the stream is truncated. The checksum is at the end
+    /// of the stream, so a corruption that breaks the structure of the items is reported as
+    /// such before it is reached. With the `lz4` feature, a
+    /// stream written by [`save_compressed_to`](LruCache::save_compressed_to) is detected
+    /// and decompressed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(b"apple".to_vec(), b"red".to_vec());
+    /// let mut bytes = Vec::new();
+    /// cache.save_to(&mut bytes).unwrap();
+    /// let mut cache: LruCache<Vec<u8>, Vec<u8>> = LruCache::load_from(&bytes[..]).unwrap();
+    /// assert_eq!(cache.get(&b"apple".to_vec()), Some(&b"red".to_vec()));
+    /// ```
+    pub fn load_from<R: Read>(reader: R) -> io::Result<LruCache<K, V>>
+    where
+        K: From<Vec<u8>>,
+        V: From<Vec<u8>>,
+    {
+        #[cfg(feature = "lz4")]
+        {
+            // Peek at the magic number of a frame and put it back in front of the stream. A
+            // shorter stream is left to read_from, which reports it like without lz4
+            let (mut reader, mut magic, mut len) = (reader, [0; 4], 0);
+            while len < magic.len() {
+                match reader.read(&mut magic[len..]) {
+                    Ok(0) => break,
+                    Ok(n) => len += n,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            let reader = (&magic[..len]).chain(reader);
+            if magic == LZ4_MAGIC {
+                return Self::read_from(FrameDecoder::new(reader));
+            }
+            Self::read_from(reader)
+        }
+        #[cfg(not(feature = "lz4"))]
+        Self::read_from(reader)
+    }
+
+    /// Creates a new cache that holds `cap` items, see [`new`](LruCache::new), and warms its
+    /// backup cache with the items of a file written by [`save_to`](LruCache::save_to).
+    /// The restored items are served until they age out, but they do not crowd out the
+    /// new hot items: one that is not accessed before the second flip is discarded. When
+    /// the file holds more than `cap` items, the ones of its main cache are kept first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::fs::File;
+    /// use std::num::NonZeroUsize;
+    /// let cap = NonZeroUsize::new(2).unwrap();
+    /// let mut cache = LruCache::new(cap);
+    ///
+    /// let path = std::env::temp_dir().join("fliplru-doc-warm");
+    /// cache.put("apple", "red");
+    /// cache.save_to(File::create(&path).unwrap()).unwrap();
+    /// let mut cache: LruCache<Vec<u8>, Vec<u8>> = LruCache::warm_from_file(&path, cap).unwrap();
+    /// assert_eq!(cache.get(&b"apple".to_vec()), Some(&b"red".to_vec()));
+    /// std::fs::remove_file(path).unwrap();
+    /// ```
+    pub fn warm_from_file<P: AsRef<Path>>(path: P, cap: NonZeroUsize) -> io::Result<LruCache<K, V>>
+    where
+        K: From<Vec<u8>>,
+        V: From<Vec<u8>>,
+    {
+        let mut saved = LruCache::load_from(BufReader::new(File::open(path)?))?;
+        let mut cache = LruCache::new(cap);
+        let items = saved.l1_map.drain().chain(saved.l2_map.drain());
+        cache
+            .l2_map
+            .extend(items.take(cap.get()).map(|(k, e)| (k, e.renewed())));
+        Ok(cache)
+    }
+
+    fn read_from<R: Read>(mut reader: R) -> io::Result<LruCache<K, V>>
+    where
+        K: From<Vec<u8>>,
+        V: From<Vec<u8>>,
+    {
+        read_header(&mut reader)?;
+        let mut payload = Checksum::new(&mut reader);
+        let hot_cap = read_cap(&mut payload)?;
+        let cold_cap = read_cap(&mut payload)?;
+        let flips = read_u64(&mut payload)?;
+        let mut cache = LruCache::restoring(hot_cap, cold_cap);
+        cache.flips = usize::try_from(flips).map_err(|_| invalid("the flips overflow"))?;
+        // A tier may hold more items than its capacity, e.g., with a manual flip mode
+        read_items(&mut payload, &mut cache.l1_map)?;
+        read_items(&mut payload, &mut cache.l2_map)?;
+        let sum = payload.hash;
+        if read_u64(&mut reader)? != sum {
+            return Err(rejected(SnapshotError::ChecksumMismatch));
+        }
+        if cache.l2_map.keys().any(|k| cache.l1_map.contains_key(k)) {
+            return Err(invalid("a key is in both tiers"));
+        }
+        Ok(cache)
+    }
+}
+
+fn read_items<K: Hash + Eq + From<Vec<u8>>, V: From<Vec<u8>>, R: Read>(
+    reader: &mut R,
+    map: &mut HashMap<K, Entry<V>>,
+) -> io::Result<()> {
+    let len = read_u64(reader)?;
+    for _ in 0..len {
+        let k = K::from(read_bytes(reader)?);
+        let v = V::from(read_bytes(reader)?);
+        if map.insert(k, Entry::new(v, Priority::Normal)).is_some() {
+            return Err(invalid("a key appears more than once"));
+        }
+    }
+    Ok(())
+}
+
+// Computes the FNV-1a hash of the bytes that go through it.
+pub(crate) struct Checksum<T> {
+    inner: T,
+    pub(crate) hash: u64,
+}
+
+impl<T> Checksum<T> {
+    pub(crate) fn new(inner: T) -> Checksum<T> {
+        Checksum {
+            inner,
+            hash: 0xcbf29ce484222325,
+        }
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        self.hash = bytes.iter().fold(self.hash, |h, &b| {
+            (h ^ b as u64).wrapping_mul(0x100000001b3)
+        });
+    }
+}
+
+impl<W: Write> Write for Checksum<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<R: Read> Read for Checksum<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+// Reads the magic number and the format version.
+pub(crate) fn read_header<R: Read>(reader: &mut R) -> io::Result<()> {
+    let mut header = [0; 8];
+    reader.read_exact(&mut header).map_err(|e| match e.kind() {
+        ErrorKind::UnexpectedEof => rejected(SnapshotError::NotACache),
+        _ => e,
+    })?;
+    if header[..4] != MAGIC {
+        return Err(rejected(SnapshotError::NotACache));
+    }
+    let version = u32::from_le_bytes(header[4..].try_into().unwrap());
+    if version != VERSION {
+        return Err(rejected(SnapshotError::UnsupportedVersion(version)));
+    }
+    Ok(())
+}
+
+fn write_u64<W: Write>(writer: &mut W, n: u64) -> io::Result<()> {
+    writer.write_all(&n.to_le_bytes())
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    write_u64(writer, bytes.len() as u64)?;
+    writer.write_all(bytes)
+}
+
+pub(crate) fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut n = [0; 8];
+    reader.read_exact(&mut n)?;
+    Ok(u64::from_le_bytes(n))
+}
+
+// Reads through `take`, so that a corrupt length does not allocate up front.
+fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let len = read_u64(reader)?;
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+pub(crate) fn read_cap<R: Read>(reader: &mut R) -> io::Result<NonZeroUsize> {
+    usize::try_from(read_u64(reader)?)
+        .ok()
+        .and_then(NonZeroUsize::new)
+        .ok_or_else(|| invalid("a capacity is zero or too large"))
+}
+
+pub(crate) fn invalid(msg: &'static str) -> io::Error {
+    rejected(SnapshotError::Invalid(msg))
+}
+
+pub(crate) fn rejected(err: SnapshotError) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SnapshotError;
+    use crate::{FlipMode, LruCache, Tier};
+    use core::num::NonZeroUsize;
+    use std::io::ErrorKind;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_save_and_load() {
+        let mut cache =
+            LruCache::with_caps(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(4).unwrap());
+        for i in 0..5u8 {
+            cache.put(Vec::from([i]), Vec::from([i; 3]));
+        }
+        let mut bytes = Vec::new();
+        cache.save_to(&mut bytes).unwrap();
+        let copy: LruCache<Vec<u8>, Vec<u8>> = LruCache::load_from(&bytes[..]).unwrap();
+
+        assert_eq!(copy.cold_cap(), 4);
+        assert_eq!(copy.get_flips(), 2);
+        for i in 0..5u8 {
+            assert_eq!(copy.peek_entry(&[i][..]), cache.peek_entry(&[i][..]));
+        }
+
+        // A truncated stream fails
+        let err = LruCache::<Vec<u8>, Vec<u8>>::load_from(&bytes[..bytes.len() - 1]);
+        assert_eq!(err.err().map(|e| e.kind()), Some(ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn test_tiers_over_capacity() {
+        let cap = NonZeroUsize::new(8).unwrap();
+        let every = FlipMode::EveryInsertions(NonZeroUsize::new(12).unwrap());
+        let round_trip = |cache: &LruCache<Vec<u8>, Vec<u8>>, len: u8| {
+            let mut bytes = Vec::new();
+            cache.save_to(&mut bytes).unwrap();
+            let copy: LruCache<Vec<u8>, Vec<u8>> = LruCache::load_from(&bytes[..]).unwrap();
+            for i in 0..len {
+                assert_eq!(copy.peek_entry(&[i][..]), cache.peek_entry(&[i][..]));
+            }
+        };
+
+        // The main cache outgrows its capacity when the flips are not driven by it
+        for mode in [FlipMode::Manual, every] {
+            let mut cache = LruCache::new(cap).with_flip_mode(mode);
+            for i in 0..10u8 {
+                cache.put(Vec::from([i]), Vec::from([i]));
+            }
+            round_trip(&cache, 10);
+        }
+
+        // A shrunk capacity holds more items until the next flip
+        let mut cache = LruCache::new(cap).with_autotune(NonZeroUsize::new(1).unwrap(), cap);
+        for i in 0..7u8 {
+            cache.put(Vec::from([i]), Vec::from([i]));
+        }
+        for _ in 0..200 {
+            cache.get(&[0][..]);
+        }
+        assert!(cache.hot_cap() < 7);
+        round_trip(&cache, 7);
+    }
+
+    #[test]
+    fn test_load_growable() {
+        // The maps are not allocated for the capacities
+        let mut cache = LruCache::new_growable(NonZeroUsize::new(1 << 40).unwrap());
+        cache.put(Vec::from(*b"apple"), Vec::from(*b"red"));
+        let mut bytes = Vec::new();
+        cache.save_to(&mut bytes).unwrap();
+        let copy: LruCache<Vec<u8>, Vec<u8>> = LruCache::load_from(&bytes[..]).unwrap();
+        assert_eq!(copy.cap().get(), 1 << 40);
+        assert!(copy.l1_map.capacity() < 1 << 10);
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_compressed() {
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        for i in 0..3u8 {
+            cache.put(Vec::from([i]), Vec::from([i; 1000]));
+        }
+        let (mut plain, mut compressed) = (Vec::new(), Vec::new());
+        cache.save_to(&mut plain).unwrap();
+        cache.save_compressed_to(&mut compressed).unwrap();
+        assert!(compressed.len() * 10 < plain.len());
+
+        // Both are detected
+        for bytes in [plain, compressed] {
+            let copy: LruCache<Vec<u8>, Vec<u8>> = LruCache::load_from(&bytes[..]).unwrap();
+            assert_eq!(copy.peek(&[0][..]), Some(&Vec::from([0; 1000])));
+            assert_eq!(copy.get_flips(), 1);
+        }
+    }
+
+    #[test]
+    fn test_rejected_streams() {
+        let reason = |bytes: &[u8]| {
+            let err = LruCache::<Vec<u8>, Vec<u8>>::load_from(bytes)
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+            let err = err
+                .get_ref()
+                .and_then(|e| e.downcast_ref::<SnapshotError>());
+            err.copied()
+        };
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        cache.put(Vec::from(*b"apple"), Vec::from(*b"red"));
+        let mut bytes = Vec::new();
+        cache.save_to(&mut bytes).unwrap();
+
+        let mut corrupt = bytes.clone();
+        // Flip a bit of the value, in front of the number of cold items and the checksum
+        *corrupt.iter_mut().rev().nth(16).unwrap() ^= 1;
+        assert_eq!(reason(&corrupt), Some(SnapshotError::ChecksumMismatch));
+        // A corrupt capacity is not allocated before the checksum is checked
+        let mut corrupt = bytes.clone();
+        corrupt[15] = 0x7f;
+        assert_eq!(reason(&corrupt), Some(SnapshotError::ChecksumMismatch));
+        let mut newer = bytes.clone();
+        newer[4] = 2;
+        assert_eq!(reason(&newer), Some(SnapshotError::UnsupportedVersion(2)));
+        assert_eq!(reason(&bytes[1..]), Some(SnapshotError::NotACache));
+        // Also when it is shorter than the magic number of a compressed stream
+        for len in 0..4 {
+            assert_eq!(reason(&bytes[..len]), Some(SnapshotError::NotACache));
+        }
+    }
+
+    #[test]
+    fn test_warm_from_file() {
+        let path = std::env::temp_dir().join("fliplru-test-warm");
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        for i in 0..4u8 {
+            cache.put(Vec::from([i]), Vec::from([i]));
+        }
+        cache
+            .save_to(std::fs::File::create(&path).unwrap())
+            .unwrap();
+
+        // Only the items of the saved main cache fit, and they are cold
+        let cap = NonZeroUsize::new(2).unwrap();
+        let mut warm: LruCache<Vec<u8>, Vec<u8>> = LruCache::warm_from_file(&path, cap).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let view = warm.to_frozen_copy();
+        assert_eq!(view.tier(&[2][..]), Some(Tier::Cold));
+        assert_eq!(view.tier(&[3][..]), Some(Tier::Cold));
+        assert_eq!(view.len(), 2);
+        assert_eq!(warm.get_flips(), 0);
+
+        // New items then fill the main cache without a flip
+        warm.put(Vec::from([8]), Vec::new());
+        warm.put(Vec::from([9]), Vec::new());
+        assert_eq!(warm.get_flips(), 0);
+        assert_eq!(warm.get(&[2][..]), Some(&Vec::from([2])));
+    }
+
+    #[test]
+    fn test_save_hot() {
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        for i in 0..4u8 {
+            cache.put(Vec::from([i]), Vec::from([i; 100]));
+        }
+        let (mut all, mut hot) = (Vec::new(), Vec::new());
+        cache.save_to(&mut all).unwrap();
+        cache.save_hot_to(&mut hot).unwrap();
+        assert!(hot.len() < all.len());
+
+        let copy: LruCache<Vec<u8>, Vec<u8>> = LruCache::load_from(&hot[..]).unwrap();
+        let view = copy.to_frozen_copy();
+        assert_eq!(view.tier(&[3][..]), Some(Tier::Hot));
+        assert_eq!(view.tier(&[1][..]), None);
+        assert_eq!(view.len(), 2);
+        assert_eq!(copy.get_flips(), 1);
+    }
+}
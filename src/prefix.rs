@@ -0,0 +1,55 @@
+use crate::LruCache;
+use allocator_api2::alloc::Allocator;
+use core::hash::{BuildHasher, Hash};
+
+impl<K: Hash + Eq + AsRef<str>, V, S: BuildHasher + Clone, A: Allocator + Clone>
+    LruCache<K, V, S, A>
+{
+    /// Removes all the items whose key starts with the prefix from the main and the backup
+    /// cache, e.g., every route under `/users/42/`, and returns how many were removed. Like
+    /// [`retain`](LruCache::retain), the items are handed to the eviction listener.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(1024).unwrap());
+    ///
+    /// cache.put("/users/42/profile", "profile");
+    /// cache.put("/users/42/settings", "settings");
+    /// cache.put("/users/420/profile", "other");
+    /// assert_eq!(cache.invalidate_prefix("/users/42/"), 2);
+    /// assert_eq!(cache.len(), 1);
+    /// ```
+    pub fn invalidate_prefix(&mut self, prefix: &str) -> usize {
+        let mut removed = 0;
+        self.retain(|k, _| {
+            let keep = !k.as_ref().starts_with(prefix);
+            removed += usize::from(!keep);
+            keep
+        });
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::LruCache;
+    use alloc::string::String;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_invalidate_prefix() {
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        cache.put(String::from("/a/1"), 1);
+        cache.put(String::from("/b/1"), 2);
+        cache.put(String::from("/a/2"), 3);
+        // Both the backup and the main cache are swept
+        assert_eq!(cache.invalidate_prefix("/a/"), 2);
+        assert_eq!(cache.get("/b/1"), Some(&2));
+        assert_eq!(cache.invalidate_prefix("/c/"), 0);
+        assert_eq!(cache.invalidate_prefix(""), 1);
+        assert!(cache.is_empty());
+    }
+}
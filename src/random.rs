@@ -0,0 +1,76 @@
+/// A source of random numbers used by the cache, e.g., to decide whether an item
+/// found in the backup cache is promoted.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::RandomSource;
+///
+/// struct Alternate(bool);
+///
+/// impl RandomSource for Alternate {
+///     fn next_u32(&mut self) -> u32 {
+///         self.0 = !self.0;
+///         if self.0 { u32::MAX } else { 0 }
+///     }
+/// }
+/// ```
+pub trait RandomSource {
+    /// Returns the next uniformly distributed random number.
+    fn next_u32(&mut self) -> u32;
+}
+
+/// A simple linear congruential generator. It is not suitable for cryptographic use,
+/// but it is cheap and works without `std`.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::{Lcg, RandomSource};
+/// let mut a = Lcg::new(42);
+/// let mut b = Lcg::new(42);
+/// assert_eq!(a.next_u32(), b.next_u32());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Lcg {
+    state: u64,
+}
+
+impl Lcg {
+    /// Creates a new generator from the given seed.
+    pub const fn new(seed: u64) -> Lcg {
+        Lcg { state: seed }
+    }
+}
+
+// The seed of the default generator.
+pub(crate) const DEFAULT_SEED: u64 = 0x853c_49e6_748f_ea9b;
+
+impl Default for Lcg {
+    fn default() -> Lcg {
+        Lcg::new(DEFAULT_SEED)
+    }
+}
+
+impl RandomSource for Lcg {
+    fn next_u32(&mut self) -> u32 {
+        // Multiplier and increment from Knuth's MMIX
+        self.state = self
+            .state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.state >> 32) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lcg, RandomSource};
+
+    #[test]
+    fn test_lcg_spread() {
+        let mut rng = Lcg::default();
+        let high = (0..1000).filter(|_| rng.next_u32() > u32::MAX / 2).count();
+        assert!(high > 400 && high < 600);
+    }
+}
@@ -0,0 +1,861 @@
+use crate::{Clock, LruCache};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::hash::Hash;
+use core::mem;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// The source of the values of a [`ReadThroughCache`].
+///
+/// # Example
+///
+/// ```
+/// use fliplru::CacheLoader;
+/// use std::collections::HashMap;
+///
+/// struct Db(HashMap<u64, String>);
+///
+/// impl CacheLoader<u64, String> for Db {
+///     type Error = ();
+///
+///     fn load(&mut self, k: &u64) -> Result<String, ()> {
+///         self.0.get(k).cloned().ok_or(())
+///     }
+/// }
+/// ```
+pub trait CacheLoader<K, V> {
+    /// The error of a failed load.
+    type Error;
+
+    /// Loads the value of the key.
+    fn load(&mut self, k: &K) -> Result<V, Self::Error>;
+
+    /// Loads the values of the keys in one go and returns the items found, e.g., with a
+    /// multi-get of the backend. The keys that are not returned are treated as failed
+    /// loads. By default, the keys are loaded one by one.
+    fn load_many(&mut self, keys: &[K]) -> Result<Vec<(K, V)>, Self::Error>
+    where
+        K: Clone,
+    {
+        keys.iter()
+            .map(|k| Ok((k.clone(), self.load(k)?)))
+            .collect()
+    }
+}
+
+/// The result of [`ReadThroughCache::lookup`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lookup<'a, V> {
+    /// The value of the key is in the cache.
+    Hit(&'a V),
+    /// The key is remembered as missing, see [`ReadThroughCache::with_negative_ttl`].
+    NegativeHit,
+    /// The key is not known to the cache.
+    Miss,
+}
+
+/// A read-through layer: a lookup of a key that is not present in the cache loads its
+/// value from the loader and puts it into the cache, so the call sites do not carry the
+/// loading code.
+pub struct ReadThroughCache<K, V, L> {
+    cache: LruCache<K, V>,
+    loader: L,
+    negatives: Option<Negatives<K>>,
+    reloads: Option<ReloadQueue<K>>,
+}
+
+// The keys remembered as missing until their expiry time. Like the cache, they flip
+// between two maps, so that a flood of distinct misses is bounded by twice the capacity.
+struct Negatives<K> {
+    keys: HashMap<K, Duration>,
+    old_keys: HashMap<K, Duration>,
+    ttl: Duration,
+    clock: Box<dyn Clock + Send + Sync>,
+}
+
+impl<K: Hash + Eq> Negatives<K> {
+    fn contains(&mut self, k: &K) -> bool {
+        let now = self.clock.now();
+        for keys in [&mut self.keys, &mut self.old_keys] {
+            match keys.get(k) {
+                Some(&expiry) if expiry > now => return true,
+                Some(_) => {
+                    keys.remove(k);
+                    return false;
+                }
+                None => {}
+            }
+        }
+        false
+    }
+
+    fn insert(&mut self, k: K, cap: usize) {
+        self.old_keys.remove(&k);
+        if self.keys.len() >= cap && !self.keys.contains_key(&k) {
+            // The emptied backup map is reused for the next generation
+            mem::swap(&mut self.keys, &mut self.old_keys);
+            self.keys.clear();
+        }
+        self.keys.insert(k, self.clock.now() + self.ttl);
+    }
+
+    fn remove(&mut self, k: &K) {
+        self.keys.remove(k);
+        self.old_keys.remove(k);
+    }
+}
+
+// The load times of the cached values, for the reload queue.
+struct ReloadQueue<K> {
+    // The load time and whether a reload is queued
+    ages: HashMap<K, (Duration, bool)>,
+    due: Vec<K>,
+    ttl: Duration,
+    ahead: Duration,
+    clock: Box<dyn Clock + Send + Sync>,
+}
+
+impl<K: Hash + Eq + Clone> ReloadQueue<K> {
+    // Queues a reload of the value of the key if it is aging. Returns whether it expired.
+    fn check(&mut self, k: &K) -> bool {
+        let now = self.clock.now();
+        let Some((loaded, queued)) = self.ages.get_mut(k) else {
+            // Put into the cache behind our back
+            self.ages.insert(k.clone(), (now, false));
+            return false;
+        };
+        let age = now.saturating_sub(*loaded);
+        if age >= self.ttl {
+            return true;
+        }
+        if age >= self.ahead && !*queued {
+            *queued = true;
+            self.due.push(k.clone());
+        }
+        false
+    }
+}
+
+impl<K: Hash + Eq, V, L: CacheLoader<K, V>> ReadThroughCache<K, V, L> {
+    /// Creates a read-through layer of the cache in front of the loader.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheLoader, LruCache, ReadThroughCache};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// struct Square;
+    ///
+    /// impl CacheLoader<i32, i32> for Square {
+    ///     type Error = ();
+    ///
+    ///     fn load(&mut self, &k: &i32) -> Result<i32, ()> {
+    ///         Ok(k * k)
+    ///     }
+    /// }
+    ///
+    /// let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = ReadThroughCache::new(cache, Square);
+    /// assert_eq!(cache.get(&3), Ok(Some(&9)));
+    /// ```
+    pub fn new(cache: LruCache<K, V>, loader: L) -> ReadThroughCache<K, V, L> {
+        ReadThroughCache {
+            cache,
+            loader,
+            negatives: None,
+            reloads: None,
+        }
+    }
+
+    /// Enables negative caching: the keys whose load fails, or that are put with
+    /// [`put_negative`], are remembered as missing for `ttl` as measured by the clock.
+    /// [`get`] does not call the loader for them in the meantime. Like the items, the
+    /// negatives flip when the capacity of the cache is reached, so at most twice as many
+    /// are remembered.
+    ///
+    /// [`put_negative`]: ReadThroughCache::put_negative
+    /// [`get`]: ReadThroughCache::get
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheLoader, Lookup, LruCache, ReadThroughCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// struct Missing(u32);
+    ///
+    /// impl CacheLoader<i32, i32> for Missing {
+    ///     type Error = ();
+    ///
+    ///     fn load(&mut self, _: &i32) -> Result<i32, ()> {
+    ///         self.0 += 1;
+    ///         Err(())
+    ///     }
+    /// }
+    ///
+    /// let secs = Arc::new(AtomicU64::new(0));
+    /// let clock = {
+    ///     let secs = secs.clone();
+    ///     move || Duration::from_secs(secs.load(Ordering::Relaxed))
+    /// };
+    /// let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = ReadThroughCache::new(cache, Missing(0))
+    ///     .with_negative_ttl(Duration::from_secs(5), clock);
+    ///
+    /// assert_eq!(cache.get(&1), Err(()));
+    /// assert_eq!(cache.get(&1), Ok(None));
+    /// assert_eq!(cache.loader().0, 1);
+    ///
+    /// secs.store(5, Ordering::Relaxed);
+    /// assert_eq!(cache.lookup(&1), Lookup::Miss);
+    /// ```
+    pub fn with_negative_ttl<C>(mut self, ttl: Duration, clock: C) -> ReadThroughCache<K, V, L>
+    where
+        C: Clock + Send + Sync + 'static,
+    {
+        self.negatives = Some(Negatives {
+            keys: HashMap::new(),
+            old_keys: HashMap::new(),
+            ttl,
+            clock: Box::new(clock),
+        });
+        self
+    }
+
+    /// Enables a queue of the keys due for reload: the values expire `ttl` after they were
+    /// loaded or put, as measured by the clock, and [`get`] loads them again. A value
+    /// accessed after the fraction `ahead` of its `ttl` is still served, and its key is
+    /// queued for a reload.
+    ///
+    /// Nothing is reloaded in the background, and nothing is scheduled: the caller must
+    /// drive the reloads by calling [`reload_due`], e.g., from a timer or a task of its
+    /// runtime. The hot keys are only kept from expiring if `reload_due` is called within
+    /// the rest of their `ttl`, otherwise the queued values expire on a [`get`] like the
+    /// others. This only covers this synchronous cache, the `AsyncBackedCache` has no
+    /// reload queue.
+    ///
+    /// [`get`]: ReadThroughCache::get
+    /// [`reload_due`]: ReadThroughCache::reload_due
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ahead` is not within `0.0..=1.0`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheLoader, LruCache, ReadThroughCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// struct Version(i32);
+    ///
+    /// impl CacheLoader<&'static str, i32> for Version {
+    ///     type Error = ();
+    ///
+    ///     fn load(&mut self, _: &&'static str) -> Result<i32, ()> {
+    ///         self.0 += 1;
+    ///         Ok(self.0)
+    ///     }
+    /// }
+    ///
+    /// let secs = Arc::new(AtomicU64::new(0));
+    /// let clock = {
+    ///     let secs = secs.clone();
+    ///     move || Duration::from_secs(secs.load(Ordering::Relaxed))
+    /// };
+    /// let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = ReadThroughCache::new(cache, Version(0))
+    ///     .with_reload_queue(Duration::from_secs(10), 0.5, clock);
+    ///
+    /// assert_eq!(cache.get(&"config"), Ok(Some(&1)));
+    /// secs.store(6, Ordering::Relaxed);
+    /// assert_eq!(cache.get(&"config"), Ok(Some(&1)));
+    ///
+    /// // Driven by the caller, e.g., on a timer
+    /// assert_eq!(cache.reload_due(), Ok(1));
+    /// assert_eq!(cache.get(&"config"), Ok(Some(&2)));
+    /// ```
+    pub fn with_reload_queue<C>(
+        mut self,
+        ttl: Duration,
+        ahead: f32,
+        clock: C,
+    ) -> ReadThroughCache<K, V, L>
+    where
+        C: Clock + Send + Sync + 'static,
+    {
+        assert!(
+            (0.0..=1.0).contains(&ahead),
+            "reload-ahead fraction must be within 0.0..=1.0"
+        );
+        self.reloads = Some(ReloadQueue {
+            ages: HashMap::new(),
+            due: Vec::new(),
+            ttl,
+            ahead: ttl.mul_f32(ahead),
+            clock: Box::new(clock),
+        });
+        self
+    }
+
+    /// Reloads the values of the keys due for reload, see [`with_reload_queue`], that are
+    /// still in the cache, and returns their number. If the loader fails, the error is
+    /// returned, the cached value is kept and the other keys stay queued.
+    ///
+    /// [`with_reload_queue`]: ReadThroughCache::with_reload_queue
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheLoader, LruCache, ReadThroughCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::time::Duration;
+    ///
+    /// struct Square;
+    ///
+    /// impl CacheLoader<i32, i32> for Square {
+    ///     type Error = ();
+    ///
+    ///     fn load(&mut self, &k: &i32) -> Result<i32, ()> {
+    ///         Ok(k * k)
+    ///     }
+    /// }
+    ///
+    /// let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = ReadThroughCache::new(cache, Square);
+    /// assert_eq!(cache.reload_due(), Ok(0));
+    /// ```
+    pub fn reload_due(&mut self) -> Result<usize, L::Error>
+    where
+        K: Clone,
+    {
+        let Some(reloads) = &mut self.reloads else {
+            return Ok(0);
+        };
+        let mut reloaded = 0;
+        while let Some(k) = reloads.due.pop() {
+            // Skip the keys that were loaded again or discarded since
+            let Some(age) = reloads.ages.get_mut(&k).filter(|(_, queued)| *queued) else {
+                continue;
+            };
+            if self.cache.peek(&k).is_none() {
+                reloads.ages.remove(&k);
+                continue;
+            }
+            match self.loader.load(&k) {
+                Ok(v) => {
+                    *age = (reloads.clock.now(), false);
+                    self.cache.put(k, v);
+                    reloaded += 1;
+                }
+                Err(e) => {
+                    age.1 = false;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(reloaded)
+    }
+
+    // Discards the value of the key if it expired, and queues it to be reloaded if it is
+    // aging, for the reload queue.
+    fn expire(&mut self, k: &K)
+    where
+        K: Clone,
+    {
+        if let Some(reloads) = &mut self.reloads {
+            if self.cache.peek(k).is_some() && reloads.check(k) {
+                self.cache.remove(k);
+            }
+        }
+    }
+
+    // Records the load time of the value of the key for the reload queue.
+    fn loaded(&mut self, k: K) {
+        if let Some(reloads) = &mut self.reloads {
+            // Forget the keys that were discarded from the cache
+            if reloads.ages.len() >= self.cache.cap().get() * 2 {
+                reloads.ages.retain(|k, _| self.cache.peek(k).is_some());
+            }
+            reloads.ages.insert(k, (reloads.clock.now(), false));
+        }
+    }
+
+    /// Reports whether the value of the key is in the cache, the key is remembered as
+    /// missing, or neither, without loading it. A hit is promoted, see [`LruCache::get`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheLoader, Lookup, LruCache, ReadThroughCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::time::Duration;
+    ///
+    /// struct Square;
+    ///
+    /// impl CacheLoader<i32, i32> for Square {
+    ///     type Error = ();
+    ///
+    ///     fn load(&mut self, &k: &i32) -> Result<i32, ()> {
+    ///         Ok(k * k)
+    ///     }
+    /// }
+    ///
+    /// let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = ReadThroughCache::new(cache, Square)
+    ///     .with_negative_ttl(Duration::from_secs(5), || Duration::ZERO);
+    ///
+    /// cache.put(1, 1);
+    /// cache.put_negative(2);
+    /// assert_eq!(cache.lookup(&1), Lookup::Hit(&1));
+    /// assert_eq!(cache.lookup(&2), Lookup::NegativeHit);
+    /// assert_eq!(cache.lookup(&3), Lookup::Miss);
+    /// ```
+    pub fn lookup<'a>(&'a mut self, k: &K) -> Lookup<'a, V> {
+        if let Some(v) = self.cache.get(k) {
+            return Lookup::Hit(v);
+        }
+
+        if self.negatives.as_mut().is_some_and(|n| n.contains(k)) {
+            Lookup::NegativeHit
+        } else {
+            Lookup::Miss
+        }
+    }
+
+    /// Returns a reference to the value of the key, see [`LruCache::get`]. If the key is
+    /// not present in the cache, its value is loaded and put into the cache. If the loader
+    /// fails, the error is returned and the cache is left unchanged. `None` is returned for
+    /// the keys remembered as missing, see [`with_negative_ttl`].
+    ///
+    /// [`with_negative_ttl`]: ReadThroughCache::with_negative_ttl
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheLoader, LruCache, ReadThroughCache};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// struct Positive;
+    ///
+    /// impl CacheLoader<i32, i32> for Positive {
+    ///     type Error = i32;
+    ///
+    ///     fn load(&mut self, &k: &i32) -> Result<i32, i32> {
+    ///         if k > 0 { Ok(k * 10) } else { Err(k) }
+    ///     }
+    /// }
+    ///
+    /// let mut cache = ReadThroughCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()), Positive);
+    /// assert_eq!(cache.get(&1), Ok(Some(&10)));
+    /// assert_eq!(cache.get(&-1), Err(-1));
+    /// assert_eq!(cache.cache().len(), 1);
+    /// ```
+    pub fn get<'a>(&'a mut self, k: &K) -> Result<Option<&'a V>, L::Error>
+    where
+        K: Clone,
+    {
+        self.expire(k);
+        let this = match LruCache::get_in(self, |s| &mut s.cache, k, |_| true) {
+            Ok(v) => return Ok(Some(v)),
+            Err(this) => this,
+        };
+        if this.negatives.as_mut().is_some_and(|n| n.contains(k)) {
+            return Ok(None);
+        }
+
+        match this.loader.load(k) {
+            Ok(v) => {
+                this.loaded(k.clone());
+                this.cache.put(k.clone(), v);
+                // The value was not found, which get would count as a hit
+                Ok(this.cache.peek(k))
+            }
+            Err(e) => {
+                this.put_negative(k.clone());
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns clones of the values of the keys, in the order of the keys, like [`get`].
+    /// The keys that are not present in the cache are loaded with a single call of
+    /// [`CacheLoader::load_many`]. `None` is returned for the keys remembered as missing or
+    /// not found by the loader. If the loader fails, the error is returned and the cache
+    /// is left unchanged.
+    ///
+    /// [`get`]: ReadThroughCache::get
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheLoader, LruCache, ReadThroughCache};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// struct Db(Vec<Vec<i32>>);
+    ///
+    /// impl CacheLoader<i32, i32> for Db {
+    ///     type Error = ();
+    ///
+    ///     fn load(&mut self, &k: &i32) -> Result<i32, ()> {
+    ///         self.0.push(vec![k]);
+    ///         Ok(k * 10)
+    ///     }
+    ///
+    ///     fn load_many(&mut self, keys: &[i32]) -> Result<Vec<(i32, i32)>, ()> {
+    ///         self.0.push(keys.to_vec());
+    ///         Ok(keys.iter().filter(|&&k| k > 0).map(|&k| (k, k * 10)).collect())
+    ///     }
+    /// }
+    ///
+    /// let cache = LruCache::new(NonZeroUsize::new(4).unwrap());
+    /// let mut cache = ReadThroughCache::new(cache, Db(Vec::new()));
+    /// cache.put(1, 1);
+    /// assert_eq!(cache.get_many(&[1, 2, -3, 4]), Ok(vec![Some(1), Some(20), None, Some(40)]));
+    /// assert_eq!(cache.loader().0, [vec![2, -3, 4]]);
+    /// ```
+    pub fn get_many(&mut self, keys: &[K]) -> Result<Vec<Option<V>>, L::Error>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let mut values = Vec::with_capacity(keys.len());
+        let mut missing = Vec::new();
+        for k in keys {
+            self.expire(k);
+            values.push(match self.lookup(k) {
+                Lookup::Hit(v) => Some(v.clone()),
+                Lookup::NegativeHit => None,
+                Lookup::Miss => {
+                    missing.push(k.clone());
+                    None
+                }
+            });
+        }
+        if missing.is_empty() {
+            return Ok(values);
+        }
+
+        let loaded: HashMap<K, V> = match self.loader.load_many(&missing) {
+            Ok(items) => items.into_iter().collect(),
+            Err(e) => {
+                missing.into_iter().for_each(|k| self.put_negative(k));
+                return Err(e);
+            }
+        };
+        for (v, k) in values.iter_mut().zip(keys) {
+            if v.is_none() {
+                *v = loaded.get(k).cloned();
+            }
+        }
+        for k in missing {
+            if !loaded.contains_key(&k) {
+                self.put_negative(k);
+            }
+        }
+        for (k, v) in loaded {
+            self.loaded(k.clone());
+            self.cache.put(k, v);
+        }
+        Ok(values)
+    }
+
+    /// Remembers the key as missing, e.g., when it is known to be deleted upstream, so
+    /// that [`get`] returns `None` for it without calling the loader. Its value is removed
+    /// from the cache. Does nothing unless negative caching is enabled, see
+    /// [`with_negative_ttl`].
+    ///
+    /// [`get`]: ReadThroughCache::get
+    /// [`with_negative_ttl`]: ReadThroughCache::with_negative_ttl
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheLoader, LruCache, ReadThroughCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::time::Duration;
+    ///
+    /// struct Square;
+    ///
+    /// impl CacheLoader<i32, i32> for Square {
+    ///     type Error = ();
+    ///
+    ///     fn load(&mut self, &k: &i32) -> Result<i32, ()> {
+    ///         Ok(k * k)
+    ///     }
+    /// }
+    ///
+    /// let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = ReadThroughCache::new(cache, Square)
+    ///     .with_negative_ttl(Duration::from_secs(5), || Duration::ZERO);
+    ///
+    /// assert_eq!(cache.get(&3), Ok(Some(&9)));
+    /// cache.put_negative(3);
+    /// assert_eq!(cache.get(&3), Ok(None));
+    /// ```
+    pub fn put_negative(&mut self, k: K) {
+        if let Some(negatives) = &mut self.negatives {
+            self.cache.remove(&k);
+            negatives.insert(k, self.cache.cap().get());
+        }
+    }
+
+    /// Puts a key-value pair into the cache without loading it, see [`LruCache::put`]. The
+    /// key is no longer remembered as missing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheLoader, LruCache, ReadThroughCache};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// struct Square;
+    ///
+    /// impl CacheLoader<i32, i32> for Square {
+    ///     type Error = ();
+    ///
+    ///     fn load(&mut self, &k: &i32) -> Result<i32, ()> {
+    ///         Ok(k * k)
+    ///     }
+    /// }
+    ///
+    /// let mut cache = ReadThroughCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()), Square);
+    /// assert_eq!(cache.put(3, 10), None);
+    /// assert_eq!(cache.get(&3), Ok(Some(&10)));
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Option<V> {
+        if let Some(negatives) = &mut self.negatives {
+            negatives.remove(&k);
+        }
+        if let Some(reloads) = &mut self.reloads {
+            // A new key is aged from its first lookup, as that does not need a clone
+            if let Some(age) = reloads.ages.get_mut(&k) {
+                *age = (reloads.clock.now(), false);
+            }
+        }
+        self.cache.put(k, v)
+    }
+
+    /// Returns the cache.
+    pub fn cache(&self) -> &LruCache<K, V> {
+        &self.cache
+    }
+
+    /// Returns the cache mutably, e.g., to invalidate keys so that they are loaded again.
+    pub fn cache_mut(&mut self) -> &mut LruCache<K, V> {
+        &mut self.cache
+    }
+
+    /// Returns the loader.
+    pub fn loader(&self) -> &L {
+        &self.loader
+    }
+
+    /// Returns the loader mutably.
+    pub fn loader_mut(&mut self) -> &mut L {
+        &mut self.loader
+    }
+
+    /// Returns the cache and the loader.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheLoader, LruCache, ReadThroughCache};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// struct Square;
+    ///
+    /// impl CacheLoader<i32, i32> for Square {
+    ///     type Error = ();
+    ///
+    ///     fn load(&mut self, &k: &i32) -> Result<i32, ()> {
+    ///         Ok(k * k)
+    ///     }
+    /// }
+    ///
+    /// let mut cache = ReadThroughCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()), Square);
+    /// cache.get(&3).unwrap();
+    /// let (cache, Square) = cache.into_parts();
+    /// assert_eq!(cache.len(), 1);
+    /// ```
+    pub fn into_parts(self) -> (LruCache<K, V>, L) {
+        (self.cache, self.loader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheLoader, Lookup, ReadThroughCache};
+    use crate::LruCache;
+    use alloc::sync::Arc;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::num::NonZeroUsize;
+    use core::sync::atomic::{AtomicU64, Ordering};
+    use core::time::Duration;
+
+    // Loads the double of the even keys and counts the loads
+    struct Even(usize);
+
+    impl CacheLoader<u32, u32> for Even {
+        type Error = u32;
+
+        fn load(&mut self, &k: &u32) -> Result<u32, u32> {
+            if k % 2 == 1 {
+                return Err(k);
+            }
+            self.0 += 1;
+            Ok(k * 2)
+        }
+    }
+
+    #[test]
+    fn test_read_through() {
+        let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        let mut cache = ReadThroughCache::new(cache, Even(0));
+
+        // Only the second lookup is counted as a hit
+        assert_eq!(cache.get(&2), Ok(Some(&4)));
+        assert_eq!(cache.cache().gen_hits.l1, 0);
+        assert_eq!(cache.get(&2), Ok(Some(&4)));
+        assert_eq!(cache.cache().gen_hits.l1, 1);
+        assert_eq!(cache.loader().0, 1);
+        // A failed load is not cached
+        assert_eq!(cache.get(&3), Err(3));
+        assert_eq!(cache.cache().len(), 1);
+
+        // An invalidated key is loaded again
+        cache.cache_mut().pop(&2);
+        assert_eq!(cache.get(&2), Ok(Some(&4)));
+        let (cache, loader) = cache.into_parts();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(loader.0, 2);
+    }
+
+    #[test]
+    fn test_negative_caching() {
+        let secs = Arc::new(AtomicU64::new(0));
+        let clock = {
+            let secs = secs.clone();
+            move || Duration::from_secs(secs.load(Ordering::Relaxed))
+        };
+        let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        let mut cache =
+            ReadThroughCache::new(cache, Even(0)).with_negative_ttl(Duration::from_secs(10), clock);
+
+        // A failed load is remembered until it expires
+        assert_eq!(cache.get(&3), Err(3));
+        assert_eq!(cache.lookup(&3), Lookup::NegativeHit);
+        assert_eq!(cache.get(&3), Ok(None));
+        secs.store(10, Ordering::Relaxed);
+        assert_eq!(cache.lookup(&3), Lookup::Miss);
+        assert_eq!(cache.get(&3), Err(3));
+
+        // An explicit negative replaces the value, and a put replaces the negative
+        assert_eq!(cache.get(&2), Ok(Some(&4)));
+        cache.put_negative(2);
+        assert_eq!(cache.get(&2), Ok(None));
+        cache.put(2, 5);
+        assert_eq!(cache.lookup(&2), Lookup::Hit(&5));
+        assert_eq!(cache.loader().0, 1);
+
+        // A flood of distinct misses within the ttl is bounded by twice the capacity
+        for k in (101..200).step_by(2) {
+            assert_eq!(cache.get(&k), Err(k));
+        }
+        let negatives = cache.negatives.as_ref().unwrap();
+        assert!(negatives.keys.len() + negatives.old_keys.len() <= 4);
+        assert_eq!(cache.lookup(&199), Lookup::NegativeHit);
+        assert_eq!(cache.lookup(&197), Lookup::NegativeHit);
+    }
+
+    #[test]
+    fn test_reload_queue() {
+        let secs = Arc::new(AtomicU64::new(0));
+        let clock = {
+            let secs = secs.clone();
+            move || Duration::from_secs(secs.load(Ordering::Relaxed))
+        };
+        let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        let mut cache = ReadThroughCache::new(cache, Even(0)).with_reload_queue(
+            Duration::from_secs(10),
+            0.8,
+            clock,
+        );
+
+        assert_eq!(cache.get(&2), Ok(Some(&4)));
+        assert_eq!(cache.get(&4), Ok(Some(&8)));
+        secs.store(8, Ordering::Relaxed);
+        // Aging values are served and queued once
+        assert_eq!(cache.get(&2), Ok(Some(&4)));
+        assert_eq!(cache.get(&2), Ok(Some(&4)));
+        assert_eq!(cache.loader().0, 2);
+        assert_eq!(cache.reload_due(), Ok(1));
+        assert_eq!(cache.loader().0, 3);
+        assert_eq!(cache.reload_due(), Ok(0));
+
+        // The reloaded value is fresh, the other one expired and is loaded on access
+        secs.store(12, Ordering::Relaxed);
+        assert_eq!(cache.get(&2), Ok(Some(&4)));
+        assert_eq!(cache.loader().0, 3);
+        assert_eq!(cache.get(&4), Ok(Some(&8)));
+        assert_eq!(cache.loader().0, 4);
+    }
+
+    // Loads the odd keys in batches and records them
+    struct Batch(Vec<Vec<u32>>);
+
+    impl CacheLoader<u32, u32> for Batch {
+        type Error = ();
+
+        fn load(&mut self, _: &u32) -> Result<u32, ()> {
+            unreachable!()
+        }
+
+        fn load_many(&mut self, keys: &[u32]) -> Result<Vec<(u32, u32)>, ()> {
+            self.0.push(keys.to_vec());
+            Ok(keys
+                .iter()
+                .filter(|&&k| k % 2 == 1)
+                .map(|&k| (k, k))
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_load_many() {
+        let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        let mut cache = ReadThroughCache::new(cache, Batch(Vec::new()))
+            .with_negative_ttl(Duration::from_secs(10), || Duration::ZERO);
+
+        // The misses are fetched in one call, across both tiers
+        cache.put(1, 10);
+        cache.put(3, 30);
+        cache.put(5, 50);
+        assert_eq!(
+            cache.get_many(&[1, 2, 7, 5]),
+            Ok(vec![Some(10), None, Some(7), Some(50)])
+        );
+        assert_eq!(cache.loader().0, [vec![2, 7]]);
+
+        // A key that was not found is remembered as missing
+        assert_eq!(cache.get_many(&[2, 7]), Ok(vec![None, Some(7)]));
+        assert_eq!(cache.loader().0.len(), 1);
+
+        // The default loads the keys one by one
+        let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        let mut cache = ReadThroughCache::new(cache, Even(0));
+        assert_eq!(cache.get_many(&[2, 4]), Ok(vec![Some(4), Some(8)]));
+        assert_eq!(cache.get_many(&[6, 3]), Err(3));
+        assert_eq!(cache.loader().0, 3);
+    }
+}
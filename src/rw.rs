@@ -0,0 +1,383 @@
+use crate::stats::{CacheStats, Counters};
+use crate::{LruCache, Tier};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::mem;
+use std::sync::{Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+// Number of promotions that are queued before they are applied.
+const PENDING_PROMOTIONS: usize = 64;
+
+/// A concurrent cache for read-mostly workloads: lookups only take a shared lock on the
+/// [`LruCache`], so hits in the main cache do not contend. Hits in the backup cache are
+/// queued and promoted later under the exclusive lock, when the queue is full or with the
+/// next write.
+///
+/// The shared lookups do not count as accesses of the cache, so they do not drive the
+/// features that depend on them, like the adaptive split or the recency order of
+/// [`FlipMode::StrictLru`](crate::FlipMode::StrictLru). The queue is never waited for: a
+/// hit in the backup cache is not promoted if the queue is busy or full.
+pub struct RwLruCache<K, V> {
+    cache: RwLock<LruCache<K, V>>,
+    counters: Counters,
+    pending: Mutex<Vec<K>>,
+}
+
+impl<K: Hash + Eq + Clone, V> RwLruCache<K, V> {
+    /// Wraps the cache, keeping its configuration.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, RwLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache: RwLruCache<isize, &str> = RwLruCache::new(LruCache::new(NonZeroUsize::new(10).unwrap()));
+    /// ```
+    pub fn new(cache: LruCache<K, V>) -> RwLruCache<K, V> {
+        RwLruCache {
+            cache: RwLock::new(cache),
+            counters: Counters::default(),
+            pending: Mutex::new(Vec::with_capacity(PENDING_PROMOTIONS)),
+        }
+    }
+
+    /// Takes the shared lock for direct read access to the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, RwLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = RwLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.read().peek(&1), Some(&"a"));
+    /// ```
+    pub fn read(&self) -> RwLockReadGuard<'_, LruCache<K, V>> {
+        self.cache.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Takes the exclusive lock for direct access to the whole API of the cache, after
+    /// applying the queued promotions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, RwLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = RwLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.write().put(1, "a");
+    /// assert_eq!(cache.write().get(&1), Some(&"a"));
+    /// ```
+    pub fn write(&self) -> RwLockWriteGuard<'_, LruCache<K, V>> {
+        let mut cache = self.cache.write().unwrap_or_else(PoisonError::into_inner);
+        self.promote(&mut cache);
+        cache
+    }
+
+    /// Applies the queued promotions now.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, RwLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = RwLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// cache.write().flip();
+    /// assert_eq!(cache.get(&1), Some("a"));
+    /// cache.promote_pending();
+    ///
+    /// // 1 is in the main cache again and survives a flip
+    /// cache.write().flip();
+    /// assert_eq!(cache.get(&1), Some("a"));
+    /// ```
+    pub fn promote_pending(&self) {
+        drop(self.write());
+    }
+
+    fn promote(&self, cache: &mut LruCache<K, V>) {
+        let keys = mem::take(&mut *self.pending.lock().unwrap_or_else(PoisonError::into_inner));
+        for k in &keys {
+            cache.get(k);
+        }
+    }
+
+    /// Calls the function with the value of the key under the shared lock and returns its
+    /// result, or `None` if the key is not present in the cache. A hit in the backup cache
+    /// is queued for promotion.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, RwLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = RwLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put("apple", String::from("red"));
+    /// assert_eq!(cache.get_with(&"apple", |v| v.len()), Some(3));
+    /// assert_eq!(cache.get_with(&"banana", |v| v.len()), None);
+    /// ```
+    pub fn get_with<Q, R, F>(&self, k: &Q, f: F) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&V) -> R,
+    {
+        let mut full = false;
+        let r = {
+            let cache = self.read();
+            let (rk, v, tier) = self.counters.record(cache.peek_entry(k))?;
+            if tier == Tier::Cold {
+                if let Ok(mut pending) = self.pending.try_lock() {
+                    if pending.len() < PENDING_PROMOTIONS {
+                        pending.push(rk.clone());
+                    }
+                    full = pending.len() >= PENDING_PROMOTIONS;
+                }
+            }
+            f(v)
+        };
+        if full {
+            self.promote_pending();
+        }
+        Some(r)
+    }
+
+    /// Returns a clone of the value of the key or `None` if it is not present in the cache,
+    /// see [`get_with`].
+    ///
+    /// [`get_with`]: RwLruCache::get_with
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, RwLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = RwLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some("a"));
+    /// ```
+    pub fn get<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.get_with(k, V::clone)
+    }
+
+    /// Calls the function with the mutable value of the key under the exclusive lock, see
+    /// [`LruCache::get_mut`], and returns its result, or `None` if the key is not present in
+    /// the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, RwLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = RwLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put("apple", 8);
+    /// cache.get_mut_with(&"apple", |v| *v += 1);
+    /// assert_eq!(cache.get(&"apple"), Some(9));
+    /// ```
+    pub fn get_mut_with<Q, R, F>(&self, k: &Q, f: F) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&mut V) -> R,
+    {
+        self.counters.record(self.write().get_mut(k).map(f))
+    }
+
+    /// Puts a key-value pair into the cache under the exclusive lock, see
+    /// [`LruCache::put`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, RwLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = RwLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&self, k: K, v: V) -> Option<V> {
+        self.write().put(k, v)
+    }
+
+    /// Removes the key from the cache and returns its value, see [`LruCache::pop`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, RwLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = RwLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.write().pop(k)
+    }
+
+    /// Removes all the items from the cache, see [`LruCache::clear`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, RwLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = RwLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// cache.clear();
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn clear(&self) {
+        self.write().clear()
+    }
+
+    /// Returns the number of key-value pairs that are guaranteed to be in the cache, see
+    /// [`LruCache::len`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, RwLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = RwLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, RwLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = RwLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// Returns the hit, miss and flip counts of the cache. Only the lookups through this
+    /// wrapper are counted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheStats, LruCache, RwLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = RwLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// cache.get(&1);
+    /// cache.get(&2);
+    /// assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 1, flips: 0 });
+    /// ```
+    pub fn stats(&self) -> CacheStats {
+        self.counters.stats(self.read().get_flips())
+    }
+
+    /// Returns metric on the number of times the cache flipped, see
+    /// [`LruCache::get_flips`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, RwLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = RwLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.read().get_flips()
+    }
+
+    /// Returns the wrapped cache after applying the queued promotions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, RwLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = RwLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    /// cache.put(1, "a");
+    /// let mut cache = cache.into_inner();
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// ```
+    pub fn into_inner(self) -> LruCache<K, V> {
+        let mut cache = self
+            .cache
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        for k in self
+            .pending
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+        {
+            cache.get(&k);
+        }
+        cache
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RwLruCache, PENDING_PROMOTIONS};
+    use crate::LruCache;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_deferred_promotions() {
+        let cache = RwLruCache::new(LruCache::new(NonZeroUsize::new(200).unwrap()));
+        for i in 0..300 {
+            cache.put(i, i);
+        }
+        assert_eq!(cache.get_flips(), 1);
+
+        // Hits in the backup cache are promoted once the queue is full
+        for i in 0..PENDING_PROMOTIONS - 1 {
+            assert_eq!(cache.get(&i), Some(i));
+        }
+        assert_eq!(cache.read().len(), 200);
+        assert_eq!(cache.pending.lock().unwrap().len(), PENDING_PROMOTIONS - 1);
+        assert_eq!(
+            cache.get(&(PENDING_PROMOTIONS - 1)),
+            Some(PENDING_PROMOTIONS - 1)
+        );
+        assert!(cache.pending.lock().unwrap().is_empty());
+
+        // The promoted keys survive the next flip
+        cache.write().flip();
+        for i in 0..PENDING_PROMOTIONS {
+            assert_eq!(cache.get(&i), Some(i));
+        }
+    }
+}
@@ -0,0 +1,382 @@
+use crate::{Cache, CacheStats, Lcg, RandomSource};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::hash::{BuildHasher, Hash};
+use core::mem;
+use core::num::NonZeroUsize;
+use hashbrown::hash_map::DefaultHashBuilder;
+use hashbrown::raw::RawTable;
+
+// The sample size of Redis' maxmemory policies.
+const DEFAULT_SAMPLES: usize = 5;
+
+struct Slot<K, V> {
+    key: K,
+    value: V,
+    hash: u64,
+    // Tick of the last access
+    tick: u64,
+}
+
+/// An approximate LRU Cache in a single map without flips: when it is full, it samples
+/// a few random items and evicts the least recently used of them, like the `maxmemory`
+/// policies of Redis. More samples make the evictions closer to those of an exact LRU
+/// Cache at the cost of slower insertions, see
+/// [`with_samples`](SampledLruCache::with_samples).
+///
+/// The items are stored in a vector that the map indexes, so that they can be sampled
+/// uniformly in constant time.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::SampledLruCache;
+/// use std::num::NonZeroUsize;
+/// let mut cache = SampledLruCache::new(NonZeroUsize::new(2).unwrap());
+///
+/// cache.put(1, "a");
+/// cache.put(2, "b");
+/// cache.put(3, "c");
+/// assert_eq!(cache.len(), 2);
+/// assert_eq!(cache.get(&3), Some(&"c"));
+/// ```
+pub struct SampledLruCache<K, V> {
+    // Indexes of the slots by the hashes of their keys
+    index: RawTable<usize>,
+    slots: Vec<Slot<K, V>>,
+    hasher: DefaultHashBuilder,
+    cap: NonZeroUsize,
+    samples: NonZeroUsize,
+    tick: u64,
+    rng: Lcg,
+    hits: u64,
+    misses: u64,
+}
+
+impl<K: Hash + Eq, V> SampledLruCache<K, V> {
+    /// Creates a new cache that holds `cap` items and samples 5 items per eviction.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SampledLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: SampledLruCache<u64, &str> = SampledLruCache::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> SampledLruCache<K, V> {
+        SampledLruCache {
+            index: RawTable::with_capacity(cap.get()),
+            slots: Vec::with_capacity(cap.get()),
+            hasher: DefaultHashBuilder::default(),
+            cap,
+            samples: NonZeroUsize::new(DEFAULT_SAMPLES).unwrap(),
+            tick: 0,
+            rng: Lcg::default(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Sets the number of items sampled per eviction. Sampling the whole capacity makes
+    /// the evictions exact, but linear in `cap`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SampledLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = SampledLruCache::new(NonZeroUsize::new(2).unwrap())
+    ///     .with_samples(NonZeroUsize::new(16).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.get(&1);
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.get(&2), None);
+    /// ```
+    pub fn with_samples(mut self, samples: NonZeroUsize) -> SampledLruCache<K, V> {
+        self.samples = samples;
+        self
+    }
+
+    /// Returns a reference to the value of the key in the cache or `None` if it is not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SampledLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = SampledLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert_eq!(cache.get(&2), None);
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_mut(k).map(|v| &*v)
+    }
+
+    /// Returns a mutable reference to the value of the key in the cache or `None` if it
+    /// is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SampledLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = SampledLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put("apple", 8);
+    /// *cache.get_mut("apple").unwrap() += 1;
+    /// assert_eq!(cache.get("apple"), Some(&9));
+    /// ```
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let Some(i) = self.find(k) else {
+            self.misses += 1;
+            return None;
+        };
+        self.hits += 1;
+        self.tick += 1;
+        let slot = &mut self.slots[i];
+        slot.tick = self.tick;
+        Some(&mut slot.value)
+    }
+
+    /// Returns a reference to the value of the key without updating its last access, or
+    /// `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SampledLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = SampledLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.peek(&1), Some(&"a"));
+    /// ```
+    pub fn peek<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find(k).map(|i| &self.slots[i].value)
+    }
+
+    /// Puts a key-value pair into cache, evicting a sampled item first if the cache is
+    /// full. If the key already exists in the cache, then it updates the key's value and
+    /// returns the old value. Otherwise, `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SampledLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = SampledLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Option<V> {
+        self.tick += 1;
+        if let Some(i) = self.find(&k) {
+            let slot = &mut self.slots[i];
+            slot.tick = self.tick;
+            return Some(mem::replace(&mut slot.value, v));
+        }
+        if self.slots.len() >= self.cap.get() {
+            let lru = self.sample_lru();
+            self.remove(lru);
+        }
+        let hash = self.hasher.hash_one(&k);
+        let slots = &self.slots;
+        self.index.insert(hash, slots.len(), |&i| slots[i].hash);
+        self.slots.push(Slot {
+            key: k,
+            value: v,
+            hash,
+            tick: self.tick,
+        });
+        None
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it was not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SampledLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = SampledLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let i = self.find(k)?;
+        Some(self.remove(i).value)
+    }
+
+    /// Returns the number of key-value pairs in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SampledLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = SampledLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SampledLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = SampledLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Returns the maximum number of items the cache holds.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SampledLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: SampledLruCache<u32, u32> = SampledLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert_eq!(cache.cap().get(), 2);
+    /// ```
+    pub fn cap(&self) -> NonZeroUsize {
+        self.cap
+    }
+
+    /// Returns the hits and misses of the lookups. The cache never flips.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheStats, SampledLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = SampledLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.get(&1);
+    /// cache.get(&2);
+    /// assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 1, flips: 0 });
+    /// ```
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            flips: 0,
+        }
+    }
+
+    fn find<Q>(&self, k: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hasher.hash_one(k);
+        let slots = &self.slots;
+        self.index
+            .get(hash, |&i| slots[i].key.borrow() == k)
+            .copied()
+    }
+
+    // Returns the slot of the least recently used of the sampled items.
+    fn sample_lru(&mut self) -> usize {
+        let len = self.slots.len() as u64;
+        (0..self.samples.get())
+            .map(|_| {
+                let r = u64::from(self.rng.next_u32()) << 32 | u64::from(self.rng.next_u32());
+                (r % len) as usize
+            })
+            .min_by_key(|&i| self.slots[i].tick)
+            .unwrap_or(0)
+    }
+
+    // Removes the slot, moving the last slot into its place.
+    fn remove(&mut self, i: usize) -> Slot<K, V> {
+        self.index.remove_entry(self.slots[i].hash, |&j| j == i);
+        let slot = self.slots.swap_remove(i);
+        if let Some(moved) = self.slots.get(i) {
+            let last = self.slots.len();
+            if let Some(j) = self.index.get_mut(moved.hash, |&j| j == last) {
+                *j = i;
+            }
+        }
+        slot
+    }
+}
+
+impl<K: Hash + Eq, V: Clone> Cache<K, V> for SampledLruCache<K, V> {
+    fn get(&mut self, k: &K) -> Option<V> {
+        SampledLruCache::get(self, k).cloned()
+    }
+
+    fn put(&mut self, k: K, v: V) -> Option<V> {
+        SampledLruCache::put(self, k, v)
+    }
+
+    fn pop(&mut self, k: &K) -> Option<V> {
+        SampledLruCache::pop(self, k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SampledLruCache;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_sampled_eviction() {
+        let mut cache = SampledLruCache::new(NonZeroUsize::new(64).unwrap())
+            .with_samples(NonZeroUsize::new(8).unwrap());
+        for i in 0..64 {
+            cache.put(i, i);
+        }
+        // The hot keys are accessed after every insertion and are never the stalest
+        for i in 64..1000 {
+            cache.put(i, i);
+            for k in 0..4 {
+                assert_eq!(cache.get(&k), Some(&k));
+            }
+        }
+        assert_eq!(cache.len(), 64);
+        for i in 936..1000 {
+            if let Some(&v) = cache.peek(&i) {
+                assert_eq!(v, i);
+            }
+        }
+        assert_eq!(cache.pop(&999), Some(999));
+        assert_eq!(cache.len(), 63);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::LruCache;
+use allocator_api2::alloc::Global;
+use core::hash::{BuildHasher, Hash};
+use core::num::NonZeroUsize;
+
+/// A hasher builder whose keys come from a seed, so that the hashes cannot be predicted
+/// by whoever controls the keys of the cache. This makes hash flooding impractical.
+///
+/// Without the `getrandom` feature, the default hasher of [`LruCache::new`] and of the
+/// other caches uses predictable keys, so it does not resist hash flooding. With the
+/// feature, its keys are seeded once per process from the random number generator of
+/// the operating system, and a `SeededState` is only needed to choose the seed.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::allocator_api2::alloc::Global;
+/// use fliplru::{LruCache, SeededState};
+/// use std::num::NonZeroUsize;
+/// // A seed from a hardware random number generator
+/// let hasher = SeededState::new([0x243f_6a88, 0x85a3_08d3, 0x1319_8a2e, 0x0370_7344]);
+/// let cap = NonZeroUsize::new(2).unwrap();
+/// let mut cache = LruCache::with_hasher_in(cap, cap, hasher, Global);
+/// cache.put(1, "a");
+/// assert_eq!(cache.get(&1), Some(&"a"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SeededState(ahash::RandomState);
+
+impl SeededState {
+    /// Creates a hasher builder from a seed, which must be random to resist hash flooding.
+    pub const fn new(seed: [u64; 4]) -> SeededState {
+        SeededState(ahash::RandomState::with_seeds(
+            seed[0], seed[1], seed[2], seed[3],
+        ))
+    }
+
+    /// Creates a hasher builder seeded from the random number generator of the operating
+    /// system, or returns the error of [`getrandom`] if it is unavailable.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SeededState;
+    /// assert!(SeededState::from_entropy().is_ok());
+    /// ```
+    #[cfg(feature = "getrandom")]
+    pub fn from_entropy() -> Result<SeededState, getrandom::Error> {
+        let mut seed = [0; 4];
+        for k in &mut seed {
+            *k = getrandom::u64()?;
+        }
+        Ok(SeededState::new(seed))
+    }
+}
+
+impl BuildHasher for SeededState {
+    type Hasher = ahash::AHasher;
+
+    fn build_hasher(&self) -> ahash::AHasher {
+        self.0.build_hasher()
+    }
+}
+
+impl<K: Hash + Eq, V> LruCache<K, V, SeededState> {
+    /// Creates a new LRU Cache like [`new`](LruCache::new) whose keys are hashed with the
+    /// given seed, see [`SeededState`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::with_seed(NonZeroUsize::new(2).unwrap(), [1, 2, 3, 4]);
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// ```
+    pub fn with_seed(cap: NonZeroUsize, seed: [u64; 4]) -> LruCache<K, V, SeededState> {
+        LruCache::with_hasher_in(cap, cap, SeededState::new(seed), Global)
+    }
+
+    /// Creates a new LRU Cache like [`new`](LruCache::new) whose keys are hashed with a
+    /// seed from the random number generator of the operating system, see
+    /// [`SeededState::from_entropy`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the random number generator is unavailable.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new_random(NonZeroUsize::new(2).unwrap());
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// ```
+    #[cfg(feature = "getrandom")]
+    pub fn new_random(cap: NonZeroUsize) -> LruCache<K, V, SeededState> {
+        let hasher = SeededState::from_entropy().expect("getrandom failed to seed the hasher");
+        LruCache::with_hasher_in(cap, cap, hasher, Global)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SeededState;
+    use core::hash::BuildHasher;
+
+    #[test]
+    fn test_seeded_hashes() {
+        let (a, b) = (
+            SeededState::new([1, 2, 3, 4]),
+            SeededState::new([4, 3, 2, 1]),
+        );
+        assert_eq!(a.hash_one("apple"), a.clone().hash_one("apple"));
+        assert_ne!(a.hash_one("apple"), b.hash_one("apple"));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::{Entry, LruCache, Priority};
+use alloc::vec::Vec;
+use core::hash::Hash;
+use core::num::NonZeroUsize;
+use hashbrown::HashMap;
+use serde::de::{Deserialize, Deserializer, Error};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// The capacities, the flip metric and the items of both tiers are serialized. The
+/// configuration, e.g., the flip mode, the hooks and the observer, and the per item
+/// bookkeeping, e.g., the priorities, are not, so a deserialized cache starts with the
+/// defaults of [`LruCache::with_caps`]. Its maps are only allocated for the items it holds,
+/// like the ones of [`LruCache::new_growable`], whatever the capacities.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::LruCache;
+/// use std::num::NonZeroUsize;
+/// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+///
+/// cache.put(1, "a");
+/// cache.put(2, "b");
+/// cache.put(3, "c");
+/// let json = serde_json::to_string(&cache).unwrap();
+/// let mut cache: LruCache<i32, String> = serde_json::from_str(&json).unwrap();
+/// assert_eq!(cache.get(&1).map(String::as_str), Some("a"));
+/// assert_eq!(cache.get_flips(), 1);
+/// ```
+impl<K: Hash + Eq + Serialize, V: Serialize> Serialize for LruCache<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("LruCache", 5)?;
+        s.serialize_field("hot_cap", &self.hot_cap)?;
+        s.serialize_field("cold_cap", &self.cold_cap)?;
+        s.serialize_field("flips", &self.flips)?;
+        s.serialize_field("hot", &Items(&self.l1_map))?;
+        s.serialize_field("cold", &Items(&self.l2_map))?;
+        s.end()
+    }
+}
+
+/// Deserialization fails if a key appears more than once, e.g., because the input was not
+/// serialized from a cache. A tier may hold more items than its capacity, as the tiers of
+/// a cache that flips manually or was resized do.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::LruCache;
+///
+/// let json = r#"{"hot_cap":2,"cold_cap":2,"flips":0,"hot":[[1,"a"],[1,"b"]],"cold":[]}"#;
+/// assert!(serde_json::from_str::<LruCache<i32, String>>(json).is_err());
+/// ```
+impl<'de, K: Hash + Eq + Deserialize<'de>, V: Deserialize<'de>> Deserialize<'de>
+    for LruCache<K, V>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = Repr::<K, V>::deserialize(deserializer)?;
+        let mut cache = LruCache::restoring(repr.hot_cap, repr.cold_cap);
+        cache.flips = repr.flips;
+        fill(&mut cache.l1_map, repr.hot)?;
+        fill(&mut cache.l2_map, repr.cold)?;
+        if cache.l2_map.keys().any(|k| cache.l1_map.contains_key(k)) {
+            return Err(D::Error::custom("a key is in both tiers"));
+        }
+        Ok(cache)
+    }
+}
+
+// Serializes the items of a map as a sequence of key-value pairs.
+struct Items<'a, K, V>(&'a HashMap<K, Entry<V>>);
+
+impl<K: Serialize, V: Serialize> Serialize for Items<'_, K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(|(k, e)| (k, &e.value)))
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct Repr<K, V> {
+    hot_cap: NonZeroUsize,
+    cold_cap: NonZeroUsize,
+    flips: usize,
+    hot: Vec<(K, V)>,
+    cold: Vec<(K, V)>,
+}
+
+fn fill<K: Hash + Eq, V, E: Error>(
+    map: &mut HashMap<K, Entry<V>>,
+    items: Vec<(K, V)>,
+) -> Result<(), E> {
+    map.reserve(items.len());
+    for (k, v) in items {
+        if map.insert(k, Entry::new(v, Priority::Normal)).is_some() {
+            return Err(E::custom("a key appears more than once"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FlipMode, LruCache, Tier};
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_round_trip() {
+        let mut cache =
+            LruCache::with_caps(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(4).unwrap());
+        for i in 0..5 {
+            cache.put(i, i * 10);
+        }
+        let json = serde_json::to_string(&cache).unwrap();
+        let mut copy: LruCache<u32, u32> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(copy.hot_cap(), 2);
+        assert_eq!(copy.cold_cap(), 4);
+        assert_eq!(copy.get_flips(), cache.get_flips());
+        // Every item is restored into its tier
+        for i in 0..5 {
+            assert_eq!(copy.peek_entry(&i), cache.peek_entry(&i));
+        }
+        assert_eq!(copy.peek_entry(&0).map(|(_, _, t)| t), Some(Tier::Cold));
+        assert_eq!(copy.get(&1), Some(&10));
+    }
+
+    #[test]
+    fn test_round_trip_growable() {
+        // The maps are not allocated for the capacities
+        let mut cache = LruCache::new_growable(NonZeroUsize::new(1 << 40).unwrap());
+        cache.put(1, 10);
+        let json = serde_json::to_string(&cache).unwrap();
+        let mut copy: LruCache<u32, u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy.cap().get(), 1 << 40);
+        assert!(copy.l1_map.capacity() < 1 << 10);
+        for i in 2..100 {
+            copy.put(i, i * 10);
+        }
+        copy.flip();
+        assert!(copy.l1_map.capacity() < 1 << 10);
+        assert_eq!(copy.get(&1), Some(&10));
+    }
+
+    #[test]
+    fn test_round_trip_over_capacity() {
+        // A cache that flips manually is not bounded by its capacity
+        let mut cache =
+            LruCache::new(NonZeroUsize::new(2).unwrap()).with_flip_mode(FlipMode::Manual);
+        for i in 0..5 {
+            cache.put(i, i * 10);
+        }
+        let json = serde_json::to_string(&cache).unwrap();
+        let copy: LruCache<u32, u32> = serde_json::from_str(&json).unwrap();
+        for i in 0..5 {
+            assert_eq!(copy.peek(&i), Some(&(i * 10)));
+        }
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let dup = r#"{"hot_cap":2,"cold_cap":2,"flips":0,"hot":[[1,1]],"cold":[[1,2]]}"#;
+        assert!(serde_json::from_str::<LruCache<u32, u32>>(dup).is_err());
+        let zero = r#"{"hot_cap":0,"cold_cap":2,"flips":0,"hot":[],"cold":[]}"#;
+        assert!(serde_json::from_str::<LruCache<u32, u32>>(zero).is_err());
+    }
+}
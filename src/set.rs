@@ -0,0 +1,198 @@
+use crate::LruCache;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::num::NonZeroUsize;
+
+/// A set of the recently seen keys with the flip semantics of [`LruCache`], e.g., to
+/// deduplicate messages by their IDs. The last `cap` keys are guaranteed to be in the set
+/// and up to the last `cap*2` keys can be found.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::LruSet;
+/// use std::num::NonZeroUsize;
+/// let mut seen = LruSet::new(NonZeroUsize::new(1024).unwrap());
+///
+/// for id in [7, 8, 7] {
+///     if !seen.insert(id) {
+///         println!("duplicate message {id}");
+///     }
+/// }
+/// assert_eq!(seen.len(), 2);
+/// ```
+pub struct LruSet<K> {
+    cache: LruCache<K, ()>,
+}
+
+impl<K: Hash + Eq> LruSet<K> {
+    /// Creates a new set that holds `cap` keys.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruSet;
+    /// use std::num::NonZeroUsize;
+    /// let set: LruSet<u64> = LruSet::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> LruSet<K> {
+        LruSet {
+            cache: LruCache::new(cap),
+        }
+    }
+
+    /// Inserts the key into the set and returns true if it was not present.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruSet;
+    /// use std::num::NonZeroUsize;
+    /// let mut set = LruSet::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert!(set.insert("a"));
+    /// assert!(!set.insert("a"));
+    /// ```
+    pub fn insert(&mut self, k: K) -> bool {
+        self.cache.put(k, ()).is_none()
+    }
+
+    /// Returns true if the key is present in the set. Like [`LruCache::get`], this counts
+    /// as a use of the key, which keeps it in the set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruSet;
+    /// use std::num::NonZeroUsize;
+    /// let mut set = LruSet::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// set.insert(1);
+    /// set.insert(2);
+    /// set.insert(3);
+    /// assert!(set.contains(&1));
+    /// assert!(!set.contains(&4));
+    /// ```
+    pub fn contains<Q>(&mut self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.get(k).is_some()
+    }
+
+    /// Removes the key from the set and returns true if it was present.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruSet;
+    /// use std::num::NonZeroUsize;
+    /// let mut set = LruSet::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// set.insert(1);
+    /// assert!(set.remove(&1));
+    /// assert!(!set.remove(&1));
+    /// ```
+    pub fn remove<Q>(&mut self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.pop(k).is_some()
+    }
+
+    /// Returns the number of keys that are guaranteed to be in the set, like
+    /// [`LruCache::len`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruSet;
+    /// use std::num::NonZeroUsize;
+    /// let mut set = LruSet::new(NonZeroUsize::new(2).unwrap());
+    /// set.insert(1);
+    /// assert_eq!(set.len(), 1);
+    /// set.insert(2);
+    /// set.insert(3);
+    /// assert_eq!(set.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Returns a bool indicating whether the set is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruSet;
+    /// use std::num::NonZeroUsize;
+    /// let mut set = LruSet::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(set.is_empty());
+    /// set.insert(1);
+    /// assert!(!set.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Removes all the keys from the set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruSet;
+    /// use std::num::NonZeroUsize;
+    /// let mut set = LruSet::new(NonZeroUsize::new(2).unwrap());
+    /// set.insert(1);
+    /// set.clear();
+    /// assert!(!set.contains(&1));
+    /// ```
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    /// Returns metric on the number of times the set became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruSet;
+    /// use std::num::NonZeroUsize;
+    /// let mut set = LruSet::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     set.insert(i);
+    /// }
+    /// assert_eq!(set.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.cache.get_flips()
+    }
+}
+
+impl<K: Hash + Eq> Extend<K> for LruSet<K> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        self.cache.put_many(iter.into_iter().map(|k| (k, ())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LruSet;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_dedup() {
+        let mut seen = LruSet::new(NonZeroUsize::new(2).unwrap());
+        let fresh: usize = [1, 2, 1, 3, 4, 1, 2]
+            .iter()
+            .filter(|&&id| seen.insert(id))
+            .count();
+        // 2 was discarded by a flip before it was seen again
+        assert_eq!(fresh, 5);
+        seen.extend([5, 6]);
+        assert!(seen.contains(&5));
+        assert!(!seen.contains(&1));
+    }
+}
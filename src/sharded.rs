@@ -0,0 +1,525 @@
+use crate::{Cache, CacheStats, InsertGuard, LruCache, SyncLruCache, ValueGuard};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::hash::{BuildHasher, Hash};
+use core::num::NonZeroUsize;
+use hashbrown::hash_map::DefaultHashBuilder;
+#[cfg(feature = "rayon")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// A concurrent cache that splits the keys across independent [`LruCache`] shards, each
+/// with its own lock, capacity and flip counter, so that threads working on different
+/// shards do not contend. It has the API of [`SyncLruCache`] taking `&self`.
+///
+/// Each shard flips on its own, so the guarantee that the last `cap` items are in the
+/// cache only holds per shard.
+pub struct ShardedLruCache<K, V> {
+    shards: Box<[SyncLruCache<K, V>]>,
+    hasher: DefaultHashBuilder,
+}
+
+impl<K: Hash + Eq, V> ShardedLruCache<K, V> {
+    /// Creates a cache of `shards` shards that together hold `cap` items, i.e., every
+    /// shard holds `cap / shards` items rounded up.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: ShardedLruCache<isize, &str> =
+    ///     ShardedLruCache::new(NonZeroUsize::new(1000).unwrap(), NonZeroUsize::new(8).unwrap());
+    /// assert_eq!(cache.shards().len(), 8);
+    /// assert_eq!(cache.shards()[0].lock().cap().get(), 125);
+    /// ```
+    pub fn new(cap: NonZeroUsize, shards: NonZeroUsize) -> ShardedLruCache<K, V> {
+        let shard_cap = NonZeroUsize::new(cap.get().div_ceil(shards.get())).unwrap();
+        ShardedLruCache::with_shards(shards, |_| LruCache::new(shard_cap))
+    }
+
+    /// Creates a cache of `shards` shards, calling the function with the index of every
+    /// shard to create it. This allows configuring the shards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{FlipMode, LruCache, ShardedLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache: ShardedLruCache<isize, &str> = ShardedLruCache::with_shards(NonZeroUsize::new(4).unwrap(), |_| {
+    ///     LruCache::new(NonZeroUsize::new(100).unwrap()).with_flip_mode(FlipMode::StrictLru)
+    /// });
+    /// ```
+    pub fn with_shards<F>(shards: NonZeroUsize, mut f: F) -> ShardedLruCache<K, V>
+    where
+        F: FnMut(usize) -> LruCache<K, V>,
+    {
+        ShardedLruCache {
+            shards: (0..shards.get()).map(|i| SyncLruCache::new(f(i))).collect(),
+            hasher: DefaultHashBuilder::default(),
+        }
+    }
+
+    /// Returns the shards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(8).unwrap(), NonZeroUsize::new(4).unwrap());
+    /// for i in 0..100 {
+    ///     cache.put(i, i);
+    /// }
+    /// let flips: Vec<usize> = cache.shards().iter().map(|s| s.get_flips()).collect();
+    /// assert_eq!(flips.iter().sum::<usize>(), cache.get_flips());
+    /// ```
+    pub fn shards(&self) -> &[SyncLruCache<K, V>] {
+        &self.shards
+    }
+
+    /// Returns the shard of the key.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(8).unwrap(), NonZeroUsize::new(4).unwrap());
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.shard(&1).get(&1), Some("a"));
+    /// ```
+    pub fn shard<Q>(&self, k: &Q) -> &SyncLruCache<K, V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        &self.shards[self.index(k)]
+    }
+
+    fn index<Q>(&self, k: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        // The shards hash the keys the same way and use the low bits for their buckets,
+        // so pick the shard from the high bits
+        let hash = self.hasher.hash_one(k) >> 32;
+        (hash % self.shards.len() as u64) as usize
+    }
+
+    /// Calls the function with the value of the key, see [`SyncLruCache::get_with`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(8).unwrap(), NonZeroUsize::new(4).unwrap());
+    ///
+    /// cache.put("apple", String::from("red"));
+    /// assert_eq!(cache.get_with(&"apple", |v| v.len()), Some(3));
+    /// ```
+    pub fn get_with<Q, R, F>(&self, k: &Q, f: F) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&V) -> R,
+    {
+        self.shard(k).get_with(k, f)
+    }
+
+    /// Calls the function with the mutable value of the key, see
+    /// [`SyncLruCache::get_mut_with`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(8).unwrap(), NonZeroUsize::new(4).unwrap());
+    ///
+    /// cache.put("apple", 8);
+    /// cache.get_mut_with(&"apple", |v| *v += 1);
+    /// assert_eq!(cache.get(&"apple"), Some(9));
+    /// ```
+    pub fn get_mut_with<Q, R, F>(&self, k: &Q, f: F) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&mut V) -> R,
+    {
+        self.shard(k).get_mut_with(k, f)
+    }
+
+    /// Returns a clone of the value of the key or `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(8).unwrap(), NonZeroUsize::new(4).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some("a"));
+    /// ```
+    pub fn get<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.shard(k).get(k)
+    }
+
+    /// Returns a guard that derefs to the value of the key and keeps its shard locked, see
+    /// [`SyncLruCache::get_guard`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(8).unwrap(), NonZeroUsize::new(4).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(*cache.get_guard(&1).unwrap(), "a");
+    /// ```
+    pub fn get_guard<'a, Q>(&'a self, k: &'a Q) -> Option<ValueGuard<'a, K, V, Q>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(k).get_guard(k)
+    }
+
+    /// Returns a clone of the value of the key or loads it, coalescing concurrent loads of
+    /// the key, see [`SyncLruCache::get_or_insert_with`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(8).unwrap(), NonZeroUsize::new(4).unwrap());
+    ///
+    /// assert_eq!(cache.get_or_insert_with(1, || "a"), "a");
+    /// assert_eq!(cache.get(&1), Some("a"));
+    /// ```
+    pub fn get_or_insert_with<F>(&self, k: K, f: F) -> V
+    where
+        K: Clone,
+        V: Clone,
+        F: FnOnce() -> V,
+    {
+        self.shard(&k).get_or_insert_with(k, f)
+    }
+
+    /// Returns a clone of the value of the key or a guard to compute it, see
+    /// [`SyncLruCache::get_or_guard`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(8).unwrap(), NonZeroUsize::new(4).unwrap());
+    ///
+    /// cache.get_or_guard(1).err().unwrap().insert("a");
+    /// assert_eq!(cache.get(&1), Some("a"));
+    /// ```
+    pub fn get_or_guard(&self, k: K) -> Result<V, InsertGuard<'_, K, V>>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.shard(&k).get_or_guard(k)
+    }
+
+    /// Puts the items into their shards in parallel, e.g., to warm the cache at startup.
+    /// The items are hashed and grouped by shard on the rayon threads, and each group is
+    /// put with a single lock of its shard, see the [`Extend`] impl of [`LruCache`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use rayon::prelude::*;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(1000).unwrap(), NonZeroUsize::new(4).unwrap());
+    ///
+    /// cache.par_extend((0..100).into_par_iter().map(|i| (i, i * 2)));
+    /// assert_eq!(cache.len(), 100);
+    /// assert_eq!(cache.get(&21), Some(42));
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn par_extend<I>(&self, items: I)
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+        K: Send + Sync,
+        V: Send,
+    {
+        let groups = || -> Vec<Vec<(K, V)>> { self.shards.iter().map(|_| Vec::new()).collect() };
+        items
+            .into_par_iter()
+            .fold(groups, |mut groups, (k, v)| {
+                groups[self.index(&k)].push((k, v));
+                groups
+            })
+            .for_each(|groups| {
+                for (shard, group) in self.shards.iter().zip(groups) {
+                    if !group.is_empty() {
+                        shard.lock().extend(group);
+                    }
+                }
+            });
+    }
+
+    /// Puts a key-value pair into its shard, see [`LruCache::put`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(8).unwrap(), NonZeroUsize::new(4).unwrap());
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&self, k: K, v: V) -> Option<V> {
+        self.shard(&k).put(k, v)
+    }
+
+    /// Removes the key from the cache and returns its value, see [`LruCache::pop`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(8).unwrap(), NonZeroUsize::new(4).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(k).pop(k)
+    }
+
+    /// Removes all the items from all the shards, see [`LruCache::clear`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(8).unwrap(), NonZeroUsize::new(4).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.clear();
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn clear(&self) {
+        self.shards.iter().for_each(SyncLruCache::clear)
+    }
+
+    /// Returns the number of key-value pairs that are guaranteed to be in the shards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(8).unwrap(), NonZeroUsize::new(4).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// assert_eq!(cache.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(SyncLruCache::len).sum()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(8).unwrap(), NonZeroUsize::new(4).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(SyncLruCache::is_empty)
+    }
+
+    /// Returns the stats of every shard, e.g., to detect hot shards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(8).unwrap(), NonZeroUsize::new(4).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.get(&1);
+    /// let hits: Vec<u64> = cache.shard_stats().iter().map(|s| s.hits).collect();
+    /// assert_eq!(hits.iter().sum::<u64>(), 1);
+    /// ```
+    pub fn shard_stats(&self) -> Vec<CacheStats> {
+        self.shards.iter().map(SyncLruCache::stats).collect()
+    }
+
+    /// Returns the aggregated stats of the shards, see [`SyncLruCache::stats`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheStats, ShardedLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(8).unwrap(), NonZeroUsize::new(4).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.get(&1);
+    /// cache.get(&2);
+    /// assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 1, flips: 0 });
+    /// ```
+    pub fn stats(&self) -> CacheStats {
+        self.shards.iter().map(SyncLruCache::stats).sum()
+    }
+
+    /// Returns metric on the number of times the shards flipped in total. The flips of each
+    /// shard are available through [`shards`].
+    ///
+    /// [`shards`]: ShardedLruCache::shards
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::ShardedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache = ShardedLruCache::new(NonZeroUsize::new(4).unwrap(), NonZeroUsize::new(1).unwrap());
+    /// for i in 0..10 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.shards.iter().map(SyncLruCache::get_flips).sum()
+    }
+}
+
+impl<K: Hash + Eq, V: Clone> Cache<K, V> for &ShardedLruCache<K, V> {
+    fn get(&mut self, k: &K) -> Option<V> {
+        ShardedLruCache::get(self, k)
+    }
+
+    fn put(&mut self, k: K, v: V) -> Option<V> {
+        ShardedLruCache::put(self, k, v)
+    }
+
+    fn pop(&mut self, k: &K) -> Option<V> {
+        ShardedLruCache::pop(self, k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShardedLruCache;
+    use crate::{Cache, Layered, LruCache};
+    use core::num::NonZeroUsize;
+    use std::sync::Arc;
+    use std::thread;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_sharded() {
+        let cache = Arc::new(ShardedLruCache::new(
+            NonZeroUsize::new(4000).unwrap(),
+            NonZeroUsize::new(4).unwrap(),
+        ));
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let cache = cache.clone();
+                thread::spawn(move || {
+                    for i in 0..250 {
+                        cache.put(t * 250 + i, i);
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        // Every key is in its own shard and the keys are spread over all the shards
+        assert_eq!(cache.len(), 1000);
+        assert_eq!(cache.get(&999), Some(249));
+        for shard in cache.shards() {
+            let len = shard.len();
+            assert!(len > 150 && len < 350, "unbalanced shard of {len} items");
+        }
+        assert_eq!(cache.get_flips(), 0);
+
+        for i in 0..2000 {
+            cache.get(&i);
+        }
+        let stats = cache.shard_stats();
+        assert_eq!(stats.iter().map(|s| s.hits).sum::<u64>(), 1001);
+        assert_eq!(cache.stats().misses, 1000);
+        assert!(stats.iter().all(|s| s.hits > 100));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_extend() {
+        use rayon::prelude::*;
+
+        let cache = ShardedLruCache::new(
+            NonZeroUsize::new(1000).unwrap(),
+            NonZeroUsize::new(8).unwrap(),
+        );
+        cache.par_extend((0..800u64).into_par_iter().map(|i| (i, i)));
+        assert_eq!(cache.len(), 800);
+        assert!((0..800).all(|i| cache.get(&i) == Some(i)));
+
+        // Existing keys are replaced
+        cache.par_extend((0..10u64).into_par_iter().map(|i| (i, i + 1)));
+        assert_eq!(cache.get(&3), Some(4));
+        assert_eq!(cache.len(), 800);
+    }
+
+    #[test]
+    fn test_layered_over_shards() {
+        let shared = ShardedLruCache::new(
+            NonZeroUsize::new(100).unwrap(),
+            NonZeroUsize::new(4).unwrap(),
+        );
+        shared.put(1, 10);
+        // A small cache per thread in front of the shared one
+        thread::scope(|s| {
+            for _ in 0..2 {
+                s.spawn(|| {
+                    let local = LruCache::new(NonZeroUsize::new(2).unwrap());
+                    let mut cache = Layered::new(local, &shared);
+                    assert_eq!(cache.get(&1), Some(10));
+                    assert_eq!(cache.get(&1), Some(10));
+                    assert_eq!(cache.upper_mut().peek(&1), Some(&10));
+                });
+            }
+        });
+        let stats = shared.stats();
+        assert_eq!((stats.hits, stats.misses), (2, 0));
+    }
+}
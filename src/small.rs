@@ -0,0 +1,302 @@
+use crate::{FixedLruCache, LruCache};
+use alloc::boxed::Box;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::num::NonZeroUsize;
+
+/// An LRU Cache with the semantics of [`LruCache`] that stores up to `N` items per tier
+/// inline, like [`FixedLruCache`], and only allocates the maps of an [`LruCache`] for a
+/// larger capacity. This keeps many tiny caches, e.g., per-connection memo tables,
+/// allocation-free and compact.
+///
+/// The inline storage hashes the keys with FNV-1a, which is fast for small keys but not
+/// resistant to collision attacks, and has room for `N` items whatever the capacity.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::SmallLruCache;
+/// use std::num::NonZeroUsize;
+/// let mut cache: SmallLruCache<u32, &str> = SmallLruCache::new(NonZeroUsize::new(4).unwrap());
+/// assert!(cache.is_inline());
+///
+/// cache.put(1, "a");
+/// assert_eq!(cache.get(&1), Some(&"a"));
+/// ```
+pub struct SmallLruCache<K, V, const N: usize = 8> {
+    storage: Storage<K, V, N>,
+}
+
+enum Storage<K, V, const N: usize> {
+    Inline(FixedLruCache<K, V, N>),
+    // Boxed, so that the inline caches are not as large as an LruCache
+    Heap(Box<LruCache<K, V>>),
+}
+
+impl<K: Hash + Eq, V, const N: usize> SmallLruCache<K, V, N> {
+    /// Creates a new cache that holds `cap` items like [`LruCache::new`], inline if `cap`
+    /// is at most `N`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SmallLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: SmallLruCache<u32, u32, 4> = SmallLruCache::new(NonZeroUsize::new(16).unwrap());
+    /// assert!(!cache.is_inline());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> SmallLruCache<K, V, N> {
+        let storage = if cap.get() <= N {
+            Storage::Inline(FixedLruCache::with_limit(cap.get()))
+        } else {
+            Storage::Heap(Box::new(LruCache::new(cap)))
+        };
+        SmallLruCache { storage }
+    }
+
+    /// Returns a bool indicating whether the items are stored inline or in the maps of an
+    /// [`LruCache`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SmallLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: SmallLruCache<u32, u32> = SmallLruCache::new(NonZeroUsize::new(8).unwrap());
+    /// assert!(cache.is_inline());
+    /// ```
+    pub fn is_inline(&self) -> bool {
+        matches!(self.storage, Storage::Inline(_))
+    }
+
+    /// Returns a reference to the value of the key in the cache or `None` if it is not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SmallLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: SmallLruCache<_, _> = SmallLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert_eq!(cache.get(&4), None);
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match &mut self.storage {
+            Storage::Inline(cache) => cache.get(k),
+            Storage::Heap(cache) => cache.get(k),
+        }
+    }
+
+    /// Returns a mutable reference to the value of the key in the cache or `None` if it
+    /// is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SmallLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: SmallLruCache<_, _> = SmallLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put("apple", 8);
+    /// *cache.get_mut("apple").unwrap() += 1;
+    /// assert_eq!(cache.get("apple"), Some(&9));
+    /// ```
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match &mut self.storage {
+            Storage::Inline(cache) => cache.get_mut(k),
+            Storage::Heap(cache) => cache.get_mut(k),
+        }
+    }
+
+    /// Returns a reference to the value of the key without promoting it, or `None` if it
+    /// is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SmallLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: SmallLruCache<_, _> = SmallLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.peek(&1), Some(&"a"));
+    /// ```
+    pub fn peek<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match &self.storage {
+            Storage::Inline(cache) => cache.peek(k),
+            Storage::Heap(cache) => cache.peek(k),
+        }
+    }
+
+    /// Puts a key-value pair into cache. If the key already exists in the cache, then it updates
+    /// the key's value and returns the old value. Otherwise, `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SmallLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: SmallLruCache<_, _> = SmallLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Option<V> {
+        match &mut self.storage {
+            Storage::Inline(cache) => cache.put(k, v),
+            Storage::Heap(cache) => cache.put(k, v),
+        }
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it was not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SmallLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: SmallLruCache<_, _> = SmallLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match &mut self.storage {
+            Storage::Inline(cache) => cache.pop(k),
+            Storage::Heap(cache) => cache.pop(k),
+        }
+    }
+
+    /// Returns the number of key-value pairs that are guaranteed to be in the cache, like
+    /// [`LruCache::len`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SmallLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: SmallLruCache<_, _> = SmallLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.len(), 1);
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        match &self.storage {
+            Storage::Inline(cache) => cache.len(),
+            Storage::Heap(cache) => cache.len(),
+        }
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SmallLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: SmallLruCache<_, _> = SmallLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        match &self.storage {
+            Storage::Inline(cache) => cache.is_empty(),
+            Storage::Heap(cache) => cache.is_empty(),
+        }
+    }
+
+    /// Returns metric on the number of times the cache became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SmallLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: SmallLruCache<_, _> = SmallLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        match &self.storage {
+            Storage::Inline(cache) => cache.get_flips(),
+            Storage::Heap(cache) => cache.get_flips(),
+        }
+    }
+
+    /// Reset the flip metric.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::SmallLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache: SmallLruCache<_, _> = SmallLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// cache.reset();
+    /// assert_eq!(cache.get_flips(), 0);
+    /// ```
+    pub fn reset(&mut self) {
+        match &mut self.storage {
+            Storage::Inline(cache) => cache.reset(),
+            Storage::Heap(cache) => cache.reset(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SmallLruCache;
+    use core::num::NonZeroUsize;
+
+    fn check<const N: usize>(mut cache: SmallLruCache<u32, u32, N>) {
+        for i in 0..7 {
+            cache.put(i, i);
+        }
+        assert_eq!(cache.get_flips(), 2);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&3), Some(&3));
+    }
+
+    #[test]
+    fn test_inline_and_heap() {
+        // The same workload flips the same way inline and on the heap
+        let cap = NonZeroUsize::new(3).unwrap();
+        let inline: SmallLruCache<u32, u32, 4> = SmallLruCache::new(cap);
+        let heap: SmallLruCache<u32, u32, 2> = SmallLruCache::new(cap);
+        assert!(inline.is_inline());
+        assert!(!heap.is_inline());
+        check(inline);
+        check(heap);
+    }
+}
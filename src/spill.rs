@@ -0,0 +1,21 @@
+/// Receives the generation that [`LruCache`](crate::LruCache) discards at a flip, e.g., to
+/// move it into a slower tier like a disk or a remote cache instead of losing it.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::SpillSink;
+/// use std::collections::HashMap;
+///
+/// struct Disk(HashMap<u64, String>);
+///
+/// impl SpillSink<u64, String> for Disk {
+///     fn spill(&mut self, items: &mut dyn Iterator<Item = (u64, String)>) {
+///         self.0.extend(items);
+///     }
+/// }
+/// ```
+pub trait SpillSink<K, V> {
+    /// Takes the discarded items. The items that are not consumed are dropped.
+    fn spill(&mut self, items: &mut dyn Iterator<Item = (K, V)>);
+}
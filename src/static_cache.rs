@@ -0,0 +1,163 @@
+use crate::LruCache;
+use core::borrow::Borrow;
+use core::cell::RefCell;
+use core::hash::Hash;
+use critical_section::Mutex;
+
+/// An [`LruCache`] for a `static`, shared between the main loop and the interrupt handlers
+/// of a firmware. The cache is initialized once with [`init`](StaticLruCache::init) and
+/// then accessed through closures that run in a critical section of the
+/// [`critical_section`] crate, so the target must provide its implementation.
+///
+/// A closure must not access the same cache again, which panics.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::{LruCache, StaticLruCache};
+/// use std::num::NonZeroUsize;
+/// static CACHE: StaticLruCache<u32, u32> = StaticLruCache::new();
+///
+/// CACHE.init(LruCache::new(NonZeroUsize::new(16).unwrap()));
+/// CACHE.with(|cache| cache.put(1, 10));
+/// assert_eq!(CACHE.get(&1), Some(10));
+/// ```
+pub struct StaticLruCache<K, V> {
+    cache: Mutex<RefCell<Option<LruCache<K, V>>>>,
+}
+
+impl<K, V> StaticLruCache<K, V> {
+    /// Creates an uninitialized cache, which can be the value of a `static`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::StaticLruCache;
+    /// static CACHE: StaticLruCache<u32, &str> = StaticLruCache::new();
+    /// assert!(!CACHE.is_initialized());
+    /// ```
+    pub const fn new() -> StaticLruCache<K, V> {
+        StaticLruCache {
+            cache: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    /// Returns a bool indicating whether the cache was initialized or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, StaticLruCache};
+    /// use std::num::NonZeroUsize;
+    /// static CACHE: StaticLruCache<u32, &str> = StaticLruCache::new();
+    ///
+    /// CACHE.init(LruCache::new(NonZeroUsize::new(16).unwrap()));
+    /// assert!(CACHE.is_initialized());
+    /// ```
+    pub fn is_initialized(&self) -> bool {
+        critical_section::with(|cs| self.cache.borrow_ref(cs).is_some())
+    }
+}
+
+impl<K: Hash + Eq, V> StaticLruCache<K, V> {
+    /// Initializes the cache with `cache` and returns true. If it was already initialized,
+    /// then `cache` is dropped and false is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, StaticLruCache};
+    /// use std::num::NonZeroUsize;
+    /// static CACHE: StaticLruCache<u32, &str> = StaticLruCache::new();
+    ///
+    /// assert!(CACHE.init(LruCache::new(NonZeroUsize::new(16).unwrap())));
+    /// assert!(!CACHE.init(LruCache::new(NonZeroUsize::new(32).unwrap())));
+    /// assert_eq!(CACHE.with(|cache| cache.cap().get()), Some(16));
+    /// ```
+    pub fn init(&self, cache: LruCache<K, V>) -> bool {
+        // The rejected cache is dropped outside of the critical section
+        let rejected = critical_section::with(|cs| {
+            let mut slot = self.cache.borrow_ref_mut(cs);
+            match *slot {
+                Some(_) => Some(cache),
+                None => {
+                    *slot = Some(cache);
+                    None
+                }
+            }
+        });
+        rejected.is_none()
+    }
+
+    /// Calls the function with the cache in a critical section and returns its result, or
+    /// `None` if the cache is not initialized.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, StaticLruCache};
+    /// use std::num::NonZeroUsize;
+    /// static CACHE: StaticLruCache<u32, u32> = StaticLruCache::new();
+    /// assert_eq!(CACHE.with(|cache| cache.put(1, 10)), None);
+    ///
+    /// CACHE.init(LruCache::new(NonZeroUsize::new(16).unwrap()));
+    /// assert_eq!(CACHE.with(|cache| cache.put(1, 10)), Some(None));
+    /// assert_eq!(CACHE.with(|cache| cache.len()), Some(1));
+    /// ```
+    pub fn with<T, F>(&self, f: F) -> Option<T>
+    where
+        F: FnOnce(&mut LruCache<K, V>) -> T,
+    {
+        critical_section::with(|cs| self.cache.borrow_ref_mut(cs).as_mut().map(f))
+    }
+
+    /// Returns a clone of the value of the key or `None` if it is not present in the cache
+    /// or the cache is not initialized.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, StaticLruCache};
+    /// use std::num::NonZeroUsize;
+    /// static CACHE: StaticLruCache<u32, &str> = StaticLruCache::new();
+    ///
+    /// CACHE.init(LruCache::new(NonZeroUsize::new(16).unwrap()));
+    /// CACHE.with(|cache| cache.put(1, "a"));
+    /// assert_eq!(CACHE.get(&1), Some("a"));
+    /// assert_eq!(CACHE.get(&2), None);
+    /// ```
+    pub fn get<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.with(|cache| cache.get(k).cloned()).flatten()
+    }
+}
+
+impl<K, V> Default for StaticLruCache<K, V> {
+    fn default() -> StaticLruCache<K, V> {
+        StaticLruCache::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StaticLruCache;
+    use crate::LruCache;
+    use core::num::NonZeroUsize;
+
+    static CACHE: StaticLruCache<u32, u32> = StaticLruCache::new();
+
+    #[test]
+    fn test_static_cache() {
+        assert_eq!(CACHE.get(&0), None);
+        assert!(CACHE.init(LruCache::new(NonZeroUsize::new(2).unwrap())));
+        for i in 0..3 {
+            CACHE.with(|cache| cache.put(i, i));
+        }
+        assert_eq!(CACHE.get(&0), Some(0));
+        assert_eq!(CACHE.with(|cache| cache.get_flips()), Some(1));
+    }
+}
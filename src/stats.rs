@@ -0,0 +1,112 @@
+use core::iter::Sum;
+use core::ops::Add;
+#[cfg(all(
+    any(feature = "std", feature = "lock_api"),
+    not(feature = "portable-atomic")
+))]
+use core::sync::atomic::{AtomicU64, Ordering};
+// For the targets without native 64-bit atomics, e.g., thumbv6m
+#[cfg(all(
+    any(feature = "std", feature = "lock_api"),
+    feature = "portable-atomic"
+))]
+use portable_atomic::{AtomicU64, Ordering};
+
+/// The hit, miss and flip counts of a concurrent cache, e.g., of a
+/// [`SyncLruCache`](crate::SyncLruCache). The stats of several caches or shards add up to
+/// their aggregate.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::CacheStats;
+///
+/// let a = CacheStats { hits: 3, misses: 1, flips: 0 };
+/// let b = CacheStats { hits: 1, misses: 3, flips: 2 };
+/// assert_eq!(a + b, CacheStats { hits: 4, misses: 4, flips: 2 });
+/// assert_eq!((a + b).hit_ratio(), 0.5);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CacheStats {
+    /// The number of lookups that found the key.
+    pub hits: u64,
+    /// The number of lookups that did not find the key.
+    pub misses: u64,
+    /// The number of flips.
+    pub flips: usize,
+}
+
+impl CacheStats {
+    /// Returns the share of the lookups that found the key, or 0 if there were none.
+    pub fn hit_ratio(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+impl Add for CacheStats {
+    type Output = CacheStats;
+
+    fn add(self, other: CacheStats) -> CacheStats {
+        CacheStats {
+            hits: self.hits + other.hits,
+            misses: self.misses + other.misses,
+            flips: self.flips + other.flips,
+        }
+    }
+}
+
+impl Sum for CacheStats {
+    fn sum<I: Iterator<Item = CacheStats>>(iter: I) -> CacheStats {
+        iter.fold(CacheStats::default(), Add::add)
+    }
+}
+
+// Lookup counters that can be updated under a shared lock.
+#[cfg(any(feature = "std", feature = "lock_api"))]
+#[derive(Default)]
+pub(crate) struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[cfg(any(feature = "std", feature = "lock_api"))]
+impl Counters {
+    // Counts the result of a lookup and passes it through.
+    pub(crate) fn record<T>(&self, found: Option<T>) -> Option<T> {
+        let counter = if found.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    pub(crate) fn stats(&self, flips: usize) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            flips,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "defmt"))]
+mod tests {
+    use super::CacheStats;
+    use crate::{CacheEvent, EvictionReason, Tier};
+
+    fn assert_format<T: defmt::Format>() {}
+
+    #[test]
+    fn test_defmt_format() {
+        assert_format::<CacheStats>();
+        assert_format::<CacheEvent>();
+        assert_format::<Tier>();
+        assert_format::<EvictionReason>();
+    }
+}
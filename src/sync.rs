@@ -0,0 +1,657 @@
+use crate::flight::{Flights, Leader};
+use crate::stats::{CacheStats, Counters};
+use crate::{Cache, LruCache};
+use alloc::sync::Arc;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::ops::Deref;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// An [`LruCache`] behind a [`Mutex`] that can be shared between threads, e.g., in an
+/// `Arc`. All methods take `&self` and lock the cache for the duration of the call.
+///
+/// The values are accessed through closures like [`get_with`], so no guard outlives a
+/// call. A panic while the lock is held does not poison the cache for the other threads.
+///
+/// [`get_with`]: SyncLruCache::get_with
+pub struct SyncLruCache<K, V> {
+    cache: Mutex<LruCache<K, V>>,
+    counters: Counters,
+    flights: Flights<K, V>,
+}
+
+impl<K: Hash + Eq, V> SyncLruCache<K, V> {
+    /// Wraps the cache, keeping its configuration.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache: SyncLruCache<isize, &str> = SyncLruCache::new(LruCache::new(NonZeroUsize::new(10).unwrap()));
+    /// ```
+    pub fn new(cache: LruCache<K, V>) -> SyncLruCache<K, V> {
+        SyncLruCache {
+            cache: Mutex::new(cache),
+            counters: Counters::default(),
+            flights: Flights::new(),
+        }
+    }
+
+    /// Locks the cache for direct access to its whole API.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.lock().peek_oldest(), None);
+    /// ```
+    pub fn lock(&self) -> MutexGuard<'_, LruCache<K, V>> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Calls the function with the value of the key, see [`LruCache::get`], and returns
+    /// its result, or `None` if the key is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put("apple", String::from("red"));
+    /// assert_eq!(cache.get_with(&"apple", |v| v.len()), Some(3));
+    /// assert_eq!(cache.get_with(&"banana", |v| v.len()), None);
+    /// ```
+    pub fn get_with<Q, R, F>(&self, k: &Q, f: F) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&V) -> R,
+    {
+        self.counters.record(self.lock().get(k).map(f))
+    }
+
+    /// Calls the function with the mutable value of the key, see [`LruCache::get_mut`],
+    /// and returns its result, or `None` if the key is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put("apple", 8);
+    /// cache.get_mut_with(&"apple", |v| *v += 1);
+    /// assert_eq!(cache.get(&"apple"), Some(9));
+    /// ```
+    pub fn get_mut_with<Q, R, F>(&self, k: &Q, f: F) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&mut V) -> R,
+    {
+        self.counters.record(self.lock().get_mut(k).map(f))
+    }
+
+    /// Returns a guard that derefs to the value of the key, or `None` if it is not present
+    /// in the cache. The cache stays locked until the guard is dropped, as an alternative
+    /// to [`get_with`](SyncLruCache::get_with) when the value is used across statements.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put("apple", String::from("red"));
+    /// let v = cache.get_guard(&"apple").unwrap();
+    /// assert_eq!(v.len(), 3);
+    /// assert_eq!(*v, "red");
+    /// ```
+    pub fn get_guard<'a, Q>(&'a self, k: &'a Q) -> Option<ValueGuard<'a, K, V, Q>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut cache = self.lock();
+        self.counters.record(cache.get(k))?;
+        Some(ValueGuard { cache, key: k })
+    }
+
+    /// Returns a clone of the value of the key or `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some("a"));
+    /// ```
+    pub fn get<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.get_with(k, V::clone)
+    }
+
+    /// Returns a clone of the value of the key, or calls the function to load the value
+    /// and puts it into the cache if the key is not present. Concurrent calls for the same
+    /// key are coalesced: only one of them loads the value while the others wait for it.
+    /// The lock is not held while loading. If the loader panics, one of the waiting calls
+    /// loads the value instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// assert_eq!(cache.get_or_insert_with(1, || "a"), "a");
+    /// assert_eq!(cache.get_or_insert_with(1, || unreachable!()), "a");
+    /// ```
+    pub fn get_or_insert_with<F>(&self, k: K, f: F) -> V
+    where
+        K: Clone,
+        V: Clone,
+        F: FnOnce() -> V,
+    {
+        match self.get_or_guard(k) {
+            Ok(v) => v,
+            Err(guard) => {
+                let v = f();
+                guard.insert(v.clone());
+                v
+            }
+        }
+    }
+
+    /// Returns a clone of the value of the key, or a guard that marks the key as being
+    /// computed if it is not present. The caller that gets the guard computes the value
+    /// and publishes it with [`InsertGuard::insert`], while the concurrent calls for the
+    /// key block until then and return the published value instead of recomputing it. If
+    /// the guard is dropped without a value, e.g., when the computation fails, one of the
+    /// blocked calls gets the guard instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// match cache.get_or_guard(1) {
+    ///     Ok(_) => unreachable!(),
+    ///     Err(guard) => guard.insert("a"),
+    /// }
+    /// assert_eq!(cache.get_or_guard(1).ok(), Some("a"));
+    /// ```
+    pub fn get_or_guard(&self, k: K) -> Result<V, InsertGuard<'_, K, V>>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        loop {
+            let flight = {
+                let mut cache = self.lock();
+                if let Some(v) = self.counters.record(cache.get(&k)) {
+                    return Ok(v.clone());
+                }
+                // Joining under the lock ensures that a landed load is in the cache
+                match self.flights.join(&k) {
+                    Some(flight) => flight,
+                    None => {
+                        return Err(InsertGuard {
+                            cache: self,
+                            leader: Leader::new(&self.flights, k),
+                        })
+                    }
+                }
+            };
+            if let Some(v) = flight.wait() {
+                return Ok(v);
+            }
+        }
+    }
+
+    /// Puts a key-value pair into the cache, see [`LruCache::put`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&self, k: K, v: V) -> Option<V> {
+        self.lock().put(k, v)
+    }
+
+    /// Removes the key from the cache and returns its value, see [`LruCache::pop`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lock().pop(k)
+    }
+
+    /// Removes all the items from the cache, see [`LruCache::clear`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// cache.clear();
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn clear(&self) {
+        self.lock().clear()
+    }
+
+    /// Returns the number of key-value pairs that are guaranteed to be in the cache, see
+    /// [`LruCache::len`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Returns the hit, miss and flip counts of the cache. Only the lookups through this
+    /// wrapper are counted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{CacheStats, LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.put(1, "a");
+    /// cache.get(&1);
+    /// cache.get(&2);
+    /// assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 1, flips: 0 });
+    /// ```
+    pub fn stats(&self) -> CacheStats {
+        self.counters.stats(self.lock().get_flips())
+    }
+
+    /// Returns metric on the number of times the cache flipped, see
+    /// [`LruCache::get_flips`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.lock().get_flips()
+    }
+
+    /// Returns the wrapped cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    /// cache.put(1, "a");
+    /// let mut cache = cache.into_inner();
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// ```
+    pub fn into_inner(self) -> LruCache<K, V> {
+        self.cache
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The value of a key that keeps the cache locked, returned by
+/// [`SyncLruCache::get_guard`].
+pub struct ValueGuard<'a, K, V, Q: ?Sized = K> {
+    cache: MutexGuard<'a, LruCache<K, V>>,
+    key: &'a Q,
+}
+
+impl<K, V, Q> Deref for ValueGuard<'_, K, V, Q>
+where
+    K: Hash + Eq + Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+{
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        // The key was looked up under the lock, which is still held
+        self.cache.peek(self.key).unwrap()
+    }
+}
+
+/// A key being computed, returned by [`SyncLruCache::get_or_guard`]. The concurrent
+/// lookups of the key block until the value is published with [`insert`] or the guard is
+/// dropped.
+///
+/// [`insert`]: InsertGuard::insert
+pub struct InsertGuard<'a, K: Hash + Eq + Clone, V: Clone> {
+    cache: &'a SyncLruCache<K, V>,
+    leader: Leader<'a, K, V>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> InsertGuard<'_, K, V> {
+    /// Returns the key being computed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache: SyncLruCache<_, &str> = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// let guard = cache.get_or_guard(1).err().unwrap();
+    /// assert_eq!(guard.key(), &1);
+    /// ```
+    pub fn key(&self) -> &K {
+        self.leader.key()
+    }
+
+    /// Puts the value into the cache and hands it to the lookups waiting for the key.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SyncLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache = SyncLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+    ///
+    /// cache.get_or_guard(1).err().unwrap().insert("a");
+    /// assert_eq!(cache.get(&1), Some("a"));
+    /// ```
+    pub fn insert(self, v: V) {
+        let mut cache = self.cache.lock();
+        let k = self.leader.land(v.clone());
+        cache.put(k, v);
+    }
+}
+
+impl<K: Hash + Eq, V: Clone> Cache<K, V> for &SyncLruCache<K, V> {
+    fn get(&mut self, k: &K) -> Option<V> {
+        SyncLruCache::get(self, k)
+    }
+
+    fn put(&mut self, k: K, v: V) -> Option<V> {
+        SyncLruCache::put(self, k, v)
+    }
+
+    fn pop(&mut self, k: &K) -> Option<V> {
+        SyncLruCache::pop(self, k)
+    }
+}
+
+impl<K: Hash + Eq, V> From<LruCache<K, V>> for SyncLruCache<K, V> {
+    fn from(cache: LruCache<K, V>) -> SyncLruCache<K, V> {
+        SyncLruCache::new(cache)
+    }
+}
+
+/// A cloneable handle of a [`SyncLruCache`], whose clones all share the same cache. It
+/// dereferences to the [`SyncLruCache`] for the cache API.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::{LruCache, SharedLruCache};
+/// use std::num::NonZeroUsize;
+/// use std::thread;
+///
+/// let cache = SharedLruCache::new(LruCache::new(NonZeroUsize::new(10).unwrap()));
+/// let handle = cache.clone();
+/// thread::spawn(move || handle.put(1, "a")).join().unwrap();
+/// assert_eq!(cache.get(&1), Some("a"));
+/// ```
+pub struct SharedLruCache<K, V> {
+    cache: Arc<SyncLruCache<K, V>>,
+}
+
+impl<K: Hash + Eq, V> SharedLruCache<K, V> {
+    /// Wraps the cache, keeping its configuration.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, SharedLruCache};
+    /// use std::num::NonZeroUsize;
+    /// let cache: SharedLruCache<isize, &str> = SharedLruCache::new(LruCache::new(NonZeroUsize::new(10).unwrap()));
+    /// ```
+    pub fn new(cache: LruCache<K, V>) -> SharedLruCache<K, V> {
+        SharedLruCache {
+            cache: Arc::new(SyncLruCache::new(cache)),
+        }
+    }
+}
+
+impl<K, V> Clone for SharedLruCache<K, V> {
+    fn clone(&self) -> SharedLruCache<K, V> {
+        SharedLruCache {
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl<K, V> Deref for SharedLruCache<K, V> {
+    type Target = SyncLruCache<K, V>;
+
+    fn deref(&self) -> &SyncLruCache<K, V> {
+        &self.cache
+    }
+}
+
+impl<K: Hash + Eq, V: Clone> Cache<K, V> for SharedLruCache<K, V> {
+    fn get(&mut self, k: &K) -> Option<V> {
+        self.cache.get(k)
+    }
+
+    fn put(&mut self, k: K, v: V) -> Option<V> {
+        self.cache.put(k, v)
+    }
+
+    fn pop(&mut self, k: &K) -> Option<V> {
+        self.cache.pop(k)
+    }
+}
+
+impl<K: Hash + Eq, V> From<LruCache<K, V>> for SharedLruCache<K, V> {
+    fn from(cache: LruCache<K, V>) -> SharedLruCache<K, V> {
+        SharedLruCache::new(cache)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SharedLruCache, SyncLruCache};
+    use crate::LruCache;
+    use core::num::NonZeroUsize;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use std::time::Duration;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_shared_between_threads() {
+        let cache = Arc::new(SyncLruCache::new(LruCache::new(
+            NonZeroUsize::new(100).unwrap(),
+        )));
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let cache = cache.clone();
+                thread::spawn(move || {
+                    for i in 0..50 {
+                        cache.put(t * 50 + i, i);
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        // The last 100 puts are guaranteed to be in the cache
+        assert_eq!(cache.len(), 100);
+        assert_eq!(cache.get_flips(), 1);
+    }
+
+    #[test]
+    fn test_shared_handle() {
+        fn assert_send_sync<T: Send + Sync + Clone>(_: &T) {}
+
+        let cache = SharedLruCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+        assert_send_sync(&cache);
+        let handles: Vec<_> = (0..2).map(|_| cache.clone()).collect();
+        handles[0].put(1, "a");
+        handles[1].put(2, "b");
+        drop(handles);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&1), Some("a"));
+    }
+
+    #[test]
+    fn test_coalesced_loads() {
+        let cache = Arc::new(SyncLruCache::new(LruCache::new(
+            NonZeroUsize::new(2).unwrap(),
+        )));
+        let loads = Arc::new(AtomicUsize::new(0));
+        let barrier = Arc::new(Barrier::new(8));
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let (cache, loads, barrier) = (cache.clone(), loads.clone(), barrier.clone());
+                thread::spawn(move || {
+                    barrier.wait();
+                    cache.get_or_insert_with(1, || {
+                        loads.fetch_add(1, Ordering::Relaxed);
+                        thread::sleep(Duration::from_millis(50));
+                        10
+                    })
+                })
+            })
+            .collect();
+        for t in threads {
+            assert_eq!(t.join().unwrap(), 10);
+        }
+        assert_eq!(loads.load(Ordering::Relaxed), 1);
+
+        // A panicking loader hands the load to the next caller
+        let c = cache.clone();
+        assert!(thread::spawn(move || c.get_or_insert_with(2, || panic!()))
+            .join()
+            .is_err());
+        assert_eq!(cache.get_or_insert_with(2, || 20), 20);
+    }
+
+    #[test]
+    fn test_insert_guard() {
+        let cache = Arc::new(SyncLruCache::new(LruCache::new(
+            NonZeroUsize::new(2).unwrap(),
+        )));
+        let guard = cache.get_or_guard(1).err().unwrap();
+        let c = cache.clone();
+        let waiter = thread::spawn(move || c.get_or_guard(1).ok());
+
+        // The waiter blocks on the guard instead of getting one
+        thread::sleep(Duration::from_millis(50));
+        assert!(!waiter.is_finished());
+        guard.insert(10);
+        assert_eq!(waiter.join().unwrap(), Some(10));
+
+        // A dropped guard hands the computation to the next caller
+        drop(cache.get_or_guard(2).err().unwrap());
+        cache.get_or_guard(2).err().unwrap().insert(20);
+        assert_eq!(cache.get(&2), Some(20));
+    }
+
+    #[test]
+    fn test_value_guard() {
+        let cache = Arc::new(SyncLruCache::new(LruCache::new(
+            NonZeroUsize::new(2).unwrap(),
+        )));
+        cache.put(1, 10);
+        cache.put(2, 20);
+        cache.put(3, 30);
+        assert!(cache.get_guard(&4).is_none());
+
+        // A hit in the cold tier is promoted and the guard holds the lock
+        let guard = cache.get_guard(&1).unwrap();
+        let c = cache.clone();
+        let writer = thread::spawn(move || c.put(1, 11));
+        thread::sleep(Duration::from_millis(50));
+        assert!(!writer.is_finished());
+        assert_eq!(*guard, 10);
+        drop(guard);
+
+        assert_eq!(writer.join().unwrap(), Some(10));
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+    }
+}
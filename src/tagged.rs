@@ -0,0 +1,346 @@
+use alloc::boxed::Box;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::mem;
+use core::num::NonZeroUsize;
+use hashbrown::{HashMap, HashSet};
+
+// A value with the tags it depends on.
+struct Tagged<V> {
+    value: V,
+    tags: Box<[u64]>,
+}
+
+/// An LRU Cache with the flip semantics of [`LruCache`](crate::LruCache) whose items can be
+/// tagged with the hashes of the upstream objects they depend on, so that
+/// [`invalidate_tag`](TaggedLruCache::invalidate_tag) removes every item that depends on
+/// an object that changed.
+///
+/// An index from the tags to their keys is kept along with the items, so the keys are
+/// cloned once per tag and the flips unindex the generation they discard.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::TaggedLruCache;
+/// use std::num::NonZeroUsize;
+/// let mut cache = TaggedLruCache::new(NonZeroUsize::new(1024).unwrap());
+///
+/// const USER_1: u64 = 1;
+/// const ORG_7: u64 = 7;
+/// cache.put_tagged("/users/1", "alice", &[USER_1, ORG_7]);
+/// cache.put_tagged("/orgs/7", "acme", &[ORG_7]);
+/// cache.put("/health", "ok");
+/// assert_eq!(cache.invalidate_tag(ORG_7), 2);
+/// assert_eq!(cache.len(), 1);
+/// ```
+pub struct TaggedLruCache<K, V> {
+    l1_map: HashMap<K, Tagged<V>>,
+    l2_map: HashMap<K, Tagged<V>>,
+    index: HashMap<u64, HashSet<K>>,
+    cap: NonZeroUsize,
+    flips: usize,
+}
+
+impl<K: Hash + Eq + Clone, V> TaggedLruCache<K, V> {
+    /// Creates a new LRU Cache that holds at least `cap` items.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TaggedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: TaggedLruCache<u64, &str> = TaggedLruCache::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> TaggedLruCache<K, V> {
+        TaggedLruCache {
+            l1_map: HashMap::with_capacity(cap.get()),
+            l2_map: HashMap::new(),
+            index: HashMap::new(),
+            cap,
+            flips: 0,
+        }
+    }
+
+    /// Returns a reference to the value of the key in the cache or `None` if it is not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TaggedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = TaggedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert_eq!(cache.get(&4), None);
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_mut(k).map(|v| &*v)
+    }
+
+    /// Returns a mutable reference to the value of the key in the cache or `None` if it
+    /// is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TaggedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = TaggedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put("apple", 8);
+    /// *cache.get_mut("apple").unwrap() += 1;
+    /// assert_eq!(cache.get("apple"), Some(&9));
+    /// ```
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if !self.l1_map.contains_key(k) {
+            // A hit in the backup cache moves the item back into the main cache, where it
+            // keeps its tags
+            let (rk, rv) = self.l2_map.remove_entry(k)?;
+            self.insert(rk, rv);
+        }
+        self.l1_map.get_mut(k).map(|e| &mut e.value)
+    }
+
+    /// Returns a reference to the value of the key without updating the LRU list, or
+    /// `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TaggedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = TaggedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.peek(&1), Some(&"a"));
+    /// ```
+    pub fn peek<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let e = self.l1_map.get(k).or_else(|| self.l2_map.get(k))?;
+        Some(&e.value)
+    }
+
+    /// Puts a key-value pair without tags into cache, like
+    /// [`put_tagged`](TaggedLruCache::put_tagged).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TaggedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = TaggedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Option<V> {
+        self.put_tagged(k, v, &[])
+    }
+
+    /// Puts a key-value pair into cache that depends on the tags. If the key already exists
+    /// in the cache, then it updates the key's value and tags and returns the old value.
+    /// Otherwise, `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TaggedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = TaggedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put_tagged(1, "a", &[10]);
+    /// // The new value of the key replaces its tags
+    /// cache.put_tagged(1, "alpha", &[20]);
+    /// assert_eq!(cache.invalidate_tag(10), 0);
+    /// assert_eq!(cache.invalidate_tag(20), 1);
+    /// ```
+    pub fn put_tagged(&mut self, k: K, v: V, tags: &[u64]) -> Option<V> {
+        let old = self.remove(&k);
+        for &tag in tags {
+            self.index.entry(tag).or_default().insert(k.clone());
+        }
+        let e = Tagged {
+            value: v,
+            tags: tags.into(),
+        };
+        self.insert(k, e);
+        old
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it was not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TaggedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = TaggedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put_tagged(1, "a", &[10]);
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.remove(k)
+    }
+
+    /// Removes all the items tagged with the tag and returns how many were removed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TaggedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = TaggedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put_tagged(1, "a", &[10]);
+    /// cache.put_tagged(2, "b", &[10, 20]);
+    /// assert_eq!(cache.invalidate_tag(10), 2);
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn invalidate_tag(&mut self, tag: u64) -> usize {
+        let Some(keys) = self.index.remove(&tag) else {
+            return 0;
+        };
+        for k in &keys {
+            if let Some(e) = self.l1_map.remove(k).or_else(|| self.l2_map.remove(k)) {
+                Self::unindex(&mut self.index, k, &e.tags);
+            }
+        }
+        keys.len()
+    }
+
+    /// Returns the number of key-value pairs in the main and the backup cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TaggedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = TaggedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.put(3, "c");
+    /// assert_eq!(cache.len(), 3);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.l1_map.len() + self.l2_map.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TaggedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = TaggedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.l1_map.is_empty() && self.l2_map.is_empty()
+    }
+
+    /// Returns metric on the number of times the main cache became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TaggedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = TaggedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     cache.put(i, i);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.flips
+    }
+
+    // Removes the key from both maps and from the index.
+    fn remove<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let e = self.l1_map.remove(k).or_else(|| self.l2_map.remove(k))?;
+        Self::unindex(&mut self.index, k, &e.tags);
+        Some(e.value)
+    }
+
+    // Inserts a key that is in neither map, flipping first if the main cache is full.
+    fn insert(&mut self, k: K, e: Tagged<V>) {
+        if self.l1_map.len() >= self.cap.get() {
+            self.flips += 1;
+            let discarded = mem::replace(&mut self.l2_map, mem::take(&mut self.l1_map));
+            for (dk, de) in &discarded {
+                Self::unindex(&mut self.index, dk, &de.tags);
+            }
+        }
+        self.l1_map.insert(k, e);
+    }
+
+    fn unindex<Q>(index: &mut HashMap<u64, HashSet<K>>, k: &Q, tags: &[u64])
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        for tag in tags {
+            if let Some(keys) = index.get_mut(tag) {
+                keys.remove(k);
+                if keys.is_empty() {
+                    index.remove(tag);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TaggedLruCache;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_index_across_flips() {
+        let mut cache = TaggedLruCache::new(NonZeroUsize::new(2).unwrap());
+        cache.put_tagged("a", 1, &[7]);
+        cache.put_tagged("b", 2, &[7, 8]);
+        // The flip keeps the index of the backup cache
+        cache.put_tagged("c", 3, &[8]);
+        assert_eq!(cache.invalidate_tag(8), 2);
+        assert_eq!(cache.peek("a"), Some(&1));
+
+        // The next flips discard a, and with it its entry of the index
+        cache.put("d", 4);
+        cache.put("e", 5);
+        cache.put("f", 6);
+        assert_eq!(cache.get_flips(), 2);
+        assert_eq!(cache.invalidate_tag(7), 0);
+        assert!(cache.index.is_empty());
+    }
+}
@@ -0,0 +1,298 @@
+use crate::Clock;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::mem;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// A cache whose flips are driven by time only: one map holds the items put during the
+/// current interval of `ttl` and the other the items of the previous interval, which are
+/// discarded at the next flip. An item is thus kept for at least `ttl` and less than
+/// `2*ttl` after it was put, however often it is accessed, e.g., to count the requests of
+/// the clients of a rate limiter.
+///
+/// The number of items is not bounded; it is the number of keys put in two intervals.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::TtlCache;
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let secs = Arc::new(AtomicU64::new(0));
+/// let clock = {
+///     let secs = secs.clone();
+///     move || Duration::from_secs(secs.load(Ordering::Relaxed))
+/// };
+/// let mut requests = TtlCache::new(Duration::from_secs(60), clock);
+///
+/// for _ in 0..3 {
+///     *requests.get_or_insert_with("10.0.0.1", || 0) += 1;
+/// }
+/// assert_eq!(requests.get("10.0.0.1"), Some(&3));
+/// secs.store(120, Ordering::Relaxed);
+/// assert_eq!(requests.get("10.0.0.1"), None);
+/// ```
+pub struct TtlCache<K, V, C> {
+    current: HashMap<K, V>,
+    previous: HashMap<K, V>,
+    ttl: Duration,
+    clock: C,
+    // Start of the current interval
+    start: Duration,
+    flips: usize,
+}
+
+impl<K: Hash + Eq, V, C: Clock> TtlCache<K, V, C> {
+    /// Creates a new cache whose items expire `ttl` to `2*ttl` after they are put, as
+    /// measured by the clock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ttl` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TtlCache;
+    /// use std::time::Duration;
+    /// let clock = || Duration::ZERO;
+    /// let cache: TtlCache<u64, u64, _> = TtlCache::new(Duration::from_secs(60), clock);
+    /// ```
+    pub fn new(ttl: Duration, clock: C) -> TtlCache<K, V, C> {
+        assert!(!ttl.is_zero(), "ttl must not be zero");
+        TtlCache {
+            current: HashMap::new(),
+            previous: HashMap::new(),
+            ttl,
+            start: clock.now(),
+            clock,
+            flips: 0,
+        }
+    }
+
+    /// Returns a reference to the value of the key or `None` if it is not present in the
+    /// cache or expired.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TtlCache;
+    /// use std::time::Duration;
+    /// let mut cache = TtlCache::new(Duration::from_secs(60), || Duration::ZERO);
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert_eq!(cache.get(&2), None);
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_mut(k).map(|v| &*v)
+    }
+
+    /// Returns a mutable reference to the value of the key or `None` if it is not present
+    /// in the cache or expired.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TtlCache;
+    /// use std::time::Duration;
+    /// let mut cache = TtlCache::new(Duration::from_secs(60), || Duration::ZERO);
+    ///
+    /// cache.put("apple", 8);
+    /// *cache.get_mut("apple").unwrap() += 1;
+    /// assert_eq!(cache.get("apple"), Some(&9));
+    /// ```
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.flip_if_due();
+        match self.current.get_mut(k) {
+            Some(v) => Some(v),
+            None => self.previous.get_mut(k),
+        }
+    }
+
+    /// Returns a mutable reference to the value of the key, putting the value returned by
+    /// the function first if the key is not present in the cache or expired.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TtlCache;
+    /// use std::time::Duration;
+    /// let mut cache = TtlCache::new(Duration::from_secs(60), || Duration::ZERO);
+    ///
+    /// *cache.get_or_insert_with("apple", || 0) += 1;
+    /// *cache.get_or_insert_with("apple", || 0) += 1;
+    /// assert_eq!(cache.get("apple"), Some(&2));
+    /// ```
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, k: K, f: F) -> &mut V {
+        self.flip_if_due();
+        // An item keeps its age, so it stays in the previous interval
+        if let Some(v) = self.previous.get_mut(&k) {
+            return v;
+        }
+        self.current.entry(k).or_insert_with(f)
+    }
+
+    /// Puts a key-value pair into the cache, where it expires `ttl` to `2*ttl` later. If
+    /// the key already exists in the cache, then it updates the key's value and returns the
+    /// old value. Otherwise, `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TtlCache;
+    /// use std::time::Duration;
+    /// let mut cache = TtlCache::new(Duration::from_secs(60), || Duration::ZERO);
+    ///
+    /// assert_eq!(None, cache.put(1, "a"));
+    /// assert_eq!(Some("a"), cache.put(1, "alpha"));
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Option<V> {
+        self.flip_if_due();
+        let old = self.previous.remove(&k);
+        self.current.insert(k, v).or(old)
+    }
+
+    /// Removes the key from the cache and returns its value, or `None` if it was not
+    /// present in the cache or expired.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TtlCache;
+    /// use std::time::Duration;
+    /// let mut cache = TtlCache::new(Duration::from_secs(60), || Duration::ZERO);
+    ///
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.pop(&1), Some("a"));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.flip_if_due();
+        self.current.remove(k).or_else(|| self.previous.remove(k))
+    }
+
+    /// Returns the number of key-value pairs in the cache, including the expired ones that
+    /// a lookup or a put has yet to discard.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TtlCache;
+    /// use std::time::Duration;
+    /// let mut cache = TtlCache::new(Duration::from_secs(60), || Duration::ZERO);
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// assert_eq!(cache.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.current.len() + self.previous.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TtlCache;
+    /// use std::time::Duration;
+    /// let mut cache = TtlCache::new(Duration::from_secs(60), || Duration::ZERO);
+    /// assert!(cache.is_empty());
+    /// cache.put(1, "a");
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.current.is_empty() && self.previous.is_empty()
+    }
+
+    /// Returns metric on the number of intervals that have passed, as counted by the
+    /// accesses.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::TtlCache;
+    /// use std::time::Duration;
+    /// let mut cache = TtlCache::new(Duration::from_secs(60), || Duration::from_secs(0));
+    /// cache.put(1, "a");
+    /// assert_eq!(cache.get_flips(), 0);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.flips
+    }
+
+    // Flips once for every interval that has passed, which is at most twice as that
+    // discards all the items.
+    fn flip_if_due(&mut self) {
+        let now = self.clock.now();
+        let elapsed = now.saturating_sub(self.start);
+        if elapsed < self.ttl {
+            return;
+        }
+        let intervals = elapsed.as_nanos() / self.ttl.as_nanos();
+        self.start = u32::try_from(intervals)
+            .ok()
+            .and_then(|n| self.ttl.checked_mul(n))
+            .map_or(now, |d| self.start + d);
+        self.flips = self.flips.saturating_add(intervals as usize);
+        if intervals >= 2 {
+            self.previous.clear();
+            self.current.clear();
+        } else {
+            // The emptied map of the discarded interval is reused for the new one
+            mem::swap(&mut self.current, &mut self.previous);
+            self.current.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TtlCache;
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicU64, Ordering};
+    use core::time::Duration;
+
+    #[test]
+    fn test_time_buckets() {
+        let secs = Arc::new(AtomicU64::new(0));
+        let clock = {
+            let secs = secs.clone();
+            move || Duration::from_secs(secs.load(Ordering::Relaxed))
+        };
+        let mut cache = TtlCache::new(Duration::from_secs(10), clock);
+        cache.put("a", 1);
+        secs.store(9, Ordering::Relaxed);
+        cache.put("b", 2);
+
+        // A read does not renew the item, unlike a put
+        secs.store(15, Ordering::Relaxed);
+        assert_eq!(cache.get("a"), Some(&1));
+        cache.put("b", 3);
+        secs.store(20, Ordering::Relaxed);
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), Some(&3));
+        assert_eq!(cache.get_flips(), 2);
+
+        // Two intervals without access expire everything
+        secs.store(45, Ordering::Relaxed);
+        assert!(cache.get("b").is_none());
+        assert!(cache.is_empty());
+        assert_eq!(cache.get_flips(), 4);
+    }
+}
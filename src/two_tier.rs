@@ -0,0 +1,350 @@
+use crate::LruCache;
+use core::hash::Hash;
+
+/// The slower tier behind a [`TwoTierCache`], e.g., a disk or a remote store.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::Backend;
+/// use std::collections::HashMap;
+///
+/// #[derive(Default)]
+/// struct Disk(HashMap<i32, i32>);
+///
+/// impl Backend<i32, i32> for Disk {
+///     type Error = ();
+///
+///     fn load(&mut self, k: &i32) -> Result<Option<i32>, ()> {
+///         Ok(self.0.get(k).copied())
+///     }
+///
+///     fn store(&mut self, &k: &i32, &v: &i32) -> Result<(), ()> {
+///         self.0.insert(k, v);
+///         Ok(())
+///     }
+///
+///     fn remove(&mut self, k: &i32) -> Result<(), ()> {
+///         self.0.remove(k);
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait Backend<K, V> {
+    /// The error of a failed backend call.
+    type Error;
+
+    /// Loads the value of the key, or returns `None` if the backend does not have it.
+    fn load(&mut self, k: &K) -> Result<Option<V>, Self::Error>;
+
+    /// Stores the value of the key.
+    fn store(&mut self, k: &K, v: &V) -> Result<(), Self::Error>;
+
+    /// Removes the key.
+    fn remove(&mut self, k: &K) -> Result<(), Self::Error>;
+}
+
+/// An [`LruCache`] as the in-memory tier in front of a slower backend. The puts and
+/// removals are applied to the backend first, so it holds every item, and a lookup that
+/// misses the cache falls through to the backend and promotes the value into the cache.
+pub struct TwoTierCache<K, V, B> {
+    cache: LruCache<K, V>,
+    backend: B,
+}
+
+impl<K: Hash + Eq, V, B: Backend<K, V>> TwoTierCache<K, V, B> {
+    /// Creates a two-tier cache of the cache in front of the backend.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{Backend, LruCache, TwoTierCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::collections::HashMap;
+    ///
+    /// #[derive(Default)]
+    /// struct Disk(HashMap<i32, i32>);
+    ///
+    /// impl Backend<i32, i32> for Disk {
+    ///     type Error = ();
+    ///
+    ///     fn load(&mut self, k: &i32) -> Result<Option<i32>, ()> {
+    ///         Ok(self.0.get(k).copied())
+    ///     }
+    ///
+    ///     fn store(&mut self, &k: &i32, &v: &i32) -> Result<(), ()> {
+    ///         self.0.insert(k, v);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn remove(&mut self, k: &i32) -> Result<(), ()> {
+    ///         self.0.remove(k);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = TwoTierCache::new(cache, Disk::default());
+    /// cache.put(1, 10).unwrap();
+    /// assert_eq!(cache.get(&1), Ok(Some(&10)));
+    /// ```
+    pub fn new(cache: LruCache<K, V>, backend: B) -> TwoTierCache<K, V, B> {
+        TwoTierCache { cache, backend }
+    }
+
+    /// Returns a reference to the value of the key, see [`LruCache::get`]. If the key is
+    /// not present in the cache, its value is loaded from the backend and put into the
+    /// cache. `None` is returned if the backend does not have it either.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{Backend, LruCache, TwoTierCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::collections::HashMap;
+    ///
+    /// #[derive(Default)]
+    /// struct Disk(HashMap<i32, i32>);
+    ///
+    /// impl Backend<i32, i32> for Disk {
+    ///     type Error = ();
+    ///
+    ///     fn load(&mut self, k: &i32) -> Result<Option<i32>, ()> {
+    ///         Ok(self.0.get(k).copied())
+    ///     }
+    ///
+    ///     fn store(&mut self, &k: &i32, &v: &i32) -> Result<(), ()> {
+    ///         self.0.insert(k, v);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn remove(&mut self, k: &i32) -> Result<(), ()> {
+    ///         self.0.remove(k);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut disk = Disk::default();
+    /// disk.0.insert(1, 10);
+    /// let mut cache = TwoTierCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()), disk);
+    /// assert_eq!(cache.get(&1), Ok(Some(&10)));
+    /// assert_eq!(cache.cache().len(), 1);
+    /// assert_eq!(cache.get(&2), Ok(None));
+    /// ```
+    pub fn get<'a>(&'a mut self, k: &K) -> Result<Option<&'a V>, B::Error>
+    where
+        K: Clone,
+    {
+        let cache = match LruCache::get_in(&mut self.cache, |c| c, k, |_| true) {
+            Ok(v) => return Ok(Some(v)),
+            Err(cache) => cache,
+        };
+
+        match self.backend.load(k)? {
+            Some(v) => {
+                cache.put(k.clone(), v);
+                // The value was not found, which get would count as a hit
+                Ok(cache.peek(k))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Stores the key-value pair in the backend and then puts it into the cache, see
+    /// [`LruCache::put`]. If the backend fails, the error is returned and the cache is left
+    /// unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{Backend, LruCache, TwoTierCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::collections::HashMap;
+    ///
+    /// #[derive(Default)]
+    /// struct Disk(HashMap<i32, i32>);
+    ///
+    /// impl Backend<i32, i32> for Disk {
+    ///     type Error = ();
+    ///
+    ///     fn load(&mut self, k: &i32) -> Result<Option<i32>, ()> {
+    ///         Ok(self.0.get(k).copied())
+    ///     }
+    ///
+    ///     fn store(&mut self, &k: &i32, &v: &i32) -> Result<(), ()> {
+    ///         self.0.insert(k, v);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn remove(&mut self, k: &i32) -> Result<(), ()> {
+    ///         self.0.remove(k);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut cache = TwoTierCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()), Disk::default());
+    /// assert_eq!(cache.put(1, 10), Ok(None));
+    /// assert_eq!(cache.put(1, 11), Ok(Some(10)));
+    /// assert_eq!(cache.backend().0[&1], 11);
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Result<Option<V>, B::Error> {
+        self.backend.store(&k, &v)?;
+        Ok(self.cache.put(k, v))
+    }
+
+    /// Removes the key from the backend and then from the cache, and returns its cached
+    /// value, see [`LruCache::pop`]. If the backend fails, the error is returned and the
+    /// cache is left unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{Backend, LruCache, TwoTierCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::collections::HashMap;
+    ///
+    /// #[derive(Default)]
+    /// struct Disk(HashMap<i32, i32>);
+    ///
+    /// impl Backend<i32, i32> for Disk {
+    ///     type Error = ();
+    ///
+    ///     fn load(&mut self, k: &i32) -> Result<Option<i32>, ()> {
+    ///         Ok(self.0.get(k).copied())
+    ///     }
+    ///
+    ///     fn store(&mut self, &k: &i32, &v: &i32) -> Result<(), ()> {
+    ///         self.0.insert(k, v);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn remove(&mut self, k: &i32) -> Result<(), ()> {
+    ///         self.0.remove(k);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut cache = TwoTierCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()), Disk::default());
+    /// cache.put(1, 10).unwrap();
+    /// assert_eq!(cache.remove(&1), Ok(Some(10)));
+    /// assert_eq!(cache.get(&1), Ok(None));
+    /// ```
+    pub fn remove(&mut self, k: &K) -> Result<Option<V>, B::Error> {
+        self.backend.remove(k)?;
+        Ok(self.cache.pop(k))
+    }
+
+    /// Returns the cache.
+    pub fn cache(&self) -> &LruCache<K, V> {
+        &self.cache
+    }
+
+    /// Returns the backend.
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Returns the backend mutably. Changes made through it are not reflected in the cache.
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    /// Returns the cache and the backend.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{Backend, LruCache, TwoTierCache};
+    /// use std::num::NonZeroUsize;
+    /// use std::collections::HashMap;
+    ///
+    /// #[derive(Default)]
+    /// struct Disk(HashMap<i32, i32>);
+    ///
+    /// impl Backend<i32, i32> for Disk {
+    ///     type Error = ();
+    ///
+    ///     fn load(&mut self, k: &i32) -> Result<Option<i32>, ()> {
+    ///         Ok(self.0.get(k).copied())
+    ///     }
+    ///
+    ///     fn store(&mut self, &k: &i32, &v: &i32) -> Result<(), ()> {
+    ///         self.0.insert(k, v);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn remove(&mut self, k: &i32) -> Result<(), ()> {
+    ///         self.0.remove(k);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut cache = TwoTierCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()), Disk::default());
+    /// cache.put(1, 10).unwrap();
+    /// let (cache, disk) = cache.into_parts();
+    /// assert_eq!((cache.len(), disk.0.len()), (1, 1));
+    /// ```
+    pub fn into_parts(self) -> (LruCache<K, V>, B) {
+        (self.cache, self.backend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backend, TwoTierCache};
+    use crate::LruCache;
+    use core::num::NonZeroUsize;
+    use hashbrown::HashMap;
+
+    // Fails the stores of odd values and counts the loads
+    #[derive(Default)]
+    struct Even {
+        items: HashMap<u32, u32>,
+        loads: usize,
+    }
+
+    impl Backend<u32, u32> for Even {
+        type Error = u32;
+
+        fn load(&mut self, k: &u32) -> Result<Option<u32>, u32> {
+            self.loads += 1;
+            Ok(self.items.get(k).copied())
+        }
+
+        fn store(&mut self, &k: &u32, &v: &u32) -> Result<(), u32> {
+            if v % 2 == 1 {
+                return Err(v);
+            }
+            self.items.insert(k, v);
+            Ok(())
+        }
+
+        fn remove(&mut self, k: &u32) -> Result<(), u32> {
+            self.items.remove(k);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_two_tiers() {
+        let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        let mut cache = TwoTierCache::new(cache, Even::default());
+        for i in 0..6 {
+            assert_eq!(cache.put(i, i * 2), Ok(None));
+        }
+        assert_eq!(cache.put(5, 3), Err(3));
+
+        // The discarded items fall through to the backend and are promoted
+        // and only the second lookup is counted as a hit
+        assert_eq!(cache.get(&0), Ok(Some(&0)));
+        assert_eq!(cache.backend().loads, 1);
+        assert_eq!(cache.cache().gen_hits.l1, 0);
+        assert_eq!(cache.get(&0), Ok(Some(&0)));
+        assert_eq!(cache.backend().loads, 1);
+        assert_eq!(cache.cache().gen_hits.l1, 1);
+
+        assert_eq!(cache.remove(&5), Ok(Some(10)));
+        assert_eq!(cache.get(&5), Ok(None));
+        assert_eq!(cache.backend().loads, 2);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::LruCache;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::num::NonZeroUsize;
+
+// A value with the version of the upstream data it was read from.
+struct Versioned<V> {
+    value: V,
+    version: u64,
+}
+
+/// An LRU Cache whose values carry a `u64` version, e.g., from upstream invalidation
+/// events that can arrive out of order. [`put_if_newer`](VersionedLruCache::put_if_newer)
+/// only overwrites a value with an older version.
+///
+/// The versions are forgotten along with the items, so a stale value can be put again
+/// after its key was discarded.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::VersionedLruCache;
+/// use std::num::NonZeroUsize;
+/// let mut cache = VersionedLruCache::new(NonZeroUsize::new(1024).unwrap());
+///
+/// assert!(cache.put_if_newer("user:1", "bob", 7));
+/// // An event of an older version that arrives late is ignored
+/// assert!(!cache.put_if_newer("user:1", "alice", 6));
+/// assert_eq!(cache.get_versioned("user:1"), Some((&"bob", 7)));
+/// ```
+pub struct VersionedLruCache<K, V> {
+    cache: LruCache<K, Versioned<V>>,
+}
+
+impl<K: Hash + Eq, V> VersionedLruCache<K, V> {
+    /// Creates a new LRU Cache that holds at least `cap` items.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::VersionedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: VersionedLruCache<u64, &str> =
+    ///     VersionedLruCache::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> VersionedLruCache<K, V> {
+        VersionedLruCache {
+            cache: LruCache::new(cap),
+        }
+    }
+
+    /// Puts a key-value pair of the version into the cache, unless the key already holds a
+    /// value of the same or a newer version. Returns whether the value was put.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::VersionedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = VersionedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert!(cache.put_if_newer(1, "a", 1));
+    /// assert!(!cache.put_if_newer(1, "b", 1));
+    /// assert!(cache.put_if_newer(1, "c", 2));
+    /// assert_eq!(cache.get(&1), Some(&"c"));
+    /// ```
+    pub fn put_if_newer(&mut self, k: K, v: V, version: u64) -> bool {
+        if self
+            .cache
+            .peek(&k)
+            .is_some_and(|old| old.version >= version)
+        {
+            return false;
+        }
+        self.cache.put(k, Versioned { value: v, version });
+        true
+    }
+
+    /// Returns a reference to the value of the key in the cache or `None` if it is not
+    /// present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::VersionedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = VersionedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put_if_newer(1, "a", 1);
+    /// assert_eq!(cache.get(&1), Some(&"a"));
+    /// assert_eq!(cache.get(&2), None);
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.get(k).map(|e| &e.value)
+    }
+
+    /// Returns a reference to the value of the key in the cache along with its version, or
+    /// `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::VersionedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = VersionedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put_if_newer(1, "a", 42);
+    /// assert_eq!(cache.get_versioned(&1), Some((&"a", 42)));
+    /// ```
+    pub fn get_versioned<Q>(&mut self, k: &Q) -> Option<(&V, u64)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.get(k).map(|e| (&e.value, e.version))
+    }
+
+    /// Returns the version of the value of the key without updating the LRU list, or
+    /// `None` if it is not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::VersionedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = VersionedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put_if_newer(1, "a", 42);
+    /// assert_eq!(cache.version(&1), Some(42));
+    /// ```
+    pub fn version<Q>(&self, k: &Q) -> Option<u64>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.peek(k).map(|e| e.version)
+    }
+
+    /// Removes the key from the cache and returns its value and version, or `None` if it
+    /// was not present in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::VersionedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = VersionedLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// cache.put_if_newer(1, "a", 42);
+    /// assert_eq!(cache.pop(&1), Some(("a", 42)));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<(V, u64)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.pop(k).map(|e| (e.value, e.version))
+    }
+
+    /// Returns the number of key-value pairs that are guaranteed to be in the cache, like
+    /// [`LruCache::len`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::VersionedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = VersionedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// cache.put_if_newer(1, "a", 1);
+    /// cache.put_if_newer(2, "b", 1);
+    /// assert_eq!(cache.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::VersionedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = VersionedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    /// cache.put_if_newer(1, "a", 1);
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Returns metric on the number of times the cache became full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::VersionedLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = VersionedLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// for i in 0..5 {
+    ///     cache.put_if_newer(i, i, 1);
+    /// }
+    /// assert_eq!(cache.get_flips(), 2);
+    /// ```
+    pub fn get_flips(&self) -> usize {
+        self.cache.get_flips()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VersionedLruCache;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_out_of_order_versions() {
+        let mut cache = VersionedLruCache::new(NonZeroUsize::new(2).unwrap());
+        for (v, version) in [("v3", 3), ("v1", 1), ("v5", 5), ("v4", 4)] {
+            cache.put_if_newer("k", v, version);
+        }
+        assert_eq!(cache.get_versioned("k"), Some((&"v5", 5)));
+
+        // The version is also checked in the backup cache
+        cache.put_if_newer("a", "a", 1);
+        cache.put_if_newer("b", "b", 1);
+        assert_eq!(cache.get_flips(), 1);
+        assert!(!cache.put_if_newer("k", "v2", 2));
+        assert!(cache.put_if_newer("k", "v6", 6));
+        assert_eq!(cache.pop("k"), Some(("v6", 6)));
+    }
+}
@@ -0,0 +1,198 @@
+use crate::LruCache;
+use alloc::sync::{Arc, Weak};
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::num::NonZeroUsize;
+
+/// An LRU Cache of resources that are owned elsewhere. It holds [`Weak`] references, so it
+/// never keeps a resource alive, and treats the ones that were dropped as misses.
+///
+/// The dead references are removed when they are looked up, by
+/// [`remove_dead`](WeakLruCache::remove_dead), or by the flips like any other item.
+///
+/// # Example
+///
+/// ```
+/// use fliplru::WeakLruCache;
+/// use std::num::NonZeroUsize;
+/// use std::sync::Arc;
+/// let mut cache = WeakLruCache::new(NonZeroUsize::new(2).unwrap());
+///
+/// let texture = Arc::new([0u8; 64]);
+/// cache.put("grass", &texture);
+/// assert!(cache.get("grass").is_some());
+/// drop(texture);
+/// assert!(cache.get("grass").is_none());
+/// ```
+pub struct WeakLruCache<K, T> {
+    cache: LruCache<K, Weak<T>>,
+}
+
+impl<K: Hash + Eq, T> WeakLruCache<K, T> {
+    /// Creates a new cache that holds `cap` references.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::WeakLruCache;
+    /// use std::num::NonZeroUsize;
+    /// let cache: WeakLruCache<u64, String> = WeakLruCache::new(NonZeroUsize::new(10).unwrap());
+    /// ```
+    pub fn new(cap: NonZeroUsize) -> WeakLruCache<K, T> {
+        WeakLruCache {
+            cache: LruCache::new(cap),
+        }
+    }
+
+    /// Returns the resource of the key, or `None` if it is not present in the cache or it
+    /// was dropped, in which case the key is removed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::WeakLruCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::Arc;
+    /// let mut cache = WeakLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// let a = Arc::new("a");
+    /// cache.put(1, &a);
+    /// assert_eq!(cache.get(&1), Some(a));
+    /// assert_eq!(cache.get(&2), None);
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<Arc<T>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let resource = self.cache.get(k)?.upgrade();
+        if resource.is_none() {
+            self.cache.pop(k);
+        }
+        resource
+    }
+
+    /// Puts a reference to the resource into the cache under the key and returns the
+    /// previous resource of the key, if it is still alive.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::WeakLruCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::Arc;
+    /// let mut cache = WeakLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// let (a, b) = (Arc::new("a"), Arc::new("b"));
+    /// assert_eq!(cache.put(1, &a), None);
+    /// assert_eq!(cache.put(1, &b), Some(a));
+    /// ```
+    pub fn put(&mut self, k: K, resource: &Arc<T>) -> Option<Arc<T>> {
+        self.cache.put(k, Arc::downgrade(resource))?.upgrade()
+    }
+
+    /// Removes the key from the cache and returns its resource, if it is still alive.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::WeakLruCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::Arc;
+    /// let mut cache = WeakLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// let a = Arc::new("a");
+    /// cache.put(1, &a);
+    /// assert_eq!(cache.pop(&1), Some(a));
+    /// assert_eq!(cache.pop(&1), None);
+    /// ```
+    pub fn pop<Q>(&mut self, k: &Q) -> Option<Arc<T>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.pop(k)?.upgrade()
+    }
+
+    /// Removes the references to the resources that were dropped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::WeakLruCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::Arc;
+    /// let mut cache = WeakLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// let (a, b) = (Arc::new("a"), Arc::new("b"));
+    /// cache.put(1, &a);
+    /// cache.put(2, &b);
+    /// drop(a);
+    /// cache.remove_dead();
+    /// assert_eq!(cache.len(), 1);
+    /// ```
+    pub fn remove_dead(&mut self) {
+        self.cache.retain(|_, w| w.strong_count() > 0);
+    }
+
+    /// Returns the number of references that are guaranteed to be in the cache, like
+    /// [`LruCache::len`], including the ones whose resources were dropped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::WeakLruCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::Arc;
+    /// let mut cache = WeakLruCache::new(NonZeroUsize::new(2).unwrap());
+    ///
+    /// let a = Arc::new("a");
+    /// cache.put(1, &a);
+    /// assert_eq!(cache.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Returns a bool indicating whether the cache is empty or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::WeakLruCache;
+    /// use std::num::NonZeroUsize;
+    /// use std::sync::Arc;
+    /// let mut cache = WeakLruCache::new(NonZeroUsize::new(2).unwrap());
+    /// assert!(cache.is_empty());
+    ///
+    /// let a = Arc::new("a");
+    /// cache.put(1, &a);
+    /// assert!(!cache.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WeakLruCache;
+    use alloc::sync::Arc;
+    use core::num::NonZeroUsize;
+
+    #[test]
+    fn test_weak_values() {
+        let mut cache = WeakLruCache::new(NonZeroUsize::new(2).unwrap());
+        let (a, b) = (Arc::new(1), Arc::new(2));
+        cache.put("a", &a);
+        cache.put("b", &b);
+        // The cache does not own the resources
+        assert_eq!(Arc::strong_count(&a), 1);
+
+        drop(b);
+        assert_eq!(cache.get("a"), Some(a.clone()));
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.pop("a"), Some(a));
+    }
+}
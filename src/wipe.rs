@@ -0,0 +1,148 @@
+use crate::{LruCache, Wipe};
+use allocator_api2::alloc::Allocator;
+use core::hash::{BuildHasher, Hash};
+use zeroize::Zeroize;
+
+impl<K: Hash + Eq, V: Zeroize, S: BuildHasher + Clone, A: Allocator + Clone> LruCache<K, V, S, A> {
+    /// Wipes the values with [`Zeroize`] when the cache drops them: along with their
+    /// generation at a flip, when they are replaced or removed by the cache, when it is
+    /// cleared and when it is dropped. The items of a whole generation are wiped where the
+    /// map stores them. Use [`with_key_zeroize`] to wipe the keys too.
+    ///
+    /// The values that [`put`] and [`put_cold`] replace are wiped too, so they return
+    /// `None` then. The discarded generations are not parked for
+    /// [`with_incremental_drop`](LruCache::with_incremental_drop) or
+    /// [`invalidate_all`](LruCache::invalidate_all), but wiped right away.
+    ///
+    /// The items that are handed to an eviction listener or a spill sink are theirs to
+    /// wipe, as are the values that are returned, e.g., by [`pop`]. The maps may also
+    /// leave copies of the items behind when they grow or move them between the tiers,
+    /// which [`new`](LruCache::new) avoids for the main cache by allocating it upfront.
+    ///
+    /// [`with_key_zeroize`]: LruCache::with_key_zeroize
+    /// [`put`]: LruCache::put
+    /// [`put_cold`]: LruCache::put_cold
+    /// [`pop`]: LruCache::pop
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_zeroize();
+    ///
+    /// cache.put("session", vec![0x2a_u8; 32]);
+    /// cache.clear();
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn with_zeroize(self) -> LruCache<K, V, S, A> {
+        self.with_wipe(Wipe {
+            value: V::zeroize,
+            key: None,
+        })
+    }
+
+    /// Like [`with_zeroize`](LruCache::with_zeroize), but wipes the keys along with the
+    /// values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::LruCache;
+    /// use std::num::NonZeroUsize;
+    /// let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_key_zeroize();
+    ///
+    /// cache.put(String::from("alice"), vec![0x2a_u8; 32]);
+    /// cache.clear();
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn with_key_zeroize(self) -> LruCache<K, V, S, A>
+    where
+        K: Zeroize,
+    {
+        self.with_wipe(Wipe {
+            value: V::zeroize,
+            key: Some(K::zeroize),
+        })
+    }
+
+    fn with_wipe(mut self, wipe: Wipe<K, V>) -> LruCache<K, V, S, A> {
+        self.hooks.wipe = Some(wipe);
+        self.l1_map.wipe = Some(wipe);
+        self.l2_map.wipe = Some(wipe);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::LruCache;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::num::NonZeroUsize;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use zeroize::Zeroize;
+
+    static WIPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Secret(Vec<u8>);
+
+    impl Zeroize for Secret {
+        fn zeroize(&mut self) {
+            self.0.zeroize();
+            WIPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_zeroize() {
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_zeroize();
+        for i in 0..5 {
+            cache.put(i, Secret(vec![i; 4]));
+        }
+        // The first generation was discarded at the second flip
+        assert_eq!(WIPED.load(Ordering::Relaxed), 2);
+
+        cache.retain(|&k, _| k != 2);
+        assert_eq!(WIPED.load(Ordering::Relaxed), 3);
+        // A returned value is not wiped
+        let popped = cache.pop(&3).unwrap();
+        assert_eq!(popped.0, [3; 4]);
+        drop(cache);
+        assert_eq!(WIPED.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn test_wipe_in_place() {
+        static AT: AtomicUsize = AtomicUsize::new(0);
+
+        struct Located([u8; 4]);
+
+        impl Zeroize for Located {
+            fn zeroize(&mut self) {
+                self.0.zeroize();
+                AT.store(self as *mut Located as usize, Ordering::Relaxed);
+            }
+        }
+
+        let mut cache = LruCache::new(NonZeroUsize::new(2).unwrap()).with_zeroize();
+        cache.put(1, Located([1; 4]));
+        let at = cache.peek(&1).unwrap() as *const Located as usize;
+        cache.clear();
+        // The value was wiped where the map stored it, not as a copy moved out of it
+        assert_eq!(AT.load(Ordering::Relaxed), at);
+
+        // The replaced values are wiped instead of returned
+        cache.put(2, Located([2; 4]));
+        assert!(cache.put(2, Located([3; 4])).is_none());
+        assert!(cache.put_cold(2, Located([4; 4])).is_none());
+        cache.put(3, Located([5; 4]));
+        cache.put(4, Located([6; 4]));
+        let at = cache.peek(&3).unwrap() as *const Located as usize;
+        assert!(cache.put(3, Located([7; 4])).is_none());
+        assert_eq!(cache.peek(&3).map(|v| v.0), Some([7; 4]));
+        assert_eq!(cache.peek(&2).map(|v| v.0), Some([4; 4]));
+        // Moving 3 out of the backup cache wiped it there
+        assert_eq!(AT.load(Ordering::Relaxed), at);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::LruCache;
+use core::borrow::Borrow;
+use core::hash::Hash;
+
+/// The store behind a [`WriteThroughCache`].
+///
+/// # Example
+///
+/// ```
+/// use fliplru::WriteBackend;
+/// use std::collections::HashMap;
+///
+/// struct Db(HashMap<u64, String>);
+///
+/// impl WriteBackend<u64, String> for Db {
+///     type Error = ();
+///
+///     fn store(&mut self, k: &u64, v: &String) -> Result<(), ()> {
+///         self.0.insert(*k, v.clone());
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait WriteBackend<K, V> {
+    /// The error of a failed store.
+    type Error;
+
+    /// Stores the value of the key.
+    fn store(&mut self, k: &K, v: &V) -> Result<(), Self::Error>;
+}
+
+/// A write-through layer: every put is stored in the backend before it is cached, so the
+/// cache never holds a value that the backend does not.
+///
+/// Mutable access to the cached values is not provided as it would bypass the backend.
+pub struct WriteThroughCache<K, V, B> {
+    cache: LruCache<K, V>,
+    backend: B,
+}
+
+impl<K: Hash + Eq, V, B: WriteBackend<K, V>> WriteThroughCache<K, V, B> {
+    /// Creates a write-through layer of the cache in front of the backend.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, WriteBackend, WriteThroughCache};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// struct Log(Vec<(i32, i32)>);
+    ///
+    /// impl WriteBackend<i32, i32> for Log {
+    ///     type Error = ();
+    ///
+    ///     fn store(&mut self, &k: &i32, &v: &i32) -> Result<(), ()> {
+    ///         self.0.push((k, v));
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+    /// let mut cache = WriteThroughCache::new(cache, Log(Vec::new()));
+    /// cache.put(1, 10).unwrap();
+    /// assert_eq!(cache.get(&1), Some(&10));
+    /// assert_eq!(cache.backend().0, [(1, 10)]);
+    /// ```
+    pub fn new(cache: LruCache<K, V>, backend: B) -> WriteThroughCache<K, V, B> {
+        WriteThroughCache { cache, backend }
+    }
+
+    /// Returns a reference to the value of the key in the cache or `None` if it is not
+    /// present in the cache, see [`LruCache::get`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, WriteBackend, WriteThroughCache};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// struct Positive;
+    ///
+    /// impl WriteBackend<i32, i32> for Positive {
+    ///     type Error = i32;
+    ///
+    ///     fn store(&mut self, _: &i32, &v: &i32) -> Result<(), i32> {
+    ///         if v > 0 { Ok(()) } else { Err(v) }
+    ///     }
+    /// }
+    ///
+    /// let mut cache = WriteThroughCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()), Positive);
+    /// cache.put(1, 10).unwrap();
+    /// assert_eq!(cache.get(&1), Some(&10));
+    /// assert_eq!(cache.get(&2), None);
+    /// ```
+    pub fn get<'a, Q>(&'a mut self, k: &Q) -> Option<&'a V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.get(k)
+    }
+
+    /// Stores the key-value pair in the backend and then puts it into the cache, see
+    /// [`LruCache::put`]. If the backend fails, the error is returned and the cache is left
+    /// unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, WriteBackend, WriteThroughCache};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// struct Positive;
+    ///
+    /// impl WriteBackend<i32, i32> for Positive {
+    ///     type Error = i32;
+    ///
+    ///     fn store(&mut self, _: &i32, &v: &i32) -> Result<(), i32> {
+    ///         if v > 0 { Ok(()) } else { Err(v) }
+    ///     }
+    /// }
+    ///
+    /// let mut cache = WriteThroughCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()), Positive);
+    /// assert_eq!(cache.put(1, 10), Ok(None));
+    /// assert_eq!(cache.put(1, -1), Err(-1));
+    /// assert_eq!(cache.get(&1), Some(&10));
+    /// ```
+    pub fn put(&mut self, k: K, v: V) -> Result<Option<V>, B::Error> {
+        self.backend.store(&k, &v)?;
+        Ok(self.cache.put(k, v))
+    }
+
+    /// Returns the cache.
+    pub fn cache(&self) -> &LruCache<K, V> {
+        &self.cache
+    }
+
+    /// Returns the backend.
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Returns the backend mutably. Changes made through it are not reflected in the cache.
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    /// Returns the cache and the backend.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fliplru::{LruCache, WriteBackend, WriteThroughCache};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// struct Positive;
+    ///
+    /// impl WriteBackend<i32, i32> for Positive {
+    ///     type Error = i32;
+    ///
+    ///     fn store(&mut self, _: &i32, &v: &i32) -> Result<(), i32> {
+    ///         if v > 0 { Ok(()) } else { Err(v) }
+    ///     }
+    /// }
+    ///
+    /// let mut cache = WriteThroughCache::new(LruCache::new(NonZeroUsize::new(2).unwrap()), Positive);
+    /// cache.put(1, 10).unwrap();
+    /// let (cache, Positive) = cache.into_parts();
+    /// assert_eq!(cache.len(), 1);
+    /// ```
+    pub fn into_parts(self) -> (LruCache<K, V>, B) {
+        (self.cache, self.backend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WriteBackend, WriteThroughCache};
+    use crate::LruCache;
+    use core::num::NonZeroUsize;
+
+    // Accepts only even values and counts the stores
+    struct Even(usize);
+
+    impl WriteBackend<u32, u32> for Even {
+        type Error = u32;
+
+        fn store(&mut self, _: &u32, &v: &u32) -> Result<(), u32> {
+            if v % 2 == 1 {
+                return Err(v);
+            }
+            self.0 += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_through() {
+        let cache = LruCache::new(NonZeroUsize::new(2).unwrap());
+        let mut cache = WriteThroughCache::new(cache, Even(0));
+
+        assert_eq!(cache.put(1, 2), Ok(None));
+        assert_eq!(cache.put(1, 4), Ok(Some(2)));
+        // A failed store keeps the cached value
+        assert_eq!(cache.put(1, 5), Err(5));
+        assert_eq!(cache.get(&1), Some(&4));
+        assert_eq!(cache.put(2, 7), Err(7));
+        assert_eq!(cache.get(&2), None);
+
+        let (cache, backend) = cache.into_parts();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(backend.0, 2);
+    }
+}